@@ -3,11 +3,12 @@ use std::env;
 use craft::workspace::Workspace;
 use craft::ops::{self, CompileOptions, MessageFormat};
 use craft::util::important_paths::find_root_manifest_for_wd;
-use craft::util::{CliResult, Config};
+use craft::util::{CliResult, Config, human};
 
 #[derive(RustcDecodable)]
 pub struct Options {
     flag_package: Vec<String>,
+    flag_all: bool,
     flag_jobs: Option<u32>,
     flag_features: Vec<String>,
     flag_all_features: bool,
@@ -18,6 +19,12 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_message_format: MessageFormat,
+    flag_future_incompat: bool,
+    flag_out_dir: Option<String>,
+    flag_dump_fingerprints: bool,
+    flag_sanitizer: Option<String>,
+    flag_verify_reproducible: bool,
+    flag_build_plan: bool,
     flag_release: bool,
     flag_lib: bool,
     flag_bin: Vec<String>,
@@ -26,17 +33,20 @@ pub struct Options {
     flag_bench: Vec<String>,
     flag_locked: bool,
     flag_frozen: bool,
+    flag_offline: bool,
+    arg_args: Vec<String>,
 }
 
 pub const USAGE: &'static str = "
 Compile a local package and all of its dependencies
 
 Usage:
-    craft build [options]
+    craft build [options] [--] [<args>...]
 
 Options:
     -h, --help                   Print this message
     -p SPEC, --package SPEC ...  Package to build
+    --all                        Build all packages in the workspace
     -j N, --jobs N               Number of parallel jobs, defaults to # of CPUs
     --lib                        Build only this package's library
     --bin NAME                   Build only the specified binary
@@ -52,14 +62,31 @@ Options:
     -v, --verbose ...            Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
-    --message-format FMT         Error format: human, json [default: human]
+    --message-format FMT         Error format: human, short, json [default: human]
+    --future-incompat            Report deprecated constructs found during the build
+    --out-dir PATH               Copy final artifacts to this directory
+    --dump-fingerprints          Write the fingerprint of every unit and why it's rebuilt
+    --sanitizer SANITIZER        Instrument the code: address, undefined, thread
+    --verify-reproducible        Build twice and check that the artifacts are identical
+    --build-plan                 Print the commands of the build as JSON instead of running them
     --frozen                     Require Craft.lock and cache are up to date
     --locked                     Require Craft.lock is up to date
+    --offline                    Run without accessing the network
 
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package should be built. If it is not given, then the
-current package is built. For more information on SPEC and its format, see the
-`craft help pkgid` command.
+current package is built, or the `workspace.default-members` at the root of a
+workspace. A virtual workspace without `default-members` builds all of its
+members. For more information on SPEC and its format, see the `craft help pkgid`
+command.
+
+The trailing arguments after `--` are passed to the compiler, but only when
+compiling the selected packages, e.g. `craft build -- -Werror` rejects warnings
+of the current package but not of its dependencies.
+
+The `short` message format only prints the one line summary of every compiler
+diagnostic. The `json` one prints machine readable messages to stdout, even if
+`--quiet` is given.
 
 Compilation can be configured via the use of profiles which are configured in
 the manifest. The default profile for this command is `dev`, but passing
@@ -73,7 +100,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_quiet,
                           &options.flag_color,
                           options.flag_frozen,
-                          options.flag_locked)?;
+                          options.flag_locked,
+                          options.flag_offline)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
 
@@ -85,6 +113,7 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         all_features: options.flag_all_features,
         no_default_features: options.flag_no_default_features,
         spec: &options.flag_package,
+        all: options.flag_all,
         mode: ops::CompileMode::Build,
         release: options.flag_release,
         filter: ops::CompileFilter::new(options.flag_lib,
@@ -93,11 +122,28 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                                         &options.flag_example,
                                         &options.flag_bench),
         message_format: options.flag_message_format,
+        future_incompat: options.flag_future_incompat,
+        out_dir: options.flag_out_dir.as_ref().map(|d| &d[..]),
+        dump_fingerprints: options.flag_dump_fingerprints,
+        sanitizer: options.flag_sanitizer.as_ref().map(|s| &s[..]),
+        build_plan: options.flag_build_plan,
         target_doc_args: None,
-        target_cc_args: None,
+        target_cc_args: if options.arg_args.is_empty() {
+            None
+        } else {
+            Some(&options.arg_args[..])
+        },
     };
 
+    if options.flag_build_plan && options.flag_verify_reproducible {
+        return Err(human("`--build-plan` can't be combined with `--verify-reproducible`").into());
+    }
+
     let ws = Workspace::new(&root, config)?;
-    ops::compile(&ws, &opts)?;
+    if options.flag_verify_reproducible {
+        ops::verify_reproducible(&ws, &opts)?;
+    } else {
+        ops::compile(&ws, &opts)?;
+    }
     Ok(None)
 }
@@ -0,0 +1,114 @@
+use std::env;
+
+use craft::workspace::Workspace;
+use craft::ops::{self, CompileOptions, MessageFormat};
+use craft::util::important_paths::find_root_manifest_for_wd;
+use craft::util::{CliResult, Config};
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    arg_opts: Vec<String>,
+    flag_package: Option<String>,
+    flag_jobs: Option<u32>,
+    flag_features: Vec<String>,
+    flag_all_features: bool,
+    flag_no_default_features: bool,
+    flag_target: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_message_format: MessageFormat,
+    flag_release: bool,
+    flag_lib: bool,
+    flag_bin: Vec<String>,
+    flag_example: Vec<String>,
+    flag_test: Vec<String>,
+    flag_bench: Vec<String>,
+    flag_locked: bool,
+    flag_frozen: bool,
+    flag_offline: bool,
+}
+
+pub const USAGE: &'static str = "
+Compile a package and all of its dependencies, passing extra flags to the
+compiler for the package itself
+
+Usage:
+    craft cc [options] [--] [<opts>...]
+
+Options:
+    -h, --help               Print this message
+    -p SPEC, --package SPEC  Package to build
+    -j N, --jobs N           Number of parallel jobs, defaults to # of CPUs
+    --lib                    Build only this package's library
+    --bin NAME               Build only the specified binary
+    --example NAME           Build only the specified example
+    --test NAME              Build only the specified test target
+    --bench NAME             Build only the specified benchmark target
+    --release                Build artifacts in release mode, with optimizations
+    --features FEATURES      Features to compile for the package
+    --all-features           Build all available features
+    --no-default-features    Do not compile default features for the package
+    --target TRIPLE          Target triple which compiles will be for
+    --manifest-path PATH     Path to the manifest to fetch dependencies for
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --message-format FMT     Error format: human, short, json [default: human]
+    --frozen                 Require Craft.lock and cache are up to date
+    --locked                 Require Craft.lock is up to date
+    --offline                Run without accessing the network
+
+The specified target for the current package (or package specified by SPEC if
+provided) will be compiled along with all of its dependencies. The specified
+<opts>... will all be passed to the compiler invocations of the package's
+targets, but not to the ones of its dependencies. Note that the compiler will
+still unconditionally receive arguments such as -I and -L, and the specified
+<opts>... will simply be added to the compiler invocations.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    debug!("executing; cmd=craft-cc; args={:?}",
+           env::args().collect::<Vec<_>>());
+    config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked,
+                          options.flag_offline)?;
+
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+
+    let spec = options.flag_package.map_or_else(Vec::new, |s| vec![s]);
+
+    let opts = CompileOptions {
+        config: config,
+        jobs: options.flag_jobs,
+        target: options.flag_target.as_ref().map(|t| &t[..]),
+        features: &options.flag_features,
+        all_features: options.flag_all_features,
+        no_default_features: options.flag_no_default_features,
+        spec: &spec,
+        all: false,
+        mode: ops::CompileMode::Build,
+        release: options.flag_release,
+        filter: ops::CompileFilter::new(options.flag_lib,
+                                        &options.flag_bin,
+                                        &options.flag_test,
+                                        &options.flag_example,
+                                        &options.flag_bench),
+        message_format: options.flag_message_format,
+        future_incompat: false,
+        out_dir: None,
+        dump_fingerprints: false,
+        sanitizer: None,
+        build_plan: false,
+        target_doc_args: None,
+        target_cc_args: Some(&options.arg_opts[..]),
+    };
+
+    let ws = Workspace::new(&root, config)?;
+    ops::compile(&ws, &opts)?;
+    Ok(None)
+}
@@ -15,6 +15,7 @@ pub struct Options {
     flag_color: Option<String>,
     flag_release: bool,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
 }
 
@@ -35,11 +36,16 @@ Options:
     --color WHEN                 Coloring: auto, always, never
     --frozen                     Require Craft.lock and cache are up to date
     --locked                     Require Craft.lock is up to date
+    --offline                    Run without accessing the network
 
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package's artifacts should be cleaned out. If it is not
 given, then all packages' artifacts are removed. For more information on SPEC
 and its format, see the `craft help pkgid` command.
+
+Without --package, passing --release or --target only removes the directory of
+the release profile or the given target, the debug profile unless --release is
+passed as well.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -49,7 +55,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_quiet,
                           &options.flag_color,
                           options.flag_frozen,
-                          options.flag_locked)?;
+                          options.flag_locked,
+                          options.flag_offline)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
     let opts = ops::CleanOptions {
@@ -22,6 +22,7 @@ pub struct Options {
     flag_lib: bool,
     flag_bin: Vec<String>,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
 }
 
@@ -48,9 +49,10 @@ Options:
     -v, --verbose ...            Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
-    --message-format FMT         Error format: human, json [default: human]
+    --message-format FMT         Error format: human, short, json [default: human]
     --frozen                     Require Craft.lock and cache are up to date
     --locked                     Require Craft.lock is up to date
+    --offline                    Run without accessing the network
 
 By default the documentation for the local package and all dependencies is
 built. The output is all placed in `target/doc`.
@@ -66,7 +68,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                    options.flag_quiet,
                    &options.flag_color,
                    options.flag_frozen,
-                   options.flag_locked)?;
+                   options.flag_locked,
+                   options.flag_offline)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
 
@@ -81,8 +84,14 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
             all_features: options.flag_all_features,
             no_default_features: options.flag_no_default_features,
             spec: &options.flag_package,
+            all: false,
             filter: ops::CompileFilter::new(options.flag_lib, &options.flag_bin, &empty, &empty, &empty),
             message_format: options.flag_message_format,
+            future_incompat: false,
+            out_dir: None,
+            dump_fingerprints: false,
+            sanitizer: None,
+            build_plan: false,
             release: options.flag_release,
             mode: ops::CompileMode::Doc { deps: !options.flag_no_deps },
             target_cc_args: None,
@@ -0,0 +1,43 @@
+use craft::ops;
+use craft::util::{CliResult, Config};
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_offline: bool,
+    flag_locked: bool,
+    arg_warning: String,
+}
+
+pub const USAGE: &'static str = "
+Explain a compiler warning
+
+Usage:
+    craft explain [options] <warning>
+
+Options:
+    -h, --help               Print this message
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Craft.lock and cache are up to date
+    --locked                 Require Craft.lock is up to date
+    --offline                Run without accessing the network
+
+The <warning> is the name of the warning as passed to the compiler, with or
+without the `-W` prefix, for example `unused-variable` or `-Wshadow`.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    config.configure(options.flag_verbose,
+                          options.flag_quiet,
+                          &options.flag_color,
+                          options.flag_frozen,
+                          options.flag_locked,
+                          options.flag_offline)?;
+    println!("{}", ops::explain(&options.arg_warning));
+    Ok(None)
+}
@@ -10,6 +10,7 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
 }
 
@@ -27,6 +28,7 @@ Options:
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Craft.lock and cache are up to date
     --locked                 Require Craft.lock is up to date
+    --offline                Run without accessing the network
 
 If a lockfile is available, this command will ensure that all of the git
 dependencies and/or registries dependencies are downloaded and locally
@@ -43,7 +45,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_quiet,
                           &options.flag_color,
                           options.flag_frozen,
-                          options.flag_locked)?;
+                          options.flag_locked,
+                          options.flag_offline)?;
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
     let ws = Workspace::new(&root, config)?;
     ops::fetch(&ws)?;
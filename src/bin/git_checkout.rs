@@ -10,6 +10,7 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
 }
 
@@ -27,6 +28,7 @@ Options:
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Craft.lock and cache are up to date
     --locked                 Require Craft.lock is up to date
+    --offline                Run without accessing the network
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -34,7 +36,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_quiet,
                           &options.flag_color,
                           options.flag_frozen,
-                          options.flag_locked)?;
+                          options.flag_locked,
+                          options.flag_offline)?;
     let Options { flag_url: url, flag_reference: reference, .. } = options;
 
     let url = url.to_url()?;
@@ -0,0 +1,165 @@
+use craft::ops::{self, CompileOptions, CompileFilter, InstalledPackage, MessageFormat};
+use craft::source::{SourceId, GitReference};
+use craft::util::{CliResult, Config, ToUrl, human};
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_jobs: Option<u32>,
+    flag_features: Vec<String>,
+    flag_all_features: bool,
+    flag_no_default_features: bool,
+    flag_debug: bool,
+    flag_bin: Vec<String>,
+    flag_example: Vec<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_message_format: MessageFormat,
+    flag_root: Option<String>,
+    flag_list: bool,
+    flag_force: bool,
+    flag_frozen: bool,
+    flag_locked: bool,
+    flag_offline: bool,
+
+    arg_chest: Option<String>,
+    flag_vers: Option<String>,
+
+    flag_git: Option<String>,
+    flag_branch: Option<String>,
+    flag_tag: Option<String>,
+    flag_rev: Option<String>,
+
+    flag_path: Option<String>,
+    flag_index: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Install a Craft binary
+
+Usage:
+    craft install [options] [<chest>]
+    craft install [options] --list
+
+Specifying what chest to install:
+    --vers VERS               Specify a version to install from the registry
+    --index INDEX             Registry index to install from
+    --git URL                 Git URL to install the specified chest from
+    --branch BRANCH           Branch to use when installing from git
+    --tag TAG                 Tag to use when installing from git
+    --rev SHA                 Specific commit to use when installing from git
+    --path PATH               Filesystem path to local chest to install
+
+Build and install options:
+    -h, --help                Print this message
+    -j N, --jobs N            Number of parallel jobs, defaults to # of CPUs
+    -f, --force               Force overwriting existing chests or binaries
+    --features FEATURES       Space-separated list of features to activate
+    --all-features            Build all available features
+    --no-default-features     Do not build the `default` feature
+    --debug                   Build in debug mode instead of release mode
+    --bin NAME                Only install the binary NAME
+    --example EXAMPLE         Install the example EXAMPLE instead of binaries
+    --root DIR                Directory to install packages into
+    --list                    List all installed packages and their binaries
+    -v, --verbose ...         Use verbose output
+    -q, --quiet               Less output printed to stdout
+    --color WHEN              Coloring: auto, always, never
+    --message-format FMT      Error format: human, short, json [default: human]
+    --frozen                  Require Craft.lock and cache are up to date
+    --locked                  Require Craft.lock is up to date
+    --offline                 Run without accessing the network
+
+This command manages Craft's local set of installed binary chests. Only packages
+which have [[bin]] targets can be installed, and all binaries are installed into
+the installation root's `bin` folder. The installation root is determined, in
+order of precedence, by `--root`, `$CRAFT_INSTALL_ROOT`, the `install.root`
+configuration key, and finally the home directory (which is either
+`$CRAFT_HOME` if set or `$HOME/.craft` by default).
+
+The chest is taken from the registry given by `--index` or the `registry.index`
+configuration key, unless `--git` or `--path` point elsewhere. Without a chest
+the package in the current directory is installed.
+
+With `--list` and `--message-format json` the installed packages are printed as
+a JSON array of objects with their `package`, `version` and `bins`.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<Vec<InstalledPackage>>> {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline)?;
+
+    let root = options.flag_root.as_ref().map(|s| &s[..]);
+
+    if options.flag_list {
+        if options.flag_message_format == MessageFormat::Json {
+            return Ok(Some(ops::install_list_json(root, config)?));
+        }
+        ops::install_list(root, config)?;
+        return Ok(None);
+    }
+
+    let compile_opts = CompileOptions {
+        config: config,
+        jobs: options.flag_jobs,
+        target: None,
+        features: &options.flag_features,
+        all_features: options.flag_all_features,
+        no_default_features: options.flag_no_default_features,
+        spec: &[],
+        all: false,
+        mode: ops::CompileMode::Build,
+        release: !options.flag_debug,
+        filter: CompileFilter::new(false, &options.flag_bin, &[], &options.flag_example, &[]),
+        message_format: options.flag_message_format,
+        future_incompat: false,
+        out_dir: None,
+        dump_fingerprints: false,
+        sanitizer: None,
+        build_plan: false,
+        target_doc_args: None,
+        target_cc_args: None,
+    };
+
+    let source = if let Some(url) = options.flag_git {
+        let url = url.to_url()?;
+        let gitref = if let Some(branch) = options.flag_branch {
+            GitReference::Branch(branch)
+        } else if let Some(tag) = options.flag_tag {
+            GitReference::Tag(tag)
+        } else if let Some(rev) = options.flag_rev {
+            GitReference::Rev(rev)
+        } else {
+            GitReference::Branch("master".to_string())
+        };
+        SourceId::for_git(&url, gitref)
+    } else if let Some(path) = options.flag_path {
+        SourceId::for_path(&config.cwd().join(path))?
+    } else if options.arg_chest.is_none() {
+        SourceId::for_path(config.cwd())?
+    } else {
+        let index = match options.flag_index {
+            Some(index) => index,
+            None => {
+                match config.get_string("registry.index")? {
+                    Some(index) => index.val,
+                    None => {
+                        return Err(human("no registry to install from, pass `--index`, `--git` or `--path` \
+                                          or set `registry.index`")
+                            .into())
+                    }
+                }
+            }
+        };
+        SourceId::for_registry(&index.to_url()?)
+    };
+
+    let chest = options.arg_chest.as_ref().map(|s| &s[..]);
+    let vers = options.flag_vers.as_ref().map(|s| &s[..]);
+    ops::install(root, chest, &source, vers, &compile_opts, options.flag_force)?;
+    Ok(None)
+}
@@ -4,17 +4,28 @@ use craft::util::important_paths::find_root_manifest_for_wd;
 #[derive(RustcDecodable)]
 pub struct LocateProjectFlags {
     flag_manifest_path: Option<String>,
+    flag_message_format: LocationFormat,
+}
+
+#[derive(RustcDecodable, PartialEq)]
+pub enum LocationFormat {
+    Json,
+    Plain,
 }
 
 pub const USAGE: &'static str = "
-Print a JSON representation of a Craft.toml file's location
+Print the location of a Craft.toml file, as JSON by default
 
 Usage:
     craft locate-project [options]
 
 Options:
     --manifest-path PATH    Path to the manifest to locate
+    --message-format FMT    Output format: json, plain [default: json]
     -h, --help              Print this message
+
+With `--message-format plain` only the absolute path of the manifest is printed,
+which is handy in shell scripts.
 ";
 
 #[derive(RustcEncodable)]
@@ -29,5 +40,9 @@ pub fn execute(flags: LocateProjectFlags, config: &Config) -> CliResult<Option<P
         .chain_error(|| human("Your project path contains characters not representable in Unicode"))
         .map_err(|e| CliError::new(e, 1))?;
 
+    if flags.flag_message_format == LocationFormat::Plain {
+        println!("{}", string);
+        return Ok(None);
+    }
     Ok(Some(ProjectLocation { root: string.to_string() }))
 }
@@ -0,0 +1,56 @@
+use std::io;
+
+use craft::ops;
+use craft::util::{CliResult, ChainError, Config, human};
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    arg_token: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Save an API token from the registry locally
+
+Usage:
+    craft login [options] [<token>]
+
+Options:
+    -h, --help               Print this message
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Craft.lock and cache are up to date
+    --locked                 Require Craft.lock is up to date
+
+The token is read from standard input if it isn't passed as an argument. It is
+stored in `$CRAFT_HOME/credentials` and used by `craft publish`.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     false)?;
+    let token = match options.arg_token {
+        Some(token) => token,
+        None => {
+            config.shell().status("Login", "please paste the API token below")?;
+            let mut line = String::new();
+            io::stdin().read_line(&mut line).chain_error(|| human("failed to read the token from stdin"))?;
+            line
+        }
+    };
+    let token = token.trim().to_string();
+    if token.is_empty() {
+        return Err(human("the API token must not be empty").into());
+    }
+    ops::registry_login(config, token)?;
+    Ok(None)
+}
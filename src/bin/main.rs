@@ -32,6 +32,7 @@ pub struct Flags {
     arg_args: Vec<String>,
     flag_locked: bool,
     flag_frozen: bool,
+    flag_offline: bool,
 }
 
 const USAGE: &'static str = "
@@ -50,6 +51,7 @@ Options:
     --color WHEN        Coloring: auto, always, never
     --frozen            Require Craft.lock and cache are up to date
     --locked            Require Craft.lock is up to date
+    --offline           Run without accessing the network
 
 Some common craft commands are (see all commands with --list):
     build               Compile the current project (alias: b)
@@ -72,19 +74,29 @@ fn main() {
 macro_rules! each_subcommand{
     ($mac:ident) => {
         $mac!(build);
+        $mac!(cc);
         $mac!(clean);
         $mac!(doc);
+        $mac!(explain);
         $mac!(fetch);
         $mac!(generate_lockfile);
         $mac!(git_checkout);
+        $mac!(install);
         $mac!(locate_project);
+        $mac!(login);
         $mac!(metadata);
         $mac!(new);
         $mac!(package);
         $mac!(pkgid);
+        $mac!(publish);
         $mac!(run);
+        $mac!(search);
+        $mac!(tree);
+        $mac!(uninstall);
         $mac!(update);
+        $mac!(vendor);
         $mac!(verify_project);
+        $mac!(why);
     }
 }
 
@@ -98,7 +110,8 @@ fn execute(flags: Flags, config: &Config) -> CliResult<Option<()>> {
                    flags.flag_quiet,
                    &flags.flag_color,
                    flags.flag_frozen,
-                   flags.flag_locked)?;
+                   flags.flag_locked,
+                   flags.flag_offline)?;
 
     init_git_transports(config);
     let _token = craft::util::job::setup();
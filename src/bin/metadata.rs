@@ -15,6 +15,7 @@ pub struct Options {
     flag_quiet: Option<bool>,
     flag_verbose: u32,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
 }
 
@@ -33,13 +34,14 @@ Options:
     --no-deps                  Output information only about the root package
                                and don't fetch dependencies.
     --manifest-path PATH       Path to the manifest
-    --format-version VERSION   Format version [default: 1]
-                               Valid values: 1
+    --format-version VERSION   Format version [default: 2]
+                               Valid values: 1, 2
     -v, --verbose ...          Use verbose output
     -q, --quiet                No output printed to stdout
     --color WHEN               Coloring: auto, always, never
     --frozen                   Require Craft.lock and cache are up to date
     --locked                   Require Craft.lock is up to date
+    --offline                  Run without accessing the network
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<ExportInfo>> {
@@ -47,7 +49,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<ExportInfo
                           options.flag_quiet,
                           &options.flag_color,
                           options.flag_frozen,
-                          options.flag_locked)?;
+                          options.flag_locked,
+                          options.flag_offline)?;
     let manifest = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
 
     let options = OutputMetadataOptions {
@@ -1,5 +1,6 @@
 use std::env;
 
+use craft::manifest::Language;
 use craft::ops;
 use craft::util::{CliResult, Config};
 
@@ -13,7 +14,11 @@ pub struct Options {
     arg_path: String,
     flag_name: Option<String>,
     flag_vcs: Option<ops::VersionControl>,
+    flag_with_config: bool,
+    flag_lang: Option<Language>,
+    flag_template: Option<String>,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
 }
 
@@ -32,11 +37,20 @@ Options:
     --bin               Use a binary (application) template
     --lib               Use a library template
     --name NAME         Set the resulting package name
+    --lang LANG         Language of the package, c (the default) or cpp
+    --template SRC      Create the package from a template directory or git URL
+    --with-config       Add a .craft/config with commented defaults
     -v, --verbose ...   Use verbose output
     -q, --quiet         No output printed to stdout
     --color WHEN        Coloring: auto, always, never
     --frozen            Require Craft.lock and cache are up to date
     --locked            Require Craft.lock is up to date
+    --offline           Run without accessing the network
+
+A template is copied into the new package in place of the default manifest and
+sources. The placeholders `{{name}}` and `{{authors}}` in its file names and
+contents are replaced by the package name and author. Git templates are checked
+out from their `master` branch.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -46,15 +60,20 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_quiet,
                           &options.flag_color,
                           options.flag_frozen,
-                          options.flag_locked)?;
+                          options.flag_locked,
+                          options.flag_offline)?;
 
-    let Options { flag_bin, flag_lib, arg_path, flag_name, flag_vcs, .. } = options;
+    let Options { flag_bin, flag_lib, arg_path, flag_name, flag_vcs, flag_with_config, flag_lang, flag_template, .. } =
+        options;
 
     let opts = ops::NewOptions::new(flag_vcs,
                                     flag_bin,
                                     flag_lib,
                                     &arg_path,
-                                    flag_name.as_ref().map(|s| s.as_ref()));
+                                    flag_name.as_ref().map(|s| s.as_ref()),
+                                    flag_with_config,
+                                    flag_lang,
+                                    flag_template.as_ref().map(|s| s.as_ref()));
 
     let opts_lib = opts.lib;
     ops::new(opts, config)?;
@@ -15,6 +15,7 @@ pub struct Options {
     flag_allow_dirty: bool,
     flag_jobs: Option<u32>,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
 }
 
@@ -37,6 +38,7 @@ Options:
     --color WHEN            Coloring: auto, always, never
     --frozen                Require Craft.lock and cache are up to date
     --locked                Require Craft.lock is up to date
+    --offline               Run without accessing the network
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -44,7 +46,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_quiet,
                           &options.flag_color,
                           options.flag_frozen,
-                          options.flag_locked)?;
+                          options.flag_locked,
+                          options.flag_offline)?;
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
     let ws = Workspace::new(&root, config)?;
     ops::package(&ws,
@@ -10,6 +10,7 @@ pub struct Options {
     flag_color: Option<String>,
     flag_manifest_path: Option<String>,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
     flag_package: Option<String>,
     arg_spec: Option<String>,
@@ -30,6 +31,7 @@ Options:
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Craft.lock and cache are up to date
     --locked                 Require Craft.lock is up to date
+    --offline                Run without accessing the network
 
 Given a <spec> argument, print out the fully qualified package id specifier.
 This command will generate an error if <spec> is ambiguous as to which package
@@ -52,7 +54,8 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_quiet,
                           &options.flag_color,
                           options.flag_frozen,
-                          options.flag_locked)?;
+                          options.flag_locked,
+                          options.flag_offline)?;
     let root = find_root_manifest_for_wd(options.flag_manifest_path.clone(), config.cwd())?;
     let ws = Workspace::new(&root, config)?;
 
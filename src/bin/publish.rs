@@ -0,0 +1,70 @@
+use craft::workspace::Workspace;
+use craft::ops;
+use craft::util::{CliResult, Config};
+use craft::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_index: Option<String>,
+    flag_token: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_no_verify: bool,
+    flag_allow_dirty: bool,
+    flag_jobs: Option<u32>,
+    flag_dry_run: bool,
+    flag_frozen: bool,
+    flag_offline: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Upload a package to the registry
+
+Usage:
+    craft publish [options]
+
+Options:
+    -h, --help               Print this message
+    --index INDEX            Registry index to upload the package to
+    --token TOKEN            Token to use when uploading
+    --no-verify              Don't verify package tarball before publish
+    --allow-dirty            Allow publishing with a dirty source directory
+    --manifest-path PATH     Path to the manifest of the package to publish
+    -j N, --jobs N           Number of parallel jobs, defaults to # of CPUs
+    --dry-run                Perform all checks without uploading
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Craft.lock and cache are up to date
+    --locked                 Require Craft.lock is up to date
+    --offline                Run without accessing the network
+
+The registry is taken from `--index` or the `registry.index` config value. The
+token is taken from `--token`, the `registry.token` config value, the token
+stored by `craft login` or the credential process configured for the registry.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    ops::publish(&ws,
+                 &ops::PublishOpts {
+                     config: config,
+                     token: options.flag_token,
+                     index: options.flag_index,
+                     verify: !options.flag_no_verify,
+                     allow_dirty: options.flag_allow_dirty,
+                     jobs: options.flag_jobs,
+                     dry_run: options.flag_dry_run,
+                 })?;
+    Ok(None)
+}
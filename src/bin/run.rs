@@ -1,6 +1,6 @@
 use craft::workspace::Workspace;
 use craft::ops::{self, MessageFormat};
-use craft::util::{CliResult, CliError, Config, Human};
+use craft::util::{CliResult, CliError, Config, Human, human};
 use craft::util::important_paths::find_root_manifest_for_wd;
 
 #[derive(RustcDecodable)]
@@ -19,7 +19,11 @@ pub struct Options {
     flag_message_format: MessageFormat,
     flag_release: bool,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
+    flag_env: Vec<String>,
+    flag_cwd: Option<String>,
+    flag_lib_test: bool,
     arg_args: Vec<String>,
 }
 
@@ -43,9 +47,13 @@ Options:
     -v, --verbose ...       Use verbose output
     -q, --quiet             No output printed to stdout
     --color WHEN            Coloring: auto, always, never
-    --message-format FMT    Error format: human, json [default: human]
+    --message-format FMT    Error format: human, short, json [default: human]
+    --env KEY=VALUE ...     Set an environment variable for the binary
+    --cwd DIR               Directory to run the binary in
+    --lib-test              Run the library built as a test executable
     --frozen                Require Craft.lock and cache are up to date
     --locked                Require Craft.lock is up to date
+    --offline               Run without accessing the network
 
 If neither `--bin` nor `--example` are given, then if the project only has one
 bin target it will be run. Otherwise `--bin` specifies the bin target to run,
@@ -62,10 +70,15 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_quiet,
                           &options.flag_color,
                           options.flag_frozen,
-                          options.flag_locked)?;
+                          options.flag_locked,
+                          options.flag_offline)?;
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
 
+    if options.flag_lib_test && (options.flag_bin.is_some() || options.flag_example.is_some()) {
+        return Err(human("`--lib-test` can't be combined with `--bin` or `--example`").into());
+    }
+
     let (mut examples, mut bins) = (Vec::new(), Vec::new());
     if let Some(s) = options.flag_bin {
         bins.push(s);
@@ -82,9 +95,22 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         all_features: options.flag_all_features,
         no_default_features: options.flag_no_default_features,
         spec: &[],
+        all: false,
         release: options.flag_release,
-        mode: ops::CompileMode::Build,
-        filter: if examples.is_empty() && bins.is_empty() {
+        mode: if options.flag_lib_test {
+            ops::CompileMode::Test
+        } else {
+            ops::CompileMode::Build
+        },
+        filter: if options.flag_lib_test {
+            ops::CompileFilter::Only {
+                lib: true,
+                tests: &[],
+                benches: &[],
+                bins: &[],
+                examples: &[],
+            }
+        } else if examples.is_empty() && bins.is_empty() {
             ops::CompileFilter::Everything
         } else {
             ops::CompileFilter::Only {
@@ -96,12 +122,21 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
             }
         },
         message_format: options.flag_message_format,
+        future_incompat: false,
+        out_dir: None,
+        dump_fingerprints: false,
+        sanitizer: None,
+        build_plan: false,
         target_doc_args: None,
         target_cc_args: None,
     };
 
     let ws = Workspace::new(&root, config)?;
-    match ops::run(&ws, &compile_opts, &options.arg_args)? {
+    match ops::run(&ws,
+                   &compile_opts,
+                   &options.arg_args,
+                   &options.flag_env,
+                   options.flag_cwd.as_ref().map(|d| &d[..]))? {
         None => Ok(None),
         Some(err) => {
             // If we never actually spawned the process then that sounds pretty
@@ -0,0 +1,49 @@
+use craft::ops;
+use craft::util::{CliResult, Config};
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    arg_query: String,
+    flag_index: Option<String>,
+    flag_limit: Option<u32>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    flag_offline: bool,
+}
+
+pub const USAGE: &'static str = "
+Search packages in the registry
+
+Usage:
+    craft search [options] <query>
+
+Options:
+    -h, --help               Print this message
+    --index INDEX            Registry index to search in
+    --limit LIMIT            Limit the number of results (default: 10, max: 100)
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Craft.lock and cache are up to date
+    --locked                 Require Craft.lock is up to date
+    --offline                Run without accessing the network
+
+The registry is taken from `--index` or the `registry.index` config value.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline)?;
+    ops::search(config,
+                &options.arg_query,
+                options.flag_index,
+                options.flag_limit.unwrap_or(10))?;
+    Ok(None)
+}
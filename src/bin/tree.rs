@@ -0,0 +1,66 @@
+use craft::ops;
+use craft::workspace::Workspace;
+use craft::util::{CliResult, Config};
+use craft::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    flag_features: Vec<String>,
+    flag_all_features: bool,
+    flag_no_default_features: bool,
+    flag_invert: Option<String>,
+    flag_duplicates: bool,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_offline: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Display the dependency graph of a project as a tree
+
+Usage:
+    craft tree [options]
+
+Options:
+    -h, --help                 Print this message
+    --features FEATURES        Space-separated list of features to activate
+    --all-features             Activate all available features
+    --no-default-features      Do not activate the `default` feature
+    -i SPEC, --invert SPEC     Show the packages depending on the given package
+    -d, --duplicates           Show only the packages present at several
+                               versions and the packages depending on them
+    --manifest-path PATH       Path to the manifest of the project
+    -v, --verbose ...          Use verbose output
+    -q, --quiet                No output printed to stdout
+    --color WHEN               Coloring: auto, always, never
+    --frozen                   Require Craft.lock and cache are up to date
+    --locked                   Require Craft.lock is up to date
+    --offline                  Run without accessing the network
+
+Packages which were already displayed are not expanded again, they are marked
+with `(*)` instead.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    ops::tree(&ws,
+              &ops::TreeOptions {
+                  features: options.flag_features,
+                  all_features: options.flag_all_features,
+                  no_default_features: options.flag_no_default_features,
+                  invert: options.flag_invert,
+                  duplicates: options.flag_duplicates,
+              })?;
+    Ok(None)
+}
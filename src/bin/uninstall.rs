@@ -0,0 +1,52 @@
+use craft::ops;
+use craft::util::{CliResult, Config};
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    arg_spec: String,
+    flag_bin: Vec<String>,
+    flag_root: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Remove the binaries of an installed package
+
+Usage:
+    craft uninstall [options] <spec>
+
+Options:
+    -h, --help                Print this message
+    --root DIR                Directory to uninstall packages from
+    --bin NAME                Only uninstall the binary NAME
+    -v, --verbose ...         Use verbose output
+    -q, --quiet               Less output printed to stdout
+    --color WHEN              Coloring: auto, always, never
+    --frozen                  Require Craft.lock and cache are up to date
+    --locked                  Require Craft.lock is up to date
+
+The argument SPEC is a package id specification (see `craft help pkgid`) to
+specify which package should be uninstalled. By default all binaries are
+uninstalled for a package, `--bin` can be passed several times to only
+uninstall particular ones.
+
+The installation root is determined, in order of precedence, by `--root`,
+`$CRAFT_INSTALL_ROOT`, the `install.root` config value and `$CRAFT_HOME`.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     false)?;
+
+    let root = options.flag_root.as_ref().map(|s| &s[..]);
+    ops::uninstall(root, &options.arg_spec, &options.flag_bin, config)?;
+    Ok(None)
+}
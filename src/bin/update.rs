@@ -10,11 +10,13 @@ pub struct Options {
     flag_package: Vec<String>,
     flag_aggressive: bool,
     flag_precise: Option<String>,
+    flag_dry_run: bool,
     flag_manifest_path: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
 }
 
@@ -29,12 +31,14 @@ Options:
     -p SPEC, --package SPEC ...  Package to update
     --aggressive                 Force updating all dependencies of <name> as well
     --precise PRECISE            Update a single dependency to exactly PRECISE
+    --dry-run                    Print what would be updated without writing Craft.lock
     --manifest-path PATH         Path to the chest's manifest
     -v, --verbose ...            Use verbose output
     -q, --quiet                  No output printed to stdout
     --color WHEN                 Coloring: auto, always, never
     --frozen                     Require Craft.lock and cache are up to date
     --locked                     Require Craft.lock is up to date
+    --offline                    Run without accessing the network
 
 This command requires that a `Craft.lock` already exists as generated by
 `craft build` or related commands.
@@ -49,7 +53,8 @@ If PRECISE is specified, then --aggressive must not also be specified. The
 argument PRECISE is a string representing a precise revision that the package
 being updated should be updated to. For example, if the package comes from a git
 repository, then PRECISE would be the exact revision that the repository should
-be updated to.
+be updated to. For a registry package it has to be a version the registry
+provides.
 
 If SPEC is not given, then all dependencies will be re-resolved and
 updated.
@@ -64,13 +69,15 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
                           options.flag_quiet,
                           &options.flag_color,
                           options.flag_frozen,
-                          options.flag_locked)?;
+                          options.flag_locked,
+                          options.flag_offline)?;
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
 
     let update_opts = ops::UpdateOptions {
         aggressive: options.flag_aggressive,
         precise: options.flag_precise.as_ref().map(|s| &s[..]),
         to_update: &options.flag_package,
+        dry_run: options.flag_dry_run,
         config: config,
     };
 
@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use craft::ops;
+use craft::workspace::Workspace;
+use craft::util::{CliResult, Config};
+use craft::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    arg_path: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_offline: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Vendor all dependencies of a project locally
+
+Usage:
+    craft vendor [options] [<path>]
+
+Options:
+    -h, --help               Print this message
+    --manifest-path PATH     Path to the manifest of the project
+    -v, --verbose ...        Use verbose output
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Craft.lock and cache are up to date
+    --locked                 Require Craft.lock is up to date
+    --offline                Run without accessing the network
+
+The sources of all registry and git dependencies are copied into <path>,
+`vendor` by default, relative to the workspace root. Path dependencies are
+left alone. The printed configuration has to be added to `.craft/config` to
+build from the vendored sources.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    let path = options.arg_path.unwrap_or("vendor".to_string());
+    let snippet = ops::vendor(&ws, Path::new(&path))?;
+    println!("{}", snippet);
+    Ok(None)
+}
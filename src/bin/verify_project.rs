@@ -17,6 +17,7 @@ pub struct Flags {
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
     flag_frozen: bool,
+    flag_offline: bool,
     flag_locked: bool,
 }
 
@@ -35,6 +36,7 @@ Options:
     --color WHEN            Coloring: auto, always, never
     --frozen                Require Craft.lock and cache are up to date
     --locked                Require Craft.lock is up to date
+    --offline               Run without accessing the network
 ";
 
 pub fn execute(args: Flags, config: &Config) -> CliResult<Option<Error>> {
@@ -42,7 +44,8 @@ pub fn execute(args: Flags, config: &Config) -> CliResult<Option<Error>> {
                           args.flag_quiet,
                           &args.flag_color,
                           args.flag_frozen,
-                          args.flag_locked)?;
+                          args.flag_locked,
+                          args.flag_offline)?;
 
     let mut contents = String::new();
     let filename = args.flag_manifest_path.unwrap_or("Craft.toml".into());
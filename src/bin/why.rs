@@ -0,0 +1,65 @@
+use craft::ops;
+use craft::workspace::Workspace;
+use craft::util::{CliResult, Config};
+use craft::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(RustcDecodable)]
+pub struct Options {
+    arg_spec: String,
+    flag_features: Vec<String>,
+    flag_all_features: bool,
+    flag_no_default_features: bool,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_offline: bool,
+    flag_locked: bool,
+}
+
+pub const USAGE: &'static str = "
+Explain why a package is part of the dependency graph
+
+Usage:
+    craft why [options] <spec>
+
+Options:
+    -h, --help                 Print this message
+    --features FEATURES        Space-separated list of features to activate
+    --all-features             Activate all available features
+    --no-default-features      Do not activate the `default` feature
+    --manifest-path PATH       Path to the manifest of the project
+    -v, --verbose ...          Use verbose output
+    -q, --quiet                No output printed to stdout
+    --color WHEN               Coloring: auto, always, never
+    --frozen                   Require Craft.lock and cache are up to date
+    --locked                   Require Craft.lock is up to date
+    --offline                  Run without accessing the network
+
+Every path from the workspace to the packages matching <spec> is printed, along
+with the version requirements each package has on the next one. The
+requirements on the package itself which keep its version down the most are
+marked as binding.
+
+A version can be given to pick one of several versions, like `foo:1.2.3`.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     options.flag_offline)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    ops::why(&ws,
+             &ops::WhyOptions {
+                 features: options.flag_features,
+                 all_features: options.flag_all_features,
+                 no_default_features: options.flag_no_default_features,
+                 spec: options.arg_spec,
+             })?;
+    Ok(None)
+}
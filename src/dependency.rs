@@ -3,7 +3,7 @@ use std::fmt;
 use std::rc::Rc;
 use std::str::FromStr;
 
-use semver::{ReqParseError, VersionReq};
+use semver::{ReqParseError, Version, VersionReq};
 use rustc_serialize::{Encoder, Encodable};
 
 use package_id::PackageId;
@@ -28,6 +28,7 @@ pub struct DependencyInner {
     only_match_name: bool,
 
     optional: bool,
+    public: bool,
     default_features: bool,
     features: Vec<String>,
 
@@ -35,6 +36,27 @@ pub struct DependencyInner {
     platform: Option<Platform>,
 }
 
+/// A library of the system, like `z = { pkg-config = "zlib >= 1.2" }`. It isn't
+/// resolved to a package, its flags are queried from `pkg-config` when building.
+#[derive(PartialEq, Clone, Debug)]
+pub struct SystemDependency {
+    name: String,
+    module: String,
+    req: Option<String>,
+    link: Option<LinkKind>,
+
+    // This dependency should be used only for this platform. `None` means *all platforms*.
+    platform: Option<Platform>,
+}
+
+/// How a native library is linked, selected with `static=` or `dylib=` in
+/// front of its name or with the `link` key of a `pkg-config` dependency.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum LinkKind {
+    Static,
+    Dylib,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Platform {
     Name(String),
@@ -49,6 +71,7 @@ struct SerializedDependency<'a> {
     kind: Kind,
 
     optional: bool,
+    public: bool,
     uses_default_features: bool,
     features: &'a [String],
     target: Option<&'a Platform>,
@@ -62,6 +85,7 @@ impl Encodable for Dependency {
                 req: self.version_req().to_string(),
                 kind: self.kind(),
                 optional: self.is_optional(),
+                public: self.is_public(),
                 uses_default_features: self.uses_default_features(),
                 features: self.features(),
                 target: self.platform(),
@@ -104,7 +128,10 @@ impl DependencyInner {
                  deprecated_extra: Option<(&PackageId, &Config)>)
                  -> CraftResult<DependencyInner> {
         let (specified_req, version_req) = match version {
-            Some(v) => (true, DependencyInner::parse_with_deprecated(v, deprecated_extra)?),
+            Some(v) => {
+                check_satisfiable(v)?;
+                (true, DependencyInner::parse_with_deprecated(v, deprecated_extra)?)
+            }
             None => (false, VersionReq::any()),
         };
 
@@ -159,6 +186,7 @@ this warning.
             kind: Kind::Normal,
             only_match_name: true,
             optional: false,
+            public: false,
             features: Vec::new(),
             default_features: true,
             specified_req: false,
@@ -215,6 +243,13 @@ this warning.
         self
     }
 
+    /// Sets whether the dependency is public, i.e. its headers are visible to
+    /// dependents of the depending package as well.
+    pub fn set_public(mut self, public: bool) -> DependencyInner {
+        self.public = public;
+        self
+    }
+
     /// Set the source id for this dependency
     pub fn set_source_id(mut self, id: SourceId) -> DependencyInner {
         self.source_id = id;
@@ -259,6 +294,10 @@ this warning.
         self.optional
     }
 
+    pub fn is_public(&self) -> bool {
+        self.public
+    }
+
     /// Returns true if the default features of the dependency are requested.
     pub fn uses_default_features(&self) -> bool {
         self.default_features
@@ -277,7 +316,18 @@ this warning.
     /// Returns true if the package (`id`) can fulfill this dependency request.
     pub fn matches_id(&self, id: &PackageId) -> bool {
         self.name == id.name() &&
-        (self.only_match_name || (self.req.matches(id.version()) && &self.source_id == id.source_id()))
+        (self.only_match_name ||
+         (self.req.matches(id.version()) && self.allows_prerelease(id.version()) &&
+          &self.source_id == id.source_id()))
+    }
+
+    /// Pre-release versions are opt-in. `VersionReq::matches` already only lets
+    /// them fulfill requirements mentioning a pre-release of the same version,
+    /// except for `*`, which matches anything. So `*` doesn't accept them here,
+    /// unless the dependency has no version requirement and accepts whatever
+    /// version it points to.
+    fn allows_prerelease(&self, version: &Version) -> bool {
+        !version.is_prerelease() || !self.specified_req || self.req != VersionReq::any()
     }
 
     pub fn into_dependency(self) -> Dependency {
@@ -285,6 +335,74 @@ this warning.
     }
 }
 
+// `VersionReq` happily parses requirements no version can ever fulfill, like
+// `>=1.5, <1.2`, so check the bounds set by comparison operators up front.
+// Operators whose meaning changes for partial versions (`<=1.5` is `<1.6.0`)
+// are only taken into account for full versions.
+fn check_satisfiable(req: &str) -> CraftResult<()> {
+    if req.trim().is_empty() {
+        bail!("version requirement cannot be empty, use `*` to allow any version")
+    }
+
+    // The tightest bounds so far, along with whether they're inclusive
+    let mut lower: Option<(Version, bool)> = None;
+    let mut upper: Option<(Version, bool)> = None;
+    for comparator in req.split(',').map(str::trim) {
+        let op_len = comparator.find(|c: char| c.is_digit(10)).unwrap_or(0);
+        let (op, version) = (comparator[..op_len].trim(), &comparator[op_len..]);
+        let full = version.split('.').count() >= 3;
+        let version = match Version::parse(&pad_version(version)) {
+            Ok(v) => v,
+            Err(..) => continue,
+        };
+        let (lower_bound, upper_bound) = match op {
+            ">=" => (Some(true), None),
+            ">" => (Some(false), None),
+            "<" => (None, Some(false)),
+            "<=" if full => (None, Some(true)),
+            "=" if full => (Some(true), Some(true)),
+            _ => continue,
+        };
+        if let Some(inclusive) = lower_bound {
+            let tighter = match lower {
+                Some((ref v, inc)) => version > *v || (version == *v && inc && !inclusive),
+                None => true,
+            };
+            if tighter {
+                lower = Some((version.clone(), inclusive));
+            }
+        }
+        if let Some(inclusive) = upper_bound {
+            let tighter = match upper {
+                Some((ref v, inc)) => version < *v || (version == *v && inc && !inclusive),
+                None => true,
+            };
+            if tighter {
+                upper = Some((version, inclusive));
+            }
+        }
+    }
+
+    if let (Some((lo, lo_inclusive)), Some((hi, hi_inclusive))) = (lower, upper) {
+        if lo > hi || (lo == hi && !(lo_inclusive && hi_inclusive)) {
+            bail!("version requirement `{}` can never be satisfied", req)
+        }
+    }
+    Ok(())
+}
+
+// Fills up a partial version like `1.2` with zeros to a full `1.2.0`
+fn pad_version(version: &str) -> String {
+    let mut version = version.to_string();
+    let parts = version.split('-').next().unwrap().split('.').count();
+    if !version.contains('-') {
+        for _ in parts..3 {
+            version.push_str(".0");
+        }
+    }
+    version
+}
+
 impl Dependency {
     /// Attempt to create a `Dependency` from an entry in the manifest.
     pub fn parse(name: &str,
@@ -354,6 +472,12 @@ impl Dependency {
         self.inner.is_optional()
     }
 
+    /// Returns true if the headers of this dependency propagate to dependents
+    /// of the depending package.
+    pub fn is_public(&self) -> bool {
+        self.inner.is_public()
+    }
+
     /// Returns true if the default features of the dependency are requested.
     pub fn uses_default_features(&self) -> bool {
         self.inner.uses_default_features()
@@ -386,6 +510,94 @@ impl Dependency {
     }
 }
 
+impl SystemDependency {
+    /// Parses the `pkg-config` specification of the dependency `name`, which is
+    /// the name of the `pkg-config` module optionally followed by a version
+    /// comparison, like `zlib >= 1.2`. `link` selects how the library is
+    /// linked, leaving the choice to the linker if `None`.
+    pub fn parse(name: &str,
+                 spec: &str,
+                 link: Option<LinkKind>,
+                 platform: Option<Platform>)
+                 -> CraftResult<SystemDependency> {
+        let spec = spec.trim();
+        let (module, req) = match spec.find(char::is_whitespace) {
+            Some(pos) => (&spec[..pos], spec[pos..].trim()),
+            None => (spec, ""),
+        };
+        if module.is_empty() {
+            bail!("dependency ({}) specifies an empty `pkg-config` module", name)
+        }
+        let operators = ["<=", ">=", "!=", "=", "<", ">"];
+        if !req.is_empty() && !operators.iter().any(|op| req.starts_with(op)) {
+            bail!("the `pkg-config` version requirement `{}` of dependency ({}) must start with one of \
+                   `=`, `!=`, `<`, `<=`, `>` or `>=`",
+                  req,
+                  name)
+        }
+        Ok(SystemDependency {
+            name: name.to_string(),
+            module: module.to_string(),
+            req: if req.is_empty() { None } else { Some(req.to_string()) },
+            link: link,
+            platform: platform,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name of the `pkg-config` module of the library.
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    /// The required version of the library, like `>= 1.2`.
+    pub fn version_req(&self) -> Option<&str> {
+        self.req.as_ref().map(|s| &s[..])
+    }
+
+    /// How the library should be linked, `None` if it isn't specified.
+    pub fn link(&self) -> Option<LinkKind> {
+        self.link
+    }
+
+    pub fn platform(&self) -> Option<&Platform> {
+        self.platform.as_ref()
+    }
+}
+
+impl fmt::Display for SystemDependency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.req {
+            Some(ref req) => write!(f, "`{}` ({})", self.module, req),
+            None => write!(f, "`{}`", self.module),
+        }
+    }
+}
+
+impl FromStr for LinkKind {
+    type Err = Box<CraftError>;
+
+    fn from_str(s: &str) -> CraftResult<LinkKind> {
+        match s {
+            "static" => Ok(LinkKind::Static),
+            "dylib" => Ok(LinkKind::Dylib),
+            _ => bail!("unknown link kind `{}`, expected `static` or `dylib`", s),
+        }
+    }
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LinkKind::Static => "static".fmt(f),
+            LinkKind::Dylib => "dylib".fmt(f),
+        }
+    }
+}
+
 impl Platform {
     pub fn matches(&self, name: &str) -> bool {
         match *self {
@@ -422,3 +634,73 @@ impl fmt::Display for Platform {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use package_id::PackageId;
+    use source::SourceId;
+    use super::{DependencyInner, LinkKind, SystemDependency};
+
+    // Returns the highest of `versions` fulfilling `req`, like the resolver
+    // picks its candidates.
+    fn highest_match(req: &str, versions: &[&str]) -> Option<String> {
+        let source_id = SourceId::for_path(Path::new("/")).unwrap();
+        let dep = DependencyInner::parse("foo", Some(req), &source_id, None).unwrap();
+        versions.iter()
+            .map(|v| PackageId::new("foo", *v, &source_id).unwrap())
+            .filter(|id| dep.matches_id(id))
+            .map(|id| id.version().clone())
+            .max()
+            .map(|v| v.to_string())
+    }
+
+    #[test]
+    fn wildcard_selects_highest_stable() {
+        let versions = ["0.9.0", "1.0.0", "1.1.0-pre", "2.0.0-beta"];
+        assert_eq!(highest_match("*", &versions), Some("1.0.0".to_string()));
+    }
+
+    #[test]
+    fn prerelease_is_opt_in() {
+        let versions = ["1.0.0", "1.1.0-pre"];
+        assert_eq!(highest_match("^1.0.0", &versions), Some("1.0.0".to_string()));
+        assert_eq!(highest_match("^1.1.0-pre", &versions), Some("1.1.0-pre".to_string()));
+        assert_eq!(highest_match("^1.0.0", &["2.0.0-beta"]), None);
+    }
+
+    #[test]
+    fn compound_requirement() {
+        let versions = ["1.1.0", "1.2.0", "1.4.0", "1.5.0"];
+        assert_eq!(highest_match(">=1.2, <1.5", &versions), Some("1.4.0".to_string()));
+        assert_eq!(highest_match(">= 1.2, < 1.5", &["1.5.0"]), None);
+    }
+
+    #[test]
+    fn unsatisfiable_requirements_error() {
+        let source_id = SourceId::for_path(Path::new("/")).unwrap();
+        let parse = |req| DependencyInner::parse("foo", Some(req), &source_id, None);
+        assert!(parse("").is_err());
+        assert!(parse(">=1.5, <1.2").is_err());
+        assert!(parse(">1.2.0, <=1.2.0").is_err());
+        assert!(parse(">=1.2.0, <=1.2").is_ok());
+        assert!(parse("=1.2.0").is_ok());
+    }
+
+    #[test]
+    fn system_dependency_specification() {
+        let dep = SystemDependency::parse("z", " zlib  >= 1.2 ", None, None).unwrap();
+        assert_eq!(dep.module(), "zlib");
+        assert_eq!(dep.version_req(), Some(">= 1.2"));
+        assert_eq!(dep.to_string(), "`zlib` (>= 1.2)");
+
+        let dep = SystemDependency::parse("z", "zlib", Some(LinkKind::Static), None).unwrap();
+        assert_eq!(dep.version_req(), None);
+        assert_eq!(dep.to_string(), "`zlib`");
+        assert_eq!(dep.link(), Some(LinkKind::Static));
+
+        assert!(SystemDependency::parse("z", "", None, None).is_err());
+        assert!(SystemDependency::parse("z", "zlib 1.2", None, None).is_err());
+    }
+}
@@ -1,15 +1,20 @@
 //! Metadata description about packages
+use std::collections::HashMap;
 use std::fmt;
 use std::path::{PathBuf, Path};
+use std::str::FromStr;
 
 use semver::Version;
 use rustc_serialize::{Encoder, Encodable};
+use toml;
+use url::Url;
 
-use dependency::Dependency;
+use dependency::{Dependency, SystemDependency};
 use package_id::{PackageId, Metadata};
 use package_id_spec::PackageIdSpec;
 use source::SourceId;
 use summary::Summary;
+use util::{CraftError, CraftResult};
 use workspace::WorkspaceConfig;
 
 pub enum EitherManifest {
@@ -23,6 +28,8 @@ pub struct Manifest {
     summary: Summary,
     targets: Vec<Target>,
     links: Option<String>,
+    precompiled_header: Option<String>,
+    language: Language,
     warnings: Vec<String>,
     exclude: Vec<String>,
     include: Vec<String>,
@@ -30,12 +37,15 @@ pub struct Manifest {
     profiles: Profiles,
     publish: bool,
     replace: Vec<(PackageIdSpec, Dependency)>,
+    patch: HashMap<Url, Vec<Dependency>>,
+    system_dependencies: Vec<SystemDependency>,
     workspace: WorkspaceConfig,
 }
 
 #[derive(Clone, Debug)]
 pub struct VirtualManifest {
     replace: Vec<(PackageIdSpec, Dependency)>,
+    patch: HashMap<Url, Vec<Dependency>>,
     workspace: WorkspaceConfig,
     profiles: Profiles,
 }
@@ -59,6 +69,44 @@ pub struct ManifestMetadata {
     pub homepage: Option<String>, // url
     pub repository: Option<String>, // url
     pub documentation: Option<String>, // url
+    pub metadata: Option<toml::Value>, // opaque to craft, for external tools
+}
+
+/// The language the sources of a package are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    C,
+    Cpp,
+}
+
+impl Language {
+    /// The value of `package.language` in the manifest.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Language::C => "c",
+            Language::Cpp => "cpp",
+        }
+    }
+
+    /// The extension of the sources generated for this language.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Language::C => "c",
+            Language::Cpp => "cpp",
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = Box<CraftError>;
+
+    fn from_str(s: &str) -> CraftResult<Language> {
+        match s {
+            "c" => Ok(Language::C),
+            "cpp" => Ok(Language::Cpp),
+            _ => bail!("unknown language `{}`, expected `c` or `cpp`", s),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -163,23 +211,24 @@ pub struct Target {
     doctest: bool,
     harness: bool, // whether to use the test harness (--test)
     for_host: bool,
+    filename: Option<String>,
+    // Compiler flags and `-D` defines of only this target, the latter sorted by name and with an
+    // optional value.
+    cflags: Vec<String>,
+    defines: Vec<(String, Option<String>)>,
 }
 
 #[derive(RustcEncodable)]
-struct SerializedTarget<'a> {
+pub struct SerializedTarget<'a> {
     kind: &'a TargetKind,
     name: &'a str,
-    src_path: &'a str,
+    chest_name: String,
+    src_path: String,
 }
 
 impl Encodable for Target {
     fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
-        SerializedTarget {
-                kind: &self.kind,
-                name: &self.name,
-                src_path: &self.src_path.display().to_string(),
-            }
-            .encode(s)
+        self.serialized(Path::new("")).encode(s)
     }
 }
 
@@ -189,10 +238,14 @@ impl Manifest {
                exclude: Vec<String>,
                include: Vec<String>,
                links: Option<String>,
+               precompiled_header: Option<String>,
+               language: Language,
                metadata: ManifestMetadata,
                profiles: Profiles,
                publish: bool,
                replace: Vec<(PackageIdSpec, Dependency)>,
+               patch: HashMap<Url, Vec<Dependency>>,
+               system_dependencies: Vec<SystemDependency>,
                workspace: WorkspaceConfig)
                -> Manifest {
         Manifest {
@@ -202,10 +255,14 @@ impl Manifest {
             exclude: exclude,
             include: include,
             links: links,
+            precompiled_header: precompiled_header,
+            language: language,
             metadata: metadata,
             profiles: profiles,
             publish: publish,
             replace: replace,
+            patch: patch,
+            system_dependencies: system_dependencies,
             workspace: workspace,
         }
     }
@@ -262,10 +319,31 @@ impl Manifest {
         &self.replace
     }
 
+    /// The `[patch]` section, the dependencies overriding those of the source
+    /// with the given URL.
+    pub fn patch(&self) -> &HashMap<Url, Vec<Dependency>> {
+        &self.patch
+    }
+
     pub fn links(&self) -> Option<&str> {
         self.links.as_ref().map(|s| &s[..])
     }
 
+    /// The header to precompile, relative to the package root.
+    pub fn precompiled_header(&self) -> Option<&str> {
+        self.precompiled_header.as_ref().map(|s| &s[..])
+    }
+
+    /// The language of the package, `Language::C` unless configured.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// The libraries of the system found with `pkg-config`.
+    pub fn system_dependencies(&self) -> &[SystemDependency] {
+        &self.system_dependencies
+    }
+
     pub fn workspace_config(&self) -> &WorkspaceConfig {
         &self.workspace
     }
@@ -285,11 +363,13 @@ impl Manifest {
 
 impl VirtualManifest {
     pub fn new(replace: Vec<(PackageIdSpec, Dependency)>,
+               patch: HashMap<Url, Vec<Dependency>>,
                workspace: WorkspaceConfig,
                profiles: Profiles)
                -> VirtualManifest {
         VirtualManifest {
             replace: replace,
+            patch: patch,
             workspace: workspace,
             profiles: profiles,
         }
@@ -299,6 +379,10 @@ impl VirtualManifest {
         &self.replace
     }
 
+    pub fn patch(&self) -> &HashMap<Url, Vec<Dependency>> {
+        &self.patch
+    }
+
     pub fn workspace_config(&self) -> &WorkspaceConfig {
         &self.workspace
     }
@@ -321,6 +405,9 @@ impl Target {
             for_host: false,
             tested: true,
             benched: true,
+            filename: None,
+            cflags: Vec::new(),
+            defines: Vec::new(),
         }
     }
 
@@ -397,10 +484,26 @@ impl Target {
         &self.name
     }
 
+    /// The on-disk name of the produced executable, if it differs from the
+    /// target name.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_ref().map(|s| &s[..])
+    }
+
     pub fn chest_name(&self) -> String {
         self.name.replace("-", "_")
     }
 
+    /// The serialized form of this target, with its source path joined to `root`
+    pub fn serialized(&self, root: &Path) -> SerializedTarget {
+        SerializedTarget {
+            kind: &self.kind,
+            name: &self.name,
+            chest_name: self.chest_name(),
+            src_path: root.join(&self.src_path).display().to_string(),
+        }
+    }
+
     pub fn src_path(&self) -> &Path {
         &self.src_path
     }
@@ -421,6 +524,14 @@ impl Target {
         self.harness
     }
 
+    pub fn cflags(&self) -> &[String] {
+        &self.cflags
+    }
+
+    pub fn defines(&self) -> &[(String, Option<String>)] {
+        &self.defines
+    }
+
     pub fn documented(&self) -> bool {
         self.doc
     }
@@ -475,6 +586,22 @@ impl Target {
         self.kind == TargetKind::CustomBuild
     }
 
+    /// Returns whether the source of this target is a C++ translation unit.
+    pub fn is_cxx(&self) -> bool {
+        match self.src_path.extension().and_then(|e| e.to_str()) {
+            Some("cc") | Some("cpp") | Some("cxx") | Some("c++") | Some("C") => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether the source of this target is written in assembly.
+    pub fn is_asm(&self) -> bool {
+        match self.src_path.extension().and_then(|e| e.to_str()) {
+            Some("s") | Some("S") => true,
+            _ => false,
+        }
+    }
+
     /// Returns the arguments suitable for `--crate-type` to pass to cc.
     pub fn cc_chest_types(&self) -> Vec<&str> {
         match self.kind {
@@ -521,6 +648,21 @@ impl Target {
         self.doc = doc;
         self
     }
+
+    pub fn set_filename(&mut self, filename: Option<String>) -> &mut Target {
+        self.filename = filename;
+        self
+    }
+
+    pub fn set_cflags(&mut self, cflags: Vec<String>) -> &mut Target {
+        self.cflags = cflags;
+        self
+    }
+
+    pub fn set_defines(&mut self, defines: Vec<(String, Option<String>)>) -> &mut Target {
+        self.defines = defines;
+        self
+    }
 }
 
 impl fmt::Display for Target {
@@ -1,21 +1,23 @@
-use std::collections::{HashSet, HashMap, BTreeSet};
+use std::collections::{HashSet, HashMap, BTreeMap, BTreeSet};
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::str;
 use std::sync::Arc;
 
 use dependency::Dependency;
 use dependency::Kind as DepKind;
-use manifest::{Target, Profile, TargetKind, Profiles};
+use manifest::{Language, Target, Profile, TargetKind, Profiles};
 use package::{Package, PackageSet};
 use package_id::{PackageId, Metadata};
 use resolver::Resolve;
 use util::{CraftResult, ChainError, internal, Config, profile};
+use util::machine_message::Invocation;
 use workspace::Workspace;
 
 use super::TargetConfig;
-use super::custom_build::{BuildState, BuildScripts};
-use super::fingerprint::Fingerprint;
+use super::custom_build::{BuildMap, BuildState, BuildScripts};
+use super::fingerprint::{Fingerprint, HeaderGraph};
 use super::layout::{Layout, LayoutProxy};
 use super::links::Links;
 use super::{Kind, Compilation, BuildConfig};
@@ -31,19 +33,29 @@ pub struct Unit<'a> {
 pub struct Context<'a, 'cfg: 'a> {
     pub config: &'cfg Config,
     pub resolve: &'a Resolve,
-    pub current_package: PackageId,
+    /// The packages built by default in the workspace, as opposed to their
+    /// dependencies
+    pub primary_packages: HashSet<PackageId>,
+    /// All the packages of the workspace, built by default or not
+    pub members: HashSet<PackageId>,
     pub compilation: Compilation<'cfg>,
     pub packages: &'a PackageSet<'cfg>,
     pub build_state: Arc<BuildState>,
     pub build_explicit_deps: HashMap<Unit<'a>, (PathBuf, Vec<String>)>,
     pub fingerprints: HashMap<Unit<'a>, Arc<Fingerprint>>,
+    pub header_graph: HeaderGraph,
     pub compiled: HashSet<Unit<'a>>,
     pub build_config: BuildConfig,
     pub build_scripts: HashMap<Unit<'a>, Arc<BuildScripts>>,
+    /// The flags of the system libraries found with `pkg-config`
+    pub pkg_config: BuildMap,
     pub links: Links<'a>,
+    /// The commands planned for `--build-plan` instead of running them
+    pub build_plan: Vec<Invocation>,
     pub used_in_plugin: HashSet<Unit<'a>>,
 
     host: Layout,
+    warned_header_collisions: HashSet<String>,
     target: Option<Layout>,
     target_info: TargetInfo,
     host_info: TargetInfo,
@@ -75,12 +87,15 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             None => None,
         };
 
-        let current_package = ws.current()?.package_id().clone();
+        let primary_packages = ws.default_members().map(|p| p.package_id().clone()).collect();
+        let members = ws.members().map(|p| p.package_id().clone()).collect();
+        let header_graph = HeaderGraph::load(ws.target_dir().into_path_unlocked().join(".craft-header-graph"));
         Ok(Context {
             host: host_layout,
             target: target_layout,
             resolve: resolve,
-            current_package: current_package,
+            primary_packages: primary_packages,
+            members: members,
             packages: packages,
             config: config,
             target_info: TargetInfo::default(),
@@ -89,12 +104,16 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             build_state: Arc::new(BuildState::new(&build_config)),
             build_config: build_config,
             fingerprints: HashMap::new(),
+            header_graph: header_graph,
             profiles: profiles,
             compiled: HashSet::new(),
             build_scripts: HashMap::new(),
+            pkg_config: HashMap::new(),
             build_explicit_deps: HashMap::new(),
             links: Links::new(),
+            build_plan: Vec::new(),
             used_in_plugin: HashSet::new(),
+            warned_header_collisions: HashSet::new(),
         })
     }
 
@@ -195,7 +214,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
 
     /// Returns the appropriate directory layout for either a plugin or not.
     pub fn layout(&self, unit: &Unit) -> LayoutProxy {
-        let primary = unit.pkg.package_id() == &self.current_package;
+        let primary = self.is_primary_package(unit.pkg.package_id());
         match unit.kind {
             Kind::Host => LayoutProxy::new(&self.host, primary),
             Kind::Target => {
@@ -232,6 +251,21 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         self.build_config.requested_target.as_ref().map(|s| &s[..])
     }
 
+    /// Returns the features activated for the package of `unit`.
+    ///
+    /// When cross compiling, packages built for the host because of build
+    /// dependencies don't share the features requested for the target and
+    /// vice versa.
+    pub fn unit_features(&self, unit: &Unit) -> Option<&'a HashSet<String>> {
+        let resolve = self.resolve;
+        let id = unit.pkg.package_id();
+        if self.requested_target().is_none() || unit.target.is_custom_build() {
+            return resolve.features(id);
+        }
+        resolve.features_for(id, unit.kind == Kind::Host)
+            .or_else(|| resolve.features(id))
+    }
+
     /// Get the metadata for a target in a specific profile
     pub fn target_metadata(&self, unit: &Unit) -> Option<Metadata> {
         let metadata = unit.target.metadata();
@@ -283,7 +317,9 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
     pub fn file_stem(&self, unit: &Unit) -> String {
         match self.target_metadata(unit) {
             Some(ref metadata) => format!("{}{}", unit.target.chest_name(), metadata.extra_filename),
-            None if unit.target.allows_underscores() => unit.target.name().to_string(),
+            None if unit.target.allows_underscores() => {
+                unit.target.filename().unwrap_or(unit.target.name()).to_string()
+            }
             None => unit.target.chest_name(),
         }
     }
@@ -385,7 +421,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                         // If the dependency is optional, then we're only activating it
                         // if the corresponding feature was activated
                         if d.is_optional() {
-                            match self.resolve.features(id) {
+                            match self.unit_features(unit) {
                                 Some(f) if f.contains(d.name()) => {}
                                 _ => return false,
                             }
@@ -403,7 +439,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                             Ok(Unit {
                                 pkg: pkg,
                                 target: t,
-                                profile: self.lib_profile(id),
+                                profile: self.lib_profile(),
                                 kind: unit.kind.for_target(t),
                             })
                         })
@@ -436,7 +472,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                 Unit {
                     pkg: unit.pkg,
                     target: t,
-                    profile: self.lib_profile(id),
+                    profile: self.lib_profile(),
                     kind: unit.kind.for_target(t),
                 }
             }));
@@ -519,7 +555,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             ret.push(Unit {
                 pkg: dep,
                 target: lib,
-                profile: self.lib_profile(dep.package_id()),
+                profile: self.lib_profile(),
                 kind: unit.kind.for_target(lib),
             });
             if self.build_config.doc_all {
@@ -565,7 +601,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             Unit {
                 pkg: unit.pkg,
                 target: t,
-                profile: self.lib_profile(unit.pkg.package_id()),
+                profile: self.lib_profile(),
                 kind: unit.kind.for_target(t),
             }
         })
@@ -595,11 +631,94 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         self.target_config(kind).linker.as_ref().map(|s| s.as_ref())
     }
 
+    /// Whether a particular host or target links with Apple's ld64, which
+    /// doesn't know the `-Bstatic` and `-Bdynamic` flags of GNU ld.
+    pub fn links_with_ld64(&self, kind: Kind) -> bool {
+        let triple = match kind {
+            Kind::Host => self.host_triple(),
+            Kind::Target => self.target_triple(),
+        };
+        triple.contains("-apple-")
+    }
+
     /// Get the user-specified `ar` program for a particular host or target
     pub fn ar(&self, kind: Kind) -> Option<&Path> {
         self.target_config(kind).ar.as_ref().map(|s| s.as_ref())
     }
 
+    /// Get the `ranlib` program to index the static libraries of a particular
+    /// host or target with, if any, and whether it always runs.
+    ///
+    /// A user-specified one always runs. Otherwise the `ranlib` found in `PATH`
+    /// only runs where the platform requires an index, and only on archives
+    /// which `ar` didn't write one for.
+    pub fn ranlib(&self, kind: Kind) -> Option<(PathBuf, bool)> {
+        if let Some(ref ranlib) = self.target_config(kind).ranlib {
+            return Some((ranlib.clone(), true));
+        }
+        let triple = match kind {
+            Kind::Host => self.host_triple(),
+            Kind::Target => self.target_triple(),
+        };
+        if !triple.contains("-apple-") {
+            return None;
+        }
+        env::var_os("PATH")
+            .and_then(|paths| env::split_paths(&paths).map(|dir| dir.join("ranlib")).find(|p| p.is_file()))
+            .map(|ranlib| (ranlib, false))
+    }
+
+    /// Get the resource compiler for the `.rc` files of a particular host or
+    /// target, which is only needed when targeting Windows.
+    pub fn rc(&self, kind: Kind) -> Option<PathBuf> {
+        if let Some(ref rc) = self.target_config(kind).rc {
+            return Some(rc.clone());
+        }
+        let triple = match kind {
+            Kind::Host => self.host_triple(),
+            Kind::Target => self.target_triple(),
+        };
+        if triple.ends_with("-windows-msvc") {
+            Some(PathBuf::from("rc"))
+        } else if triple.ends_with("-windows-gnu") {
+            Some(PathBuf::from("windres"))
+        } else {
+            None
+        }
+    }
+
+    /// Get the driver which should be used to link `unit`, if it differs from
+    /// the default compiler.
+    ///
+    /// An explicitly configured `build.link-driver` always wins, otherwise the
+    /// C++ driver is selected as soon as a C++ object takes part in the link.
+    pub fn link_driver(&self, unit: &Unit<'a>) -> CraftResult<Option<PathBuf>> {
+        if unit.target.is_lib() && !unit.profile.test {
+            return Ok(None);
+        }
+        if let Some(ref driver) = self.build_config.link_driver {
+            return Ok(Some(driver.clone()));
+        }
+        if self.uses_cxx(unit)? {
+            return Ok(Some(self.config.cxx()?.to_path_buf()));
+        }
+        Ok(None)
+    }
+
+    /// Returns whether `unit` or any library it links against was compiled
+    /// from a C++ source, or belongs to a package written in C++.
+    fn uses_cxx(&self, unit: &Unit<'a>) -> CraftResult<bool> {
+        if unit.target.is_cxx() || unit.pkg.manifest().language() == Language::Cpp {
+            return Ok(true);
+        }
+        for dep in self.dep_targets(unit)? {
+            if dep.target.linkable() && !dep.profile.doc && self.uses_cxx(&dep)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     /// Get the target configuration for a particular host or target
     fn target_config(&self, kind: Kind) -> &TargetConfig {
         match kind {
@@ -613,7 +732,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         self.build_config.jobs
     }
 
-    pub fn lib_profile(&self, _pkg: &PackageId) -> &'a Profile {
+    pub fn lib_profile(&self) -> &'a Profile {
         let (normal, test) = if self.build_config.release {
             (&self.profiles.release, &self.profiles.bench_deps)
         } else {
@@ -622,22 +741,140 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         if self.build_config.test { test } else { normal }
     }
 
-    pub fn build_script_profile(&self, pkg: &PackageId) -> &'a Profile {
+    pub fn build_script_profile(&self, _pkg: &PackageId) -> &'a Profile {
         // TODO: should build scripts always be built with the same library
         //       profile? How is this controlled at the CLI layer?
-        self.lib_profile(pkg)
+        self.lib_profile()
     }
 
     pub fn cflags_args(&self, unit: &Unit) -> CraftResult<Vec<String>> {
         env_args(self.config, &self.build_config, unit.kind, "CFLAGS")
     }
 
+    pub fn asflags_args(&self, unit: &Unit) -> CraftResult<Vec<String>> {
+        env_args(self.config, &self.build_config, unit.kind, "ASFLAGS")
+    }
+
+    /// Returns the extra flags for compiling `unit`, which are `ASFLAGS` for
+    /// assembly sources and `CFLAGS` otherwise.
+    pub fn compile_flags_args(&self, unit: &Unit) -> CraftResult<Vec<String>> {
+        let mut args = if unit.target.is_asm() {
+            self.asflags_args(unit)?
+        } else {
+            self.cflags_args(unit)?
+        };
+        args.extend(self.sanitizer_args(unit));
+        Ok(args)
+    }
+
+    /// Returns the flags instrumenting `unit` with the sanitizer selected by
+    /// `--sanitizer`, which apply to the same units as `CFLAGS` do.
+    ///
+    /// As the compiler links the unit as well, they also pull in the runtime
+    /// of the sanitizer.
+    pub fn sanitizer_args(&self, unit: &Unit) -> Vec<String> {
+        let sanitizer = match self.build_config.sanitizer {
+            Some(ref sanitizer) if target_flags_apply(&self.build_config, unit.kind) => sanitizer,
+            _ => return Vec::new(),
+        };
+        let mut args = vec![format!("-fsanitize={}", sanitizer)];
+        if sanitizer == "address" {
+            args.push("-fno-omit-frame-pointer".to_string());
+        }
+        args
+    }
+
     pub fn docflags_args(&self, unit: &Unit) -> CraftResult<Vec<String>> {
         env_args(self.config, &self.build_config, unit.kind, "DOCFLAGS")
     }
 
+    /// Returns the `include` directories of the dependencies of `unit`, along
+    /// with the packages exposing them, in the order they are searched.
+    ///
+    /// The `include` directory of a package holds the headers it exposes.
+    /// Those of direct dependencies are always visible, whereas the ones of
+    /// indirect dependencies are only if every dependency on the way is
+    /// `public`.
+    pub fn include_dirs(&self, unit: &Unit<'a>) -> CraftResult<Vec<(&'a Package, PathBuf)>> {
+        let mut dirs = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending = self.dep_targets(unit)?;
+        while !pending.is_empty() {
+            let dep = pending.remove(0);
+            if !dep.target.linkable() || dep.profile.doc || !visited.insert(dep.pkg.package_id()) {
+                continue;
+            }
+            let dir = dep.pkg.root().join("include");
+            if dir.is_dir() {
+                dirs.push((dep.pkg, dir));
+            }
+            for next in self.dep_targets(&dep)? {
+                let public = dep.pkg
+                    .dependencies()
+                    .iter()
+                    .any(|d| d.is_public() && d.matches_id(next.pkg.package_id()));
+                if public {
+                    pending.push(next);
+                }
+            }
+        }
+        Ok(dirs)
+    }
+
+    /// Warns about headers of the same name exposed by more than one of the
+    /// include directories of `unit`, as only the first one found is ever
+    /// included. Each collision is only reported once per build.
+    pub fn warn_header_collisions(&mut self, unit: &Unit<'a>) -> CraftResult<()> {
+        let mut exporters: BTreeMap<PathBuf, Vec<&PackageId>> = BTreeMap::new();
+        for (pkg, dir) in self.include_dirs(unit)? {
+            for header in headers(&dir)? {
+                exporters.entry(header).or_insert(Vec::new()).push(pkg.package_id());
+            }
+        }
+        for (header, pkgs) in exporters {
+            if pkgs.len() < 2 {
+                continue;
+            }
+            let msg = format!("header `{}` is exported by multiple dependencies of `{}`, only the one of `{}` \
+                               is used: {}",
+                              header.display(),
+                              unit.pkg.name(),
+                              pkgs[0],
+                              pkgs.iter().map(|p| format!("`{}`", p)).collect::<Vec<_>>().join(", "));
+            if self.warned_header_collisions.insert(msg.clone()) {
+                self.config.shell().warn(msg)?;
+            }
+        }
+        return Ok(());
+
+        // The paths of all the headers below `dir`, relative to it.
+        fn headers(dir: &Path) -> CraftResult<Vec<PathBuf>> {
+            let mut headers = Vec::new();
+            let mut pending = vec![dir.to_path_buf()];
+            while let Some(next) = pending.pop() {
+                for entry in fs::read_dir(&next)? {
+                    let path = entry?.path();
+                    if path.is_dir() {
+                        pending.push(path);
+                    } else if path.extension().and_then(|e| e.to_str()).map_or(false, |e| e == "h" || e == "hpp") {
+                        headers.push(path.strip_prefix(dir).unwrap().to_path_buf());
+                    }
+                }
+            }
+            Ok(headers)
+        }
+    }
+
+    pub fn is_primary_package(&self, pkg: &PackageId) -> bool {
+        self.primary_packages.contains(pkg)
+    }
+
+    pub fn is_member(&self, pkg: &PackageId) -> bool {
+        self.members.contains(pkg)
+    }
+
     pub fn show_warnings(&self, pkg: &PackageId) -> bool {
-        pkg == &self.current_package || pkg.source_id().is_path() || self.config.extra_verbose()
+        self.is_primary_package(pkg) || pkg.source_id().is_path() || self.config.extra_verbose()
     }
 }
 
@@ -663,12 +900,13 @@ fn env_args(config: &Config, build_config: &BuildConfig, kind: Kind, name: &str)
     // This means that, e.g. even if the specified --target is the
     // same as the host, build scripts in plugins won't get
     // CFLAGS.
-    let compiling_with_target = build_config.requested_target.is_some();
-    let is_target_kind = kind == Kind::Target;
-
-    if compiling_with_target && !is_target_kind {
-        // This is probably a build script or plugin and we're
-        // compiling with --target. In this scenario there are
+    //
+    // The `target-applies-to-host` config value overrides this: if set to
+    // true the target flags are applied to host builds as well, and if set
+    // to false host builds never get them, not even without --target.
+    if !target_flags_apply(build_config, kind) {
+        // This is probably a build script or plugin which is kept
+        // apart from the target flags. In this scenario there are
         // no cflags we can apply.
         return Ok(Vec::new());
     }
@@ -700,3 +938,11 @@ fn env_args(config: &Config, build_config: &BuildConfig, kind: Kind, name: &str)
 
     Ok(Vec::new())
 }
+
+// Whether the flags for the target, like `CFLAGS`, apply to units of `kind`,
+// see `env_args`.
+fn target_flags_apply(build_config: &BuildConfig, kind: Kind) -> bool {
+    let compiling_with_target = build_config.requested_target.is_some();
+    let applies_to_host = build_config.target_applies_to_host.unwrap_or(!compiling_with_target);
+    kind == Kind::Target || applies_to_host
+}
@@ -1,28 +1,48 @@
 use std::collections::{HashMap, BTreeSet, HashSet};
+use std::env;
+use std::fmt;
 use std::fs;
 use std::path::{PathBuf, Path};
 use std::str;
 use std::sync::{Mutex, Arc};
 
+use dependency::LinkKind;
 use package_id::PackageId;
-use util::{CraftResult, Human, Freshness, internal, ChainError, profile, paths};
+use util::{self, CraftResult, Human, Freshness, human, internal, ChainError, profile, paths, machine_message};
 
 use super::job::Work;
 use super::{fingerprint, Kind, Context, Unit};
 
 /// Contains the parsed output of a custom build script.
-#[derive(Clone, Debug, Hash)]
+#[derive(Clone, Debug, Default, Hash)]
 pub struct BuildOutput {
     /// Paths to pass to cc with the `-L` flag
     pub library_paths: Vec<PathBuf>,
     /// Names and link kinds of libraries, suitable for the `-l` flag
-    pub library_links: Vec<String>,
+    pub library_links: Vec<LibraryLink>,
+    /// Defines to pass to the compiler, as `NAME` or `NAME=VALUE`
+    pub cfgs: Vec<String>,
+    /// Additional environment variables to run the compiler with.
+    pub env: Vec<(String, String)>,
     /// Metadata to pass to the immediate dependencies
     pub metadata: Vec<(String, String)>,
     /// Glob paths to trigger a rerun of this build script.
     pub rerun_if_changed: Vec<String>,
     /// Warnings generated by this build,
     pub warnings: Vec<String>,
+    /// Generated sources to compile along with the package's own sources,
+    /// relative ones are looked up in `OUT_DIR`
+    pub compile_sources: Vec<PathBuf>,
+    /// Flags to compile the package with, like `-I` and `-D`
+    pub cflags: Vec<String>,
+}
+
+/// A native library to link, written as `[KIND=]NAME` like `static=foo`.
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub struct LibraryLink {
+    pub name: String,
+    /// How the library is linked, left to the linker if `None`
+    pub kind: Option<LinkKind>,
 }
 
 pub type BuildMap = HashMap<(PackageId, Kind), BuildOutput>;
@@ -87,7 +107,7 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftRes
     // environment variables. Note that the profile-related environment
     // variables are not set with this the build script's profile but rather the
     // package's library profile.
-    let profile = cx.lib_profile(unit.pkg.package_id());
+    let profile = cx.lib_profile();
     let to_exec = to_exec.into_os_string();
     let mut cmd = cx.compilation.host_process(to_exec, unit.pkg)?;
     cmd.env("OUT_DIR", &build_output)
@@ -116,7 +136,7 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftRes
 
     // Be sure to pass along all enabled features for this package, this is the
     // last piece of statically known information that we have.
-    if let Some(features) = cx.resolve.features(unit.pkg.package_id()) {
+    if let Some(features) = cx.unit_features(unit) {
         for feat in features.iter() {
             cmd.env(&format!("CRAFT_FEATURE_{}", super::envify(feat)), "1");
         }
@@ -139,17 +159,28 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftRes
             })
             .collect::<Vec<_>>()
     };
+    if cx.build_config.build_plan {
+        // The metadata of the build scripts of dependencies is passed on
+        // in the environment once they ran.
+        let mut invocation = machine_message::Invocation::new(unit.pkg.package_id(), unit.target, &cmd);
+        invocation.input(cmd.get_program())
+            .output(&build_output)
+            .unknown_until_run(!lib_deps.is_empty());
+        cx.build_plan.push(invocation);
+    }
+
     let pkg_name = unit.pkg.to_string();
     let build_state = cx.build_state.clone();
     let id = unit.pkg.package_id().clone();
     let output_file = build_output.parent().unwrap().join("output");
-    let all = (id.clone(), pkg_name.clone(), build_state.clone(), output_file.clone());
+    let all = (id.clone(), pkg_name.clone(), build_state.clone(), output_file.clone(), build_output.clone());
     let build_scripts = super::load_build_deps(cx, unit);
     let kind = unit.kind;
+    let json_messages = cx.build_config.json_errors;
 
     // Check to see if the build script as already run, and if it has keep
     // track of whether it has told us about some explicit dependencies
-    let prev_output = BuildOutput::parse_file(&output_file, &pkg_name).ok();
+    let prev_output = BuildOutput::parse_file(&output_file, &pkg_name, &build_output).ok();
     let rerun_if_changed = match prev_output {
         Some(ref prev) => prev.rerun_if_changed.clone(),
         None => Vec::new(),
@@ -208,7 +239,8 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftRes
                                  &mut |err_line| {
                                      state.stderr(err_line);
                                      Ok(())
-                                 })
+                                 },
+                                 true)
             .map_err(|mut e| {
                 e.desc = format!("failed to run custom build command for `{}`\n{}",
                                  pkg_name,
@@ -224,7 +256,11 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftRes
         // This is also the location where we provide feedback into the build
         // state informing what variables were discovered via our script as
         // well.
-        let parsed_output = BuildOutput::parse(&output.stdout, &pkg_name)?;
+        let parsed_output = BuildOutput::parse(&output.stdout, &pkg_name, &build_output)?;
+
+        if json_messages {
+            machine_message::BuildScript::new(&id, &parsed_output).emit();
+        }
         build_state.insert(id, kind, parsed_output);
         Ok(())
     });
@@ -233,11 +269,15 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftRes
     // itself to run when we actually end up just discarding what we calculated
     // above.
     let fresh = Work::new(move |_tx| {
-        let (id, pkg_name, build_state, output_file) = all;
+        let (id, pkg_name, build_state, output_file, build_output) = all;
         let output = match prev_output {
             Some(output) => output,
-            None => BuildOutput::parse_file(&output_file, &pkg_name)?,
+            None => BuildOutput::parse_file(&output_file, &pkg_name, &build_output)?,
         };
+
+        if json_messages {
+            machine_message::BuildScript::new(&id, &output).emit();
+        }
         build_state.insert(id, kind, output);
         Ok(())
     });
@@ -276,19 +316,23 @@ impl BuildState {
 }
 
 impl BuildOutput {
-    pub fn parse_file(path: &Path, pkg_name: &str) -> CraftResult<BuildOutput> {
+    pub fn parse_file(path: &Path, pkg_name: &str, out_dir: &Path) -> CraftResult<BuildOutput> {
         let contents = paths::read_bytes(path)?;
-        BuildOutput::parse(&contents, pkg_name)
+        BuildOutput::parse(&contents, pkg_name, out_dir)
     }
 
     // Parses the output of a script.
-    // The `pkg_name` is used for error messages.
-    pub fn parse(input: &[u8], pkg_name: &str) -> CraftResult<BuildOutput> {
+    // The `pkg_name` is used for error messages, relative sources to compile
+    // are taken to be in the `out_dir` of the script.
+    pub fn parse(input: &[u8], pkg_name: &str, out_dir: &Path) -> CraftResult<BuildOutput> {
         let mut library_paths = Vec::new();
         let mut library_links = Vec::new();
+        let mut cfgs = Vec::new();
+        let mut env = Vec::new();
         let mut metadata = Vec::new();
         let mut rerun_if_changed = Vec::new();
         let mut warnings = Vec::new();
+        let mut compile_sources = Vec::new();
         let whence = format!("build script of `{}`", pkg_name);
 
         for line in input.split(|b| *b == b'\n') {
@@ -322,10 +366,13 @@ impl BuildOutput {
                     library_links.extend(links.into_iter());
                     library_paths.extend(libs.into_iter());
                 }
-                "cc-link-lib" => library_links.push(value.to_string()),
+                "cc-link-lib" => library_links.push(LibraryLink::parse(value, &whence)?),
                 "cc-link-search" => library_paths.push(PathBuf::from(value)),
+                "cc-cfg" => cfgs.push(value.to_string()),
+                "cc-env" => env.push(BuildOutput::parse_cc_env(value, &whence)?),
                 "warning" => warnings.push(value.to_string()),
                 "rerun-if-changed" => rerun_if_changed.push(value.to_string()),
+                "compile-source" => compile_sources.push(out_dir.join(value)),
                 _ => metadata.push((key.to_string(), value.to_string())),
             }
         }
@@ -333,13 +380,17 @@ impl BuildOutput {
         Ok(BuildOutput {
             library_paths: library_paths,
             library_links: library_links,
+            cfgs: cfgs,
+            env: env,
             metadata: metadata,
             rerun_if_changed: rerun_if_changed,
             warnings: warnings,
+            compile_sources: compile_sources,
+            cflags: Vec::new(),
         })
     }
 
-    pub fn parse_cc_flags(value: &str, whence: &str) -> CraftResult<(Vec<PathBuf>, Vec<String>)> {
+    pub fn parse_cc_flags(value: &str, whence: &str) -> CraftResult<(Vec<PathBuf>, Vec<LibraryLink>)> {
         let value = value.trim();
         let mut flags_iter = value.split(|c: char| c.is_whitespace())
             .filter(|w| w.chars().any(|c| !c.is_whitespace()));
@@ -363,7 +414,7 @@ impl BuildOutput {
                 }
             };
             match flag {
-                "-l" => library_links.push(value.to_string()),
+                "-l" => library_links.push(LibraryLink::parse(value, whence)?),
                 "-L" => library_paths.push(PathBuf::from(value)),
 
                 // was already checked above
@@ -372,6 +423,117 @@ impl BuildOutput {
         }
         Ok((library_paths, library_links))
     }
+
+    pub fn parse_cc_env(value: &str, whence: &str) -> CraftResult<(String, String)> {
+        let mut iter = value.splitn(2, '=');
+        let name = iter.next();
+        let val = iter.next();
+        match (name, val) {
+            (Some(n), Some(v)) => Ok((n.to_owned(), v.to_owned())),
+            _ => bail!("Variable cc-env has no value in {}: {}", whence, value),
+        }
+    }
+}
+
+impl LibraryLink {
+    /// Parses a library given as `[KIND=]NAME`, where `KIND` is either
+    /// `static` or `dylib`. The `whence` is used for error messages.
+    pub fn parse(value: &str, whence: &str) -> CraftResult<LibraryLink> {
+        let mut iter = value.rsplitn(2, '=');
+        let name = iter.next().unwrap_or("");
+        let kind = match iter.next() {
+            Some(kind) => {
+                Some(kind.parse::<LinkKind>()
+                    .chain_error(|| human(format!("Invalid library `{}` in {}", value, whence)))?)
+            }
+            None => None,
+        };
+        if name.is_empty() {
+            bail!("Library without a name in {}: `{}`", whence, value)
+        }
+        Ok(LibraryLink {
+            name: name.to_string(),
+            kind: kind,
+        })
+    }
+}
+
+impl fmt::Display for LibraryLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            Some(kind) => write!(f, "{}={}", kind, self.name),
+            None => self.name.fmt(f),
+        }
+    }
+}
+
+/// Queries `pkg-config` for the flags of the system libraries the package of
+/// `unit` depends on, and records them in `cx.pkg_config`.
+///
+/// The `PKG_CONFIG` environment variable overrides the `pkg-config` to run. A
+/// build plan doesn't run anything, so no flags are recorded for it.
+pub fn pkg_config<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftResult<()> {
+    let key = (unit.pkg.package_id().clone(), unit.kind);
+    if cx.pkg_config.contains_key(&key) {
+        return Ok(());
+    }
+    if cx.build_config.build_plan {
+        cx.pkg_config.insert(key, BuildOutput::default());
+        return Ok(());
+    }
+    let triple = match unit.kind {
+            Kind::Host => cx.host_triple(),
+            Kind::Target => cx.target_triple(),
+        }
+        .to_string();
+    let program = env::var_os("PKG_CONFIG").unwrap_or_else(|| "pkg-config".into());
+
+    let mut output = BuildOutput::default();
+    for dep in unit.pkg.manifest().system_dependencies() {
+        if !dep.platform().map_or(true, |p| p.matches(&triple)) {
+            continue;
+        }
+        let run = |args: &[&str]| -> CraftResult<String> {
+            let mut cmd = util::process(&program);
+            cmd.args(args).arg(match dep.version_req() {
+                Some(req) => format!("{} {}", dep.module(), req),
+                None => dep.module().to_string(),
+            });
+            let out = cmd.exec_with_output()
+                .chain_error(|| {
+                    human(format!("failed to find the system library {} with `pkg-config`, which `{}` depends on",
+                                  dep,
+                                  unit.pkg.name()))
+                })?;
+            String::from_utf8(out.stdout)
+                .map_err(|_| internal(format!("`pkg-config` printed invalid UTF-8 for {}", dep)))
+        };
+        // A static library also needs the private libraries it depends on,
+        // but only the libraries of the module itself are linked statically.
+        let (flags, kind_libs) = if dep.link() == Some(LinkKind::Static) {
+            (run(&["--cflags", "--libs", "--static"])?, Some(run(&["--libs-only-l"])?))
+        } else {
+            (run(&["--cflags", "--libs"])?, None)
+        };
+        for flag in flags.split_whitespace() {
+            if flag.starts_with("-L") && flag.len() > 2 {
+                output.library_paths.push(PathBuf::from(&flag[2..]));
+            } else if flag.starts_with("-l") && flag.len() > 2 {
+                let kind = match kind_libs {
+                    Some(ref libs) if !libs.split_whitespace().any(|l| l == flag) => None,
+                    _ => dep.link(),
+                };
+                output.library_links.push(LibraryLink {
+                    name: flag[2..].to_string(),
+                    kind: kind,
+                });
+            } else {
+                output.cflags.push(flag.to_string());
+            }
+        }
+    }
+    cx.pkg_config.insert(key, output);
+    Ok(())
 }
 
 /// Compute the `build_scripts` map in the `Context` which tracks what build
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fs::{self, File, OpenOptions};
 use std::hash::{self, Hasher};
 use std::io::prelude::*;
@@ -6,14 +7,21 @@ use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use filetime::FileTime;
+use rustc_serialize::hex::ToHex;
 use rustc_serialize::{json, Encodable, Decodable, Encoder, Decoder};
 
 use manifest::TargetKind;
 use package::Package;
-use util::{self, paths, CraftResult, Fresh, Dirty, Freshness, internal, profile, ChainError};
+use package_id::PackageId;
+use source::SourceMap;
+use util::{self, paths, CraftResult, Graph, Sha256, Fresh, Dirty, Freshness, human, internal, profile, ChainError};
 
 use super::job::Work;
 use super::context::{Context, Unit};
+use super::custom_build;
+use super::pch;
+use super::resource;
+use super::unity;
 
 /// A tuple result of the `prepare_foo` functions in this module.
 ///
@@ -67,10 +75,7 @@ pub fn prepare_target<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) ->
     // on all files in the source to ensure they haven't changed. If they have
     // changed then an error is issued.
     if compare.is_err() {
-        let source_id = unit.pkg.package_id().source_id();
-        let sources = cx.packages.sources();
-        let source = sources.get(source_id).chain_error(|| internal("missing package source"))?;
-        source.verify(unit.pkg.package_id())?;
+        verify_source(&cx.packages.sources(), unit.pkg.package_id())?;
     }
 
     let root = cx.out_dir(unit);
@@ -85,6 +90,16 @@ pub fn prepare_target<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) ->
         }
     }
 
+    if cx.build_config.dump_fingerprints {
+        let dirty_reason = match compare {
+            Ok(()) if missing_outputs => Some("output files are missing".to_string()),
+            Ok(()) => None,
+            Err(..) if fs::metadata(&loc).is_err() => Some("not built before".to_string()),
+            Err(ref e) => Some(stale_input_reason(&fingerprint).unwrap_or_else(|| e.to_string())),
+        };
+        dump_fingerprint(&loc, &fingerprint, dirty_reason)?;
+    }
+
     let allow_failure = unit.profile.cc_args.is_some();
     let write_fingerprint = Work::new(move |_| {
         match fingerprint.update_local() {
@@ -99,6 +114,13 @@ pub fn prepare_target<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) ->
     Ok((if fresh { Fresh } else { Dirty }, write_fingerprint, Work::noop()))
 }
 
+/// Runs the `Source::verify` hook of the source which provides `id`, naming the package if the
+/// verification fails so that the build is aborted before it gets compiled.
+fn verify_source(sources: &SourceMap, id: &PackageId) -> CraftResult<()> {
+    let source = sources.get(id.source_id()).chain_error(|| internal("missing package source"))?;
+    source.verify(id).chain_error(|| human(format!("failed to verify the source of package `{}`", id)))
+}
+
 /// A fingerprint can be considered to be a "short string" representing the
 /// state of a world for a package.
 ///
@@ -135,10 +157,13 @@ pub struct Fingerprint {
 enum LocalFingerprint {
     Precalculated(String),
     MtimeBased(MtimeSlot, PathBuf),
+    ContentBased(ContentSlot, PathBuf, Vec<PathBuf>),
 }
 
 struct MtimeSlot(Mutex<Option<FileTime>>);
 
+struct ContentSlot(Mutex<Option<String>>);
+
 impl Fingerprint {
     fn update_local(&self) -> CraftResult<()> {
         match self.local {
@@ -147,6 +172,13 @@ impl Fingerprint {
                 let mtime = FileTime::from_last_modification_time(&meta);
                 *slot.0.lock().unwrap() = Some(mtime);
             }
+            LocalFingerprint::ContentBased(ref slot, ref path, ref extra) => {
+                let hash = match dep_info_paths(path)? {
+                    Some(paths) => content_hash(paths.iter().chain(extra)),
+                    None => None,
+                };
+                *slot.0.lock().unwrap() = hash;
+            }
             LocalFingerprint::Precalculated(..) => return Ok(()),
         }
 
@@ -207,6 +239,19 @@ impl Fingerprint {
                           bp)
                 }
             }
+            (&LocalFingerprint::ContentBased(ref on_disk, ref ap, _),
+             &LocalFingerprint::ContentBased(ref previously_built, ref bp, _)) => {
+                let on_disk = on_disk.0.lock().unwrap();
+                let previously_built = previously_built.0.lock().unwrap();
+                if *on_disk != *previously_built {
+                    bail!("content based components have changed: previously {:?} now {:?}, \
+                           paths are {:?} and {:?}",
+                          *previously_built,
+                          *on_disk,
+                          ap,
+                          bp)
+                }
+            }
             _ => bail!("local fingerprint type has changed"),
         }
 
@@ -320,6 +365,24 @@ impl Decodable for MtimeSlot {
     }
 }
 
+impl hash::Hash for ContentSlot {
+    fn hash<H: Hasher>(&self, h: &mut H) {
+        self.0.lock().unwrap().hash(h)
+    }
+}
+
+impl Encodable for ContentSlot {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        self.0.lock().unwrap().encode(e)
+    }
+}
+
+impl Decodable for ContentSlot {
+    fn decode<D: Decoder>(d: &mut D) -> Result<ContentSlot, D::Error> {
+        Ok(ContentSlot(Mutex::new(Decodable::decode(d)?)))
+    }
+}
+
 /// Calculates the fingerprint for a package/target pair.
 ///
 /// This fingerprint is used by Craft to learn about when information such as:
@@ -340,7 +403,7 @@ fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftResu
     // First, calculate all statically known "salt data" such as the profile
     // information (compiler flags), the compiler version, activated features,
     // and target configuration.
-    let features = cx.resolve.features(unit.pkg.package_id());
+    let features = cx.unit_features(unit);
     let features = features.map(|s| {
         let mut v = s.iter().collect::<Vec<_>>();
         v.sort();
@@ -361,9 +424,37 @@ fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftResu
         .collect::<CraftResult<Vec<_>>>()?;
 
     // And finally, calculate what our own local fingerprint is
-    let local = if use_dep_info(unit) {
+    let local = if use_dep_info(unit) && cx.config.content_fingerprints()? {
         let dep_info = dep_info_loc(cx, unit);
-        let mtime = dep_info_mtime_if_fresh(&dep_info)?;
+        // Like below, the sources of a unity build, the inputs of the
+        // precompiled header and resource scripts are taken into account
+        // directly.
+        let mut extra = resource::sources(cx, unit)?;
+        if let Some(sources) = unity::sources(cx, unit)? {
+            extra.extend(sources);
+        }
+        if let Some(pch) = pch::for_unit(cx, unit) {
+            extra.extend(pch.inputs());
+        }
+        let hash = cx.header_graph.content_hash(&dep_info, &extra)?;
+        LocalFingerprint::ContentBased(ContentSlot(Mutex::new(hash)), dep_info, extra)
+    } else if use_dep_info(unit) {
+        let dep_info = dep_info_loc(cx, unit);
+        let mut mtime = cx.header_graph.mtime_if_fresh(&dep_info)?;
+        // The generated file of a unity build is only written later on, so
+        // check the sources it includes directly. This also catches sources
+        // added since the last build.
+        if let Some(sources) = unity::sources(cx, unit)? {
+            mtime = mtime.and_then(|_| mtime_if_fresh(&dep_info, sources.iter()));
+        }
+        // Changes to the precompiled header affect every source including it.
+        if let Some(pch) = pch::for_unit(cx, unit) {
+            mtime = mtime.and_then(|_| mtime_if_fresh(&dep_info, pch.inputs()));
+        }
+        // Resource scripts are compiled by a tool which doesn't write any
+        // dep-info, so they're checked directly as well.
+        let resources = resource::sources(cx, unit)?;
+        mtime = mtime.and_then(|_| mtime_if_fresh(&dep_info, resources.iter()));
         LocalFingerprint::MtimeBased(MtimeSlot(Mutex::new(mtime)), dep_info)
     } else {
         let fingerprint = pkg_fingerprint(cx, unit.pkg)?;
@@ -371,11 +462,25 @@ fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftResu
     };
     let mut deps = deps;
     deps.sort_by(|&(ref a, _), &(ref b, _)| a.cmp(b));
-    let extra_flags = if unit.profile.doc {
+    let mut extra_flags = if unit.profile.doc {
         cx.docflags_args(unit)?
     } else {
-        cx.cflags_args(unit)?
+        cx.compile_flags_args(unit)?
     };
+    if super::hides_visibility(cx, unit) {
+        extra_flags.push("-fvisibility=hidden".to_string());
+    }
+    if let Some(pch) = pch::for_unit(cx, unit) {
+        extra_flags.push("-include".to_string());
+        extra_flags.push(pch.wrapper.display().to_string());
+    }
+    if !unit.profile.doc {
+        custom_build::pkg_config(cx, unit)?;
+        let output = &cx.pkg_config[&(unit.pkg.package_id().clone(), unit.kind)];
+        extra_flags.extend(output.cflags.iter().cloned());
+        extra_flags.extend(output.library_paths.iter().map(|p| format!("-L{}", p.display())));
+        extra_flags.extend(output.library_links.iter().map(|l| format!("-l{}", l)));
+    }
     let fingerprint = Arc::new(Fingerprint {
         cc: util::hash_u64(&cx.config.cc()?.verbose_version),
         target: util::hash_u64(&unit.target),
@@ -394,7 +499,9 @@ fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CraftResu
 // We want to use the mtime for files if we're a path source, but if we're a
 // git/registry source, then the mtime of files may fluctuate, but they won't
 // change so long as the source itself remains constant (which is the
-// responsibility of the source)
+// responsibility of the source). With `build.fingerprint = "content"` the
+// contents of the files listed in the dep-info are hashed instead of their
+// mtimes being compared.
 fn use_dep_info(unit: &Unit) -> bool {
     let path = unit.pkg.summary().source_id().is_path();
     !unit.profile.doc && path
@@ -503,6 +610,51 @@ fn write_fingerprint(loc: &Path, fingerprint: &Fingerprint) -> CraftResult<()> {
     Ok(())
 }
 
+#[derive(RustcEncodable)]
+struct FingerprintDump<'a> {
+    hash: String,
+    dirty_reason: Option<String>,
+    fingerprint: &'a Fingerprint,
+}
+
+// Writes the fingerprint of a unit along with the reason it's going to be
+// rebuilt, if any, for `--dump-fingerprints`.
+fn dump_fingerprint(loc: &Path, fingerprint: &Fingerprint, dirty_reason: Option<String>) -> CraftResult<()> {
+    let dump = FingerprintDump {
+        hash: util::to_hex(fingerprint.hash()),
+        dirty_reason: dirty_reason,
+        fingerprint: fingerprint,
+    };
+    paths::write(&loc.with_extension("craft-fingerprint.json"),
+                 json::encode(&dump).unwrap().as_bytes())
+}
+
+// Names the input which made an mtime based fingerprint stale.
+fn stale_input_reason(fingerprint: &Fingerprint) -> Option<String> {
+    let output = match fingerprint.local {
+        LocalFingerprint::MtimeBased(_, ref output) => output,
+        LocalFingerprint::ContentBased(..) |
+        LocalFingerprint::Precalculated(..) => return None,
+    };
+    let mtime = match fs::metadata(output) {
+        Ok(meta) => FileTime::from_last_modification_time(&meta),
+        Err(..) => return None,
+    };
+    let paths = match dep_info_paths(output) {
+        Ok(Some(paths)) => paths,
+        _ => return None,
+    };
+    paths.iter()
+        .filter_map(|path| match fs::metadata(path) {
+            Err(..) => Some(format!("`{}` is missing", path.display())),
+            Ok(ref meta) if FileTime::from_last_modification_time(meta) > mtime => {
+                Some(format!("`{}` has changed", path.display()))
+            }
+            Ok(..) => None,
+        })
+        .next()
+}
+
 /// Prepare work for when a package starts to build
 pub fn prepare_init(cx: &mut Context, unit: &Unit) -> CraftResult<()> {
     let new1 = dir(cx, unit);
@@ -558,7 +710,126 @@ fn log_compare(unit: &Unit, compare: &CraftResult<()>) {
     }
 }
 
-fn dep_info_mtime_if_fresh(dep_info: &Path) -> CraftResult<Option<FileTime>> {
+/// The graph of the input files (sources and headers) of every unit, keyed by
+/// the unit's dep-info file.
+///
+/// It is persisted across builds so that the freshness of a unit can be
+/// checked against its inputs without parsing its dep-info file again. The
+/// whole persisted graph is discarded as soon as any dep-info file turns out
+/// to be newer than it, as that unit was rebuilt since the graph was written.
+/// Entries of dep-info files that no longer exist, e.g. after `craft clean`,
+/// are dropped whenever the graph is saved.
+pub struct HeaderGraph {
+    path: PathBuf,
+    graph: Graph<PathBuf>,
+    mtime: Option<FileTime>,
+    seen: Vec<PathBuf>,
+}
+
+impl HeaderGraph {
+    /// Loads the graph persisted at `path`, starting out empty if there is
+    /// none or it can't be read.
+    pub fn load(path: PathBuf) -> HeaderGraph {
+        let mut graph = Graph::new();
+        let mut mtime = None;
+        let persisted = paths::read(&path)
+            .ok()
+            .and_then(|s| json::decode::<BTreeMap<String, Vec<String>>>(&s).ok());
+        if let Some(persisted) = persisted {
+            for (dep_info, inputs) in persisted {
+                graph.add(PathBuf::from(dep_info),
+                          &inputs.into_iter().map(PathBuf::from).collect::<Vec<_>>());
+            }
+            mtime = fs::metadata(&path).ok().map(|m| FileTime::from_last_modification_time(&m));
+        }
+        HeaderGraph {
+            path: path,
+            graph: graph,
+            mtime: mtime,
+            seen: Vec::new(),
+        }
+    }
+
+    /// Returns the mtime of `dep_info` if none of the inputs it lists are
+    /// newer than it.
+    pub fn mtime_if_fresh(&mut self, dep_info: &Path) -> CraftResult<Option<FileTime>> {
+        self.seen.push(dep_info.to_path_buf());
+        match self.inputs(dep_info)? {
+            Some(paths) => Ok(mtime_if_fresh(dep_info, paths.iter())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the hash of the contents of the inputs `dep_info` lists along
+    /// with `extra`, if all of them can be read.
+    pub fn content_hash(&mut self, dep_info: &Path, extra: &[PathBuf]) -> CraftResult<Option<String>> {
+        self.seen.push(dep_info.to_path_buf());
+        match self.inputs(dep_info)? {
+            Some(paths) => Ok(content_hash(paths.iter().chain(extra))),
+            None => Ok(None),
+        }
+    }
+
+    fn inputs(&mut self, dep_info: &Path) -> CraftResult<Option<Vec<PathBuf>>> {
+        if self.mtime.is_some() {
+            if self.is_newer(dep_info) {
+                debug!("discarding header graph, {} is newer", dep_info.display());
+                self.graph = Graph::new();
+                self.mtime = None;
+            } else if let Some(inputs) = self.graph.edges(&dep_info.to_path_buf()) {
+                return Ok(Some(inputs.cloned().collect()));
+            }
+        }
+        let paths = dep_info_paths(dep_info)?;
+        if let Some(ref paths) = paths {
+            self.graph.add(dep_info.to_path_buf(), paths);
+        }
+        Ok(paths)
+    }
+
+    /// Whether `dep_info` was (possibly) written after the persisted graph.
+    fn is_newer(&self, dep_info: &Path) -> bool {
+        match (self.mtime, fs::metadata(dep_info)) {
+            (Some(graph), Ok(meta)) => FileTime::from_last_modification_time(&meta) >= graph,
+            _ => true,
+        }
+    }
+
+    /// Picks up the dep-info files rewritten by this build and persists the
+    /// graph for the next one, leaving out the dep-info files which are gone.
+    pub fn save(&mut self) -> CraftResult<()> {
+        for dep_info in self.seen.iter() {
+            if self.mtime.is_some() && !self.is_newer(dep_info) && self.graph.edges(dep_info).is_some() {
+                continue;
+            }
+            if let Some(paths) = dep_info_paths(dep_info)? {
+                self.graph.add(dep_info.clone(), &paths);
+            }
+        }
+
+        let persisted = self.graph
+            .get_nodes()
+            .iter()
+            .filter(|&(dep_info, _)| dep_info.exists())
+            .filter_map(|(dep_info, inputs)| {
+                let inputs = inputs.iter().map(|p| p.to_str().map(str::to_string)).collect::<Option<Vec<_>>>();
+                match (dep_info.to_str(), inputs) {
+                    (Some(dep_info), Some(inputs)) => Some((dep_info.to_string(), inputs)),
+                    _ => None,
+                }
+            })
+            .collect::<BTreeMap<_, _>>();
+        let tmp = self.path.with_extension("tmp");
+        paths::write(&tmp, json::encode(&persisted)?.as_bytes())?;
+        fs::rename(&tmp, &self.path)
+            .chain_error(|| internal(format!("failed to write `{}`", self.path.display())))?;
+        Ok(())
+    }
+}
+
+// Returns the input files listed in the dep-info file `dep_info`, or `None` if
+// it doesn't exist.
+fn dep_info_paths(dep_info: &Path) -> CraftResult<Option<Vec<PathBuf>>> {
     macro_rules! fs_try {
         ($e:expr) => (match $e { Ok(e) => e, Err(..) => return Ok(None) })
     }
@@ -569,34 +840,64 @@ fn dep_info_mtime_if_fresh(dep_info: &Path) -> CraftResult<Option<FileTime>> {
         return Ok(None);
     }
     let cwd = util::bytes2path(&cwd[..cwd.len() - 1])?;
-    let line = match f.lines().next() {
-        Some(Ok(line)) => line,
-        _ => return Ok(None),
-    };
-    let pos = line.find(": ")
-        .chain_error(|| {
+    let mut contents = String::new();
+    fs_try!(f.read_to_string(&mut contents));
+    if contents.is_empty() {
+        return Ok(None);
+    }
+    let inputs = parse_dep_info(&contents).chain_error(|| {
             internal(format!("dep-info not in an understood format: {}",
                              dep_info.display()))
         })?;
-    let deps = &line[pos + 2..];
+    Ok(Some(inputs.into_iter().map(|input| cwd.join(input)).collect()))
+}
 
-    let mut paths = Vec::new();
-    let mut deps = deps.split(' ').map(|s| s.trim()).filter(|s| !s.is_empty());
-    loop {
-        let mut file = match deps.next() {
-            Some(s) => s.to_string(),
-            None => break,
-        };
-        while file.ends_with("\\") {
-            file.pop();
-            file.push(' ');
-            file.push_str(deps.next()
-                .chain_error(|| internal(format!("malformed dep-info format, trailing \\")))?);
+/// Parses the inputs of the first rule of a Makefile style dep-info file, as
+/// written by `-MMD`. The rule may be continued over several lines and spaces
+/// in paths are escaped, any further rules like the phony targets of `-MP`
+/// are ignored.
+///
+/// Returns `None` if `contents` doesn't start with a rule.
+pub fn parse_dep_info(contents: &str) -> Option<Vec<PathBuf>> {
+    let pos = match contents.find(": ") {
+        Some(pos) if !contents[..pos].contains('\n') => pos,
+        _ => return None,
+    };
+    let mut inputs = Vec::new();
+    let mut input = String::new();
+    let mut chars = contents[pos + 2..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                match chars.next() {
+                    Some(' ') => input.push(' '),
+                    // A continued line, possibly ending in \r\n
+                    Some('\r') => {
+                        if chars.clone().next() == Some('\n') {
+                            chars.next();
+                        }
+                    }
+                    Some('\n') | None => {}
+                    Some(c) => {
+                        input.push('\\');
+                        input.push(c);
+                    }
+                }
+            }
+            '\n' => break,
+            c if c.is_whitespace() => {
+                if !input.is_empty() {
+                    inputs.push(PathBuf::from(input.clone()));
+                    input.clear();
+                }
+            }
+            c => input.push(c),
         }
-        paths.push(cwd.join(&file));
     }
-
-    Ok(mtime_if_fresh(&dep_info, paths.iter()))
+    if !input.is_empty() {
+        inputs.push(PathBuf::from(input));
+    }
+    Some(inputs)
 }
 
 fn pkg_fingerprint(cx: &Context, pkg: &Package) -> CraftResult<String> {
@@ -606,7 +907,7 @@ fn pkg_fingerprint(cx: &Context, pkg: &Package) -> CraftResult<String> {
     source.fingerprint(pkg)
 }
 
-fn mtime_if_fresh<I>(output: &Path, paths: I) -> Option<FileTime>
+pub fn mtime_if_fresh<I>(output: &Path, paths: I) -> Option<FileTime>
     where I: IntoIterator,
           I::Item: AsRef<Path>
 {
@@ -637,6 +938,56 @@ fn mtime_if_fresh<I>(output: &Path, paths: I) -> Option<FileTime>
     if any_stale { None } else { Some(mtime) }
 }
 
+/// The SHA-256 of the paths and contents of `paths`, or `None` if any of them
+/// can't be read. Headers are hashed with normalized line endings.
+pub fn content_hash<I>(paths: I) -> Option<String>
+    where I: IntoIterator,
+          I::Item: AsRef<Path>
+{
+    let mut hasher = Sha256::new();
+    for path in paths {
+        let path = path.as_ref();
+        let contents = match paths::read_bytes(path) {
+            Ok(ref contents) if is_header(path) => normalize_line_endings(contents),
+            Ok(contents) => contents,
+            Err(..) => {
+                info!("stale: {} -- missing", path.display());
+                return None;
+            }
+        };
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update(&[0]);
+        hasher.update(contents.len().to_string().as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&contents);
+    }
+    Some(hasher.finish().to_hex())
+}
+
+fn is_header(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("h") | Some("hh") | Some("hpp") | Some("hxx") => true,
+        _ => false,
+    }
+}
+
+// Turns CRLF line endings into LF and drops trailing newlines, so that headers
+// only differing in their platform's line endings hash the same.
+fn normalize_line_endings(contents: &[u8]) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(contents.len());
+    let mut bytes = contents.iter().peekable();
+    while let Some(&b) = bytes.next() {
+        if b == b'\r' && bytes.peek() == Some(&&b'\n') {
+            continue;
+        }
+        normalized.push(b);
+    }
+    while normalized.last() == Some(&b'\n') {
+        normalized.pop();
+    }
+    normalized
+}
+
 fn filename(unit: &Unit) -> String {
     let kind = match *unit.target.kind() {
         TargetKind::Lib(..) => "lib",
@@ -656,6 +1007,62 @@ fn filename(unit: &Unit) -> String {
     format!("{}{}-{}", flavor, kind, unit.target.name())
 }
 
+/// Writes a dep-info file for compilers which don't emit one themselves.
+///
+/// The local headers of `sources` are found by following their
+/// `#include "..."` directives relative to the including file. Conditional
+/// includes can't be evaluated here, so every include seen is listed, which
+/// may rebuild too often but never too rarely. System includes (`<...>`) are
+/// ignored.
+pub fn write_fallback_dep_info(dep_info: &Path, sources: &[PathBuf], cwd: &Path) -> CraftResult<()> {
+    let mut paths = Vec::new();
+    let mut pending = sources.to_vec();
+    while let Some(path) = pending.pop() {
+        if paths.contains(&path) {
+            continue;
+        }
+        let contents = match paths::read(&path) {
+            Ok(contents) => contents,
+            Err(..) => continue,
+        };
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        pending.extend(local_includes(&contents)
+            .into_iter()
+            .map(|header| dir.join(header))
+            .filter(|header| header.is_file()));
+        paths.push(path);
+    }
+
+    let mut line = format!("{}:", dep_info.display());
+    for path in paths {
+        line.push(' ');
+        line.push_str(&path.display().to_string().replace(' ', "\\ "));
+    }
+    line.push('\n');
+    paths::write(dep_info, line.as_bytes())?;
+    append_current_dir(dep_info, cwd)
+}
+
+fn local_includes(source: &str) -> Vec<&str> {
+    source.lines()
+        .filter_map(|line| {
+            let line = line.trim_left();
+            if !line.starts_with('#') {
+                return None;
+            }
+            let line = line[1..].trim_left();
+            if !line.starts_with("include") {
+                return None;
+            }
+            let line = line["include".len()..].trim_left();
+            if !line.starts_with('"') {
+                return None;
+            }
+            line[1..].find('"').map(|end| &line[1..end + 1])
+        })
+        .collect()
+}
+
 // The dep-info files emitted by the compiler all have their listed paths
 // relative to whatever the current directory was at the time that the compiler
 // was invoked. As the current directory may change over time, we need to record
@@ -672,3 +1079,74 @@ pub fn append_current_dir(path: &Path, cwd: &Path) -> CraftResult<()> {
     f.write_all(&contents)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::error::Error;
+    use std::path::PathBuf;
+
+    use dependency::Dependency;
+    use package::Package;
+    use package_id::PackageId;
+    use registry::Registry;
+    use source::{Source, SourceId, SourceMap};
+    use summary::Summary;
+    use util::{human, CraftResult};
+
+    use super::{parse_dep_info, verify_source};
+
+    struct TamperedSource;
+
+    impl Registry for TamperedSource {
+        fn query(&mut self, _dep: &Dependency) -> CraftResult<Vec<Summary>> {
+            Ok(Vec::new())
+        }
+    }
+
+    impl Source for TamperedSource {
+        fn update(&mut self) -> CraftResult<()> {
+            Ok(())
+        }
+
+        fn download(&mut self, _id: &PackageId) -> CraftResult<Package> {
+            Err(human("not supported"))
+        }
+
+        fn fingerprint(&self, _pkg: &Package) -> CraftResult<String> {
+            Ok(String::new())
+        }
+
+        fn verify(&self, _id: &PackageId) -> CraftResult<()> {
+            Err(human("the listed checksum of `src/foo.c` has changed"))
+        }
+    }
+
+    #[test]
+    fn failed_verification_names_package() {
+        let source_id = SourceId::for_directory(&env::temp_dir()).unwrap();
+        let id = PackageId::new("foo", "0.1.0", &source_id).unwrap();
+        let mut sources = SourceMap::new();
+        sources.insert(&source_id, Box::new(TamperedSource));
+
+        let err = verify_source(&sources, &id).unwrap_err();
+        assert_eq!(err.to_string(), format!("failed to verify the source of package `{}`", id));
+        assert_eq!(err.cause().unwrap().to_string(),
+                   "the listed checksum of `src/foo.c` has changed");
+    }
+
+    #[test]
+    fn parses_dep_info_over_several_lines() {
+        let dep_info = "target/debug/foo.o: src/foo.c \\\n /src/foo.h \\\n  \
+                        /src/with\\ space.h\n\n/src/foo.h:\n\n/src/with\\ space.h:\n";
+        assert_eq!(parse_dep_info(dep_info).unwrap(),
+                   vec![PathBuf::from("src/foo.c"),
+                        PathBuf::from("/src/foo.h"),
+                        PathBuf::from("/src/with space.h")]);
+    }
+
+    #[test]
+    fn rejects_dep_info_without_a_rule() {
+        assert!(parse_dep_info("src/foo.c\n/src/foo.h: \n").is_none());
+    }
+}
@@ -1,17 +1,20 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::collections::hash_map::HashMap;
 use std::fmt;
 use std::io::Write;
+use std::sync::Arc;
 use std::sync::mpsc::{channel, Sender, Receiver};
 
 use crossbeam::{self, Scope};
-use term::color::YELLOW;
+use term::color::{BLACK, RED, YELLOW};
 
 use manifest::{Target, Profile};
 use package_id::PackageId;
-use util::{Config, DependencyQueue, Fresh, Dirty, Freshness, CraftResult, ProcessBuilder, profile, internal};
+use util::{Config, DependencyQueue, Fresh, Dirty, Freshness, CraftResult, ProcessBuilder, Progress, profile,
+           internal, ChainError};
+use util::jobserver::Client;
 
-use super::{Context, Kind, Unit};
+use super::{Context, DiagnosticLevel, Kind, Unit, diagnostic_level};
 use super::job::Job;
 
 /// A management structure of the entire dependency graph to compile.
@@ -21,6 +24,7 @@ use super::job::Job;
 /// then later on the entire graph is processed and compiled.
 pub struct JobQueue<'a> {
     jobs: usize,
+    jobserver: Option<Arc<Client>>,
     queue: DependencyQueue<Key<'a>, Vec<(Job, Freshness)>>,
     tx: Sender<(Key<'a>, Message)>,
     rx: Receiver<(Key<'a>, Message)>,
@@ -29,8 +33,17 @@ pub struct JobQueue<'a> {
     compiled: HashSet<&'a PackageId>,
     documented: HashSet<&'a PackageId>,
     counts: HashMap<&'a PackageId, usize>,
+    total: usize,
+    deprecations: BTreeMap<&'a PackageId, Vec<String>>,
+    // The distinct warning and error lines printed by the compiler, along with
+    // the packages they came from
+    warnings: HashSet<String>,
+    errors: HashSet<String>,
+    diagnosed: HashSet<&'a PackageId>,
     is_release: bool,
+    is_json: bool,
     is_doc_all: bool,
+    is_future_incompat: bool,
 }
 
 /// A helper structure for metadata about the state of a building package.
@@ -59,9 +72,19 @@ enum Message {
     Run(String),
     Stdout(String),
     Stderr(String),
+    Diagnostic(Diagnostic),
+    Deprecation(String),
     Finish(CraftResult<()>),
 }
 
+/// A line of compiler output. Only whole lines are sent, which are printed
+/// as they come in, so units compiled in parallel never interleave within a
+/// line.
+enum Diagnostic {
+    Stdout(String),
+    Stderr(String),
+}
+
 impl<'a> JobState<'a> {
     pub fn running(&self, cmd: &ProcessBuilder) {
         let _ = self.tx.send((self.key, Message::Run(cmd.to_string())));
@@ -74,6 +97,20 @@ impl<'a> JobState<'a> {
     pub fn stderr(&self, err: &str) {
         let _ = self.tx.send((self.key, Message::Stderr(err.to_string())));
     }
+
+    /// Prints a line the compiler printed to stdout.
+    pub fn compiler_stdout(&self, line: &str) {
+        let _ = self.tx.send((self.key, Message::Diagnostic(Diagnostic::Stdout(line.to_string()))));
+    }
+
+    /// Prints a line the compiler printed to stderr.
+    pub fn compiler_stderr(&self, line: &str) {
+        let _ = self.tx.send((self.key, Message::Diagnostic(Diagnostic::Stderr(line.to_string()))));
+    }
+
+    pub fn deprecation(&self, line: &str) {
+        let _ = self.tx.send((self.key, Message::Deprecation(line.to_string())));
+    }
 }
 
 impl<'a> JobQueue<'a> {
@@ -81,6 +118,7 @@ impl<'a> JobQueue<'a> {
         let (tx, rx) = channel();
         JobQueue {
             jobs: cx.jobs() as usize,
+            jobserver: cx.build_config.jobserver.clone(),
             queue: DependencyQueue::new(),
             tx: tx,
             rx: rx,
@@ -89,8 +127,15 @@ impl<'a> JobQueue<'a> {
             compiled: HashSet::new(),
             documented: HashSet::new(),
             counts: HashMap::new(),
+            total: 0,
+            deprecations: BTreeMap::new(),
+            warnings: HashSet::new(),
+            errors: HashSet::new(),
+            diagnosed: HashSet::new(),
             is_release: cx.build_config.release,
+            is_json: cx.build_config.json_errors,
             is_doc_all: cx.build_config.doc_all,
+            is_future_incompat: cx.build_config.future_incompat,
         }
     }
 
@@ -104,6 +149,7 @@ impl<'a> JobQueue<'a> {
         let deps = key.dependencies(cx)?;
         self.queue.queue(Fresh, key, Vec::new(), &deps).push((job, fresh));
         *self.counts.entry(key.pkg).or_insert(0) += 1;
+        self.total += 1;
         Ok(())
     }
 
@@ -135,6 +181,9 @@ impl<'a> JobQueue<'a> {
         // and then immediately return.
         let mut error = None;
         let start_time = Instant::now();
+        let mut progress = Progress::new("Building", cx.config);
+        let mut finished = 0;
+        progress.tick(finished, self.total)?;
         loop {
             while error.is_none() && self.active < self.jobs {
                 if !queue.is_empty() {
@@ -172,9 +221,17 @@ impl<'a> JobQueue<'a> {
                         writeln!(cx.config.shell().err(), "{}", err)?;
                     }
                 }
+                Message::Diagnostic(diagnostic) => {
+                    self.print_diagnostic(&key, diagnostic, cx.config)?;
+                }
+                Message::Deprecation(line) => {
+                    self.deprecations.entry(key.pkg).or_insert(Vec::new()).push(line);
+                }
                 Message::Finish(result) => {
                     info!("end: {:?}", key);
                     self.active -= 1;
+                    finished += 1;
+                    progress.tick(finished, self.total)?;
                     match result {
                         Ok(()) => self.finish(key, cx)?,
                         Err(e) => {
@@ -192,8 +249,10 @@ impl<'a> JobQueue<'a> {
             }
         }
 
+        progress.clear()?;
+
         let build_type = if self.is_release { "release" } else { "debug" };
-        let profile = cx.lib_profile(&cx.current_package);
+        let profile = cx.lib_profile();
         let mut opt_type = String::from(if profile.opt_level == "0" {
             "unoptimized"
         } else {
@@ -216,8 +275,13 @@ impl<'a> JobQueue<'a> {
                                     opt_type,
                                     time_elapsed))?;
             }
+            self.report_diagnostics(cx.config)?;
+            if self.is_future_incompat {
+                self.report_future_incompat(cx)?;
+            }
             Ok(())
         } else if let Some(e) = error {
+            self.report_diagnostics(cx.config)?;
             Err(e)
         } else {
             debug!("queue: {:#?}", self.queue);
@@ -225,11 +289,81 @@ impl<'a> JobQueue<'a> {
         }
     }
 
+    /// Prints a line of compiler output of a unit, counting the warnings and
+    /// errors among it. The progress line is drawn again below it, so the
+    /// status of the build stays visible while the compiler prints.
+    fn print_diagnostic(&mut self, key: &Key<'a>, line: Diagnostic, config: &Config) -> CraftResult<()> {
+        let mut shell = config.shell();
+        match line {
+            Diagnostic::Stdout(line) => writeln!(shell.out(), "{}", line)?,
+            Diagnostic::Stderr(line) => {
+                shell.err().say(&line, BLACK)?;
+                let counted = match diagnostic_level(&line) {
+                    Some(DiagnosticLevel::Error) => self.errors.insert(line),
+                    Some(DiagnosticLevel::Warning) => self.warnings.insert(line),
+                    None => false,
+                };
+                if counted {
+                    self.diagnosed.insert(key.pkg);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints how many distinct warnings and errors the compiler reported, if
+    /// any. JSON messages are left to the tools parsing them.
+    fn report_diagnostics(&self, config: &Config) -> CraftResult<()> {
+        if self.is_json || self.diagnosed.is_empty() {
+            return Ok(());
+        }
+        let plural = |n: usize, noun: &str| format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" });
+        let color = if self.errors.is_empty() { YELLOW } else { RED };
+        config.shell()
+            .status_with_color("Diagnostics",
+                               format!("{}, {} across {}",
+                                       plural(self.warnings.len(), "warning"),
+                                       plural(self.errors.len(), "error"),
+                                       plural(self.diagnosed.len(), "chest")),
+                               color)
+    }
+
+    /// Print the deprecated constructs collected during the build, keyed by
+    /// package and split into first-party and dependency code.
+    fn report_future_incompat(&self, cx: &Context) -> CraftResult<()> {
+        if self.deprecations.is_empty() {
+            return cx.config.shell().status("Future-incompat", "no deprecated constructs found");
+        }
+        for (pkg, lines) in self.deprecations.iter() {
+            // The code of every member of the workspace is first-party,
+            // whether it's built by default or only as a dependency
+            let origin = if cx.is_member(pkg) {
+                "first-party"
+            } else {
+                "dependency"
+            };
+            cx.config.shell().warn(format!("{} package `{}` uses deprecated constructs:", origin, pkg))?;
+            for line in lines.iter() {
+                writeln!(cx.config.shell().err(), "    {}", line)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Executes a job in the `scope` given, pushing the spawned thread's
     /// handled onto `threads`.
     fn run(&mut self, key: Key<'a>, fresh: Freshness, job: Job, config: &Config, scope: &Scope<'a>) -> CraftResult<()> {
         info!("start: {:?}", key);
 
+        // Our own process already holds one token of the jobserver, every
+        // additional job running in parallel has to acquire another one.
+        let token = match self.jobserver {
+            Some(ref client) if self.active > 0 => {
+                Some(Client::acquire(client).chain_error(|| internal("failed to acquire a jobserver token"))?)
+            }
+            _ => None,
+        };
+
         self.active += 1;
         *self.counts.get_mut(key.pkg).unwrap() -= 1;
 
@@ -240,6 +374,7 @@ impl<'a> JobQueue<'a> {
                                   tx: my_tx.clone(),
                                   key: key,
                               });
+            drop(token);
             my_tx.send((key, Message::Finish(res))).unwrap();
         });
 
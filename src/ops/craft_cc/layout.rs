@@ -50,7 +50,7 @@ use std::path::{PathBuf, Path};
 
 use package::Package;
 use util::hex::short_hash;
-use util::{Config, FileLock, CraftResult, Filesystem, human};
+use util::{paths, Config, FileLock, CraftResult, Filesystem, human};
 use workspace::Workspace;
 
 use super::Unit;
@@ -62,6 +62,7 @@ pub struct Layout {
     build: PathBuf,
     fingerprint: PathBuf,
     examples: PathBuf,
+    pch: PathBuf,
     _lock: FileLock,
 }
 
@@ -72,6 +73,12 @@ pub struct LayoutProxy<'a> {
 
 impl Layout {
     pub fn new(ws: &Workspace, triple: Option<&str>, dest: &str) -> CraftResult<Layout> {
+        Layout::at(ws.config(), Layout::dir(ws, triple, dest)?)
+    }
+
+    /// Returns the root directory of the layout for `triple` and the `dest`
+    /// profile directory, without creating or locking it.
+    pub fn dir(ws: &Workspace, triple: Option<&str>, dest: &str) -> CraftResult<Filesystem> {
         let mut path = ws.target_dir();
         // Flexible target specifications often point at filenames, so interpret
         // the target triple as a Path and then just use the file stem as the
@@ -81,14 +88,18 @@ impl Layout {
                 .ok_or(human(format!("target was empty")))?);
         }
         path.push(dest);
-        Layout::at(ws.config(), path)
+        Ok(path)
     }
 
     pub fn at(config: &Config, root: Filesystem) -> CraftResult<Layout> {
         // For now we don't do any more finer-grained locking on the artifact
         // directory, so just lock the entire thing for the duration of this
-        // compile.
-        let lock = root.open_rw(".craft-lock", config, "build directory")?;
+        // compile. A configured lock timeout (or `--frozen`) makes a contended
+        // lock fail instead of waiting on the other process indefinitely.
+        let lock = match config.lock_timeout()? {
+            Some(timeout) => root.open_rw_timeout(".craft-lock", config, "build directory", timeout)?,
+            None => root.open_rw(".craft-lock", config, "build directory")?,
+        };
         let root = root.into_path_unlocked();
 
         Ok(Layout {
@@ -97,6 +108,7 @@ impl Layout {
             build: root.join("build"),
             fingerprint: root.join(".fingerprint"),
             examples: root.join("examples"),
+            pch: root.join("pch"),
             root: root,
             _lock: lock,
         })
@@ -112,6 +124,7 @@ impl Layout {
         mkdir(&self.fingerprint)?;
         mkdir(&self.examples)?;
         mkdir(&self.build)?;
+        mkdir(&self.pch)?;
 
         return Ok(());
 
@@ -132,6 +145,9 @@ impl Layout {
     pub fn examples(&self) -> &Path {
         &self.examples
     }
+    pub fn pch(&self) -> &Path {
+        &self.pch
+    }
     pub fn root(&self) -> &Path {
         &self.root
     }
@@ -149,7 +165,19 @@ impl Layout {
     }
 
     fn pkg_dir(&self, pkg: &Package) -> String {
-        format!("{}-{}", pkg.name(), short_hash(pkg))
+        format!("{}-{}", pkg.name(), self.pkg_hash(pkg))
+    }
+
+    // Path packages are hashed by their location relative to the build
+    // directory instead of their absolute path. This keeps directories like
+    // the `OUT_DIR` of build scripts the same no matter where a checkout lives.
+    fn pkg_hash(&self, pkg: &Package) -> String {
+        let id = pkg.package_id();
+        if id.source_id().is_path() {
+            short_hash(&(id.name(), id.version(), paths::relative_path(pkg.root(), &self.root)))
+        } else {
+            short_hash(pkg)
+        }
     }
 }
 
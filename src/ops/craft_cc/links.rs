@@ -1,6 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use package_id::PackageId;
+use resolver::Resolve;
 use util::CraftResult;
 
 use super::Unit;
@@ -18,7 +19,7 @@ impl<'a> Links<'a> {
         }
     }
 
-    pub fn validate(&mut self, unit: &Unit<'a>) -> CraftResult<()> {
+    pub fn validate(&mut self, resolve: &Resolve, unit: &Unit<'a>) -> CraftResult<()> {
         if !self.validated.insert(unit.pkg.package_id()) {
             return Ok(());
         }
@@ -33,17 +34,17 @@ impl<'a> Links<'a> {
                        than one version of the same package, but it can \
                        only be linked once; try updating or pinning your \
                        dependencies to ensure that this package only shows \
-                       up once\n\n  {}\n  {}",
+                       up once\n\n{}\n{}",
                       lib,
-                      prev,
-                      pkg)
+                      describe(resolve, prev),
+                      describe(resolve, pkg))
             } else {
                 bail!("native library `{}` is being linked to by more than \
                        one package, and can only be linked to by one \
-                       package\n\n  {}\n  {}",
+                       package\n\n{}\n{}",
                       lib,
-                      prev,
-                      pkg)
+                      describe(resolve, prev),
+                      describe(resolve, pkg))
             }
         }
         if !unit.pkg.manifest().targets().iter().any(|t| t.is_custom_build()) {
@@ -56,3 +57,51 @@ impl<'a> Links<'a> {
         Ok(())
     }
 }
+
+// Describes `pkg` for a conflict error, along with how it ends up in the
+// dependency graph so the user knows which dependency to change.
+fn describe(resolve: &Resolve, pkg: &PackageId) -> String {
+    let mut desc = format!("  {}", pkg);
+    if let Some(path) = dependency_path(resolve, pkg) {
+        if path.len() > 1 {
+            let path = path.iter()
+                .map(|id| format!("{} v{}", id.name(), id.version()))
+                .collect::<Vec<_>>();
+            desc.push_str(&format!("\n    required by: {}", path.join(" -> ")));
+        }
+    }
+    desc
+}
+
+/// Returns the shortest chain of dependencies leading from a package which
+/// nothing depends on (a root of `resolve`) to `pkg`, both included.
+fn dependency_path<'r>(resolve: &'r Resolve, pkg: &PackageId) -> Option<Vec<&'r PackageId>> {
+    let depended_on = resolve.iter()
+        .flat_map(|id| resolve.deps(id))
+        .collect::<HashSet<_>>();
+    let mut roots = resolve.iter().filter(|id| !depended_on.contains(id)).collect::<Vec<_>>();
+    roots.sort();
+
+    let mut parents = HashMap::new();
+    let mut visited = roots.iter().cloned().collect::<HashSet<_>>();
+    let mut queue = roots.into_iter().collect::<VecDeque<_>>();
+    while let Some(id) = queue.pop_front() {
+        if id == pkg {
+            let mut path = vec![id];
+            while let Some(&parent) = parents.get(path[path.len() - 1]) {
+                path.push(parent);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        let mut deps = resolve.deps(id).collect::<Vec<_>>();
+        deps.sort();
+        for dep in deps {
+            if visited.insert(dep) {
+                parents.insert(dep, id);
+                queue.push_back(dep);
+            }
+        }
+    }
+    None
+}
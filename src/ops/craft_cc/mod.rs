@@ -1,18 +1,21 @@
 use std::collections::{HashMap, HashSet};
 use std::ffi::{OsStr, OsString};
-use std::path::{self, PathBuf};
+use std::io::Read;
+use std::path::{self, Path, PathBuf};
 use std::sync::Arc;
 use std::{env, fs};
 
 use rustc_serialize::json;
 use shell::ColorConfig;
 
+use dependency::LinkKind;
 use manifest::{Profile, Profiles, Target};
 use package::{Package, PackageSet};
 use package_id::PackageId;
 use resolver::Resolve;
 use util::{self, CraftResult, ProcessBuilder, human, machine_message, Config, internal, ChainError, profile,
            join_paths, short_hash};
+use util::jobserver;
 use workspace::Workspace;
 
 use self::job::{Job, Work};
@@ -21,7 +24,7 @@ use self::job_queue::JobQueue;
 pub use self::compilation::Compilation;
 pub use self::context::{Context, Unit};
 pub use self::layout::{Layout, LayoutProxy};
-pub use self::custom_build::{BuildOutput, BuildMap, BuildScripts};
+pub use self::custom_build::{BuildOutput, BuildMap, BuildScripts, LibraryLink};
 
 mod compilation;
 mod context;
@@ -31,6 +34,9 @@ mod job;
 mod job_queue;
 mod layout;
 mod links;
+mod pch;
+mod resource;
+mod unity;
 
 #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, PartialOrd, Ord)]
 pub enum Kind {
@@ -49,11 +55,24 @@ pub struct BuildConfig {
     pub test: bool,
     pub doc_all: bool,
     pub json_errors: bool,
+    pub short_errors: bool,
+    pub future_incompat: bool,
+    pub link_driver: Option<PathBuf>,
+    pub unity: bool,
+    pub hidden_visibility: bool,
+    pub jobserver: Option<Arc<jobserver::Client>>,
+    pub target_applies_to_host: Option<bool>,
+    pub out_dir: Option<PathBuf>,
+    pub dump_fingerprints: bool,
+    pub sanitizer: Option<String>,
+    pub build_plan: bool,
 }
 
 #[derive(Clone, Default)]
 pub struct TargetConfig {
     pub ar: Option<PathBuf>,
+    pub ranlib: Option<PathBuf>,
+    pub rc: Option<PathBuf>,
     pub linker: Option<PathBuf>,
     pub overrides: HashMap<String, BuildOutput>,
 }
@@ -110,8 +129,19 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
         compile(&mut cx, &mut queue, unit)?;
     }
 
+    // A build plan only lists what would be done.
+    if cx.build_config.build_plan {
+        machine_message::BuildPlan::new(&cx.build_plan).emit();
+        return Ok(cx.compilation);
+    }
+
     // Now that we've figured out everything that we're going to do, do it!
     queue.execute(&mut cx)?;
+    cx.header_graph.save()?;
+
+    if let Some(ref out_dir) = cx.build_config.out_dir {
+        copy_to_out_dir(&cx, &units, out_dir)?;
+    }
 
     for unit in units.iter() {
         let out_dir = cx.layout(unit)
@@ -181,6 +211,45 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
     Ok(cx.compilation)
 }
 
+// Copies the final artifacts of the requested units into `out_dir`, creating
+// it if it doesn't exist yet. Artifacts of the same name overwrite each other,
+// the last one wins.
+fn copy_to_out_dir(cx: &Context, units: &[Unit], out_dir: &Path) -> CraftResult<()> {
+    if out_dir.is_file() {
+        bail!("out-dir `{}` is a file, not a directory", out_dir.display())
+    }
+    fs::create_dir_all(out_dir)
+        .chain_error(|| human(format!("failed to create out-dir `{}`", out_dir.display())))?;
+    let mut copied = HashMap::new();
+    for unit in units.iter().filter(|u| !u.profile.doc) {
+        for (filename, _linkable) in cx.target_filenames(unit)? {
+            let src = cx.out_dir(unit).join(&filename);
+            if !src.exists() {
+                continue;
+            }
+            let dst = out_dir.join(&filename);
+            if let Some(prev) = copied.insert(dst.clone(), unit.pkg.package_id()) {
+                cx.config
+                    .shell()
+                    .warn(format!("`{}` of `{}` overwrites the one of `{}` in the out-dir",
+                                  filename,
+                                  unit.pkg.package_id(),
+                                  prev))?;
+            }
+            if dst.exists() {
+                fs::remove_file(&dst).chain_error(|| human(format!("failed to remove: {}", dst.display())))?;
+            }
+            fs::hard_link(&src, &dst).or_else(|_| fs::copy(&src, &dst).map(|_| ()))
+                .chain_error(|| {
+                    human(format!("failed to link or copy `{}` to `{}`",
+                                  src.display(),
+                                  dst.display()))
+                })?;
+        }
+    }
+    Ok(())
+}
+
 fn compile<'a, 'cfg: 'a>(cx: &mut Context<'a, 'cfg>, jobs: &mut JobQueue<'a>, unit: &Unit<'a>) -> CraftResult<()> {
     if !cx.compiled.insert(*unit) {
         return Ok(());
@@ -190,7 +259,7 @@ fn compile<'a, 'cfg: 'a>(cx: &mut Context<'a, 'cfg>, jobs: &mut JobQueue<'a>, un
     // we've got everything constructed.
     let p = profile::start(format!("preparing: {}/{}", unit.pkg, unit.target.name()));
     fingerprint::prepare_init(cx, unit)?;
-    cx.links.validate(unit)?;
+    cx.links.validate(cx.resolve, unit)?;
 
     let (dirty, fresh, freshness) = if unit.profile.run_custom_build {
         custom_build::prepare(cx, unit)?
@@ -215,42 +284,139 @@ fn compile<'a, 'cfg: 'a>(cx: &mut Context<'a, 'cfg>, jobs: &mut JobQueue<'a>, un
 }
 
 fn cc(cx: &mut Context, unit: &Unit) -> CraftResult<Work> {
+    if cx.show_warnings(unit.pkg.package_id()) {
+        cx.warn_header_collisions(unit)?;
+    }
+    let root = cx.out_dir(unit);
+    // The compiler writes into a directory of its own whose contents are only
+    // moved into place once it succeeded, so a failed or killed compile never
+    // leaves a partial artifact where a finished one is expected. The build
+    // plan lists the commands writing to the final location instead.
+    let staging = if cx.build_config.build_plan {
+        root.clone()
+    } else {
+        root.join(format!(".{}.partial", cx.file_stem(unit)))
+    };
     let chest_types = unit.target.cc_chest_types();
-    let mut cc = prepare_cc(cx, chest_types, unit)?;
+    let mut cc = prepare_cc(cx, chest_types, unit, &staging)?;
 
     let name = unit.pkg.name().to_string();
-    if !cx.show_warnings(unit.pkg.package_id()) {
+    let show_warnings = cx.show_warnings(unit.pkg.package_id());
+    let future_incompat = cx.build_config.future_incompat;
+
+    // Warnings of dependencies are still needed to scan them for deprecations,
+    // they're just not shown in that case.
+    if !show_warnings && !future_incompat {
         cc.arg("-Awarnings");
     }
     let has_custom_args = unit.profile.cc_args.is_some();
 
     let filenames = cx.target_filenames(unit)?;
-    let root = cx.out_dir(unit);
 
     // Prepare the native lib state (extra -L and -l flags)
     let build_state = cx.build_state.clone();
     let current_id = unit.pkg.package_id().clone();
     let build_deps = load_build_deps(cx, unit);
+    let c_as_c = cx.link_driver(unit)?.is_some();
+    let ld64 = cx.links_with_ld64(unit.kind);
 
     // If we are a binary and the package also contains a library, then we
     // don't pass the `-l` flags.
     let pass_l_flag = unit.target.is_lib() || !unit.pkg.targets().iter().any(|t| t.is_lib());
     let do_rename = unit.target.allows_underscores() && !unit.profile.test;
-    let real_name = unit.target.name().to_string();
+    let real_name = unit.target.filename().unwrap_or(unit.target.name()).to_string();
     let chest_name = unit.target.chest_name();
-    let move_outputs_up = unit.pkg.package_id() == &cx.current_package;
+    let move_outputs_up = cx.is_primary_package(unit.pkg.package_id());
 
     let cc_dep_info_loc = if do_rename {
-            root.join(&chest_name)
+            staging.join(&chest_name)
         } else {
-            root.join(&cx.file_stem(unit))
+            staging.join(&cx.file_stem(unit))
         }
         .with_extension("d");
+    // The headers included by the sources, which the fingerprint tracks as
+    // inputs of the unit
+    cc.arg("-MMD").arg("-MF").arg(&cc_dep_info_loc);
     let dep_info_loc = fingerprint::dep_info_loc(cx, unit);
     let cwd = cx.config.cwd().to_path_buf();
+    let scanned_sources = match unity::sources(cx, unit)? {
+        Some(sources) => sources,
+        None => vec![unit.pkg.root().join(unit.target.src_path())],
+    };
+
+    let resource_sources = resource::sources(cx, unit)?;
+    let resources = resource::commands(cx, unit, &resource_sources, &root);
+
+    // The flags of the system libraries found with `pkg-config`
+    custom_build::pkg_config(cx, unit)?;
+    let system_cflags = cx.pkg_config[&(unit.pkg.package_id().clone(), unit.kind)].cflags.clone();
+
+    let mut pch = match pch::for_unit(cx, unit) {
+        Some(pch) => {
+            cc.arg("-include").arg(&pch.wrapper);
+            let cmd = pch.prepare(cx, unit, &system_cflags)?;
+            Some((pch, cmd))
+        }
+        None => None,
+    };
+
+    cc.args(&cx.compile_flags_args(unit)?);
+    {
+        let system_libs = &cx.pkg_config[&(unit.pkg.package_id().clone(), unit.kind)];
+        cc.args(&system_libs.cflags);
+        for path in system_libs.library_paths.iter() {
+            cc.arg("-L").arg(path);
+        }
+        if pass_l_flag {
+            for link in system_libs.library_links.iter() {
+                add_library_link(&mut cc, link, &system_libs.library_paths, ld64)?;
+            }
+        }
+    }
 
-    cc.args(&cx.cflags_args(unit)?);
+    // The build plan lists the commands as far as they are known now, the
+    // flags from the output of build scripts are only added once they ran.
+    if cx.build_config.build_plan {
+        let id = unit.pkg.package_id();
+        if let Some((ref pch, ref cmd)) = pch {
+            let mut invocation = machine_message::Invocation::new(id, unit.target, cmd);
+            invocation.input(&pch.header).output(&pch.output);
+            cx.build_plan.push(invocation);
+        }
+        let mut cmd = cc.clone();
+        for (&(ref rc, ref output), source) in resources.iter().zip(resource_sources.iter()) {
+            let mut invocation = machine_message::Invocation::new(id, unit.target, rc);
+            invocation.input(source).output(output);
+            cx.build_plan.push(invocation);
+            cmd.arg(output);
+        }
+        let mut invocation = machine_message::Invocation::new(id, unit.target, &cmd);
+        for source in scanned_sources.iter() {
+            invocation.input(source);
+        }
+        for &(ref filename, _linkable) in filenames.iter() {
+            invocation.output(root.join(filename));
+        }
+        // Neither are the flags of system libraries, which aren't looked up
+        // for a build plan.
+        let from_build_scripts = build_deps.as_ref()
+            .map_or(false, |deps| !deps.to_link.is_empty() || !deps.plugins.is_empty());
+        invocation.unknown_until_run(from_build_scripts || !unit.pkg.manifest().system_dependencies().is_empty());
+        cx.build_plan.push(invocation);
+    }
     let json_errors = cx.build_config.json_errors;
+    let short_errors = cx.build_config.short_errors;
+    // The compiler only sees a pipe, so it's told to color its diagnostics
+    // whenever ours are.
+    if !json_errors && cx.config.shell().err_colored() {
+        cc.arg("-fdiagnostics-color=always");
+    }
+    // Short diagnostics leave out the source excerpts with their carets and
+    // the suggested fixes.
+    if short_errors && !json_errors {
+        cc.arg("-fno-diagnostics-show-caret").arg("-fno-diagnostics-fixit-info");
+    }
+    let ranlib = cx.ranlib(unit.kind);
     let package_id = unit.pkg.package_id().clone();
     let target = unit.target.clone();
     return Ok(Work::new(move |state| {
@@ -265,8 +431,14 @@ fn cc(cx: &mut Context, unit: &Unit) -> CraftResult<Work> {
                             &build_state,
                             &build_deps,
                             pass_l_flag,
+                            c_as_c,
+                            ld64,
                             &current_id)?;
             add_plugin_deps(&mut cc, &build_state, &build_deps)?;
+            add_build_script_flags(&mut cc, &build_state, &build_deps, &current_id);
+            if let Some((_, ref mut cmd)) = pch {
+                add_build_script_flags(cmd, &build_state, &build_deps, &current_id);
+            }
         }
 
         for &(ref filename, _linkable) in filenames.iter() {
@@ -275,9 +447,29 @@ fn cc(cx: &mut Context, unit: &Unit) -> CraftResult<Work> {
                 fs::remove_file(&dst).chain_error(|| human(format!("Could not remove file: {}.", dst.display())))?;
             }
         }
+        if staging.exists() {
+            fs::remove_dir_all(&staging)
+                .chain_error(|| human(format!("Could not remove directory: {}.", staging.display())))?;
+        }
+        fs::create_dir_all(&staging)?;
+
+        // Resources are compiled up front to pass them to the linker.
+        if let Some((ref pch, ref cmd)) = pch {
+            if !pch.is_fresh(cmd) {
+                state.running(cmd);
+                cmd.exec().chain_error(|| human(format!("Could not precompile `{}`.", pch.header.display())))?;
+                pch.record_flags(cmd)?;
+            }
+        }
+
+        for &(ref rc, ref output) in resources.iter() {
+            state.running(rc);
+            rc.exec().chain_error(|| human(format!("Could not compile resources of `{}`.", name)))?;
+            cc.arg(output);
+        }
 
         state.running(&cc);
-        if json_errors {
+        let result = if json_errors {
                 cc.exec_with_streaming(&mut |line| if !line.is_empty() {
                                              Err(internal(&format!("compiler stdout is not empty: `{}`", line)))
                                          } else {
@@ -290,14 +482,39 @@ fn cc(cx: &mut Context, unit: &Unit) -> CraftResult<Work> {
                         machine_message::FromCompiler::new(&package_id, &target, compiler_message).emit();
 
                         Ok(())
-                    })
+                    },
+                                         false)
                     .map(|_| ())
             } else {
-                cc.exec()
-            }.chain_error(|| human(format!("Could not compile `{}`.", name)))?;
+                // The output is printed line by line as it comes in, keeping
+                // parallel compiles from interleaving within a line. It's left
+                // out of the error as it has been shown already.
+                cc.exec_with_streaming(&mut |line| {
+                                           state.compiler_stdout(line);
+                                           Ok(())
+                                       },
+                                       &mut |line| {
+                                           if show_warnings || !future_incompat {
+                                               state.compiler_stderr(line);
+                                           }
+                                           if future_incompat && is_deprecation(line) {
+                                               state.deprecation(line);
+                                           }
+                                           Ok(())
+                                       },
+                                       false)
+                    .map(|_| ())
+            };
+
+        // Whatever a failed compiler wrote is thrown away with the staging
+        // directory.
+        if result.is_err() {
+            let _ = fs::remove_dir_all(&staging);
+        }
+        result.chain_error(|| human(format!("Could not compile `{}`.", name)))?;
 
         if do_rename && real_name != chest_name {
-            let dst = root.join(&filenames[0].0);
+            let dst = staging.join(&filenames[0].0);
             let src = dst.with_file_name(dst.file_name()
                 .unwrap()
                 .to_str()
@@ -308,10 +525,44 @@ fn cc(cx: &mut Context, unit: &Unit) -> CraftResult<Work> {
             }
         }
 
-        if !has_custom_args || fs::metadata(&cc_dep_info_loc).is_ok() {
+        // Index the static libraries, unless `ar` already did so and running
+        // `ranlib` is only a platform default.
+        if let Some((ref ranlib, always)) = ranlib {
+            for &(ref filename, _linkable) in filenames.iter() {
+                let archive = staging.join(filename);
+                let is_archive = filename.ends_with(".a") || filename.ends_with(".lib");
+                if !is_archive || !archive.exists() || (!always && archive_has_index(&archive)) {
+                    continue;
+                }
+                let mut cmd = util::process(ranlib);
+                cmd.arg(&archive);
+                state.running(&cmd);
+                cmd.exec().chain_error(|| human(format!("Could not index `{}`.", archive.display())))?;
+            }
+        }
+
+        if fs::metadata(&cc_dep_info_loc).is_ok() {
             fs::rename(&cc_dep_info_loc, &dep_info_loc)
                 .chain_error(|| internal(format!("could not rename dep info: {:?}", cc_dep_info_loc)))?;
             fingerprint::append_current_dir(&dep_info_loc, &cwd)?;
+        } else if !has_custom_args {
+            // Not every compiler is able to emit dep-info, so fall back to
+            // scanning the sources for the local headers they include.
+            fingerprint::write_fallback_dep_info(&dep_info_loc, &scanned_sources, &cwd)?;
+        }
+
+        // The outputs are complete, so move them into place.
+        if staging != root {
+            for entry in fs::read_dir(&staging)? {
+                let src = entry?.path();
+                let dst = root.join(src.file_name().unwrap());
+                if dst.is_dir() {
+                    fs::remove_dir_all(&dst)?;
+                }
+                fs::rename(&src, &dst)
+                    .chain_error(|| internal(format!("could not move `{}` to `{}`", src.display(), dst.display())))?;
+            }
+            fs::remove_dir(&staging)?;
         }
 
         // If we're a "root chest", e.g. the target of this compilation, then we
@@ -358,24 +609,156 @@ fn cc(cx: &mut Context, unit: &Unit) -> CraftResult<Work> {
                        build_state: &BuildMap,
                        build_scripts: &BuildScripts,
                        pass_l_flag: bool,
+                       c_as_c: bool,
+                       ld64: bool,
                        current_id: &PackageId)
                        -> CraftResult<()> {
+        let mut outputs = Vec::new();
         for key in build_scripts.to_link.iter() {
             let output = build_state.get(key)
                 .chain_error(|| internal(format!("couldn't find build state for {}/{:?}", key.0, key.1)))?;
-            for path in output.library_paths.iter() {
-                cc.arg("-L").arg(path);
+            outputs.push((key, output));
+        }
+        let search_paths = outputs.iter()
+            .flat_map(|&(_, output)| output.library_paths.iter().cloned())
+            .collect::<Vec<_>>();
+        for path in search_paths.iter() {
+            cc.arg("-L").arg(path);
+        }
+        for &(key, output) in outputs.iter() {
+            if key.0 != *current_id {
+                continue;
+            }
+            // Generated sources go first so that they can use the libraries
+            // linked below.
+            for source in output.compile_sources.iter() {
+                add_source(cc, source, c_as_c);
             }
-            if key.0 == *current_id {
-                if pass_l_flag {
-                    for name in output.library_links.iter() {
-                        cc.arg("-l").arg(name);
-                    }
+            if pass_l_flag {
+                for link in output.library_links.iter() {
+                    add_library_link(cc, link, &search_paths, ld64)?;
                 }
             }
         }
         Ok(())
     }
+
+    // Add the defines and environment of the package's own build script,
+    // which apply to its precompiled header as well
+    fn add_build_script_flags(cmd: &mut ProcessBuilder,
+                              build_state: &BuildMap,
+                              build_scripts: &BuildScripts,
+                              current_id: &PackageId) {
+        for key in build_scripts.to_link.iter().filter(|key| key.0 == *current_id) {
+            if let Some(output) = build_state.get(key) {
+                for cfg in output.cfgs.iter() {
+                    cmd.arg("-D").arg(cfg);
+                }
+                for &(ref name, ref value) in output.env.iter() {
+                    cmd.env(name, value);
+                }
+            }
+        }
+    }
+}
+
+// Adds the flags linking the native library `link` to `cc`. A library which
+// has to be linked in a specific way is looked up in `search_paths` first, so
+// that a missing variant is reported instead of silently linking the other.
+// `ld64` is set when linking with Apple's linker.
+fn add_library_link(cc: &mut ProcessBuilder,
+                    link: &LibraryLink,
+                    search_paths: &[PathBuf],
+                    ld64: bool)
+                    -> CraftResult<()> {
+    let find = |filename: String| search_paths.iter().map(|dir| dir.join(&filename)).find(|p| p.is_file());
+    let archive = || find(format!("lib{}.a", link.name));
+    let shared = || find(format!("{}{}{}", env::consts::DLL_PREFIX, link.name, env::consts::DLL_SUFFIX));
+    match link.kind {
+        Some(LinkKind::Static) => {
+            if let Some(path) = archive() {
+                cc.arg(path);
+                return Ok(());
+            }
+            if let Some(path) = shared() {
+                bail!("native library `{}` has to be linked statically, but only the shared library `{}` \
+                       was found",
+                      link.name,
+                      path.display())
+            }
+            if ld64 {
+                // ld64 can't be told to skip shared libraries, but it links
+                // the archive when there is none next to it.
+                cc.arg("-l").arg(&link.name);
+            } else {
+                cc.arg("-Wl,-Bstatic").arg("-l").arg(&link.name).arg("-Wl,-Bdynamic");
+            }
+        }
+        Some(LinkKind::Dylib) => {
+            if shared().is_none() {
+                if let Some(path) = archive() {
+                    bail!("native library `{}` has to be linked dynamically, but only the static library \
+                           `{}` was found",
+                          link.name,
+                          path.display())
+                }
+            }
+            cc.arg("-l").arg(&link.name);
+        }
+        None => {
+            cc.arg("-l").arg(&link.name);
+        }
+    }
+    Ok(())
+}
+
+/// The severity of a diagnostic of the compiler.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum DiagnosticLevel {
+    Error,
+    Warning,
+}
+
+// The severity of the diagnostic a line of compiler output starts, if any.
+fn diagnostic_level(line: &str) -> Option<DiagnosticLevel> {
+    if line.contains("error:") {
+        Some(DiagnosticLevel::Error)
+    } else if line.contains("warning:") {
+        Some(DiagnosticLevel::Warning)
+    } else {
+        None
+    }
+}
+
+// Whether a line of compiler diagnostics reports the use of a deprecated
+// construct, like `-Wdeprecated-declarations` or a deprecated flag.
+fn is_deprecation(line: &str) -> bool {
+    diagnostic_level(line) == Some(DiagnosticLevel::Warning) && line.contains("deprecated")
+}
+
+// Adds the source `src` to `cmd`. A C++ link driver compiles `.c` files as C++,
+// which breaks C code that isn't valid C++ and mangles its names, so with
+// `c_as_c` their language is spelled out. `-x none` restores the detection by
+// extension for the inputs which follow, like the objects and libraries.
+fn add_source(cmd: &mut ProcessBuilder, src: &Path, c_as_c: bool) {
+    if c_as_c && src.extension().map_or(false, |e| e == "c") {
+        cmd.arg("-x").arg("c").arg(src).arg("-x").arg("none");
+    } else {
+        cmd.arg(src);
+    }
+}
+
+/// Whether the static library at `path` starts with a symbol index, as written
+/// by `ar s` or `ranlib`, in either the GNU or the BSD flavour.
+fn archive_has_index(path: &Path) -> bool {
+    let mut header = [0; 24];
+    match fs::File::open(path).and_then(|mut f| f.read_exact(&mut header)) {
+        Ok(()) => {}
+        Err(..) => return false,
+    }
+    let name = &header[8..];
+    &header[..8] == b"!<arch>\n" &&
+    (name.starts_with(b"/ ") || name.starts_with(b"/SYM64/") || name.starts_with(b"__.SYMDEF"))
 }
 
 fn load_build_deps(cx: &Context, unit: &Unit) -> Option<Arc<BuildScripts>> {
@@ -405,10 +788,23 @@ fn add_plugin_deps(cc: &mut ProcessBuilder,
     Ok(())
 }
 
-fn prepare_cc(cx: &Context, chest_types: Vec<&str>, unit: &Unit) -> CraftResult<ProcessBuilder> {
-    let mut base = cx.compilation.cc_process(unit.pkg)?;
-    build_base_args(cx, &mut base, unit, &chest_types);
-    build_plugin_args(&mut base, cx, unit);
+fn prepare_cc(cx: &Context, chest_types: Vec<&str>, unit: &Unit, out_dir: &Path) -> CraftResult<ProcessBuilder> {
+    // Targets pulling in C++ objects have to be linked by the C++ driver to
+    // get the C++ runtime, so it replaces the default compiler in that case.
+    let link_driver = cx.link_driver(unit)?;
+    let c_as_c = link_driver.is_some();
+    let mut base = match link_driver {
+        Some(driver) => cx.compilation.host_process(driver, unit.pkg)?,
+        None => cx.compilation.cc_process(unit.pkg)?,
+    };
+    // Unity builds compile a generated file including all the sources of the
+    // target in place of its entry point.
+    let src = match unity::sources(cx, unit)? {
+        Some(sources) => unity::prepare(cx, unit, &sources)?,
+        None => root_path(cx, unit),
+    };
+    build_base_args(cx, &mut base, unit, &src, c_as_c, &chest_types);
+    build_plugin_args(&mut base, cx, unit, out_dir);
     build_deps_args(&mut base, cx, unit)?;
     Ok(base)
 }
@@ -433,7 +829,7 @@ fn doc(cx: &mut Context, unit: &Unit) -> CraftResult<Work> {
 
     doc.arg("-o").arg(doc_dir);
 
-    if let Some(features) = cx.resolve.features(unit.pkg.package_id()) {
+    if let Some(features) = cx.unit_features(unit) {
         for feat in features {
             doc.arg("--cfg").arg(&format!("feature=\"{}\"", feat));
         }
@@ -479,13 +875,18 @@ fn root_path(cx: &Context, unit: &Unit) -> PathBuf {
     }
 }
 
-fn build_base_args(cx: &Context, cmd: &mut ProcessBuilder, unit: &Unit, chest_types: &[&str]) {
-    let Profile { ref opt_level,
+fn build_base_args(cx: &Context,
+                   cmd: &mut ProcessBuilder,
+                   unit: &Unit,
+                   src: &Path,
+                   c_as_c: bool,
+                   chest_types: &[&str]) {
+    let Profile { opt_level: _,
                   lto,
                   codegen_units,
-                  ref cc_args,
-                  debuginfo,
-                  debug_assertions,
+                  cc_args: _,
+                  debuginfo: _,
+                  debug_assertions: _,
                   rpath,
                   test,
                   doc: _doc,
@@ -494,10 +895,10 @@ fn build_base_args(cx: &Context, cmd: &mut ProcessBuilder, unit: &Unit, chest_ty
                   doc_args: _ } = *unit.profile;
     assert!(!run_custom_build);
 
-    // Move to cwd so the root_path() passed below is actually correct
+    // Move to cwd so the root_path() passed as `src` is actually correct
     cmd.cwd(cx.config.cwd());
 
-    cmd.arg(&root_path(cx, unit));
+    add_source(cmd, src, c_as_c);
 
     let color_config = cx.config.shell().color_config();
     if color_config != ColorConfig::Auto {
@@ -517,14 +918,12 @@ fn build_base_args(cx: &Context, cmd: &mut ProcessBuilder, unit: &Unit, chest_ty
     }
 
     let prefer_dynamic = (unit.target.for_host() && !unit.target.is_custom_build()) ||
-                         (chest_types.contains(&"dylib") && unit.pkg.package_id() != &cx.current_package);
+                         (chest_types.contains(&"dylib") && !cx.is_primary_package(unit.pkg.package_id()));
     if prefer_dynamic {
         cmd.arg("-C").arg("prefer-dynamic");
     }
 
-    if opt_level != "0" {
-        cmd.arg("-C").arg(&format!("opt-level={}", opt_level));
-    }
+    add_shared_flags(cx, cmd, unit);
 
     // If a panic mode was configured *and* we're not ever going to be used in a
     // plugin, then we can compile with that panic mode.
@@ -554,49 +953,89 @@ fn build_base_args(cx: &Context, cmd: &mut ProcessBuilder, unit: &Unit, chest_ty
         }
     }
 
-    if debuginfo {
-        cmd.arg("-g");
+    if test && unit.target.harness() {
+        cmd.arg("--test");
     }
 
-    if let Some(ref args) = *cc_args {
-        cmd.args(args);
+    match cx.target_metadata(unit) {
+        Some(m) => {
+            cmd.arg("-C").arg(&format!("metadata={}", m.metadata));
+            cmd.arg("-C").arg(&format!("extra-filename={}", m.extra_filename));
+        }
+        None => {
+            cmd.arg("-C").arg(&format!("metadata={}", short_hash(unit.pkg)));
+        }
+    }
+
+    if rpath {
+        cmd.arg("-C").arg("rpath");
+    }
+}
+
+// Adds the flags deciding how the sources of `unit` are compiled, which its
+// precompiled header has to be built with as well: the optimization level,
+// debug info, symbol visibility, cfgs and the flags of its target.
+fn add_shared_flags(cx: &Context, cmd: &mut ProcessBuilder, unit: &Unit) {
+    let profile = unit.profile;
+    if hides_visibility(cx, unit) {
+        cmd.arg("-fvisibility=hidden");
     }
 
-    if debug_assertions && opt_level != "0" {
+    if profile.opt_level != "0" {
+        cmd.arg("-C").arg(&format!("opt-level={}", profile.opt_level));
+    }
+
+    if profile.debuginfo {
+        cmd.arg("-g");
+    }
+
+    add_target_flags(cmd, unit);
+
+    if profile.debug_assertions && profile.opt_level != "0" {
         cmd.args(&["-C", "debug-assertions=on"]);
-    } else if !debug_assertions && opt_level == "0" {
+    } else if !profile.debug_assertions && profile.opt_level == "0" {
         cmd.args(&["-C", "debug-assertions=off"]);
     }
 
-    if test && unit.target.harness() {
-        cmd.arg("--test");
-    } else if test {
+    // A test harness implies `--cfg test`, see `build_base_args`
+    if profile.test && !unit.target.harness() {
         cmd.arg("--cfg").arg("test");
     }
 
-    if let Some(features) = cx.resolve.features(unit.pkg.package_id()) {
+    if let Some(features) = cx.unit_features(unit) {
         for feat in features.iter() {
             cmd.arg("--cfg").arg(&format!("feature=\"{}\"", feat));
         }
     }
+}
 
-    match cx.target_metadata(unit) {
-        Some(m) => {
-            cmd.arg("-C").arg(&format!("metadata={}", m.metadata));
-            cmd.arg("-C").arg(&format!("extra-filename={}", m.extra_filename));
-        }
-        None => {
-            cmd.arg("-C").arg(&format!("metadata={}", short_hash(unit.pkg)));
-        }
+// Adds the defines and flags of only the target of `unit`, and the arguments
+// of its profile. No shell is involved, so a define goes verbatim into a
+// single argument, whatever its value is.
+fn add_target_flags(cmd: &mut ProcessBuilder, unit: &Unit) {
+    for &(ref name, ref value) in unit.target.defines() {
+        cmd.arg("-D");
+        match *value {
+            Some(ref value) => cmd.arg(format!("{}={}", name, value)),
+            None => cmd.arg(name),
+        };
     }
+    cmd.args(unit.target.cflags());
 
-    if rpath {
-        cmd.arg("-C").arg("rpath");
+    if let Some(ref args) = unit.profile.cc_args {
+        cmd.args(args);
     }
 }
 
+// Shared libraries only export what is explicitly annotated for export if
+// hidden symbol visibility was requested.
+fn hides_visibility(cx: &Context, unit: &Unit) -> bool {
+    let chest_types = unit.target.cc_chest_types();
+    let shared = chest_types.contains(&"dylib") || chest_types.contains(&"cdylib");
+    cx.build_config.hidden_visibility && shared && !unit.profile.test
+}
 
-fn build_plugin_args(cmd: &mut ProcessBuilder, cx: &Context, unit: &Unit) {
+fn build_plugin_args(cmd: &mut ProcessBuilder, cx: &Context, unit: &Unit, out_dir: &Path) {
     fn opt(cmd: &mut ProcessBuilder, key: &str, prefix: &str, val: Option<&OsStr>) {
         if let Some(val) = val {
             let mut joined = OsString::from(prefix);
@@ -605,8 +1044,8 @@ fn build_plugin_args(cmd: &mut ProcessBuilder, cx: &Context, unit: &Unit) {
         }
     }
 
-    cmd.arg("--out-dir").arg(&cx.out_dir(unit));
-    cmd.arg("--emit=dep-info,link");
+    cmd.arg("--out-dir").arg(out_dir);
+    cmd.arg("--emit=link");
 
     if unit.kind == Kind::Target {
         opt(cmd,
@@ -640,6 +1079,10 @@ fn build_deps_args(cmd: &mut ProcessBuilder, cx: &Context, unit: &Unit) -> Craft
         }
     }
 
+    for (_, dir) in cx.include_dirs(unit)? {
+        cmd.arg("-I").arg(dir);
+    }
+
     return Ok(());
 
     fn link_to(cmd: &mut ProcessBuilder, cx: &Context, unit: &Unit) -> CraftResult<()> {
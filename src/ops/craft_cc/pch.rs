@@ -0,0 +1,123 @@
+//! Support for precompiled headers.
+//!
+//! A package can name a header included by most of its sources with
+//! `package.precompiled-header`. Before a unit of the package is compiled, the
+//! header is precompiled with the flags of the unit into the `pch` directory
+//! of the layout and then force-included into the unit with `-include`.
+//! The compiler only uses a precompiled header built with the same defines and
+//! flags, so it's precompiled again whenever they change.
+//!
+//! The header is included through a generated wrapper including the header by
+//! its absolute path, with the precompiled header living next to the wrapper.
+//! This way the includes of the header itself keep working, and the compiler
+//! can still fall back to the header if it can't use the precompiled one.
+
+use std::path::PathBuf;
+
+use manifest::Language;
+use util::{paths, CraftResult, ProcessBuilder};
+
+use super::fingerprint;
+use super::{Context, Unit};
+
+pub struct PrecompiledHeader {
+    /// The header named in the manifest
+    pub header: PathBuf,
+    /// The generated header including `header`, which units include
+    pub wrapper: PathBuf,
+    /// The precompiled header, found by the compiler next to `wrapper`
+    pub output: PathBuf,
+    /// The dep-info of `output`, listing the headers included by `header`
+    pub dep_info: PathBuf,
+    /// The command line `output` was precompiled with the last time
+    pub flags: PathBuf,
+}
+
+/// Returns the precompiled header used by `unit`, if any.
+///
+/// Build scripts and assembly sources don't use it.
+pub fn for_unit(cx: &Context, unit: &Unit) -> Option<PrecompiledHeader> {
+    let header = match unit.pkg.manifest().precompiled_header() {
+        Some(header) => unit.pkg.root().join(header),
+        None => return None,
+    };
+    if unit.profile.doc || unit.target.is_custom_build() || unit.target.is_asm() {
+        return None;
+    }
+    let dir = cx.layout(unit).proxy().pch();
+    let wrapper = dir.join(format!("{}.h", cx.file_stem(unit)));
+    let output = dir.join(format!("{}.h.gch", cx.file_stem(unit)));
+    let dep_info = dir.join(format!("{}.h.d", cx.file_stem(unit)));
+    let flags = dir.join(format!("{}.h.flags", cx.file_stem(unit)));
+    Some(PrecompiledHeader {
+        header: header,
+        wrapper: wrapper,
+        output: output,
+        dep_info: dep_info,
+        flags: flags,
+    })
+}
+
+impl PrecompiledHeader {
+    /// Writes the wrapper of the header and returns the command precompiling
+    /// it for `unit`, with the same defines and flags as the unit along with
+    /// the `system_cflags` of its system libraries. The defines of the build
+    /// script of the package are only added once it ran.
+    ///
+    /// The wrapper is only rewritten if its contents changed, so that an
+    /// unchanged precompiled header stays fresh. A build plan doesn't write it.
+    pub fn prepare(&self, cx: &Context, unit: &Unit, system_cflags: &[String]) -> CraftResult<ProcessBuilder> {
+        let contents = format!("#include \"{}\"\n", self.header.display());
+        if !cx.build_config.build_plan && paths::read(&self.wrapper).ok().as_ref() != Some(&contents) {
+            paths::write(&self.wrapper, contents.as_bytes())?;
+        }
+
+        let cpp = match self.header.extension().and_then(|e| e.to_str()) {
+            Some("hh") | Some("hpp") | Some("hxx") => true,
+            _ => unit.pkg.manifest().language() == Language::Cpp,
+        };
+        let mut cmd = cx.compilation.cc_process(unit.pkg)?;
+        cmd.cwd(cx.config.cwd())
+            .arg("-x")
+            .arg(if cpp { "c++-header" } else { "c-header" })
+            .arg(&self.wrapper)
+            .arg("-o")
+            .arg(&self.output)
+            .arg("-MD")
+            .arg("-MF")
+            .arg(&self.dep_info);
+        super::add_shared_flags(cx, &mut cmd, unit);
+        // What the test harness of the unit implies
+        if unit.profile.test && unit.target.harness() {
+            cmd.arg("--cfg").arg("test");
+        }
+        for (_, dir) in cx.include_dirs(unit)? {
+            cmd.arg("-I").arg(dir);
+        }
+        cmd.args(&cx.compile_flags_args(unit)?);
+        cmd.args(system_cflags);
+        Ok(cmd)
+    }
+
+    /// Returns the header along with the headers it included when it was
+    /// precompiled the last time.
+    pub fn inputs(&self) -> Vec<PathBuf> {
+        let mut inputs = vec![self.header.clone()];
+        if let Ok(dep_info) = paths::read(&self.dep_info) {
+            inputs.extend(fingerprint::parse_dep_info(&dep_info).unwrap_or_default());
+        }
+        inputs
+    }
+
+    /// Whether the precompiled header is up to date with its inputs and was
+    /// precompiled by `cmd`.
+    pub fn is_fresh(&self, cmd: &ProcessBuilder) -> bool {
+        self.dep_info.exists() && paths::read(&self.flags).ok() == Some(cmd.to_string()) &&
+        fingerprint::mtime_if_fresh(&self.output, self.inputs()).is_some()
+    }
+
+    /// Records `cmd` as the command the precompiled header was built with.
+    pub fn record_flags(&self, cmd: &ProcessBuilder) -> CraftResult<()> {
+        paths::write(&self.flags, cmd.to_string().as_bytes())
+    }
+}
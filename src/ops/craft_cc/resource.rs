@@ -0,0 +1,59 @@
+//! Support for Windows resource scripts.
+//!
+//! The `.rc` files living next to the entry point of a binary are compiled by
+//! the resource compiler of the target, `rc.exe` for MSVC and `windres` for
+//! the GNU toolchain, unless `target.$target.rc` names another one. The
+//! compiled resources are then passed to the linker along with the sources.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use util::{self, CraftResult, ChainError, ProcessBuilder, human};
+
+use super::{Context, Kind, Unit};
+
+/// Returns the resource scripts which are linked into `unit`.
+pub fn sources(cx: &Context, unit: &Unit) -> CraftResult<Vec<PathBuf>> {
+    if !unit.target.is_bin() || unit.profile.doc || cx.rc(unit.kind).is_none() {
+        return Ok(Vec::new());
+    }
+    let src_path = unit.pkg.root().join(unit.target.src_path());
+    let dir = src_path.parent().unwrap();
+    let entries = fs::read_dir(dir).chain_error(|| human(format!("failed to read directory `{}`", dir.display())))?;
+    let mut sources = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("rc") {
+            sources.push(path);
+        }
+    }
+    sources.sort();
+    Ok(sources)
+}
+
+/// Returns the commands compiling each of the resource `sources` of `unit`
+/// along with the resources they produce in `out_dir`.
+pub fn commands(cx: &Context, unit: &Unit, sources: &[PathBuf], out_dir: &Path) -> Vec<(ProcessBuilder, PathBuf)> {
+    let rc = match cx.rc(unit.kind) {
+        Some(rc) => rc,
+        None => return Vec::new(),
+    };
+    let triple = match unit.kind {
+        Kind::Host => cx.host_triple(),
+        Kind::Target => cx.target_triple(),
+    };
+    let msvc = triple.ends_with("-msvc");
+    let mut commands = Vec::new();
+    for source in sources {
+        let stem = source.file_stem().unwrap().to_string_lossy();
+        let output = out_dir.join(format!("{}-{}.{}", cx.file_stem(unit), stem, if msvc { "res" } else { "o" }));
+        let mut cmd = util::process(&rc);
+        if msvc {
+            cmd.arg("/nologo").arg(format!("/fo{}", output.display())).arg(source);
+        } else {
+            cmd.arg("-O").arg("coff").arg("-i").arg(source).arg("-o").arg(&output);
+        }
+        commands.push((cmd, output));
+    }
+    commands
+}
@@ -0,0 +1,121 @@
+//! Support for unity (also called jumbo) builds.
+//!
+//! With `build.unity` enabled, the C sources of a target are compiled as a
+//! single translation unit. The sources of a target are all the `.c` files
+//! living next to its entry point which aren't entry points of other targets
+//! of the same package. A generated file including each of them is passed to
+//! the compiler instead of the entry point itself.
+//!
+//! The other targets of a package link its library, so a source next to the
+//! entry point of the library only goes into the library. Compiling it into
+//! the other targets as well would define its symbols twice.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use util::{paths, CraftResult, ChainError, human};
+
+use super::{Context, Unit};
+
+/// Returns the sources which are compiled together for `unit`, or `None` if
+/// the unit is not built as a unity build.
+pub fn sources(cx: &Context, unit: &Unit) -> CraftResult<Option<Vec<PathBuf>>> {
+    if !cx.build_config.unity || unit.profile.doc || unit.target.is_custom_build() {
+        return Ok(None);
+    }
+    let src_path = unit.pkg.root().join(unit.target.src_path());
+    if src_path.extension().and_then(|e| e.to_str()) != Some("c") {
+        return Ok(None);
+    }
+
+    let entry_points = unit.pkg
+        .targets()
+        .iter()
+        .filter(|t| t.src_path() != unit.target.src_path())
+        .map(|t| unit.pkg.root().join(t.src_path()))
+        .collect::<Vec<_>>();
+
+    let dir = src_path.parent().unwrap();
+    let next_to_lib = unit.pkg
+        .targets()
+        .iter()
+        .any(|t| t.is_lib() && unit.pkg.root().join(t.src_path()).parent() == Some(dir));
+    if next_to_lib && !unit.target.is_lib() {
+        return Ok(Some(vec![src_path]));
+    }
+
+    let entries = fs::read_dir(dir).chain_error(|| human(format!("failed to read directory `{}`", dir.display())))?;
+    let mut sources = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("c") && !entry_points.contains(&path) {
+            sources.push(path);
+        }
+    }
+    sources.sort();
+    Ok(Some(sources))
+}
+
+/// Writes the translation unit including all `sources` of `unit` and returns
+/// its path. A build plan only returns the path.
+///
+/// The file is only rewritten if its contents changed, so an unchanged unity
+/// build stays fresh. Functions or variables declared `static` in more than
+/// one of the sources will clash once they end up in the same translation
+/// unit, which is reported as a warning.
+pub fn prepare(cx: &Context, unit: &Unit, sources: &[PathBuf]) -> CraftResult<PathBuf> {
+    let mut contents = String::new();
+    let mut statics = HashMap::new();
+    for source in sources {
+        contents.push_str(&format!("#include \"{}\"\n", source.display()));
+        for name in static_symbols(&paths::read(source)?) {
+            if let Some(prev) = statics.get(&name) {
+                cx.config.shell().warn(format!("`{}` is declared static in both `{}` and `{}`, which may \
+                                                fail to compile as a unity build",
+                                               name,
+                                               prev.display(),
+                                               source.display()))?;
+                continue;
+            }
+            statics.insert(name, source.clone());
+        }
+    }
+
+    let dst = cx.out_dir(unit).join(format!("{}-unity.c", cx.file_stem(unit)));
+    if !cx.build_config.build_plan && paths::read(&dst).ok().as_ref() != Some(&contents) {
+        paths::write(&dst, contents.as_bytes())?;
+    }
+    Ok(dst)
+}
+
+// Collects the names of top-level `static` functions and variables. This only
+// looks at declarations starting a line, which is how they are written in
+// practice.
+fn static_symbols(source: &str) -> Vec<String> {
+    source.lines()
+        .filter(|line| line.starts_with("static "))
+        .filter_map(|line| {
+            let decl = line.split(|c| c == '(' || c == '=' || c == ';' || c == '[').next().unwrap();
+            decl.split_whitespace()
+                .last()
+                .map(|name| name.trim_left_matches('*'))
+                .and_then(|name| if name.is_empty() { None } else { Some(name.to_string()) })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::static_symbols;
+
+    #[test]
+    fn finds_static_symbols() {
+        let source = "static int counter = 0;\n\
+                      static const char *name(void) { return \"a\"; }\n\
+                      static int table[4];\n\
+                      int exported(void);\n    \
+                      static int local;\n";
+        assert_eq!(static_symbols(source), vec!["counter", "name", "table"]);
+    }
+}
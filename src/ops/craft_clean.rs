@@ -3,7 +3,7 @@ use std::fs;
 use std::path::Path;
 
 use manifest::Profiles;
-use ops::{self, Context, BuildConfig, Kind, Unit};
+use ops::{self, Context, BuildConfig, Kind, Layout, Unit};
 use registry::PackageRegistry;
 use util::{CraftResult, human, ChainError, Config};
 use workspace::Workspace;
@@ -20,13 +20,19 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CraftResult<()> {
     let target_dir = ws.target_dir();
 
     // If we have a spec, then we need to delete some packages, otherwise, just
-    // remove the whole target directory and be done with it!
+    // remove the whole target directory, or the directory of the requested
+    // profile and target, and be done with it!
     //
     // Note that we don't bother grabbing a lock here as we're just going to
     // blow it all away anyway.
     if opts.spec.is_empty() {
-        let target_dir = target_dir.into_path_unlocked();
-        return rm_rf(&target_dir);
+        if !opts.release && opts.target.is_none() {
+            let target_dir = target_dir.into_path_unlocked();
+            return rm_rf(&target_dir);
+        }
+        let dest = if opts.release { "release" } else { "debug" };
+        let layout_dir = Layout::dir(ws, opts.target, dest)?.into_path_unlocked();
+        return rm_rf(&layout_dir);
     }
 
     let mut registry = PackageRegistry::new(opts.config)?;
@@ -86,8 +92,18 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CraftResult<()> {
 
         let root = cx.out_dir(&unit);
         for (filename, _) in cx.target_filenames(&unit)? {
-            rm_rf(&root.join(&filename))?;
+            let path = root.join(&filename);
+            rm_rf(&path)?;
+            // Outputs of the current package are linked up from `deps`
+            if root.ends_with("deps") {
+                rm_rf(&layout.proxy().dest().join(path.file_name().unwrap()))?;
+            }
         }
+
+        // The dep-info the compiler leaves behind if it's interrupted before
+        // it was moved next to the fingerprint
+        rm_rf(&root.join(cx.file_stem(unit)).with_extension("d"))?;
+        rm_rf(&root.join(unit.target.chest_name()).with_extension("d"))?;
     }
 
     Ok(())
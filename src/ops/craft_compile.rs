@@ -22,10 +22,12 @@
 //!
 
 use std::collections::HashMap;
+use std::env;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use manifest::{Profile, Target, TargetKind, Profiles};
-use ops::{self, BuildOutput};
+use ops::{self, BuildOutput, LibraryLink};
 use package::{PackageSet, Package};
 use package_id_spec::PackageIdSpec;
 use registry::PackageRegistry;
@@ -33,7 +35,8 @@ use resolver::{Method, Resolve};
 use source::{Source, SourceId};
 use sources::PathSource;
 use util::config::Config;
-use util::{CraftResult, profile, human, ChainError};
+use util::jobserver;
+use util::{CraftResult, profile, human, machine_message, ChainError};
 use workspace::Workspace;
 
 /// Contains information about how a package should be compiled.
@@ -59,6 +62,10 @@ pub struct CompileOptions<'a> {
     /// Root package to build (if None it's the current one)
     pub spec: &'a [String],
 
+    /// Whether to build all members of the workspace instead of the default
+    /// ones, if no packages are given in `spec`
+    pub all: bool,
+
     /// Filter to apply to the root package to select which targets will be built.
     pub filter: CompileFilter<'a>,
 
@@ -71,11 +78,29 @@ pub struct CompileOptions<'a> {
     /// `--error_format` flag for the compiler.
     pub message_format: MessageFormat,
 
+    /// Whether deprecated constructs reported by the compiler should be
+    /// collected into a report after the build.
+    pub future_incompat: bool,
+
+    /// Directory to copy the final artifacts to, overriding `build.out-dir`
+    pub out_dir: Option<&'a str>,
+
+    /// Whether to write the fingerprint of every unit along with the reason
+    /// it's rebuilt next to the fingerprint files
+    pub dump_fingerprints: bool,
+
+    /// Sanitizer to instrument the code with, like `address`
+    pub sanitizer: Option<&'a str>,
+
+    /// Whether to print the commands of the build as JSON instead of running
+    /// them
+    pub build_plan: bool,
+
     /// Extra arguments to be passed to doc (for main chest and dependencies)
     pub target_doc_args: Option<&'a [String]>,
 
-    /// The specified target will be compiled with all the available arguments, note that this only
-    /// accounts for the *final* invocation of cc
+    /// The targets of the selected packages will be compiled with all the available arguments, note
+    /// that this only accounts for the *final* invocations of cc, not for the dependencies
     pub target_cc_args: Option<&'a [String]>,
 }
 
@@ -90,6 +115,7 @@ pub enum CompileMode {
 #[derive(Clone, Copy, PartialEq, Eq, RustcDecodable)]
 pub enum MessageFormat {
     Human,
+    Short,
     Json,
 }
 
@@ -105,10 +131,25 @@ pub enum CompileFilter<'a> {
 }
 
 pub fn compile<'a>(ws: &Workspace<'a>, options: &CompileOptions<'a>) -> CraftResult<ops::Compilation<'a>> {
-    for key in ws.current()?.manifest().warnings().iter() {
-        options.config.shell().warn(key)?
+    let result = (|| {
+        for pkg in ws.default_members() {
+            for key in pkg.manifest().warnings().iter() {
+                options.config.shell().warn(key)?
+            }
+        }
+        compile_ws(ws, None, options)
+    })();
+    build_finished(options, result)
+}
+
+/// Lets the consumers of the JSON messages know that the build is over, no
+/// matter where it failed, be it resolving the dependencies or compiling.
+/// Entry points call this once, after all of their builds.
+pub fn build_finished<T>(options: &CompileOptions, result: CraftResult<T>) -> CraftResult<T> {
+    if options.message_format == MessageFormat::Json {
+        machine_message::BuildFinished::new(result.is_ok()).emit();
     }
-    compile_ws(ws, None, options)
+    result
 }
 
 pub fn resolve_dependencies<'a>(ws: &Workspace<'a>,
@@ -116,8 +157,13 @@ pub fn resolve_dependencies<'a>(ws: &Workspace<'a>,
                                 features: &[String],
                                 all_features: bool,
                                 no_default_features: bool,
-                                spec: &'a [String])
+                                spec: &[String])
                                 -> CraftResult<(PackageSet<'a>, Resolve)> {
+    if all_features && no_default_features {
+        bail!("`--all-features` and `--no-default-features` can't be used together, \
+               `--all-features` already enables the default features")
+    }
+
     let features = features.iter()
         .flat_map(|s| s.split_whitespace())
         .map(|s| s.to_string())
@@ -165,17 +211,22 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
                       source: Option<Box<Source + 'a>>,
                       options: &CompileOptions<'a>)
                       -> CraftResult<ops::Compilation<'a>> {
-    let root_package = ws.current()?;
     let CompileOptions { config,
                          jobs,
                          target,
                          spec,
+                         all,
                          features,
                          all_features,
                          no_default_features,
                          release,
                          mode,
                          message_format,
+                         future_incompat,
+                         out_dir,
+                         dump_fingerprints,
+                         sanitizer,
+                         build_plan,
                          ref filter,
                          ref target_doc_args,
                          ref target_cc_args } = *options;
@@ -185,12 +236,36 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
     if jobs == Some(0) {
         bail!("jobs must be at least 1")
     }
-
-    let profiles = ws.profiles();
-    if spec.len() == 0 {
-        generate_targets(root_package, profiles, mode, filter, release)?;
+    match sanitizer {
+        None | Some("address") | Some("undefined") | Some("thread") => {}
+        Some(sanitizer) => {
+            bail!("unknown sanitizer `{}`, expected one of `address`, `undefined` or `thread`",
+                  sanitizer)
+        }
     }
 
+    // Without explicitly selected packages the default members of the
+    // workspace are built, or all of them with `all`
+    let members;
+    let spec = if spec.len() > 0 {
+        spec
+    } else {
+        let profiles = ws.profiles();
+        let to_build = if all {
+            ws.members().collect::<Vec<_>>()
+        } else {
+            ws.default_members().collect::<Vec<_>>()
+        };
+        for pkg in to_build.iter() {
+            generate_targets(pkg, profiles, mode, filter, release)?;
+        }
+        members = to_build.iter()
+            .map(|pkg| PackageIdSpec::from_package_id(pkg.package_id()).to_string())
+            .collect::<Vec<_>>();
+        &members[..]
+    };
+
+    let profiles = ws.profiles();
     let (packages, resolve_with_overrides) = resolve_dependencies(ws,
                                                                   source,
                                                                   features,
@@ -198,14 +273,9 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
                                                                   no_default_features,
                                                                   spec)?;
 
-    let mut pkgids = Vec::new();
-    if spec.len() > 0 {
-        for p in spec {
-            pkgids.push(resolve_with_overrides.query(&p)?);
-        }
-    } else {
-        pkgids.push(root_package.package_id());
-    };
+    let pkgids = spec.iter()
+        .map(|p| resolve_with_overrides.query(p))
+        .collect::<CraftResult<Vec<_>>>()?;
 
     let to_builds = pkgids.iter()
         .map(|id| packages.get(id))
@@ -215,20 +285,16 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
     let mut package_targets = Vec::new();
 
     match (*target_cc_args, *target_doc_args) {
-        (Some(..), _) | (_, Some(..)) if to_builds.len() != 1 => {
-            panic!("`cc` and `doc` should not accept multiple `-p` flags")
-        }
+        (_, Some(..)) if to_builds.len() != 1 => panic!("`doc` should not accept multiple `-p` flags"),
         (Some(args), _) => {
-            let targets = generate_targets(to_builds[0], profiles, mode, filter, release)?;
-            if targets.len() == 1 {
-                let (target, profile) = targets[0];
-                let mut profile = profile.clone();
-                profile.cc_args = Some(args.to_vec());
-                general_targets.push((target, profile));
-            } else {
-                bail!("extra arguments to `cc` can only be passed to one \
-                       target, consider filtering\nthe package by passing \
-                       e.g. `--lib` or `--bin NAME` to specify a single target")
+            // Only the selected packages are compiled with the extra
+            // arguments, never their dependencies
+            for &to_build in to_builds.iter() {
+                for (target, profile) in generate_targets(to_build, profiles, mode, filter, release)? {
+                    let mut profile = profile.clone();
+                    profile.cc_args = Some(args.to_vec());
+                    general_targets.push((to_build, target, profile));
+                }
             }
         }
         (None, Some(args)) => {
@@ -237,7 +303,7 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
                 let (target, profile) = targets[0];
                 let mut profile = profile.clone();
                 profile.doc_args = Some(args.to_vec());
-                general_targets.push((target, profile));
+                general_targets.push((to_builds[0], target, profile));
             } else {
                 bail!("extra arguments to `doc` can only be passed to one \
                        target, consider filtering\nthe package by passing e.g. \
@@ -252,10 +318,8 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
         }
     };
 
-    for &(target, ref profile) in &general_targets {
-        for &to_build in to_builds.iter() {
-            package_targets.push((to_build, vec![(target, profile)]));
-        }
+    for &(to_build, target, ref profile) in &general_targets {
+        package_targets.push((to_build, vec![(target, profile)]));
     }
 
     let mut ret = {
@@ -264,6 +328,14 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
         build_config.release = release;
         build_config.test = mode == CompileMode::Test || mode == CompileMode::Bench;
         build_config.json_errors = message_format == MessageFormat::Json;
+        build_config.short_errors = message_format == MessageFormat::Short;
+        build_config.future_incompat = future_incompat;
+        if let Some(out_dir) = out_dir {
+            build_config.out_dir = Some(config.cwd().join(out_dir));
+        }
+        build_config.dump_fingerprints = dump_fingerprints;
+        build_config.sanitizer = sanitizer.map(|s| s.to_string());
+        build_config.build_plan = build_plan;
         if let CompileMode::Doc { deps } = mode {
             build_config.doc_all = deps;
         }
@@ -471,7 +543,15 @@ fn add_overrides<'a>(registry: &mut PackageRegistry<'a>, ws: &Workspace<'a>) ->
 ///
 /// * build.jobs
 /// * build.target
+/// * build.link-driver
+/// * build.unity
+/// * build.symbol-visibility
+/// * build.jobserver
+/// * target-applies-to-host
+/// * build.out-dir (or the `CRAFT_OUT_DIR` environment variable)
 /// * target.$target.ar
+/// * target.$target.ranlib
+/// * target.$target.rc
 /// * target.$target.linker
 /// * target.$target.libfoo.metadata
 fn scrape_build_config(config: &Config, jobs: Option<u32>, target: Option<String>) -> CraftResult<ops::BuildConfig> {
@@ -494,10 +574,37 @@ fn scrape_build_config(config: &Config, jobs: Option<u32>, target: Option<String
     let jobs = jobs.or(cfg_jobs).unwrap_or(::num_cpus::get() as u32);
     let cfg_target = config.get_string("build.target")?.map(|s| s.val);
     let target = target.or(cfg_target);
+    let link_driver = config.get_path("build.link-driver")?.map(|v| v.val);
+    let unity = config.get_bool("build.unity")?.map(|v| v.val).unwrap_or(false);
+    let hidden_visibility = match config.get_string("build.symbol-visibility")? {
+        Some(ref v) if v.val == "hidden" => true,
+        Some(ref v) if v.val == "default" => false,
+        Some(v) => {
+            bail!("build.symbol-visibility must be `hidden` or `default`, but found `{}` in {}",
+                  v.val,
+                  v.definition)
+        }
+        None => false,
+    };
+    let jobserver = match config.get_bool("build.jobserver")? {
+        Some(ref v) if !v.val => None,
+        _ => jobserver::Client::from_env().map(Arc::new),
+    };
+    let target_applies_to_host = config.get_bool("target-applies-to-host")?.map(|v| v.val);
+    let out_dir = match env::var_os("CRAFT_OUT_DIR") {
+        Some(dir) => Some(config.cwd().join(dir)),
+        None => config.get_path("build.out-dir")?.map(|v| v.val),
+    };
     let mut base = ops::BuildConfig {
         host_triple: config.cc()?.host.clone(),
         requested_target: target.clone(),
         jobs: jobs,
+        link_driver: link_driver,
+        unity: unity,
+        hidden_visibility: hidden_visibility,
+        jobserver: jobserver,
+        target_applies_to_host: target_applies_to_host,
+        out_dir: out_dir,
         ..Default::default()
     };
     base.host = scrape_target_config(config, &base.host_triple)?;
@@ -513,6 +620,8 @@ fn scrape_target_config(config: &Config, triple: &str) -> CraftResult<ops::Targe
     let key = format!("target.{}", triple);
     let mut ret = ops::TargetConfig {
         ar: config.get_path(&format!("{}.ar", key))?.map(|v| v.val),
+        ranlib: config.get_path(&format!("{}.ranlib", key))?.map(|v| v.val),
+        rc: config.get_path(&format!("{}.rc", key))?.map(|v| v.val),
         linker: config.get_path(&format!("{}.linker", key))?.map(|v| v.val),
         overrides: HashMap::new(),
     };
@@ -521,16 +630,21 @@ fn scrape_target_config(config: &Config, triple: &str) -> CraftResult<ops::Targe
         None => return Ok(ret),
     };
     for (lib_name, value) in table {
-        if lib_name == "ar" || lib_name == "linker" || lib_name == "cflags" {
-            continue;
+        match &lib_name[..] {
+            "ar" | "ranlib" | "rc" | "linker" | "cflags" | "asflags" => continue,
+            _ => {}
         }
 
         let mut output = BuildOutput {
             library_paths: Vec::new(),
             library_links: Vec::new(),
+            cfgs: Vec::new(),
+            env: Vec::new(),
             metadata: Vec::new(),
             rerun_if_changed: Vec::new(),
             warnings: Vec::new(),
+            compile_sources: Vec::new(),
+            cflags: Vec::new(),
         };
         for (k, value) in value.table(&lib_name)?.0 {
             let key = format!("{}.{}", key, k);
@@ -543,9 +657,10 @@ fn scrape_target_config(config: &Config, triple: &str) -> CraftResult<ops::Targe
                     output.library_links.extend(links);
                 }
                 "cc-link-lib" => {
-                    let list = value.list(&k)?;
-                    output.library_links.extend(list.iter()
-                        .map(|v| v.0.clone()));
+                    for (lib, definition) in value.list(&k)? {
+                        let whence = format!("in `{}` (in {})", key, definition.display());
+                        output.library_links.push(LibraryLink::parse(&lib, &whence)?);
+                    }
                 }
                 "cc-link-search" => {
                     let list = value.list(&k)?;
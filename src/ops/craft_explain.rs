@@ -0,0 +1,46 @@
+//! Explanations for compiler warnings
+const CLANG_REFERENCE: &'static str = "https://clang.llvm.org/docs/DiagnosticsReference.html";
+const GCC_REFERENCE: &'static str = "https://gcc.gnu.org/onlinedocs/gcc/Warning-Options.html";
+
+// Built-in descriptions of commonly seen warnings, by their `-W` name.
+const WARNINGS: &'static [(&'static str, &'static str)] =
+    &[("deprecated-declarations", "A function, variable or type marked as deprecated is used."),
+      ("format", "The arguments of a printf or scanf style call don't match its format string."),
+      ("implicit-fallthrough", "A switch case falls through to the next one without a break or an annotation."),
+      ("implicit-function-declaration", "A function is called without being declared first, so the compiler \
+                                         assumes it returns `int`."),
+      ("incompatible-pointer-types", "A pointer is converted to a pointer of an unrelated type without a \
+                                      cast."),
+      ("int-conversion", "An integer is converted to a pointer or the other way around without a cast."),
+      ("missing-prototypes", "A global function is defined without a previous prototype declaration."),
+      ("return-type", "A function returning a value may reach its end without returning anything."),
+      ("shadow", "A local declaration hides another variable or parameter of the same name."),
+      ("sign-compare", "A signed value is compared with an unsigned one, which converts the signed value."),
+      ("uninitialized", "A variable may be used before a value is assigned to it."),
+      ("unused-function", "A static function is defined but never used."),
+      ("unused-parameter", "A function parameter is never used in the function body."),
+      ("unused-variable", "A local or static variable is declared but never used.")];
+
+/// Returns an explanation of the compiler warning `name`, which may be given
+/// with or without its `-W` prefix.
+///
+/// Warnings unknown to craft point to the compiler documentation instead.
+pub fn explain(name: &str) -> String {
+    let name = name.trim_left_matches("-W").trim_left_matches('W');
+    match WARNINGS.iter().find(|&&(warning, _)| warning == name) {
+        Some(&(_, description)) => {
+            format!("-W{}: {}\n\nSee {}#w{} or {} for more information.",
+                    name,
+                    description,
+                    CLANG_REFERENCE,
+                    name,
+                    GCC_REFERENCE)
+        }
+        None => {
+            format!("No explanation available for `-W{}`, check the documentation of your compiler:\n\n    {}\n    {}",
+                    name,
+                    CLANG_REFERENCE,
+                    GCC_REFERENCE)
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use std::collections::{BTreeMap, HashSet};
 
+use dependency::Dependency;
 use ops;
 use package_id::PackageId;
-use registry::PackageRegistry;
+use registry::{PackageRegistry, Registry};
 use resolver::{Method, Resolve};
 use source::SourceId;
-use util::CraftResult;
+use util::{CraftResult, ToSemver, human};
 use util::config::Config;
 use workspace::Workspace;
 
@@ -14,6 +15,8 @@ pub struct UpdateOptions<'a> {
     pub to_update: &'a [String],
     pub precise: Option<&'a str>,
     pub aggressive: bool,
+    /// Only print what would be updated, without writing `Craft.lock`.
+    pub dry_run: bool,
 }
 
 pub fn generate_lockfile(ws: &Workspace) -> CraftResult<()> {
@@ -29,12 +32,17 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions) -> CraftResult<()>
         bail!("cannot specify both aggressive and precise simultaneously")
     }
 
+    if opts.precise.is_some() && opts.to_update.is_empty() {
+        bail!("`--precise` requires a package to update, pass it with `-p`")
+    }
+
     if ws.members().is_empty() {
         bail!("you can't generate a lockfile for an empty workspace.")
     }
 
     let previous_resolve = match ops::load_pkg_lockfile(ws)? {
         Some(resolve) => resolve,
+        None if opts.dry_run => bail!("there is no `Craft.lock` yet to compare a dry run against"),
         None => return generate_lockfile(ws),
     };
     let mut registry = PackageRegistry::new(opts.config)?;
@@ -44,6 +52,7 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions) -> CraftResult<()>
         to_avoid.extend(previous_resolve.iter());
     } else {
         let mut sources = Vec::new();
+        let mut precise_deps = Vec::new();
         for name in opts.to_update {
             let dep = previous_resolve.query(name)?;
             if opts.aggressive {
@@ -56,6 +65,9 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions) -> CraftResult<()>
                         //       seems like a pretty hokey reason to single out
                         //       the registry as well.
                         let precise = if dep.source_id().is_registry() {
+                            let version = precise.to_semver()
+                                .map_err(|_| human(format!("invalid version `{}` passed to `--precise`", precise)))?;
+                            precise_deps.push((dep, version));
                             format!("{}={}", dep.name(), precise)
                         } else {
                             precise.to_string()
@@ -67,6 +79,18 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions) -> CraftResult<()>
             }
         }
         registry.add_sources(&sources)?;
+
+        // The registries were told which version to pick, make sure they
+        // actually have it before resolving anything.
+        for (dep, version) in precise_deps {
+            let query = Dependency::parse_no_deprecated(dep.name(), None, dep.source_id())?;
+            if !registry.query(&query)?.iter().any(|s| *s.version() == version) {
+                bail!("version {} of `{}` was not found in {}",
+                      version,
+                      dep.name(),
+                      dep.source_id())
+            }
+        }
     }
 
     let resolve = ops::resolve_with_previous(&mut registry,
@@ -98,6 +122,10 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions) -> CraftResult<()>
         }
     }
 
+    if opts.dry_run {
+        opts.config.shell().warn("not updating the lock file due to dry run")?;
+        return Ok(());
+    }
     ops::write_pkg_lockfile(&ws, &resolve)?;
     return Ok(());
 
@@ -4,7 +4,7 @@ use std::env;
 use std::ffi::OsString;
 use std::fs::{self, File};
 use std::io::prelude::*;
-use std::io::SeekFrom;
+use std::io::{self, SeekFrom};
 use std::path::{Path, PathBuf};
 
 use tempdir::TempDir;
@@ -121,7 +121,7 @@ pub fn install(root: Option<&str>,
         check_overwrites(&dst, pkg, &opts.filter, &list, force)?;
     }
 
-    let compile = ops::compile_ws(&ws, Some(source), opts).chain_error(|| {
+    let compile = ops::build_finished(opts, ops::compile_ws(&ws, Some(source), opts)).chain_error(|| {
             if let Some(td) = td_opt.take() {
                 // preserve the temporary directory, so the user can inspect it
                 td.into_path();
@@ -421,6 +421,33 @@ fn write_chest_list(mut file: &File, listing: ChestListingV1) -> CraftResult<()>
         .chain_error(|| human("failed to write chest metadata"))
 }
 
+/// An installed package and the binaries it provides, as reported by
+/// `install_list_json`.
+#[derive(RustcEncodable)]
+pub struct InstalledPackage {
+    pub package: String,
+    pub version: String,
+    pub bins: Vec<String>,
+}
+
+/// Returns the packages recorded in the install tracking file under `dst`,
+/// meant to be passed on to `process_executed` for JSON output.
+pub fn install_list_json(dst: Option<&str>, config: &Config) -> CraftResult<Vec<InstalledPackage>> {
+    let dst = resolve_root(dst, config)?;
+    let dst = metadata(config, &dst)?;
+    let list = read_chest_list(dst.file())?;
+    Ok(list.v1
+        .into_iter()
+        .map(|(id, bins)| {
+            InstalledPackage {
+                package: id.name().to_string(),
+                version: id.version().to_string(),
+                bins: bins.into_iter().collect(),
+            }
+        })
+        .collect())
+}
+
 pub fn install_list(dst: Option<&str>, config: &Config) -> CraftResult<()> {
     let dst = resolve_root(dst, config)?;
     let dst = metadata(config, &dst)?;
@@ -436,62 +463,67 @@ pub fn install_list(dst: Option<&str>, config: &Config) -> CraftResult<()> {
     Ok(())
 }
 
+/// Removes the binaries installed by the package matching `spec`, or only the
+/// given `bins` of it.
+///
+/// The metadata stays locked until it's written back, and only forgets about
+/// the binaries which were actually removed, so an interrupted uninstall keeps
+/// the remaining binaries registered.
 pub fn uninstall(root: Option<&str>, spec: &str, bins: &[String], config: &Config) -> CraftResult<()> {
     let root = resolve_root(root, config)?;
     let chest_metadata = metadata(config, &root)?;
     let mut metadata = read_chest_list(chest_metadata.file())?;
-    let mut to_remove = Vec::new();
+    let id = PackageIdSpec::query_str(spec, metadata.v1.keys())?.clone();
+    let dst = chest_metadata.parent().join("bin");
+
+    let bins = bins.iter()
+        .map(|s| {
+            if s.ends_with(env::consts::EXE_SUFFIX) {
+                s.to_string()
+            } else {
+                format!("{}{}", s, env::consts::EXE_SUFFIX)
+            }
+        })
+        .collect::<BTreeSet<_>>();
+
+    let mut result = Ok(());
     {
-        let result = PackageIdSpec::query_str(spec, metadata.v1.keys())?.clone();
-        let mut installed = match metadata.v1.entry(result.clone()) {
+        let mut installed = match metadata.v1.entry(id.clone()) {
             Entry::Occupied(e) => e,
-            Entry::Vacant(..) => panic!("entry not found: {}", result),
+            Entry::Vacant(..) => panic!("entry not found: {}", id),
         };
-        let dst = chest_metadata.parent().join("bin");
-        for bin in installed.get() {
-            let bin = dst.join(bin);
-            if fs::metadata(&bin).is_err() {
-                bail!("corrupt metadata, `{}` does not exist when it should",
-                      bin.display())
-            }
-        }
-
-        let bins = bins.iter()
-            .map(|s| {
-                if s.ends_with(env::consts::EXE_SUFFIX) {
-                    s.to_string()
-                } else {
-                    format!("{}{}", s, env::consts::EXE_SUFFIX)
-                }
-            })
-            .collect::<Vec<_>>();
-
         for bin in bins.iter() {
             if !installed.get().contains(bin) {
-                bail!("binary `{}` not installed as part of `{}`", bin, result)
+                bail!("binary `{}` not installed as part of `{}`", bin, id)
             }
         }
 
-        if bins.is_empty() {
-            to_remove.extend(installed.get().iter().map(|b| dst.join(b)));
-            installed.get_mut().clear();
+        let to_remove = if bins.is_empty() {
+            installed.get().clone()
         } else {
-            for bin in bins.iter() {
-                to_remove.push(dst.join(bin));
-                installed.get_mut().remove(bin);
+            bins
+        };
+        for bin in to_remove {
+            let path = dst.join(&bin);
+            config.shell().status("Removing", path.display())?;
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                    config.shell().warn(format!("`{}` was already removed", path.display()))?;
+                }
+                Err(e) => {
+                    result = Err(human(format!("failed to remove `{}`: {}", path.display(), e)));
+                    break;
+                }
             }
+            installed.get_mut().remove(&bin);
         }
         if installed.get().is_empty() {
             installed.remove();
         }
     }
     write_chest_list(chest_metadata.file(), metadata)?;
-    for bin in to_remove {
-        config.shell().status("Removing", bin.display())?;
-        fs::remove_file(bin)?;
-    }
-
-    Ok(())
+    result
 }
 
 fn metadata(config: &Config, root: &Filesystem) -> CraftResult<FileLock> {
@@ -506,3 +538,71 @@ fn resolve_root(flag: Option<&str>, config: &Config) -> CraftResult<Filesystem>
         .map(Filesystem::new)
         .unwrap_or_else(|| config.home().clone()))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::fs::{self, File};
+
+    use tempdir::TempDir;
+
+    use package_id::PackageId;
+    use shell::{Verbosity, ColorConfig};
+    use source::SourceId;
+    use util::Config;
+    use super::{ChestListingV1, install_list_json, metadata, resolve_root, uninstall, write_chest_list};
+
+    #[test]
+    fn install_list_json_reports_tracked_bins() {
+        let td = TempDir::new("craft-install").unwrap();
+        let shell = ::shell(Verbosity::Quiet, ColorConfig::Never);
+        let config = Config::new(shell, td.path().to_path_buf(), td.path().join("home"));
+        let root = td.path().to_str().unwrap();
+
+        let id = PackageId::new("foo", "0.1.0", &SourceId::for_path(td.path()).unwrap()).unwrap();
+        let mut v1 = BTreeMap::new();
+        v1.insert(id, vec!["foo".to_string(), "foo-cli".to_string()].into_iter().collect::<BTreeSet<_>>());
+        {
+            let lock = metadata(&config, &resolve_root(Some(root), &config).unwrap()).unwrap();
+            write_chest_list(lock.file(), ChestListingV1 { v1: v1 }).unwrap();
+        }
+
+        let list = install_list_json(Some(root), &config).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].package, "foo");
+        assert_eq!(list[0].version, "0.1.0");
+        assert_eq!(list[0].bins, vec!["foo".to_string(), "foo-cli".to_string()]);
+    }
+
+    #[test]
+    fn partial_uninstall_keeps_remaining_bins() {
+        let td = TempDir::new("craft-install").unwrap();
+        let shell = ::shell(Verbosity::Quiet, ColorConfig::Never);
+        let config = Config::new(shell, td.path().to_path_buf(), td.path().join("home"));
+        let root = td.path().to_str().unwrap();
+
+        let id = PackageId::new("foo", "0.1.0", &SourceId::for_path(td.path()).unwrap()).unwrap();
+        let mut v1 = BTreeMap::new();
+        v1.insert(id, vec!["foo".to_string(), "foo-cli".to_string()].into_iter().collect::<BTreeSet<_>>());
+        {
+            let lock = metadata(&config, &resolve_root(Some(root), &config).unwrap()).unwrap();
+            write_chest_list(lock.file(), ChestListingV1 { v1: v1 }).unwrap();
+        }
+        let bin = td.path().join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        File::create(bin.join("foo")).unwrap();
+        File::create(bin.join("foo-cli")).unwrap();
+
+        assert!(uninstall(Some(root), "foo", &["bar".to_string()], &config).is_err());
+        uninstall(Some(root), "foo", &["foo-cli".to_string()], &config).unwrap();
+        assert!(bin.join("foo").exists());
+        assert!(!bin.join("foo-cli").exists());
+        let list = install_list_json(Some(root), &config).unwrap();
+        assert_eq!(list[0].bins, vec!["foo".to_string()]);
+
+        // An already removed binary is only forgotten about.
+        fs::remove_file(bin.join("foo")).unwrap();
+        uninstall(Some(root), "foo", &[], &config).unwrap();
+        assert!(install_list_json(Some(root), &config).unwrap().is_empty());
+    }
+}
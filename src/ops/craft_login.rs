@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+use std::io::SeekFrom;
+use std::io::prelude::*;
+use std::path::Path;
+
+use toml;
+
+use util::{CraftResult, Config};
+use util::toml as craft_toml;
+
+/// Stores the registry `token` in `$CRAFT_HOME/credentials`, replacing any
+/// token stored before.
+pub fn registry_login(config: &Config, token: String) -> CraftResult<()> {
+    let home = config.home();
+    home.create_dir()?;
+    create_private(&home.join("credentials").into_path_unlocked())?;
+    let mut file = home.open_rw(Path::new("credentials"), config, "the credentials file")?;
+    restrict_permissions(file.path())?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let mut toml = craft_toml::parse(&contents, file.path(), config)?;
+    let mut registry = match toml.remove("registry") {
+        Some(toml::Value::Table(registry)) => registry,
+        _ => BTreeMap::new(),
+    };
+    if registry.insert("token".to_string(), toml::Value::String(token)).is_some() {
+        config.shell().warn(format!("overwriting the registry token stored in `{}`", file.path().display()))?;
+    }
+    toml.insert("registry".to_string(), toml::Value::Table(registry));
+
+    let contents = toml::Value::Table(toml).to_string();
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(contents.as_bytes())?;
+    file.file().set_len(contents.len() as u64)?;
+    Ok(())
+}
+
+// The token grants access to the registry account, so only the owner may read
+// it. A new file is created that way, so it's never readable by others, and the
+// permissions of an existing one are fixed up.
+#[cfg(unix)]
+fn create_private(path: &Path) -> CraftResult<()> {
+    use std::fs::OpenOptions;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    OpenOptions::new().write(true).create(true).mode(0o600).open(path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_private(_path: &Path) -> CraftResult<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> CraftResult<()> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> CraftResult<()> {
+    Ok(())
+}
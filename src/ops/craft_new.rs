@@ -1,11 +1,16 @@
 use std::{env, fs};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::collections::BTreeMap;
 
 use rustc_serialize::{Decodable, Decoder};
+use git2;
 use git2::Config as GitConfig;
+use tempdir::TempDir;
 
-use util::{GitRepo, HgRepo, CraftResult, human, ChainError, internal, Config, paths};
+use manifest::Language;
+use source::GitReference;
+use sources::git::{self, GitRemote};
+use util::{GitRepo, HgRepo, CraftResult, human, ChainError, internal, Config, paths, ToUrl};
 use workspace::Workspace;
 
 use toml;
@@ -23,6 +28,9 @@ pub struct NewOptions<'a> {
     pub lib: bool,
     pub path: &'a str,
     pub name: Option<&'a str>,
+    pub with_config: bool,
+    pub language: Option<Language>,
+    pub template: Option<&'a str>,
 }
 
 struct SourceFileInformation {
@@ -37,6 +45,9 @@ struct MkOptions<'a> {
     name: &'a str,
     source_files: Vec<SourceFileInformation>,
     bin: bool,
+    with_config: bool,
+    language: Language,
+    template: Option<&'a Path>,
 }
 
 impl Decodable for VersionControl {
@@ -53,12 +64,22 @@ impl Decodable for VersionControl {
     }
 }
 
+impl Decodable for Language {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Language, D::Error> {
+        let s = d.read_str()?;
+        s.parse().map_err(|_| d.error(&format!("could not decode '{}' as language, expected `c` or `cpp`", s)))
+    }
+}
+
 impl<'a> NewOptions<'a> {
     pub fn new(version_control: Option<VersionControl>,
                bin: bool,
                lib: bool,
                path: &'a str,
-               name: Option<&'a str>)
+               name: Option<&'a str>,
+               with_config: bool,
+               language: Option<Language>,
+               template: Option<&'a str>)
                -> NewOptions<'a> {
 
         // default to lib
@@ -70,6 +91,9 @@ impl<'a> NewOptions<'a> {
             lib: is_lib,
             path: path,
             name: name,
+            with_config: with_config,
+            language: language,
+            template: template,
         }
     }
 }
@@ -100,14 +124,22 @@ fn get_name<'a>(path: &'a Path, opts: &'a NewOptions) -> CraftResult<&'a str> {
     Ok(dir_name)
 }
 
-fn check_name(name: &str) -> CraftResult<()> {
+fn check_name(name: &str, language: Language) -> CraftResult<()> {
 
     // Ban keywords
     let blacklist = ["chest", "test", "true", "auto", "break", "case", "char", "const", "continue", "default", "do",
                      "double", "else", "enum", "extern", "float", "for", "goto", "if", "int", "long", "register",
                      "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef", "union",
                      "unsigned", "void", "volatile", "while"];
-    if blacklist.contains(&name) {
+    // C++ reserves all of the C keywords and then some
+    let cpp_blacklist = ["alignas", "alignof", "and", "and_eq", "asm", "bitand", "bitor", "bool", "catch", "char16_t",
+                         "char32_t", "class", "compl", "constexpr", "const_cast", "decltype", "delete",
+                         "dynamic_cast", "explicit", "export", "false", "friend", "inline", "mutable", "namespace",
+                         "new", "noexcept", "not", "not_eq", "nullptr", "operator", "or", "or_eq", "private",
+                         "protected", "public", "reinterpret_cast", "static_assert", "static_cast", "template",
+                         "this", "thread_local", "throw", "try", "typeid", "typename", "using", "virtual", "wchar_t",
+                         "xor", "xor_eq"];
+    if blacklist.contains(&name) || (language == Language::Cpp && cpp_blacklist.contains(&name)) {
         bail!("The name `{}` cannot be used as a chest name\n\
                use --name to override chest name",
               name)
@@ -146,14 +178,15 @@ fn detect_source_paths_and_types(project_path: &Path,
         handling: H,
     }
 
-    let tests = vec![
-        Test { proposed_path: format!("src/main.c"),     handling: H::Bin },
-        Test { proposed_path: format!("main.c"),         handling: H::Bin },
-        Test { proposed_path: format!("src/{}.c", name), handling: H::Detect },
-        Test { proposed_path: format!("{}.c", name),     handling: H::Detect },
-        Test { proposed_path: format!("src/lib.c"),      handling: H::Lib },
-        Test { proposed_path: format!("lib.c"),          handling: H::Lib },
-    ];
+    let mut tests = Vec::new();
+    for ext in &["c", "cpp", "cc"] {
+        tests.push(Test { proposed_path: format!("src/main.{}", ext),     handling: H::Bin });
+        tests.push(Test { proposed_path: format!("main.{}", ext),         handling: H::Bin });
+        tests.push(Test { proposed_path: format!("src/{}.{}", name, ext), handling: H::Detect });
+        tests.push(Test { proposed_path: format!("{}.{}", name, ext),     handling: H::Detect });
+        tests.push(Test { proposed_path: format!("src/lib.{}", ext),      handling: H::Lib });
+        tests.push(Test { proposed_path: format!("lib.{}", ext),          handling: H::Lib });
+    }
 
     for i in tests {
         let pp = i.proposed_path;
@@ -220,16 +253,16 @@ cannot automatically generate Craft.toml as the main target would be ambiguous",
     Ok(())
 }
 
-fn plan_new_source_file(bin: bool, project_name: String) -> SourceFileInformation {
+fn plan_new_source_file(bin: bool, project_name: String, language: Language) -> SourceFileInformation {
     if bin {
         SourceFileInformation {
-            relative_path: "src/main.c".to_string(),
+            relative_path: format!("src/main.{}", language.extension()),
             target_name: project_name,
             bin: true,
         }
     } else {
         SourceFileInformation {
-            relative_path: "src/lib.c".to_string(),
+            relative_path: format!("src/lib.{}", language.extension()),
             target_name: project_name,
             bin: false,
         }
@@ -247,14 +280,29 @@ pub fn new(opts: NewOptions, config: &Config) -> CraftResult<()> {
     }
 
     let name = get_name(&path, &opts)?;
-    check_name(name)?;
+    let language = opts.language.unwrap_or(Language::C);
+    check_name(name, language)?;
+
+    // A template fetched from a git repository only lives until the project
+    // is created.
+    let template = match opts.template {
+        Some(template) => {
+            let tmp = TempDir::new("craft-template")?;
+            let dir = template_dir(config, template, tmp.path())?;
+            Some((tmp, dir))
+        }
+        None => None,
+    };
 
     let mkopts = MkOptions {
         version_control: opts.version_control,
         path: &path,
         name: name,
-        source_files: vec![plan_new_source_file(opts.bin, name.to_string())],
+        source_files: vec![plan_new_source_file(opts.bin, name.to_string(), language)],
         bin: opts.bin,
+        with_config: opts.with_config,
+        language: language,
+        template: template.as_ref().map(|&(_, ref dir)| dir.as_path()),
     };
 
     mk(config, &mkopts).chain_error(|| {
@@ -277,14 +325,20 @@ pub fn init(opts: NewOptions, config: &Config) -> CraftResult<()> {
     }
 
     let name = get_name(&path, &opts)?;
-    check_name(name)?;
 
     let mut src_paths_types = vec![];
 
     detect_source_paths_and_types(&path, name, &mut src_paths_types)?;
 
+    // Existing C++ sources make it a C++ project, unless told otherwise
+    let language = opts.language.unwrap_or_else(|| {
+        let cpp = src_paths_types.iter().any(|x| !x.relative_path.ends_with(".c"));
+        if cpp { Language::Cpp } else { Language::C }
+    });
+    check_name(name, language)?;
+
     if src_paths_types.len() == 0 {
-        src_paths_types.push(plan_new_source_file(opts.bin, name.to_string()));
+        src_paths_types.push(plan_new_source_file(opts.bin, name.to_string(), language));
     } else {
         // --bin option may be ignored if lib.c or src/lib.c present
         // Maybe when doing `craft init --bin` inside a library project stub,
@@ -320,6 +374,9 @@ pub fn init(opts: NewOptions, config: &Config) -> CraftResult<()> {
         name: name,
         bin: src_paths_types.iter().any(|x| x.bin),
         source_files: src_paths_types,
+        with_config: opts.with_config,
+        language: language,
+        template: None,
     };
 
     mk(config, &mkopts).chain_error(|| {
@@ -350,21 +407,22 @@ fn mk(config: &Config, opts: &MkOptions) -> CraftResult<()> {
         (_, _, true) => VersionControl::NoVcs,
     };
 
-    match vcs {
+    let ignore_file = match vcs {
         VersionControl::Git => {
             if !fs::metadata(&path.join(".git")).is_ok() {
                 GitRepo::init(path, config.cwd())?;
             }
-            paths::append(&path.join(".gitignore"), ignore.as_bytes())?;
+            Some(path.join(".gitignore"))
         }
         VersionControl::Hg => {
             if !fs::metadata(&path.join(".hg")).is_ok() {
                 HgRepo::init(path, config.cwd())?;
             }
-            paths::append(&path.join(".hgignore"), ignore.as_bytes())?;
+            Some(path.join(".hgignore"))
         }
         VersionControl::NoVcs => {
             fs::create_dir_all(path)?;
+            None
         }
     };
 
@@ -379,6 +437,44 @@ fn mk(config: &Config, opts: &MkOptions) -> CraftResult<()> {
         (None, None, name, None) => name,
     };
 
+    // A template replaces the generated manifest and sources, including the
+    // ignore file if it ships one.
+    if let Some(template) = opts.template {
+        expand_template(template, path, name, &author)
+            .chain_error(|| human(format!("failed to expand the template `{}`", template.display())))?;
+    }
+    if let Some(ignore_file) = ignore_file {
+        if opts.template.is_none() || !ignore_file.exists() {
+            paths::append(&ignore_file, ignore.as_bytes())?;
+        }
+    }
+
+    // The starter configuration is meant to be shared, so it's deliberately
+    // not added to any ignore file.
+    let config_path = path.join(".craft").join("config");
+    if opts.with_config && !fs::metadata(&config_path).is_ok() {
+        fs::create_dir_all(path.join(".craft"))?;
+        paths::write(&config_path, STARTER_CONFIG.as_bytes())?;
+    }
+
+    if opts.template.is_none() {
+        write_default_project(opts, &author)?;
+    }
+
+    if let Err(e) = Workspace::new(&path.join("Craft.toml"), config) {
+        let msg = format!("compiling this new chest may not work due to invalid workspace configuration\n\n{}",
+                          e);
+        config.shell().warn(msg)?;
+    }
+
+    Ok(())
+}
+
+// Writes the manifest with the given `author` and the source files of a new
+// project without a template.
+fn write_default_project(opts: &MkOptions, author: &str) -> CraftResult<()> {
+    let path = opts.path;
+    let name = opts.name;
     let mut crafttoml_path_specifier = String::new();
 
     // Calculare what [lib] and [[bin]]s do we need to append to Craft.toml
@@ -404,6 +500,13 @@ path = {}
         }
     }
 
+    // The language is only recorded if it isn't the default
+    let language = if opts.language != Language::C {
+        format!("language = \"{}\"\n", opts.language.name())
+    } else {
+        String::new()
+    };
+
     // Create Craft.toml file with necessary [lib] and [[bin]] sections, if needed
 
     paths::write(&path.join("Craft.toml"),
@@ -411,15 +514,15 @@ path = {}
 name = "{}"
 version = "0.1.0"
 authors = [{}]
-
+{}
 [dependencies]
 {}"#,
                          name,
-                         toml::Value::String(author),
+                         toml::Value::String(author.to_string()),
+                         language,
                          crafttoml_path_specifier)
                      .as_bytes())?;
 
-
     // Create all specified source files
     // (with respective parent directories)
     // if they are don't exist
@@ -431,16 +534,26 @@ authors = [{}]
             fs::create_dir_all(src_dir)?;
         }
 
-        let default_file_content: &[u8] = if i.bin {
-            b"\
+        let default_file_content: &[u8] = match (i.bin, opts.language) {
+            (true, Language::C) => {
+                b"\
 #include <stdio.h>
 
 int main(void) {
     printf(\"Hello, world!\");
 }
 "
-        } else {
-            b""
+            }
+            (true, Language::Cpp) => {
+                b"\
+#include <iostream>
+
+int main() {
+    std::cout << \"Hello, world!\" << std::endl;
+}
+"
+            }
+            (false, _) => b"",
         };
 
         if !fs::metadata(&path_of_source_file).map(|x| x.is_file()).unwrap_or(false) {
@@ -448,15 +561,96 @@ int main(void) {
         }
     }
 
-    if let Err(e) = Workspace::new(&path.join("Craft.toml"), config) {
-        let msg = format!("compiling this new chest may not work due to invalid workspace configuration\n\n{}",
-                          e);
-        config.shell().warn(msg)?;
+    Ok(())
+}
+
+// Returns the directory of `template`, which is either a local path or the URL
+// of a git repository, whose default branch is checked out below `tmp`. Like
+// git, the scp-like syntax `[user@]host:path` is taken for an ssh URL.
+fn template_dir(config: &Config, template: &str, tmp: &Path) -> CraftResult<PathBuf> {
+    let url = if template.contains("://") {
+        template.to_string()
+    } else if let Some(url) = scp_like_url(template) {
+        url
+    } else {
+        let dir = config.cwd().join(template);
+        if !dir.is_dir() {
+            bail!("template `{}` is not a directory", dir.display())
+        }
+        return Ok(dir);
+    };
+
+    let remote = GitRemote::new(&url.to_url()?);
+    (|| -> CraftResult<PathBuf> {
+            // The default branch is whatever the remote HEAD points to
+            let db_path = tmp.join("db");
+            let repo = git2::Repository::init_bare(&db_path)?;
+            git::fetch(&repo, &url, "+HEAD:refs/remotes/origin/HEAD", config)?;
+            let db = remote.db_at(&db_path)?;
+            let rev = db.rev_for(&GitReference::Rev("refs/remotes/origin/HEAD".to_string()))?;
+            let checkout = tmp.join("checkout");
+            db.copy_to(rev, &checkout, true, config)?;
+            Ok(checkout)
+        })()
+        .chain_error(|| human(format!("failed to fetch the template `{}`", template)))
+}
+
+// Returns the ssh URL of the scp-like `template`, which has a colon before its
+// first slash. A single letter in front of the colon is a drive letter rather
+// than a host.
+fn scp_like_url(template: &str) -> Option<String> {
+    let colon = match template.find(':') {
+        Some(colon) => colon,
+        None => return None,
+    };
+    let (host, path) = (&template[..colon], &template[colon + 1..]);
+    if host.len() <= 1 || host.contains('/') || host.contains('\\') {
+        return None;
     }
+    Some(format!("ssh://{}/{}", host, path.trim_left_matches('/')))
+}
 
+// Copies the files of the template `src` into `dst`, replacing the `{{name}}`
+// and `{{authors}}` placeholders in their paths and in their contents, as far
+// as those are text.
+fn expand_template(src: &Path, dst: &Path, name: &str, author: &str) -> CraftResult<()> {
+    let expand = |s: &str| s.replace("{{name}}", name).replace("{{authors}}", author);
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let file_name = file_name.to_str()
+            .chain_error(|| human(format!("template file `{}` has a non-unicode name", entry.path().display())))?;
+        // Left behind by checking out a template from git
+        if file_name == ".git" || file_name == ".craft-ok" {
+            continue;
+        }
+        let target = dst.join(expand(file_name));
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&target)?;
+            expand_template(&entry.path(), &target, name, author)?;
+            continue;
+        }
+        let contents = paths::read_bytes(&entry.path())?;
+        match String::from_utf8(contents) {
+            Ok(text) => paths::write(&target, expand(&text).as_bytes())?,
+            Err(e) => paths::write(&target, &e.into_bytes())?,
+        }
+    }
     Ok(())
 }
 
+const STARTER_CONFIG: &'static str = r#"# Configuration of craft for this project, uncomment a value to change it.
+[build]
+# Number of parallel jobs, defaults to the number of CPUs
+# jobs = 4
+
+# Directory for all generated artifacts
+# target-dir = "target"
+
+# Extra flags passed to the compiler for every C source
+# cflags = ["-Wall", "-Wextra"]
+"#;
+
 fn get_environment_variable(variables: &[&str]) -> Option<String> {
     variables.iter()
         .filter_map(|var| env::var(var).ok())
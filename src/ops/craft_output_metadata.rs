@@ -1,4 +1,5 @@
 use rustc_serialize::{Encodable, Encoder};
+use toml;
 
 use ops;
 use package::Package;
@@ -7,7 +8,7 @@ use resolver::Resolve;
 use util::CraftResult;
 use workspace::Workspace;
 
-const VERSION: u32 = 1;
+const VERSION: u32 = 2;
 
 pub struct OutputMetadataOptions {
     pub features: Vec<String>,
@@ -21,8 +22,9 @@ pub struct OutputMetadataOptions {
 /// used versions - considering overrides - and writes all dependencies in a JSON
 /// format to stdout.
 pub fn output_metadata(ws: &Workspace, opt: &OutputMetadataOptions) -> CraftResult<ExportInfo> {
-    if opt.version != VERSION {
-        bail!("metadata version {} not supported, only {} is currently supported",
+    // Version 2 only added to the format, so version 1 is still served
+    if opt.version < 1 || opt.version > VERSION {
+        bail!("metadata version {} not supported, only 1 to {} are currently supported",
               opt.version,
               VERSION);
     }
@@ -33,12 +35,16 @@ pub fn output_metadata(ws: &Workspace, opt: &OutputMetadataOptions) -> CraftResu
     }
 }
 
-fn metadata_no_deps(ws: &Workspace, _opt: &OutputMetadataOptions) -> CraftResult<ExportInfo> {
+fn metadata_no_deps(ws: &Workspace, opt: &OutputMetadataOptions) -> CraftResult<ExportInfo> {
     Ok(ExportInfo {
         packages: ws.members().cloned().collect(),
         workspace_members: ws.members().map(|pkg| pkg.package_id().clone()).collect(),
+        workspace_root: ws.root().display().to_string(),
+        workspace_metadata: ws.metadata().cloned(),
+        target_directory: ws.target_dir().into_path_unlocked().display().to_string(),
         resolve: None,
-        version: VERSION,
+        format_version: opt.version,
+        version: opt.version,
     })
 }
 
@@ -58,11 +64,15 @@ fn metadata_full(ws: &Workspace, opt: &OutputMetadataOptions) -> CraftResult<Exp
     Ok(ExportInfo {
         packages: packages,
         workspace_members: ws.members().map(|pkg| pkg.package_id().clone()).collect(),
+        workspace_root: ws.root().display().to_string(),
+        workspace_metadata: ws.metadata().cloned(),
+        target_directory: ws.target_dir().into_path_unlocked().display().to_string(),
         resolve: Some(MetadataResolve {
             resolve: resolve,
             root: ws.current_opt().map(|pkg| pkg.package_id().clone()),
         }),
-        version: VERSION,
+        format_version: opt.version,
+        version: opt.version,
     })
 }
 
@@ -70,7 +80,15 @@ fn metadata_full(ws: &Workspace, opt: &OutputMetadataOptions) -> CraftResult<Exp
 pub struct ExportInfo {
     packages: Vec<Package>,
     workspace_members: Vec<PackageId>,
+    workspace_root: String,
+    workspace_metadata: Option<toml::Value>,
+    /// The directory the workspace is built in, like `target`
+    target_directory: String,
     resolve: Option<MetadataResolve>,
+    /// The version of this format that was asked for, later versions only add
+    /// to earlier ones
+    format_version: u32,
+    /// The same as `format_version`, under the name earlier versions used
     version: u32,
 }
 
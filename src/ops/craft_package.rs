@@ -271,9 +271,15 @@ fn run_verify(ws: &Workspace, tar: &File, opts: &PackageOpts) -> CraftResult<()>
                         no_default_features: false,
                         all_features: false,
                         spec: &[],
+                        all: false,
                         filter: ops::CompileFilter::Everything,
                         release: false,
                         message_format: ops::MessageFormat::Human,
+                        future_incompat: false,
+                        out_dir: None,
+                        dump_fingerprints: false,
+                        sanitizer: None,
+                        build_plan: false,
                         mode: ops::CompileMode::Build,
                         target_doc_args: None,
                         target_cc_args: None,
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::io::prelude::*;
+
+use curl::easy::List;
+use rustc_serialize::json::{self, Json};
+
+use dependency::Kind;
+use ops::{self, PackageOpts};
+use package::Package;
+use ops::registry::registry_api;
+use sources::registry::remote::{http_handle, registry_token};
+use util::{CraftResult, Config, ChainError, HttpError, human, network};
+use util::errors::NetworkError;
+use workspace::Workspace;
+
+pub struct PublishOpts<'cfg> {
+    pub config: &'cfg Config,
+    pub token: Option<String>,
+    pub index: Option<String>,
+    pub verify: bool,
+    pub allow_dirty: bool,
+    pub jobs: Option<u32>,
+    pub dry_run: bool,
+}
+
+/// The metadata sent along with the tarball of a new chest.
+#[derive(RustcEncodable)]
+struct NewChest {
+    name: String,
+    vers: String,
+    deps: Vec<NewChestDependency>,
+    features: HashMap<String, Vec<String>>,
+    authors: Vec<String>,
+    description: Option<String>,
+    documentation: Option<String>,
+    homepage: Option<String>,
+    readme: Option<String>,
+    keywords: Vec<String>,
+    license: Option<String>,
+    license_file: Option<String>,
+    repository: Option<String>,
+}
+
+#[derive(RustcEncodable)]
+struct NewChestDependency {
+    name: String,
+    version_req: String,
+    features: Vec<String>,
+    optional: bool,
+    default_features: bool,
+    target: Option<String>,
+    kind: Kind,
+}
+
+/// Packages the current package and uploads it to the registry given by `--index` or the
+/// `registry.index` config value.
+pub fn publish(ws: &Workspace, opts: &PublishOpts) -> CraftResult<()> {
+    let pkg = ws.current()?;
+    let config = opts.config;
+
+    if !pkg.publish() {
+        bail!("some chests cannot be published.\n`{}` is marked as unpublishable",
+              pkg.name());
+    }
+    verify_dependencies(pkg)?;
+
+    let (source_id, api) = registry_api(config, opts.index.clone(), "publish to")?;
+
+    let token = match opts.token {
+        Some(ref token) => Some(token.clone()),
+        None => {
+            match config.get_string("registry.token")? {
+                Some(token) => Some(token.val),
+                None => {
+                    match config.get_registry_token()? {
+                        Some(token) => Some(token),
+                        None => registry_token(config, &source_id)?,
+                    }
+                }
+            }
+        }
+    };
+    let token = match token {
+        Some(token) => token,
+        None => bail!("no upload token found, pass `--token` or run `craft login`"),
+    };
+
+    // Prepare a tarball, with a non-suppressible warning if metadata
+    // is missing since this is being put online.
+    let tarball = ops::package(ws,
+                               &PackageOpts {
+                                   config: config,
+                                   verify: opts.verify,
+                                   list: false,
+                                   check_metadata: true,
+                                   allow_dirty: opts.allow_dirty,
+                                   jobs: opts.jobs,
+                               })?
+        .unwrap();
+
+    config.shell().status("Uploading", pkg.package_id().to_string())?;
+    if opts.dry_run {
+        return config.shell().warn("aborting upload due to dry run");
+    }
+
+    let mut contents = Vec::new();
+    tarball.file().read_to_end(&mut contents)?;
+    let body = upload_body(&new_chest(pkg), &contents);
+    let url = format!("{}/api/v1/chests/new", api.trim_right_matches('/'));
+
+    let mut handle = http_handle(config)?;
+    handle.put(true)?;
+    handle.url(&url)?;
+    handle.in_filesize(body.len() as u64)?;
+    // curl traces the headers it sends, the token included
+    handle.verbose(false)?;
+    let mut headers = List::new();
+    headers.append("Accept: application/json")?;
+    headers.append(&format!("Authorization: {}", token))?;
+    handle.http_headers(headers)?;
+
+    let (code, response) = network::with_retry(config, || -> Result<(u32, Vec<u8>), HttpError> {
+            // Every attempt sends the whole body again
+            let mut body = &body[..];
+            let mut response = Vec::new();
+            {
+                let mut handle = handle.transfer();
+                handle.read_function(|buf| Ok(body.read(buf).unwrap_or(0)))?;
+                handle.write_function(|buf| {
+                        response.extend_from_slice(buf);
+                        Ok(buf.len())
+                    })?;
+                handle.perform()?;
+            }
+            let code = handle.response_code()?;
+            let status = HttpError::Status {
+                url: url.clone(),
+                code: code,
+            };
+            if status.maybe_spurious() {
+                return Err(status);
+            }
+            Ok((code, response))
+        })
+        .chain_error(|| human(format!("failed to publish `{}` to `{}`", pkg.package_id(), url)))?;
+    let response = String::from_utf8_lossy(&response);
+    if let Some(errors) = api_errors(&response) {
+        bail!("failed to publish `{}` to `{}`: {}", pkg.package_id(), url, errors)
+    }
+    if code != 200 {
+        bail!("failed to publish `{}` to `{}`, got response code {}",
+              pkg.package_id(),
+              url,
+              code)
+    }
+
+    config.shell().status("Published", pkg.package_id().to_string())
+}
+
+// Dependencies can only be resolved from a registry once published.
+fn verify_dependencies(pkg: &Package) -> CraftResult<()> {
+    for dep in pkg.dependencies() {
+        if dep.source_id().is_git() {
+            bail!("chests cannot be published with git dependencies.\ndependency `{}` is a git dependency",
+                  dep.name())
+        }
+    }
+    Ok(())
+}
+
+fn new_chest(pkg: &Package) -> NewChest {
+    let md = pkg.manifest().metadata();
+    NewChest {
+        name: pkg.name().to_string(),
+        vers: pkg.version().to_string(),
+        deps: pkg.dependencies()
+            .iter()
+            .map(|dep| {
+                NewChestDependency {
+                    name: dep.name().to_string(),
+                    version_req: dep.version_req().to_string(),
+                    features: dep.features().to_vec(),
+                    optional: dep.is_optional(),
+                    default_features: dep.uses_default_features(),
+                    target: dep.platform().map(|p| p.to_string()),
+                    kind: dep.kind(),
+                }
+            })
+            .collect(),
+        features: pkg.summary().features().clone(),
+        authors: md.authors.clone(),
+        description: md.description.clone(),
+        documentation: md.documentation.clone(),
+        homepage: md.homepage.clone(),
+        readme: md.readme.clone(),
+        keywords: md.keywords.clone(),
+        license: md.license.clone(),
+        license_file: md.license_file.clone(),
+        repository: md.repository.clone(),
+    }
+}
+
+/// The body of an upload is the JSON metadata followed by the tarball, each prefixed by its
+/// length as a little endian 32 bit integer.
+fn upload_body(chest: &NewChest, tarball: &[u8]) -> Vec<u8> {
+    let metadata = json::encode(chest).unwrap();
+    let mut body = Vec::with_capacity(8 + metadata.len() + tarball.len());
+    for part in &[metadata.as_bytes(), tarball] {
+        let len = part.len() as u32;
+        body.extend_from_slice(&[len as u8, (len >> 8) as u8, (len >> 16) as u8, (len >> 24) as u8]);
+        body.extend_from_slice(part);
+    }
+    body
+}
+
+/// Extracts the error details of a registry API response like
+/// `{"errors": [{"detail": "..."}]}`, if any.
+fn api_errors(response: &str) -> Option<String> {
+    let json = match Json::from_str(response) {
+        Ok(json) => json,
+        Err(..) => return None,
+    };
+    let details = match json.find("errors").and_then(|e| e.as_array()) {
+        Some(errors) => {
+            errors.iter()
+                .filter_map(|e| e.find("detail").and_then(|d| d.as_string()))
+                .collect::<Vec<_>>()
+        }
+        None => return None,
+    };
+    if details.is_empty() {
+        None
+    } else {
+        Some(details.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::api_errors;
+
+    #[test]
+    fn extracts_api_errors() {
+        assert_eq!(api_errors(r#"{"errors":[{"detail":"a"},{"detail":"b"}]}"#),
+                   Some("a, b".to_string()));
+        assert_eq!(api_errors(r#"{"ok":true}"#), None);
+        assert_eq!(api_errors("not json"), None);
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+use tempdir::TempDir;
+
+use ops::{self, CompileOptions};
+use util::{CraftResult, Filesystem, human, ChainError};
+use workspace::Workspace;
+
+/// Builds the current package twice, each time into a fresh target directory, and compares the
+/// final artifacts of both builds byte for byte. An error listing every differing artifact is
+/// returned if the builds don't match.
+pub fn verify_reproducible(ws: &Workspace, options: &CompileOptions) -> CraftResult<()> {
+    let result = build_twice(ws, options);
+    ops::build_finished(options, result)
+}
+
+fn build_twice(ws: &Workspace, options: &CompileOptions) -> CraftResult<()> {
+    let config = ws.config();
+    let pkg = ws.current()?;
+
+    let mut outputs = Vec::new();
+    let mut dirs = Vec::new();
+    for _ in 0..2 {
+        let td = TempDir::new("craft-reproducible")?;
+        let ws = Workspace::one(pkg.clone(), config, Some(Filesystem::new(td.path().to_path_buf())))?;
+        let compilation = ops::compile_ws(&ws, None, options)?;
+        outputs.push(compilation.root_output);
+        dirs.push(td);
+    }
+
+    let differing = differing_artifacts(&outputs[0], &outputs[1])?;
+    if !differing.is_empty() {
+        bail!("the build of `{}` is not reproducible, these artifacts differ between two builds:\n  {}",
+              pkg.package_id(),
+              differing.join("\n  "));
+    }
+    config.shell().status("Verified", format!("{} builds reproducibly", pkg.package_id()))
+}
+
+/// Returns the names of the artifacts in the output directories `a` and `b` which only exist in
+/// one of them or whose contents differ. Dep-info and hidden files are skipped as they record
+/// the location of the build.
+fn differing_artifacts(a: &Path, b: &Path) -> CraftResult<Vec<String>> {
+    let artifacts = |dir: &Path| -> CraftResult<BTreeSet<String>> {
+        let mut names = BTreeSet::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with('.') && !name.ends_with(".d") {
+                names.insert(name);
+            }
+        }
+        Ok(names)
+    };
+    let read = |path: &Path| -> CraftResult<Vec<u8>> {
+        let mut contents = Vec::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_end(&mut contents))
+            .chain_error(|| human(format!("failed to read `{}`", path.display())))?;
+        Ok(contents)
+    };
+
+    let (in_a, in_b) = (artifacts(a)?, artifacts(b)?);
+    let mut differing = Vec::new();
+    for name in in_a.union(&in_b) {
+        if !in_a.contains(name) || !in_b.contains(name) || read(&a.join(name))? != read(&b.join(name))? {
+            differing.push(name.clone());
+        }
+    }
+    Ok(differing)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::prelude::*;
+
+    use tempdir::TempDir;
+
+    use super::differing_artifacts;
+
+    #[test]
+    fn lists_differing_and_missing_artifacts() {
+        let td = TempDir::new("craft-reproducible").unwrap();
+        let (a, b) = (td.path().join("a"), td.path().join("b"));
+        for (dir, files) in vec![(&a, vec![("same", "x"), ("changed", "1"), ("foo.d", "a"), ("only-a", "")]),
+                                 (&b, vec![("same", "x"), ("changed", "2"), ("foo.d", "b")])] {
+            fs::create_dir_all(dir).unwrap();
+            for (name, contents) in files {
+                File::create(dir.join(name)).unwrap().write_all(contents.as_bytes()).unwrap();
+            }
+        }
+
+        assert_eq!(differing_artifacts(&a, &b).unwrap(), vec!["changed", "only-a"]);
+    }
+}
@@ -1,51 +1,123 @@
 use std::path::Path;
 
-use ops::{self, CompileFilter};
-use util::{self, CraftResult, ProcessError};
+use manifest::TargetKind;
+use ops::{self, CompileFilter, CompileMode};
+use util::{self, CraftResult, ProcessError, ChainError, human, internal};
 use workspace::Workspace;
 
-pub fn run(ws: &Workspace, options: &ops::CompileOptions, args: &[String]) -> CraftResult<Option<ProcessError>> {
+/// Builds and runs the binary of the current package with `args`.
+///
+/// The bin or example target to run can be selected with the filter of
+/// `options`, otherwise the package has to have exactly one bin target.
+///
+/// If `options` compile in test mode, the library of the current package is
+/// built as a test executable and run instead.
+///
+/// The binary additionally sees the `KEY=VALUE` pairs of `env` in its
+/// environment and runs in `cwd`, relative to the current directory, if given.
+pub fn run(ws: &Workspace,
+           options: &ops::CompileOptions,
+           args: &[String],
+           env: &[String],
+           cwd: Option<&str>)
+           -> CraftResult<Option<ProcessError>> {
     let config = ws.config();
     let root = ws.current()?;
 
-    let mut bins = root.manifest().targets().iter().filter(|a| {
-        !a.is_lib() && !a.is_custom_build() &&
-        match options.filter {
-            CompileFilter::Everything => a.is_bin(),
-            CompileFilter::Only { .. } => options.filter.matches(a),
-        }
-    });
-    if bins.next().is_none() {
-        match options.filter {
-            CompileFilter::Everything => bail!("a bin target must be available for `craft run`"),
-            CompileFilter::Only { .. } => {
-                // this will be verified in craft_compile
+    let env = env.iter()
+        .map(|pair| match pair.find('=') {
+            Some(pos) if pos > 0 => Ok((&pair[..pos], &pair[pos + 1..])),
+            _ => Err(human(format!("invalid `--env` value `{}`, expected `KEY=VALUE`", pair))),
+        })
+        .collect::<CraftResult<Vec<_>>>()?;
+    let cwd = match cwd {
+        Some(cwd) => {
+            let cwd = config.cwd().join(cwd);
+            if !cwd.is_dir() {
+                bail!("the `--cwd` directory `{}` does not exist", cwd.display())
             }
+            cwd
         }
-    }
-    if bins.next().is_some() {
-        match options.filter {
-            CompileFilter::Everything => {
+        None => config.cwd().to_path_buf(),
+    };
+
+    // The executable to run, unless the library is run as a test executable
+    let target = if options.mode == CompileMode::Test {
+        None
+    } else {
+        let available = |kind: TargetKind| {
+            root.targets()
+                .iter()
+                .filter(|t| *t.kind() == kind)
+                .map(|t| format!("    {}", t.name()))
+                .collect::<Vec<_>>()
+        };
+        let bins = root.targets()
+            .iter()
+            .filter(|t| match options.filter {
+                CompileFilter::Everything => t.is_bin(),
+                CompileFilter::Only { .. } => (t.is_bin() || t.is_example()) && options.filter.matches(t),
+            })
+            .collect::<Vec<_>>();
+        match (bins.len(), &options.filter) {
+            (1, _) => Some(bins[0]),
+            (0, &CompileFilter::Everything) => bail!("a bin target must be available for `craft run`"),
+            (0, &CompileFilter::Only { bins: names, examples, .. }) => {
+                let (desc, name, kind) = match (names.first(), examples.first()) {
+                    (Some(name), _) => ("bin", name, TargetKind::Bin),
+                    (None, Some(name)) => ("example", name, TargetKind::Example),
+                    (None, None) => bail!("a bin target must be available for `craft run`"),
+                };
+                let names = available(kind);
+                if names.is_empty() {
+                    bail!("no {} target named `{}`, the package has no {} targets", desc, name, desc)
+                }
+                bail!("no {} target named `{}`, available {} targets:\n{}",
+                      desc,
+                      name,
+                      desc,
+                      names.join("\n"))
+            }
+            (_, &CompileFilter::Everything) => {
                 bail!("`craft run` requires that a project only have one \
                        executable; use the `--bin` option to specify which one \
-                       to run")
+                       to run, available bin targets:\n{}",
+                      available(TargetKind::Bin).join("\n"))
             }
-            CompileFilter::Only { .. } => {
+            (_, &CompileFilter::Only { .. }) => {
                 bail!("`craft run` can run at most one executable, but \
                        multiple were specified")
             }
         }
-    }
+    };
 
     let compile = ops::compile(ws, options)?;
-    let exe = &compile.binaries[0];
-    let exe = match util::without_prefix(&exe, config.cwd()) {
+    let exe = match target {
+        Some(target) => {
+            let stems = [target.filename().unwrap_or(target.name()).to_string(), target.chest_name()];
+            compile.binaries
+                .iter()
+                .find(|bin| bin.file_stem().and_then(|s| s.to_str()).map_or(false, |s| stems.iter().any(|n| n == s)))
+                .chain_error(|| human(format!("the executable of `{}` wasn't built", target.name())))?
+        }
+        None => {
+            let lib = compile.tests
+                .iter()
+                .find(|&&(ref pkg, _, _)| pkg.package_id() == root.package_id())
+                .chain_error(|| internal("the library wasn't built as a test executable"))?;
+            &lib.2
+        }
+    };
+    let exe = match util::without_prefix(&exe, &cwd) {
         Some(path) if path.file_name() == Some(path.as_os_str()) => Path::new(".").join(path).to_path_buf(),
         Some(path) => path.to_path_buf(),
         None => exe.to_path_buf(),
     };
     let mut process = compile.target_process(exe, &root)?;
-    process.args(args).cwd(config.cwd());
+    process.args(args).cwd(&cwd);
+    for &(key, value) in env.iter() {
+        process.env(key, value);
+    }
 
     config.shell().status("Running", process.to_string())?;
     Ok(process.exec_replace().err())
@@ -0,0 +1,136 @@
+use std::cmp;
+
+use curl::easy::List;
+use rustc_serialize::json;
+use term::color::BLACK;
+
+use ops::registry::registry_api;
+use sources::registry::remote::http_handle;
+use util::{CraftResult, Config, ChainError, HttpError, human, network, ToUrl};
+use util::errors::NetworkError;
+
+/// The most results the registry returns for a single search.
+const MAX_LIMIT: u32 = 100;
+
+/// The response of the registry API to a search.
+#[derive(RustcDecodable)]
+struct SearchResponse {
+    chests: Vec<Chest>,
+    meta: Meta,
+}
+
+#[derive(RustcDecodable)]
+struct Chest {
+    name: String,
+    max_version: String,
+    description: Option<String>,
+}
+
+#[derive(RustcDecodable)]
+struct Meta {
+    total: u32,
+}
+
+/// Searches the registry given by `index` or the `registry.index` config value
+/// for packages matching `query`, printing at most `limit` of them.
+pub fn search(config: &Config, query: &str, index: Option<String>, limit: u32) -> CraftResult<()> {
+    let (_, api) = registry_api(config, index, "search")?;
+
+    let mut url = format!("{}/api/v1/chests", api.trim_right_matches('/')).to_url()?;
+    url.query_pairs_mut()
+        .append_pair("q", query)
+        .append_pair("per_page", &cmp::min(limit, MAX_LIMIT).to_string());
+
+    let mut handle = http_handle(config)?;
+    handle.get(true)?;
+    handle.url(url.as_str())?;
+    let mut headers = List::new();
+    headers.append("Accept: application/json")?;
+    handle.http_headers(headers)?;
+
+    let (code, response) = network::with_retry(config, || -> Result<(u32, Vec<u8>), HttpError> {
+            // A retry after a partial transfer starts over with the response
+            let mut response = Vec::new();
+            {
+                let mut handle = handle.transfer();
+                handle.write_function(|buf| {
+                        response.extend_from_slice(buf);
+                        Ok(buf.len())
+                    })?;
+                handle.perform()?;
+            }
+            let code = handle.response_code()?;
+            let status = HttpError::Status {
+                url: url.to_string(),
+                code: code,
+            };
+            if status.maybe_spurious() {
+                return Err(status);
+            }
+            Ok((code, response))
+        })
+        .chain_error(|| human(format!("failed to search `{}`", url)))?;
+    if code != 200 {
+        bail!("failed to search `{}`, got response code {}", url, code)
+    }
+    let response = String::from_utf8_lossy(&response);
+    let response: SearchResponse = json::decode(&response)
+        .chain_error(|| human(format!("failed to decode the search results of `{}`", url)))?;
+
+    for line in format_chests(&response.chests) {
+        config.shell().say(line, BLACK)?;
+    }
+    let shown = response.chests.len() as u32;
+    if response.meta.total > shown {
+        config.shell()
+            .say(format!("... and {} more, pass `--limit` to see more", response.meta.total - shown),
+                 BLACK)?;
+    }
+    Ok(())
+}
+
+// Lines of the names, versions and descriptions of `chests`, aligned in
+// columns. Descriptions are joined into a single line.
+fn format_chests(chests: &[Chest]) -> Vec<String> {
+    let name_width = chests.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    let version_width = chests.iter().map(|c| c.max_version.len()).max().unwrap_or(0);
+    chests.iter()
+        .map(|chest| {
+            let description = chest.description
+                .as_ref()
+                .map(|d| d.split_whitespace().collect::<Vec<_>>().join(" "))
+                .unwrap_or_default();
+            let line = format!("{:name_width$}  {:version_width$}  {}",
+                               chest.name,
+                               chest.max_version,
+                               description,
+                               name_width = name_width,
+                               version_width = version_width);
+            line.trim_right().to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_chests, Chest};
+
+    fn chest(name: &str, version: &str, description: Option<&str>) -> Chest {
+        Chest {
+            name: name.to_string(),
+            max_version: version.to_string(),
+            description: description.map(|d| d.to_string()),
+        }
+    }
+
+    #[test]
+    fn aligns_search_results() {
+        let chests = [chest("zlib", "1.2.11", Some("A compression\n  library")),
+                      chest("libpng", "1.6.0", Some("PNG reference library")),
+                      chest("foo", "0.1.0", None)];
+        assert_eq!(format_chests(&chests),
+                   vec!["zlib    1.2.11  A compression library",
+                        "libpng  1.6.0   PNG reference library",
+                        "foo     0.1.0"]);
+    }
+}
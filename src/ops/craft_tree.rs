@@ -0,0 +1,144 @@
+use std::collections::{HashMap, HashSet};
+
+use term::color::BLACK;
+
+use ops;
+use package_id::PackageId;
+use resolver::Resolve;
+use util::{CraftResult, Config};
+use workspace::Workspace;
+
+const BRANCH: &'static str = "├── ";
+const LAST_BRANCH: &'static str = "└── ";
+
+pub struct TreeOptions {
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    /// Print the packages depending on this package instead of its dependencies.
+    pub invert: Option<String>,
+    /// Only print the packages present at several versions, along with the
+    /// packages depending on them.
+    pub duplicates: bool,
+}
+
+/// Prints the resolved dependency graph of the workspace members as a tree.
+///
+/// Subtrees which were already printed are only printed once, later
+/// occurrences of their root are marked with `(*)`.
+pub fn tree(ws: &Workspace, opts: &TreeOptions) -> CraftResult<()> {
+    let (_, resolve) = ops::resolve_dependencies(ws,
+                                                 None,
+                                                 &opts.features,
+                                                 opts.all_features,
+                                                 opts.no_default_features,
+                                                 &[])?;
+
+    let graph = if opts.invert.is_some() || opts.duplicates {
+        Graph::inverted(&resolve)
+    } else {
+        Graph::new(&resolve)
+    };
+
+    let roots = if opts.duplicates {
+        duplicates(&resolve)
+    } else if let Some(ref spec) = opts.invert {
+        vec![resolve.query(spec)?.clone()]
+    } else {
+        let mut members = ws.members().map(|pkg| pkg.package_id().clone()).collect::<Vec<_>>();
+        members.sort();
+        members
+    };
+
+    let config = ws.config();
+    for (i, root) in roots.iter().enumerate() {
+        if i > 0 {
+            config.shell().say("", BLACK)?;
+        }
+        let mut printed = HashSet::new();
+        print_node(config, &graph, root, "", &mut String::new(), &mut printed)?;
+    }
+    Ok(())
+}
+
+/// The edges of the dependency graph, either pointing from a package to its
+/// dependencies or, if inverted, to the packages depending on it.
+struct Graph {
+    edges: HashMap<PackageId, Vec<PackageId>>,
+}
+
+impl Graph {
+    fn new(resolve: &Resolve) -> Graph {
+        let mut edges = HashMap::new();
+        for pkg in resolve.iter() {
+            let mut deps = resolve.deps(pkg).cloned().collect::<Vec<_>>();
+            deps.sort();
+            deps.dedup();
+            edges.insert(pkg.clone(), deps);
+        }
+        Graph { edges: edges }
+    }
+
+    fn inverted(resolve: &Resolve) -> Graph {
+        let mut edges = HashMap::new();
+        for pkg in resolve.iter() {
+            for dep in resolve.deps(pkg) {
+                edges.entry(dep.clone()).or_insert_with(Vec::new).push(pkg.clone());
+            }
+        }
+        for parents in edges.values_mut() {
+            parents.sort();
+            parents.dedup();
+        }
+        Graph { edges: edges }
+    }
+
+    fn edges(&self, pkg: &PackageId) -> &[PackageId] {
+        self.edges.get(pkg).map(|edges| &edges[..]).unwrap_or(&[])
+    }
+}
+
+/// Returns the packages of which several versions are part of the graph.
+fn duplicates(resolve: &Resolve) -> Vec<PackageId> {
+    let mut by_name = HashMap::new();
+    for pkg in resolve.iter() {
+        by_name.entry(pkg.name()).or_insert_with(Vec::new).push(pkg.clone());
+    }
+    let mut duplicates = by_name.into_iter()
+        .filter(|&(_, ref pkgs)| pkgs.len() > 1)
+        .flat_map(|(_, pkgs)| pkgs)
+        .collect::<Vec<_>>();
+    duplicates.sort();
+    duplicates
+}
+
+/// Prints `pkg` below `prefix`, with `branch` connecting it to its parent,
+/// followed by the packages it has edges to.
+fn print_node(config: &Config,
+              graph: &Graph,
+              pkg: &PackageId,
+              branch: &str,
+              prefix: &mut String,
+              printed: &mut HashSet<PackageId>)
+              -> CraftResult<()> {
+    let edges = graph.edges(pkg);
+    let new = printed.insert(pkg.clone());
+    if !new && !edges.is_empty() {
+        return config.shell().say(format!("{}{}{} (*)", prefix, branch, pkg), BLACK);
+    }
+    config.shell().say(format!("{}{}{}", prefix, branch, pkg), BLACK)?;
+    if !new {
+        return Ok(());
+    }
+
+    let len = prefix.len();
+    if !branch.is_empty() {
+        prefix.push_str(if branch == LAST_BRANCH { "    " } else { "│   " });
+    }
+    for (i, edge) in edges.iter().enumerate() {
+        let branch = if i + 1 == edges.len() { LAST_BRANCH } else { BRANCH };
+        print_node(config, graph, edge, branch, prefix, printed)?;
+    }
+    prefix.truncate(len);
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json;
+
+use ops;
+use package::Package;
+use source::{GitReference, SourceId};
+use sources::PathSource;
+use util::{self, paths, CraftResult, ChainError, human, Config, Sha256};
+use workspace::Workspace;
+
+/// The checksums of a vendored package, read back by the `DirectorySource`.
+#[derive(RustcEncodable)]
+struct Checksum {
+    package: Option<String>,
+    files: BTreeMap<String, String>,
+}
+
+/// Copies the sources of all resolved dependencies of the workspace into
+/// `<name>-<version>` directories below `destination`, which is relative to the
+/// workspace root.
+///
+/// Path dependencies are skipped, git dependencies are vendored at the
+/// revision locked in `Craft.lock`. Returns the `.craft/config` snippet
+/// replacing the original sources with the vendored ones.
+pub fn vendor(ws: &Workspace, destination: &Path) -> CraftResult<String> {
+    let config = ws.config();
+    let (resolve, packages) = ops::fetch(ws)?;
+    let dst = ws.root().join(destination);
+
+    let mut ids = resolve.iter().filter(|id| !id.source_id().is_path()).collect::<Vec<_>>();
+    ids.sort();
+
+    let mut sources = BTreeSet::new();
+    for id in ids {
+        let pkg = packages.get(id)?;
+        config.shell().status("Vendoring", id)?;
+        vendor_package(config, pkg, &dst.join(format!("{}-{}", id.name(), id.version())))
+            .chain_error(|| human(format!("failed to vendor `{}`", id)))?;
+        sources.insert(id.source_id().with_precise(None));
+    }
+
+    let mut out = String::new();
+    for id in sources.iter() {
+        out.push_str(&format!("[source.\"{}\"]\n", id.to_url()));
+        if id.is_git() {
+            out.push_str(&format!("git = \"{}\"\n", id.url()));
+            match id.git_reference() {
+                Some(&GitReference::Branch(ref b)) if b == "master" => {}
+                Some(&GitReference::Branch(ref b)) => out.push_str(&format!("branch = \"{}\"\n", b)),
+                Some(&GitReference::Tag(ref t)) => out.push_str(&format!("tag = \"{}\"\n", t)),
+                Some(&GitReference::Rev(ref r)) => out.push_str(&format!("rev = \"{}\"\n", r)),
+                None => {}
+            }
+            if !id.submodules() {
+                out.push_str("submodules = false\n");
+            }
+        } else {
+            out.push_str(&format!("registry = \"{}\"\n", id.url()));
+        }
+        out.push_str("replace-with = \"vendored-sources\"\n\n");
+    }
+    out.push_str("[source.vendored-sources]\n");
+    out.push_str(&format!("directory = \"{}\"\n", destination.display()));
+    Ok(out)
+}
+
+fn vendor_package(config: &Config, pkg: &Package, dst: &Path) -> CraftResult<()> {
+    if dst.exists() {
+        fs::remove_dir_all(dst)?;
+    }
+
+    let src = PathSource::new(pkg.root(), pkg.package_id().source_id(), config);
+    let mut files = BTreeMap::new();
+    for file in src.list_files(pkg)? {
+        let relative = util::without_prefix(&file, pkg.root()).unwrap();
+        // Left behind by unpacking registry packages
+        if relative == Path::new(".craft-ok") {
+            continue;
+        }
+        let contents = paths::read_bytes(&file)?;
+        let target = dst.join(relative);
+        fs::create_dir_all(target.parent().unwrap())?;
+        paths::write(&target, &contents)?;
+
+        let mut h = Sha256::new();
+        h.update(&contents);
+        let name = relative.iter().map(|c| c.to_string_lossy()).collect::<Vec<_>>().join("/");
+        files.insert(name, h.finish().to_hex());
+    }
+
+    let checksum = Checksum {
+        package: pkg.summary().checksum().map(|s| s.to_string()),
+        files: files,
+    };
+    paths::write(&dst.join(".craft-checksum.json"),
+                 json::encode(&checksum).unwrap().as_bytes())
+}
@@ -0,0 +1,166 @@
+use semver::{Version, VersionReq};
+use term::color::BLACK;
+
+use ops;
+use package::PackageSet;
+use package_id::PackageId;
+use package_id_spec::PackageIdSpec;
+use util::{CraftResult, Graph};
+use workspace::Workspace;
+
+/// The number of paths printed for a package at most. Diamond-shaped graphs
+/// have exponentially many of them.
+const MAX_PATHS: usize = 32;
+
+pub struct WhyOptions {
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    /// The package to explain, optionally narrowed down to a version like
+    /// `foo:1.2.3`.
+    pub spec: String,
+}
+
+/// Prints the paths from a workspace member to the packages matching the spec
+/// of `opts`, along with the version requirements along the way.
+///
+/// Of the requirements on the package itself, the ones keeping its version
+/// down the most are marked as binding.
+pub fn why(ws: &Workspace, opts: &WhyOptions) -> CraftResult<()> {
+    let (packages, resolve) = ops::resolve_dependencies(ws,
+                                                        None,
+                                                        &opts.features,
+                                                        opts.all_features,
+                                                        opts.no_default_features,
+                                                        &[])?;
+    let spec = PackageIdSpec::parse(&opts.spec)?;
+    let mut targets = resolve.iter().filter(|id| spec.matches(id)).collect::<Vec<_>>();
+    if targets.is_empty() {
+        bail!("package `{}` is not part of the dependency graph", opts.spec)
+    }
+    targets.sort();
+
+    // Pointing from each package to the packages depending on it
+    let mut parents = Graph::new();
+    for pkg in resolve.iter() {
+        for dep in resolve.deps(pkg) {
+            parents.link(dep.clone(), pkg.clone());
+        }
+    }
+
+    let config = ws.config();
+    for (i, target) in targets.into_iter().enumerate() {
+        if i > 0 {
+            config.shell().say("", BLACK)?;
+        }
+        let mut paths = Vec::new();
+        let complete = collect_paths(&parents, target, &mut vec![target.clone()], &mut paths);
+        paths.sort();
+
+        // All the requirements on the package count, not only the ones of
+        // the paths shown
+        let reqs = parents.edges(target)
+            .into_iter()
+            .flat_map(|edges| edges)
+            .map(|parent| requirements(&packages, parent, target))
+            .collect::<CraftResult<Vec<_>>>()?;
+        let binding = binding_requirements(target.version(), &reqs);
+
+        config.shell().say(format!("{}", target), BLACK)?;
+        if paths.iter().all(|path| path.len() == 1) {
+            config.shell().say("  is a member of the workspace", BLACK)?;
+            continue;
+        }
+        for path in paths.iter().filter(|path| path.len() > 1) {
+            let mut line = format!("  {} v{}", path[0].name(), path[0].version());
+            for (j, edge) in path.windows(2).enumerate() {
+                let reqs = requirements(&packages, &edge[0], &edge[1])?;
+                line.push_str(&format!(" -> {} v{} ({}", edge[1].name(), edge[1].version(), reqs.join(", ")));
+                if j + 2 == path.len() && reqs.iter().any(|r| binding.contains(r)) {
+                    line.push_str(", binding");
+                }
+                line.push_str(")");
+            }
+            config.shell().say(line, BLACK)?;
+        }
+        if !complete {
+            config.shell().say(format!("  ... only the first {} paths are shown", MAX_PATHS), BLACK)?;
+        }
+    }
+    Ok(())
+}
+
+/// Collects the paths from the packages nobody depends on down to the last
+/// package of `path`, up to `MAX_PATHS` of them. Returns whether all of them
+/// were collected.
+fn collect_paths(parents: &Graph<PackageId>,
+                 pkg: &PackageId,
+                 path: &mut Vec<PackageId>,
+                 paths: &mut Vec<Vec<PackageId>>)
+                 -> bool {
+    let mut pkg_parents = parents.edges(pkg).into_iter().flat_map(|edges| edges).collect::<Vec<_>>();
+    pkg_parents.sort();
+    let mut any = false;
+    for parent in pkg_parents {
+        // Dependency cycles only exist between dev-dependencies
+        if path.contains(parent) {
+            continue;
+        }
+        if paths.len() == MAX_PATHS {
+            return false;
+        }
+        any = true;
+        path.push(parent.clone());
+        let complete = collect_paths(parents, parent, path, paths);
+        path.pop();
+        if !complete {
+            return false;
+        }
+    }
+    if !any {
+        if paths.len() == MAX_PATHS {
+            return false;
+        }
+        paths.push(path.iter().rev().cloned().collect());
+    }
+    true
+}
+
+/// The version requirements `parent` has on `child`, one per kind of
+/// dependency.
+fn requirements(packages: &PackageSet, parent: &PackageId, child: &PackageId) -> CraftResult<Vec<String>> {
+    let mut reqs = packages.get(parent)?
+        .dependencies()
+        .iter()
+        .filter(|dep| dep.name() == child.name())
+        .map(|dep| dep.version_req().to_string())
+        .collect::<Vec<_>>();
+    reqs.sort();
+    reqs.dedup();
+    Ok(reqs)
+}
+
+/// Returns the requirements of `reqs` which reject the smallest increase of
+/// `version`, i.e. the ones preventing a newer version the most.
+fn binding_requirements(version: &Version, reqs: &[Vec<String>]) -> Vec<String> {
+    let mut bumps = [version.clone(), version.clone(), version.clone()];
+    bumps[0].increment_patch();
+    bumps[1].increment_minor();
+    bumps[2].increment_major();
+    for bump in bumps.iter() {
+        let mut binding = reqs.iter()
+            .flat_map(|reqs| reqs)
+            .filter(|req| match req.parse::<VersionReq>() {
+                Ok(req) => !req.matches(bump),
+                Err(_) => false,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if !binding.is_empty() {
+            binding.sort();
+            binding.dedup();
+            return binding;
+        }
+    }
+    Vec::new()
+}
@@ -8,6 +8,12 @@ use resolver::{EncodableResolve, WorkspaceResolve, Resolve};
 use util::{CraftResult, ChainError, human, Filesystem};
 use util::toml as craft_toml;
 
+/// The format version of the lock files written by this version of craft.
+///
+/// Lock files without a `version` field predate versioning and are still
+/// understood, newer versions are not.
+const LOCKFILE_VERSION: i64 = 1;
+
 pub fn load_pkg_lockfile(ws: &Workspace) -> CraftResult<Option<Resolve>> {
     if !ws.root().join("Craft.lock").exists() {
         return Ok(None);
@@ -20,7 +26,25 @@ pub fn load_pkg_lockfile(ws: &Workspace) -> CraftResult<Option<Resolve>> {
     f.read_to_string(&mut s).chain_error(|| human(format!("failed to read file: {}", f.path().display())))?;
 
     (|| {
-            let table = craft_toml::parse(&s, f.path(), ws.config())?;
+            let mut table = craft_toml::parse(&s, f.path(), ws.config())?;
+            match table.remove("version") {
+                None => {}
+                Some(Value::Integer(version)) if version <= LOCKFILE_VERSION => {}
+                Some(Value::Integer(version)) => {
+                    let msg = format!("the lock file uses format version {}, but this version of craft only \
+                                       understands up to version {}",
+                                      version,
+                                      LOCKFILE_VERSION);
+                    if let Some(flag) = ws.config().lock_update_disallowed_by() {
+                        bail!("{}\nit can't be regenerated as {} was passed to prevent updating it",
+                              msg,
+                              flag)
+                    }
+                    ws.config().shell().warn(format!("{}, so it is ignored and will be regenerated", msg))?;
+                    return Ok(None);
+                }
+                Some(..) => bail!("expected an integer for the lock file `version`"),
+            }
             let table = toml::Value::Table(table);
             let mut d = toml::Decoder::new(table);
             let v: EncodableResolve = Decodable::decode(&mut d)?;
@@ -42,11 +66,18 @@ pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CraftResult<()>
     // Forward compatibility: if `orig` uses rootless format
     // from the future, do the same.
     let use_root_key = if let Ok(ref orig) = orig {
-        !orig.starts_with("[[package]]")
+        orig.lines().find(|line| line.starts_with('[')) != Some("[[package]]")
     } else {
         true
     };
 
+    // Lock files written before the format was versioned keep their format,
+    // so that they don't need to be updated just for the version.
+    let versioned = match orig {
+        Ok(ref orig) => orig.starts_with("version = "),
+        Err(..) => true,
+    };
+
     let mut e = Encoder::new();
     WorkspaceResolve {
             ws: ws,
@@ -57,6 +88,9 @@ pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CraftResult<()>
         .unwrap();
 
     let mut out = String::new();
+    if versioned {
+        out.push_str(&format!("version = {}\n\n", LOCKFILE_VERSION));
+    }
 
     // Note that we do not use e.toml.to_string() as we want to control the
     // exact format the toml is in to ensure pretty diffs between updates to the
@@ -93,12 +127,7 @@ pub fn write_pkg_lockfile(ws: &Workspace, resolve: &Resolve) -> CraftResult<()>
         }
     }
 
-    if !ws.config().lock_update_allowed() {
-        let flag = if ws.config().network_allowed() {
-            "--frozen"
-        } else {
-            "--locked"
-        };
+    if let Some(flag) = ws.config().lock_update_disallowed_by() {
         bail!("the lock file needs to be updated but {} was passed to \
                prevent this",
               flag);
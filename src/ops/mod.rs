@@ -1,18 +1,26 @@
 //! All available internal operations
 pub use self::craft_clean::{clean, CleanOptions};
-pub use self::craft_compile::{CompileFilter, CompileMode, MessageFormat, compile, compile_ws, resolve_dependencies,
-                              CompileOptions};
+pub use self::craft_compile::{CompileFilter, CompileMode, MessageFormat, build_finished, compile, compile_ws,
+                              resolve_dependencies, CompileOptions};
 pub use self::craft_doc::{doc, DocOptions};
+pub use self::craft_explain::explain;
 pub use self::craft_fetch::{fetch, get_resolved_packages};
 pub use self::craft_generate_lockfile::{UpdateOptions, generate_lockfile, update_lockfile};
-pub use self::craft_install::{install, install_list, uninstall};
+pub use self::craft_install::{install, install_list, install_list_json, uninstall, InstalledPackage};
+pub use self::craft_login::registry_login;
 pub use self::craft_new::{new, init, NewOptions, VersionControl};
 pub use self::craft_output_metadata::{output_metadata, OutputMetadataOptions, ExportInfo};
 pub use self::craft_package::{package, PackageOpts};
 pub use self::craft_pkgid::pkgid;
+pub use self::craft_publish::{publish, PublishOpts};
 pub use self::craft_read_manifest::{read_manifest, read_package, read_packages};
+pub use self::craft_reproducible::verify_reproducible;
 pub use self::craft_run::run;
-pub use self::craft_cc::{BuildOutput, BuildConfig, TargetConfig, Context, LayoutProxy, compile_targets,
+pub use self::craft_search::search;
+pub use self::craft_tree::{tree, TreeOptions};
+pub use self::craft_vendor::vendor;
+pub use self::craft_why::{why, WhyOptions};
+pub use self::craft_cc::{BuildOutput, BuildConfig, LibraryLink, TargetConfig, Context, LayoutProxy, compile_targets,
                             Compilation, Layout, Kind, Unit};
 pub use self::lockfile::{load_pkg_lockfile, write_pkg_lockfile};
 pub use self::resolve::{resolve_ws, resolve_with_previous};
@@ -20,15 +28,24 @@ pub use self::resolve::{resolve_ws, resolve_with_previous};
 mod craft_clean;
 mod craft_compile;
 mod craft_doc;
+mod craft_explain;
 mod craft_fetch;
 mod craft_generate_lockfile;
 mod craft_install;
+mod craft_login;
 mod craft_new;
 mod craft_output_metadata;
 mod craft_package;
 mod craft_pkgid;
+mod craft_publish;
 mod craft_read_manifest;
+mod craft_reproducible;
 mod craft_run;
+mod craft_search;
+mod craft_tree;
+mod craft_vendor;
+mod craft_why;
 mod craft_cc;
 mod lockfile;
+mod registry;
 mod resolve;
@@ -0,0 +1,26 @@
+//! Access to the API of a registry
+use source::{Source, SourceId};
+use sources::RegistrySource;
+use util::{CraftResult, Config, ChainError, human, ToUrl};
+
+/// Finds the registry given by `index` or the `registry.index` config value
+/// and updates its index to learn the URL of its API. `action` is what the
+/// registry is needed for, like "search", to explain a missing registry.
+pub fn registry_api(config: &Config, index: Option<String>, action: &str) -> CraftResult<(SourceId, String)> {
+    let index = match index {
+        Some(index) => index,
+        None => {
+            match config.get_string("registry.index")? {
+                Some(index) => index.val,
+                None => bail!("no registry to {}, pass `--index` or set `registry.index`", action),
+            }
+        }
+    };
+    let source_id = SourceId::for_registry(&index.to_url()?);
+    let mut registry = RegistrySource::remote(&source_id, config);
+    registry.update()?;
+    let api = registry.config()?
+        .chain_error(|| human(format!("registry `{}` does not have an API to {}", index, action)))?
+        .api;
+    Ok((source_id, api))
+}
@@ -17,6 +17,9 @@ use workspace::Workspace;
 pub fn resolve_ws(registry: &mut PackageRegistry, ws: &Workspace) -> CraftResult<Resolve> {
     let prev = ops::load_pkg_lockfile(ws)?;
     let resolve = resolve_with_previous(registry, ws, Method::Everything, prev.as_ref(), None, &[])?;
+    if let Some(ref prev) = prev {
+        check_replacements_locked(ws, prev, &resolve)?;
+    }
 
     // Avoid writing a lockfile if we are `craft install`ing a non local package.
     if ws.current_opt().map(|pkg| pkg.package_id().source_id().is_path()).unwrap_or(true) {
@@ -25,6 +28,59 @@ pub fn resolve_ws(registry: &mut PackageRegistry, ws: &Workspace) -> CraftResult
     Ok(resolve)
 }
 
+/// Makes sure that a `[replace]` section changed since the lock file was
+/// written doesn't go unnoticed if the lock file may not be updated.
+fn check_replacements_locked(ws: &Workspace, previous: &Resolve, resolve: &Resolve) -> CraftResult<()> {
+    let flag = match ws.config().lock_update_disallowed_by() {
+        Some(flag) => flag,
+        None => return Ok(()),
+    };
+    let mut replaced = resolve.replacements()
+        .keys()
+        .chain(previous.replacements().keys())
+        .collect::<Vec<_>>();
+    replaced.sort();
+    replaced.dedup();
+    for id in replaced {
+        let old = previous.replacements().get(id);
+        let new = resolve.replacements().get(id);
+        if old == new {
+            continue;
+        }
+        let describe = |replacement: Option<&PackageId>| match replacement {
+            Some(replacement) => format!("`{}`", replacement),
+            None => "nothing".to_string(),
+        };
+        bail!("the replacement of `{}` changed from {} to {}, but {} was passed to prevent updating the lock file",
+              id,
+              describe(old),
+              describe(new),
+              flag)
+    }
+    Ok(())
+}
+
+/// Warns about the packages of the `[patch]` section which didn't make it into
+/// the resolved graph, most likely because no dependency accepts their version.
+fn warn_unused_patches(ws: &Workspace, registry: &PackageRegistry, resolve: &Resolve) -> CraftResult<()> {
+    let mut unused = registry.patches()
+        .iter()
+        .flat_map(|(url, summaries)| summaries.iter().map(move |s| (url, s)))
+        .filter(|&(_, s)| !resolve.iter().any(|id| id == s.package_id()))
+        .collect::<Vec<_>>();
+    unused.sort_by(|a, b| a.1.package_id().cmp(b.1.package_id()));
+    for (url, summary) in unused {
+        let msg = format!("\
+            patch `{}` for `{}` was not used in the chest graph\n\
+            its version doesn't match any requirement on `{}`",
+                          summary.package_id(),
+                          url,
+                          summary.name());
+        ws.config().shell().warn(msg)?;
+    }
+    Ok(())
+}
+
 /// Resolve all dependencies for a package using an optional previous instance
 /// of resolve to guide the resolution process.
 ///
@@ -112,6 +168,10 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
         summaries.push((summary, method));
     }
 
+    for (url, deps) in ws.root_patch() {
+        registry.patch(url, deps)?;
+    }
+
     let root_replace = ws.root_replace();
 
     let replace = match previous {
@@ -131,6 +191,9 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
     };
 
     let mut resolved = resolver::resolve(&summaries, &replace, registry)?;
+    if let Method::Everything = method {
+        warn_unused_patches(ws, registry, &resolved)?;
+    }
     if let Some(previous) = previous {
         resolved.merge_from(previous)?;
     }
@@ -6,9 +6,10 @@ use std::path::{Path, PathBuf};
 
 use semver::Version;
 use rustc_serialize::{Encoder, Encodable};
+use toml;
 
 use dependency::Dependency;
-use manifest::{Manifest, Target, TargetKind};
+use manifest::{Manifest, SerializedTarget, Target, TargetKind};
 use ops;
 use package_id::{PackageId, Metadata};
 use source::SourceId;
@@ -36,9 +37,10 @@ struct SerializedPackage<'a> {
     license_file: Option<&'a str>,
     source: &'a SourceId,
     dependencies: &'a [Dependency],
-    targets: &'a [Target],
+    targets: Vec<SerializedTarget<'a>>,
     features: &'a HashMap<String, Vec<String>>,
     manifest_path: &'a str,
+    metadata: Option<&'a toml::Value>,
 }
 
 impl Encodable for Package {
@@ -57,9 +59,14 @@ impl Encodable for Package {
                 license_file: license_file,
                 source: summary.source_id(),
                 dependencies: summary.dependencies(),
-                targets: &self.manifest.targets(),
+                targets: self.manifest
+                    .targets()
+                    .iter()
+                    .map(|target| target.serialized(self.root()))
+                    .collect(),
                 features: summary.features(),
                 manifest_path: &self.manifest_path.display().to_string(),
+                metadata: manmeta.metadata.as_ref(),
             }
             .encode(s)
     }
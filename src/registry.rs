@@ -1,6 +1,8 @@
 //! Registry manipulation and description
 use std::collections::HashMap;
 
+use url::Url;
+
 use dependency::Dependency;
 use package::{Package, PackageSet};
 use package_id::PackageId;
@@ -88,6 +90,10 @@ pub struct PackageRegistry<'cfg> {
 
     locked: HashMap<SourceId, HashMap<String, Vec<(PackageId, Vec<PackageId>)>>>,
     source_config: SourceConfigMap<'cfg>,
+
+    // The packages of the `[patch]` section, keyed by the URL of the source
+    // whose packages they take precedence over.
+    patches: HashMap<Url, Vec<Summary>>,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -106,6 +112,7 @@ impl<'cfg> PackageRegistry<'cfg> {
             overrides: Vec::new(),
             source_config: source_config,
             locked: HashMap::new(),
+            patches: HashMap::new(),
         })
     }
 
@@ -184,6 +191,47 @@ impl<'cfg> PackageRegistry<'cfg> {
         sub_vec.push((id, deps));
     }
 
+    /// Adds the `[patch]` dependencies `deps` for the source at `url`.
+    ///
+    /// Each of them has to resolve to exactly one package, which is then
+    /// returned instead of the packages of `url` whenever its version is
+    /// compatible with the dependency queried.
+    pub fn patch(&mut self, url: &Url, deps: &[Dependency]) -> CraftResult<()> {
+        let mut summaries = Vec::new();
+        for dep in deps {
+            let mut results = self.query(dep)
+                .chain_error(|| human(format!("failed to load the [patch] of `{}` for `{}`", dep.name(), url)))?;
+            if results.len() != 1 {
+                bail!("the [patch] of `{}` for `{}` has to match exactly one package, but it matches {}",
+                      dep.name(),
+                      url,
+                      results.len())
+            }
+            let summary = results.pop().unwrap();
+            if summary.source_id().url() == url {
+                bail!("the [patch] of `{}` for `{}` points to the source it patches",
+                      dep.name(),
+                      url)
+            }
+            summaries.push(summary);
+        }
+        self.patches.insert(url.clone(), summaries);
+        Ok(())
+    }
+
+    pub fn patches(&self) -> &HashMap<Url, Vec<Summary>> {
+        &self.patches
+    }
+
+    fn query_patches(&self, dep: &Dependency) -> Option<Summary> {
+        self.patches
+            .get(dep.source_id().url())
+            .and_then(|patches| {
+                patches.iter().find(|s| s.name() == dep.name() && dep.version_req().matches(s.version()))
+            })
+            .cloned()
+    }
+
     fn load(&mut self, source_id: &SourceId, kind: Kind) -> CraftResult<()> {
         (|| {
                 let source = self.source_config.load(source_id)?;
@@ -303,17 +351,6 @@ impl<'cfg> PackageRegistry<'cfg> {
             .chain_error(|| human(format!("failed to find lock version of {}", real)))?;
         let mut real_deps = real_deps.clone();
 
-        let boilerplate = "\
-This is currently allowed but is known to produce buggy behavior with spurious
-recompiles and changes to the chest graph. Path overrides unfortunately were
-never intended to support this feature, so for now this message is just a
-warning. In the future, however, this message will become a hard error.
-
-To change the dependency graph via an override it's recommended to use the
-`[replace]` feature of Craft instead of the path override feature. This is
-documented online at the url below for more information.
-";
-
         for dep in override_summary.dependencies() {
             if let Some(i) = real_deps.iter().position(|id| dep.matches_id(id)) {
                 real_deps.remove(i);
@@ -322,33 +359,57 @@ documented online at the url below for more information.
             let msg = format!("\
                 path override for chest `{}` has altered the original list of\n\
                 dependencies; the dependency on `{}` was either added or\n\
-                modified to not match the previously resolved version\n\n\
-                {}",
+                modified to not match the previously resolved version",
                               override_summary.package_id().name(),
-                              dep.name(),
-                              boilerplate);
-            self.source_config.config().shell().warn(&msg)?;
-            return Ok(());
+                              dep.name());
+            return self.report_bad_override(msg);
         }
 
         for id in real_deps {
             let msg = format!("\
-                path override for chest `{}` has altered the original list of
-                dependencies; the dependency on `{}` was removed\n\n
-                {}",
+                path override for chest `{}` has altered the original list of\n\
+                dependencies; the dependency on `{}` was removed",
                               override_summary.package_id().name(),
-                              id.name(),
-                              boilerplate);
-            self.source_config.config().shell().warn(&msg)?;
-            return Ok(());
+                              id.name());
+            return self.report_bad_override(msg);
         }
 
         Ok(())
     }
+
+    // With `--frozen` or `--locked` an override altering the dependency graph is an error, as the
+    // lock file can't be updated to reflect it and the locked graph would silently be used instead.
+    fn report_bad_override(&self, msg: String) -> CraftResult<()> {
+        let config = self.source_config.config();
+        if let Some(flag) = config.lock_update_disallowed_by() {
+            bail!("{}\n\nthe lock file doesn't reflect this override but {} was passed to prevent \
+                   updating it",
+                  msg,
+                  flag);
+        }
+
+        let boilerplate = "\
+This is currently allowed but is known to produce buggy behavior with spurious
+recompiles and changes to the chest graph. Path overrides unfortunately were
+never intended to support this feature, so for now this message is just a
+warning. In the future, however, this message will become a hard error.
+
+To change the dependency graph via an override it's recommended to use the
+`[replace]` feature of Craft instead of the path override feature. This is
+documented online at the url below for more information.
+";
+        config.shell().warn(&format!("{}\n\n{}", msg, boilerplate))
+    }
 }
 
 impl<'cfg> Registry for PackageRegistry<'cfg> {
     fn query(&mut self, dep: &Dependency) -> CraftResult<Vec<Summary>> {
+        // A patch takes precedence over the source it patches, as long as its
+        // version is compatible
+        if let Some(summary) = self.query_patches(dep) {
+            return Ok(vec![summary]);
+        }
+
         // Ensure the requested source_id is loaded
         self.ensure_loaded(dep.source_id(), Kind::Normal)
             .chain_error(|| human(format!("failed to load source for a dependency on `{}`", dep.name())))?;
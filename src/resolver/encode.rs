@@ -159,6 +159,8 @@ impl EncodableResolve {
         Ok(Resolve {
             graph: g,
             features: HashMap::new(),
+            target_features: HashMap::new(),
+            host_features: HashMap::new(),
             replacements: replacements,
             checksums: checksums,
             metadata: metadata,
@@ -357,6 +359,11 @@ fn encodable_resolve_node(id: &PackageId, resolve: &Resolve) -> EncodableDepende
     }
 }
 
+// Path sources are never serialized, neither for workspace members nor for
+// other path dependencies, as they would embed the absolute location of the
+// checkout. They're reconstructed relative to the workspace root by
+// `build_path_deps` when the lock file is loaded again, which keeps
+// `Craft.lock` identical across machines.
 fn encodable_package_id(id: &PackageId) -> EncodablePackageId {
     let source = if id.source_id().is_path() {
         None
@@ -77,6 +77,8 @@ pub struct Resolve {
     graph: Graph<PackageId>,
     replacements: HashMap<PackageId, PackageId>,
     features: HashMap<PackageId, HashSet<String>>,
+    target_features: HashMap<PackageId, HashSet<String>>,
+    host_features: HashMap<PackageId, HashSet<String>>,
     checksums: HashMap<PackageId, Option<String>>,
     metadata: Metadata,
 }
@@ -228,6 +230,19 @@ unable to verify that `{0}` is the same as when the lockfile was generated
         self.features.get(pkg)
     }
 
+    /// Returns the features of `pkg` as activated by the packages depending on
+    /// it through normal and development dependencies only (`for_host` is
+    /// `false`), or through build dependencies (`for_host` is `true`).
+    ///
+    /// Returns `None` if `pkg` isn't reachable that way.
+    pub fn features_for(&self, pkg: &PackageId, for_host: bool) -> Option<&HashSet<String>> {
+        if for_host {
+            self.host_features.get(pkg)
+        } else {
+            self.target_features.get(pkg)
+        }
+    }
+
     pub fn query(&self, spec: &str) -> CraftResult<&PackageId> {
         PackageIdSpec::query_str(spec, self.iter())
     }
@@ -290,6 +305,8 @@ pub fn resolve(summaries: &[(Summary, Method)],
     let mut resolve = Resolve {
         graph: cx.resolve_graph,
         features: cx.resolve_features,
+        target_features: HashMap::new(),
+        host_features: HashMap::new(),
         checksums: HashMap::new(),
         metadata: BTreeMap::new(),
         replacements: cx.resolve_replacements,
@@ -300,6 +317,10 @@ pub fn resolve(summaries: &[(Summary, Method)],
         resolve.checksums.insert(summary.package_id().clone(), cksum);
     }
 
+    let (target_features, host_features) = split_features(&resolve, &cx.activations, summaries)?;
+    resolve.target_features = target_features;
+    resolve.host_features = host_features;
+
     check_cycles(&resolve, &cx.activations)?;
 
     trace!("resolved: {:?}", resolve);
@@ -1027,6 +1048,86 @@ impl<'a> Context<'a> {
     }
 }
 
+/// Calculates the features of all resolved packages separately for the graph
+/// reached through normal dependencies and the one reached through build
+/// dependencies, as the latter is built for the host instead of the target.
+///
+/// Features requested for the same package in both graphs are not unified
+/// here, unlike in the resolution itself.
+fn split_features(resolve: &Resolve,
+                  activations: &HashMap<(String, SourceId), Vec<Rc<Summary>>>,
+                  roots: &[(Summary, Method)])
+                  -> CraftResult<(HashMap<PackageId, HashSet<String>>, HashMap<PackageId, HashSet<String>>)> {
+    let summaries: HashMap<&PackageId, &Summary> = activations.values()
+        .flat_map(|v| v)
+        .map(|s| (s.package_id(), &**s))
+        .collect();
+
+    // (package, for host, requested features or all of them, default features, dev-dependencies)
+    let mut pending = roots.iter()
+        .map(|&(ref summary, ref method)| {
+            let requested = match *method {
+                Method::Everything => (None, true, true),
+                Method::Required { dev_deps, features, uses_default_features } => {
+                    (Some(features.to_vec()), uses_default_features, dev_deps)
+                }
+            };
+            (summary.package_id().clone(), false, requested.0, requested.1, requested.2)
+        })
+        .collect::<Vec<_>>();
+    let mut visited = HashSet::new();
+    let mut target_features = HashMap::new();
+    let mut host_features = HashMap::new();
+
+    while let Some((id, for_host, mut features, uses_default_features, dev_deps)) = pending.pop() {
+        if let Some(ref mut features) = features {
+            features.sort();
+            features.dedup();
+        }
+        if !visited.insert((id.clone(), for_host, features.clone(), uses_default_features, dev_deps)) {
+            continue;
+        }
+        let summary = match summaries.get(&id) {
+            Some(summary) => *summary,
+            None => continue,
+        };
+        let (mut feature_deps, used) = match features {
+            Some(ref features) => {
+                build_features(summary,
+                               &Method::Required {
+                                   dev_deps: dev_deps,
+                                   features: features,
+                                   uses_default_features: uses_default_features,
+                               })?
+            }
+            None => build_features(summary, &Method::Everything)?,
+        };
+        let kind_features = if for_host {
+            &mut host_features
+        } else {
+            &mut target_features
+        };
+        kind_features.entry(id.clone())
+            .or_insert(HashSet::new())
+            .extend(used);
+
+        for dep in summary.dependencies().iter().filter(|d| d.is_transitive() || dev_deps) {
+            if dep.is_optional() && !feature_deps.contains_key(dep.name()) {
+                continue;
+            }
+            let dep_id = match resolve.deps_not_replaced(&id).find(|p| dep.matches_id(p)) {
+                Some(dep_id) => resolve.replacement(dep_id).unwrap_or(dep_id).clone(),
+                None => continue,
+            };
+            let mut requested = feature_deps.remove(dep.name()).unwrap_or(Vec::new());
+            requested.extend(dep.features().iter().cloned());
+            pending.push((dep_id, for_host || dep.is_build(), Some(requested), dep.uses_default_features(), false));
+        }
+    }
+
+    Ok((target_features, host_features))
+}
+
 fn check_cycles(resolve: &Resolve, activations: &HashMap<(String, SourceId), Vec<Rc<Summary>>>) -> CraftResult<()> {
     let summaries: HashMap<&PackageId, &Summary> = activations.values()
         .flat_map(|v| v)
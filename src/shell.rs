@@ -1,5 +1,6 @@
 //! Internal shell handling for reporting purposes
-use std::{io, fmt};
+use std::{env, io, fmt};
+use std::ffi::OsString;
 use std::io::prelude::*;
 
 use term::color::{Color, BLACK, BRIGHT_RED, BRIGHT_GREEN, BRIGHT_YELLOW};
@@ -50,6 +51,10 @@ enum AdequateTerminal {
 pub struct Shell {
     terminal: AdequateTerminal,
     config: ShellConfig,
+    // The progress bar shown below all other output, and whether it's drawn
+    // currently
+    progress: Option<String>,
+    progress_drawn: bool,
 }
 
 pub struct MultiShell {
@@ -85,10 +90,19 @@ impl MultiShell {
     pub fn status<T, U>(&mut self, status: T, message: U) -> CraftResult<()>
         where T: fmt::Display,
               U: fmt::Display
+    {
+        self.status_with_color(status, message, BRIGHT_GREEN)
+    }
+
+    /// Like `status`, but with the status tag printed in `color` instead of
+    /// green.
+    pub fn status_with_color<T, U>(&mut self, status: T, message: U, color: Color) -> CraftResult<()>
+        where T: fmt::Display,
+              U: fmt::Display
     {
         match self.verbosity {
             Quiet => Ok(()),
-            _ => self.err().say_status(format!("[{}]", status), message, BRIGHT_GREEN),
+            _ => self.err().say_status(format!("[{}]", status), message, color),
         }
     }
 
@@ -131,7 +145,7 @@ impl MultiShell {
             Some("always") => Always,
             Some("never") => Never,
 
-            None => Auto,
+            None => env_color_config(),
 
             Some(arg) => {
                 bail!("argument for --color must be auto, always, or never, but found `{}`",
@@ -151,6 +165,11 @@ impl MultiShell {
         assert!(self.out.config.color_config == self.err.config.color_config);
         self.out.config.color_config
     }
+
+    /// Whether the messages printed to stderr are colored.
+    pub fn err_colored(&self) -> bool {
+        self.err.colored()
+    }
 }
 
 impl Shell {
@@ -163,6 +182,8 @@ impl Shell {
         Shell {
             terminal: term,
             config: config,
+            progress: None,
+            progress_drawn: false,
         }
     }
 
@@ -212,7 +233,58 @@ impl Shell {
         self.config.color_config = color_config;
     }
 
+    pub fn is_tty(&self) -> bool {
+        self.config.tty
+    }
+
+    /// Shows `line` as a progress bar below the other output, replacing the
+    /// previous one, or removes the bar if `line` is `None`.
+    ///
+    /// Whatever else is written to the shell clears the bar first, messages
+    /// printed with `say` or `say_status` redraw it below them afterwards.
+    pub fn set_progress(&mut self, line: Option<String>) -> CraftResult<()> {
+        self.clear_progress()?;
+        self.progress = line;
+        self.draw_progress()?;
+        Ok(())
+    }
+
+    fn clear_progress(&mut self) -> CraftResult<()> {
+        if !self.progress_drawn {
+            return Ok(());
+        }
+        self.progress_drawn = false;
+        match self.terminal {
+            Colored(ref mut c) => {
+                c.carriage_return()?;
+                c.delete_line()?;
+            }
+            NoColor(ref mut n) => write!(n, "\r\x1b[K")?,
+        }
+        Ok(())
+    }
+
+    fn draw_progress(&mut self) -> CraftResult<()> {
+        let line = match self.progress {
+            Some(ref line) if !self.progress_drawn => line,
+            _ => return Ok(()),
+        };
+        match self.terminal {
+            Colored(ref mut c) => {
+                write!(c, "{}", line)?;
+                c.flush()?;
+            }
+            NoColor(ref mut n) => {
+                write!(n, "{}", line)?;
+                n.flush()?;
+            }
+        }
+        self.progress_drawn = true;
+        Ok(())
+    }
+
     pub fn say<T: ToString>(&mut self, message: T, color: Color) -> CraftResult<()> {
+        self.clear_progress()?;
         self.reset()?;
         if color != BLACK {
             self.fg(color)?;
@@ -220,6 +292,7 @@ impl Shell {
         writeln!(self, "{}", message.to_string())?;
         self.reset()?;
         self.flush()?;
+        self.draw_progress()?;
         Ok(())
     }
 
@@ -227,6 +300,7 @@ impl Shell {
         where T: fmt::Display,
               U: fmt::Display
     {
+        self.clear_progress()?;
         self.reset()?;
         if color != BLACK {
             self.fg(color)?;
@@ -238,6 +312,7 @@ impl Shell {
         self.reset()?;
         writeln!(self, " {}", message)?;
         self.flush()?;
+        self.draw_progress()?;
         Ok(())
     }
 
@@ -285,8 +360,29 @@ impl Shell {
     }
 }
 
+/// Resolve the color configuration from the `NO_COLOR` and `CLICOLOR_FORCE`
+/// environment conventions, falling back to tty autodetection.
+fn env_color_config() -> ColorConfig {
+    color_config_from_env(env::var_os("NO_COLOR"), env::var_os("CLICOLOR_FORCE"))
+}
+
+fn color_config_from_env(no_color: Option<OsString>, clicolor_force: Option<OsString>) -> ColorConfig {
+    // `NO_COLOR` wins over `CLICOLOR_FORCE` as soon as it is set to anything
+    // non-empty, see https://no-color.org
+    if no_color.map_or(false, |v| !v.is_empty()) {
+        return Never;
+    }
+    match clicolor_force {
+        Some(ref v) if !v.is_empty() && v != "0" => Always,
+        _ => Auto,
+    }
+}
+
 impl Write for Shell {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.progress_drawn {
+            self.clear_progress().map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        }
         match self.terminal {
             Colored(ref mut c) => c.write(buf),
             NoColor(ref mut n) => n.write(buf),
@@ -300,3 +396,108 @@ impl Write for Shell {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    use term::color::BRIGHT_YELLOW;
+
+    use super::{MultiShell, Shell, ShellConfig, Verbosity, color_config_from_env};
+    use super::ColorConfig::{Auto, Always, Never};
+
+    fn tty_shell() -> Shell {
+        Shell::create(|| Box::new(Vec::new()) as Box<Write + Send>,
+                      ShellConfig {
+                          color_config: Auto,
+                          tty: true,
+                      })
+    }
+
+    // A writer whose output can still be inspected after handing it to a shell
+    #[derive(Clone)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn status_with_color_without_tty() {
+        let capture = Capture(Arc::new(Mutex::new(Vec::new())));
+        let config = ShellConfig {
+            color_config: Auto,
+            tty: false,
+        };
+        let out = Shell::create(|| Box::new(Vec::new()) as Box<Write + Send>, config);
+        let err = Shell::create(|| Box::new(capture.clone()) as Box<Write + Send>, config);
+        let mut shell = MultiShell::new(out, err, Verbosity::Normal);
+
+        shell.status_with_color("Skipping", "foo v0.5.0", BRIGHT_YELLOW).unwrap();
+        shell.set_verbosity(Verbosity::Quiet);
+        shell.status_with_color("Skipping", "bar v0.5.0", BRIGHT_YELLOW).unwrap();
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(output, "[Skipping] foo v0.5.0\n");
+    }
+
+    #[test]
+    fn progress_is_redrawn_below_status_lines() {
+        let capture = Capture(Arc::new(Mutex::new(Vec::new())));
+        let config = ShellConfig {
+            color_config: Auto,
+            tty: true,
+        };
+        let mut shell = Shell::create(|| Box::new(capture.clone()) as Box<Write + Send>, config);
+
+        shell.set_progress(Some("[Building] 0/2".to_string())).unwrap();
+        shell.say_status("[Compiling]", "foo v0.5.0", BRIGHT_YELLOW).unwrap();
+        shell.set_progress(Some("[Building] 1/2".to_string())).unwrap();
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2, "{:?}", output);
+        assert!(lines[0].starts_with("[Building] 0/2"), "{:?}", output);
+        assert!(lines[0].contains("foo v0.5.0"), "{:?}", output);
+        assert!(output.ends_with("[Building] 1/2"), "{:?}", output);
+
+        shell.set_progress(None).unwrap();
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(!output.ends_with("[Building] 1/2"), "{:?}", output);
+    }
+
+    #[test]
+    fn color_config_env_conventions() {
+        assert!(color_config_from_env(None, None) == Auto);
+        assert!(color_config_from_env(Some(OsString::from("1")), None) == Never);
+        assert!(color_config_from_env(Some(OsString::from("")), None) == Auto);
+        assert!(color_config_from_env(None, Some(OsString::from("1"))) == Always);
+        assert!(color_config_from_env(None, Some(OsString::from("0"))) == Auto);
+        assert!(color_config_from_env(Some(OsString::from("1")), Some(OsString::from("1"))) == Never);
+    }
+
+    #[test]
+    fn no_color_disables_tty_colors() {
+        let mut shell = tty_shell();
+        assert!(shell.colored());
+        shell.set_color_config(color_config_from_env(Some(OsString::from("1")), None));
+        assert!(!shell.colored());
+    }
+
+    #[test]
+    fn explicit_color_overrides_no_color() {
+        let mut shell = MultiShell::new(tty_shell(), tty_shell(), Verbosity::Normal);
+        shell.err().set_color_config(color_config_from_env(Some(OsString::from("1")), None));
+        shell.set_color_config(Some("always")).unwrap();
+        assert!(shell.color_config() == Always);
+        assert!(shell.err().colored());
+    }
+}
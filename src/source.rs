@@ -79,6 +79,10 @@ enum Kind {
 
     /// represents a directory-based registry
     Directory,
+
+    /// represents a registry whose index is fetched file by file over HTTP,
+    /// kept last as the kind is part of the hash of every source id
+    SparseRegistry,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -101,6 +105,8 @@ struct SourceIdInner {
     kind: Kind,
     // e.g. the exact git revision of the specified branch for a Git Source
     precise: Option<String>,
+    // whether the submodules of a Git Source are checked out as well
+    submodules: bool,
 }
 
 impl SourceId {
@@ -111,6 +117,7 @@ impl SourceId {
                 canonical_url: git::canonicalize_url(&url),
                 url: url,
                 precise: None,
+                submodules: true,
             }),
         }
     }
@@ -134,6 +141,7 @@ impl SourceId {
             "git" => {
                 let mut url = url.to_url()?;
                 let mut reference = GitReference::Branch("master".to_string());
+                let mut submodules = true;
                 for (k, v) in url.query_pairs() {
                     match &k[..] {
                         // map older 'ref' to branch
@@ -141,18 +149,23 @@ impl SourceId {
 
                         "rev" => reference = GitReference::Rev(v.into_owned()),
                         "tag" => reference = GitReference::Tag(v.into_owned()),
+                        "submodules" => submodules = v != "false",
                         _ => {}
                     }
                 }
                 let precise = url.fragment().map(|s| s.to_owned());
                 url.set_fragment(None);
                 url.set_query(None);
-                Ok(SourceId::for_git(&url, reference).with_precise(precise))
+                Ok(SourceId::for_git(&url, reference).with_precise(precise).with_submodules(submodules))
             }
             "registry" => {
                 let url = url.to_url()?;
                 Ok(SourceId::new(Kind::Registry, url).with_precise(Some("locked".to_string())))
             }
+            "sparse" => {
+                let url = url.to_url()?;
+                Ok(SourceId::new(Kind::SparseRegistry, url).with_precise(Some("locked".to_string())))
+            }
             "path" => {
                 let url = url.to_url()?;
                 Ok(SourceId::new(Kind::Path, url))
@@ -164,8 +177,8 @@ impl SourceId {
     pub fn to_url(&self) -> String {
         match *self.inner {
             SourceIdInner { kind: Kind::Path, ref url, .. } => format!("path+{}", url),
-            SourceIdInner { kind: Kind::Git(ref reference), ref url, ref precise, .. } => {
-                let ref_str = reference.url_ref();
+            SourceIdInner { kind: Kind::Git(ref reference), ref url, ref precise, submodules, .. } => {
+                let ref_str = git_query(reference, submodules);
 
                 let precise_str = if precise.is_some() {
                     format!("#{}", precise.as_ref().unwrap())
@@ -176,6 +189,7 @@ impl SourceId {
                 format!("git+{}{}{}", url, ref_str, precise_str)
             }
             SourceIdInner { kind: Kind::Registry, ref url, .. } => format!("registry+{}", url),
+            SourceIdInner { kind: Kind::SparseRegistry, ref url, .. } => format!("sparse+{}", url),
             SourceIdInner { kind: Kind::LocalRegistry, ref url, .. } => format!("local-registry+{}", url),
             SourceIdInner { kind: Kind::Directory, ref url, .. } => format!("directory+{}", url),
         }
@@ -191,7 +205,14 @@ impl SourceId {
         SourceId::new(Kind::Git(reference), url.clone())
     }
 
+    /// Creates the ID of the registry with the index at `url`. Indices given as
+    /// `sparse+<url>` are fetched over HTTP instead of with git.
     pub fn for_registry(url: &Url) -> SourceId {
+        if url.scheme().starts_with("sparse+") {
+            if let Ok(url) = url.as_str()["sparse+".len()..].to_url() {
+                return SourceId::new(Kind::SparseRegistry, url);
+            }
+        }
         SourceId::new(Kind::Registry, url.clone())
     }
 
@@ -214,7 +235,14 @@ impl SourceId {
     }
 
     pub fn is_registry(&self) -> bool {
-        self.inner.kind == Kind::Registry || self.inner.kind == Kind::LocalRegistry
+        match self.inner.kind {
+            Kind::Registry | Kind::SparseRegistry | Kind::LocalRegistry => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_sparse_registry(&self) -> bool {
+        self.inner.kind == Kind::SparseRegistry
     }
 
     pub fn is_git(&self) -> bool {
@@ -236,7 +264,7 @@ impl SourceId {
                 };
                 Box::new(PathSource::new(&path, self, config))
             }
-            Kind::Registry => Box::new(RegistrySource::remote(self, config)),
+            Kind::Registry | Kind::SparseRegistry => Box::new(RegistrySource::remote(self, config)),
             Kind::LocalRegistry => {
                 let path = match self.inner.url.to_file_path() {
                     Ok(p) => p,
@@ -268,6 +296,15 @@ impl SourceId {
     pub fn with_precise(&self, v: Option<String>) -> SourceId {
         SourceId { inner: Arc::new(SourceIdInner { precise: v, ..(*self.inner).clone() }) }
     }
+
+    /// Whether the submodules of a git source should be checked out along with it.
+    pub fn submodules(&self) -> bool {
+        self.inner.submodules
+    }
+
+    pub fn with_submodules(&self, v: bool) -> SourceId {
+        SourceId { inner: Arc::new(SourceIdInner { submodules: v, ..(*self.inner).clone() }) }
+    }
 }
 
 impl PartialEq for SourceId {
@@ -309,8 +346,8 @@ impl fmt::Display for SourceId {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match *self.inner {
             SourceIdInner { kind: Kind::Path, ref url, .. } => fmt::Display::fmt(url, f),
-            SourceIdInner { kind: Kind::Git(ref reference), ref url, ref precise, .. } => {
-                write!(f, "{}{}", url, reference.url_ref())?;
+            SourceIdInner { kind: Kind::Git(ref reference), ref url, ref precise, submodules, .. } => {
+                write!(f, "{}{}", url, git_query(reference, submodules))?;
 
                 if let Some(ref s) = *precise {
                     let len = cmp::min(s.len(), 8);
@@ -319,6 +356,7 @@ impl fmt::Display for SourceId {
                 Ok(())
             }
             SourceIdInner { kind: Kind::Registry, ref url, .. } |
+            SourceIdInner { kind: Kind::SparseRegistry, ref url, .. } |
             SourceIdInner { kind: Kind::LocalRegistry, ref url, .. } => write!(f, "registry {}", url),
             SourceIdInner { kind: Kind::Directory, ref url, .. } => write!(f, "dir {}", url),
         }
@@ -329,7 +367,7 @@ impl fmt::Display for SourceId {
 // same URL, but not quite, even when they actually point to the same repository.
 impl PartialEq for SourceIdInner {
     fn eq(&self, other: &SourceIdInner) -> bool {
-        if self.kind != other.kind {
+        if self.kind != other.kind || self.submodules != other.submodules {
             return false;
         }
         if self.url == other.url {
@@ -355,6 +393,10 @@ impl Ord for SourceIdInner {
             Ordering::Equal => {}
             ord => return ord,
         }
+        match self.submodules.cmp(&other.submodules) {
+            Ordering::Equal => {}
+            ord => return ord,
+        }
         match self.url.cmp(&other.url) {
             Ordering::Equal => {}
             ord => return ord,
@@ -381,6 +423,20 @@ impl hash::Hash for SourceId {
     }
 }
 
+/// Builds the query of a git source URL out of its reference and whether submodules are checked
+/// out, which is only mentioned if they aren't.
+fn git_query(reference: &GitReference, submodules: bool) -> String {
+    let mut query = reference.to_ref_string().into_iter().collect::<Vec<_>>();
+    if !submodules {
+        query.push("submodules=false".to_string());
+    }
+    if query.is_empty() {
+        String::new()
+    } else {
+        format!("?{}", query.join("&"))
+    }
+}
+
 impl GitReference {
     pub fn to_ref_string(&self) -> Option<String> {
         match *self {
@@ -395,13 +451,6 @@ impl GitReference {
             GitReference::Rev(ref s) => Some(format!("rev={}", s)),
         }
     }
-
-    fn url_ref(&self) -> String {
-        match self.to_ref_string() {
-            None => "".to_string(),
-            Some(s) => format!("?{}", s),
-        }
-    }
 }
 
 pub struct SourceMap<'src> {
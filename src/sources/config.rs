@@ -9,7 +9,7 @@ use std::path::{Path, PathBuf};
 
 use url::Url;
 
-use source::{Source, SourceId};
+use source::{GitReference, Source, SourceId};
 use sources::ReplacedSource;
 use util::{CraftResult, Config, ChainError, human, ToUrl};
 use util::config::ConfigValue;
@@ -27,6 +27,9 @@ pub struct SourceConfigMap<'cfg> {
 /// registry = 'https://github.com/rust-lang/chests.io-index'
 /// replace-with = 'foo'    # optional
 /// ```
+///
+/// Git sources are identified by their `git` URL along with the `branch`,
+/// `tag` or `rev` and `submodules` keys of the dependencies using them.
 struct SourceConfig {
     // id this source corresponds to, inferred from the various defined keys in
     // the configuration
@@ -106,22 +109,18 @@ impl<'cfg> SourceConfigMap<'cfg> {
         }
         let new_src = new_id.load(self.config);
         let old_src = id.load(self.config);
-        if new_src.supports_checksums() != old_src.supports_checksums() {
-            let (supports, no_support) = if new_src.supports_checksums() {
-                (name, orig_name)
-            } else {
-                (orig_name, name)
-            };
+        // A source without checksums can be replaced with one supporting them,
+        // like vendored git dependencies, as long as the replacement doesn't
+        // actually provide checksums for its packages.
+        if old_src.supports_checksums() && !new_src.supports_checksums() {
             bail!("\
-cannot replace `{orig}` with `{name}`, the source `{supports}` supports \
-checksums, but `{no_support}` does not
+cannot replace `{orig}` with `{name}`, the source `{orig}` supports \
+checksums, but `{name}` does not
 
 a lock file compatible with `{orig}` cannot be generated in this situation
 ",
                   orig = orig_name,
-                  name = name,
-                  supports = supports,
-                  no_support = no_support);
+                  name = name);
         }
         Ok(Box::new(ReplacedSource::new(id, &new_id, new_src)))
     }
@@ -146,6 +145,28 @@ a lock file compatible with `{orig}` cannot be generated in this situation
             path.push(s);
             srcs.push(SourceId::for_local_registry(&path)?);
         }
+        if let Some(val) = table.get("git") {
+            let url = url(val, &format!("source.{}.git", name))?;
+            let mut reference = GitReference::Branch("master".to_string());
+            if let Some(val) = table.get("branch") {
+                let (s, _) = val.string(&format!("source.{}.branch", name))?;
+                reference = GitReference::Branch(s.to_string());
+            }
+            if let Some(val) = table.get("tag") {
+                let (s, _) = val.string(&format!("source.{}.tag", name))?;
+                reference = GitReference::Tag(s.to_string());
+            }
+            if let Some(val) = table.get("rev") {
+                let (s, _) = val.string(&format!("source.{}.rev", name))?;
+                reference = GitReference::Rev(s.to_string());
+            }
+            let mut id = SourceId::for_git(&url, reference);
+            if let Some(val) = table.get("submodules") {
+                let (submodules, _) = val.boolean(&format!("source.{}.submodules", name))?;
+                id = id.with_submodules(submodules);
+            }
+            srcs.push(id);
+        }
         if let Some(val) = table.get("directory") {
             let (s, path) = val.string(&format!("source.{}.directory", name))?;
             let mut path = path.to_path_buf();
@@ -158,8 +179,8 @@ a lock file compatible with `{orig}` cannot be generated in this situation
         let mut srcs = srcs.into_iter();
         let src = srcs.next()
             .chain_error(|| {
-                human(format!("no source URL specified for `source.{}`, need either `registry` or `local-registry` \
-                               defined",
+                human(format!("no source URL specified for `source.{}`, need either `registry`, `local-registry`, \
+                               `git` or `directory` defined",
                               name))
             })?;
         if srcs.next().is_some() {
@@ -27,7 +27,8 @@ pub struct DirectorySource<'cfg> {
 
 #[derive(RustcDecodable)]
 struct Checksum {
-    package: String,
+    // `None` for packages of sources without checksums, like git repositories
+    package: Option<String>,
     files: HashMap<String, String>,
 }
 
@@ -92,8 +93,10 @@ impl<'cfg> Source for DirectorySource<'cfg> {
                 })?;
 
             let mut manifest = pkg.manifest().clone();
-            let summary = manifest.summary().clone();
-            manifest.set_summary(summary.set_checksum(cksum.package.clone()));
+            if let Some(ref package) = cksum.package {
+                let summary = manifest.summary().clone();
+                manifest.set_summary(summary.set_checksum(package.clone()));
+            }
             let pkg = Package::new(manifest, pkg.manifest_path());
             self.packages.insert(pkg.package_id().clone(), (pkg, cksum));
         }
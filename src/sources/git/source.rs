@@ -150,17 +150,25 @@ impl<'cfg> Source for GitSource<'cfg> {
             (self.remote.db_at(&db_path)?, actual_rev.unwrap())
         };
 
+        // Checkouts without submodules are kept apart so that they don't
+        // reuse a checkout which has them populated, and vice versa.
+        let submodules = self.source_id.submodules();
+        let checkout_name = if submodules {
+            actual_rev.to_string()
+        } else {
+            format!("{}-no-submodules", actual_rev)
+        };
         let checkout_path = lock.parent()
             .join("checkouts")
             .join(&self.ident)
-            .join(actual_rev.to_string());
+            .join(checkout_name);
 
         // Copy the database to the checkout location. After this we could drop
         // the lock on the database as we no longer needed it, but we leave it
         // in scope so the destructors here won't tamper with too much.
         // Checkout is immutable, so we don't need to protect it with a lock once
         // it is created.
-        repo.copy_to(actual_rev.clone(), &checkout_path, &self.config)?;
+        repo.copy_to(actual_rev.clone(), &checkout_path, submodules, &self.config)?;
 
         let source_id = self.source_id.with_precise(Some(actual_rev.to_string()));
         let path_source = PathSource::new_recursive(&checkout_path, &source_id, self.config);
@@ -1,6 +1,7 @@
 use std::env;
 use std::fmt;
 use std::fs::{self, File};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use rustc_serialize::{Encodable, Encoder};
@@ -8,7 +9,7 @@ use url::Url;
 use git2::{self, ObjectType};
 
 use source::GitReference;
-use util::{CraftResult, ChainError, human, ToUrl, internal, Config, network};
+use util::{CraftResult, ChainError, human, ToUrl, internal, Config, network, process};
 
 #[derive(PartialEq, Clone, Debug)]
 pub struct GitRevision(git2::Oid);
@@ -161,7 +162,12 @@ impl GitDatabase {
         &self.path
     }
 
-    pub fn copy_to(&self, rev: GitRevision, dest: &Path, craft_config: &Config) -> CraftResult<GitCheckout> {
+    pub fn copy_to(&self,
+                   rev: GitRevision,
+                   dest: &Path,
+                   submodules: bool,
+                   craft_config: &Config)
+                   -> CraftResult<GitCheckout> {
         let checkout = match git2::Repository::open(dest) {
             Ok(repo) => {
                 let checkout = GitCheckout::new(dest, self, rev, repo);
@@ -174,7 +180,9 @@ impl GitDatabase {
             }
             Err(..) => GitCheckout::clone_into(dest, self, rev)?,
         };
-        checkout.update_submodules(&craft_config).chain_error(|| internal("failed to update submodules"))?;
+        if submodules {
+            checkout.update_submodules(&craft_config).chain_error(|| internal("failed to update submodules"))?;
+        }
         Ok(checkout)
     }
 
@@ -538,15 +546,27 @@ fn with_authentication<T, F>(url: &str, cfg: &git2::Config, mut f: F) -> CraftRe
 }
 
 pub fn fetch(repo: &git2::Repository, url: &str, refspec: &str, config: &Config) -> CraftResult<()> {
-    if !config.network_allowed() {
-        bail!("attempting to update a git repository, but --frozen \
-               was specified")
+    if let Some(flag) = config.network_disallowed_by() {
+        bail!("attempting to update a git repository, but {} was specified",
+              flag)
+    }
+
+    if config.get_bool("net.git-fetch-with-cli")?.map(|v| v.val).unwrap_or(false) {
+        return fetch_with_cli(repo, url, refspec, config);
     }
 
     with_authentication(url, &repo.config()?, |f| {
         let mut cb = git2::RemoteCallbacks::new();
         cb.credentials(f);
 
+        // Pass on the progress messages of the remote under `-vv`
+        if config.extra_verbose() {
+            cb.sideband_progress(|data| {
+                let _ = config.shell().err().write_all(data);
+                true
+            });
+        }
+
         // Create a local anonymous remote in the repository to fetch the url
         let mut remote = repo.remote_anonymous(&url)?;
         let mut opts = git2::FetchOptions::new();
@@ -557,3 +577,20 @@ pub fn fetch(repo: &git2::Repository, url: &str, refspec: &str, config: &Config)
         Ok(())
     })
 }
+
+/// Fetches `refspec` from `url` with the `git` binary instead of libgit2, which
+/// picks up the authentication and proxy setup of the system's git.
+fn fetch_with_cli(repo: &git2::Repository, url: &str, refspec: &str, config: &Config) -> CraftResult<()> {
+    let mut cmd = process("git");
+    cmd.arg("fetch")
+        .arg("--tags")
+        .arg("--force")
+        .arg("--update-head-ok")
+        .arg(url)
+        .arg(refspec)
+        .env("GIT_DIR", repo.path())
+        .cwd(repo.path());
+    config.shell().verbose(|s| s.status("Running", &cmd.to_string()))?;
+    cmd.exec().chain_error(|| human(format!("failed to fetch `{}` with the git binary", url)))?;
+    Ok(())
+}
@@ -6,6 +6,7 @@ use std::path::{Path, PathBuf};
 use filetime::FileTime;
 use git2;
 use glob::Pattern;
+use rustc_serialize::hex::ToHex;
 
 use dependency::Dependency;
 use ops;
@@ -14,7 +15,7 @@ use package_id::PackageId;
 use registry::Registry;
 use source::{SourceId, Source};
 use summary::Summary;
-use util::{self, CraftResult, internal, internal_error, human, ChainError, Config};
+use util::{self, CraftResult, internal, internal_error, human, paths, ChainError, Config, Sha256};
 
 pub struct PathSource<'cfg> {
     id: SourceId,
@@ -263,6 +264,31 @@ impl<'cfg> PathSource<'cfg> {
         }
     }
 
+    // The SHA-256 of the paths and contents of the files of `pkg`, in sorted
+    // order so that it's the same for the same files across runs.
+    fn content_fingerprint(&self, pkg: &Package) -> CraftResult<String> {
+        let mut files = self.list_files(pkg)?;
+        files.sort();
+        let mut hasher = Sha256::new();
+        for file in files {
+            let contents = match paths::read_bytes(&file) {
+                Ok(contents) => contents,
+                // Like for mtimes, vanished and unreadable files count as
+                // empty.
+                Err(..) => Vec::new(),
+            };
+            let relative = file.strip_prefix(pkg.root()).unwrap_or(&file);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update(&[0]);
+            hasher.update(contents.len().to_string().as_bytes());
+            hasher.update(&[0]);
+            hasher.update(&contents);
+        }
+        let fingerprint = hasher.finish().to_hex();
+        trace!("fingerprint {}: {}", self.path.display(), fingerprint);
+        Ok(format!("sha256:{}", fingerprint))
+    }
+
     fn list_files_walk(&self, pkg: &Package, filter: &mut FnMut(&Path) -> bool) -> CraftResult<Vec<PathBuf>> {
         let mut ret = Vec::new();
         PathSource::walk(pkg.root(), &mut ret, true, filter)?;
@@ -335,6 +361,10 @@ impl<'cfg> Source for PathSource<'cfg> {
             return Err(internal_error("BUG: source was not updated", ""));
         }
 
+        if self.config.content_fingerprints()? {
+            return self.content_fingerprint(pkg);
+        }
+
         let mut max = FileTime::zero();
         let mut max_path = PathBuf::from("");
         for file in self.list_files(pkg)? {
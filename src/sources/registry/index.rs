@@ -1,8 +1,10 @@
+use std::cmp;
 use std::collections::HashMap;
 use std::io::prelude::*;
 use std::fs::File;
 use std::path::Path;
 
+use crossbeam;
 use rustc_serialize::json;
 
 use dependency::{Dependency, DependencyInner, Kind};
@@ -13,6 +15,13 @@ use sources::registry::{RegistryPackage, RegistryDependency, INDEX_LOCK};
 use summary::Summary;
 use util::{CraftResult, ChainError, internal, Filesystem, Config};
 
+/// The most threads decoding the lines of an index file at once.
+const MAX_DECODE_THREADS: usize = 8;
+
+/// Index files with fewer lines than this are decoded on the current thread,
+/// spawning threads costs more than it saves for them.
+const PARALLEL_DECODE_LINES: usize = 64;
+
 pub struct RegistryIndex<'cfg> {
     source_id: SourceId,
     path: Filesystem,
@@ -75,29 +84,18 @@ impl<'cfg> RegistryIndex<'cfg> {
             (self.path.clone().into_path_unlocked(), None)
         };
 
-        let fs_name = name.chars()
-            .flat_map(|c| c.to_lowercase())
-            .collect::<String>();
-
-        // see module comment for why this is structured the way it is
-        let path = match fs_name.len() {
-            1 => path.join("1").join(&fs_name),
-            2 => path.join("2").join(&fs_name),
-            3 => path.join("3").join(&fs_name[..1]).join(&fs_name),
-            _ => {
-                path.join(&fs_name[0..2])
-                    .join(&fs_name[2..4])
-                    .join(&fs_name)
-            }
-        };
+        let path = path.join(index_file_path(name));
         match File::open(&path) {
             Ok(mut f) => {
                 let mut contents = String::new();
                 f.read_to_string(&mut contents)?;
-                let ret: CraftResult<Vec<(Summary, bool)>>;
-                ret = contents.lines()
+                let lines = contents.lines()
                     .filter(|l| l.trim().len() > 0)
-                    .map(|l| self.parse_registry_package(l))
+                    .collect::<Vec<_>>();
+                let ret: CraftResult<Vec<(Summary, bool)>>;
+                ret = decode_lines(&lines)
+                    .into_iter()
+                    .map(|pkg| self.parse_registry_package(pkg?))
                     .collect();
                 ret.chain_error(|| internal(format!("failed to parse registry's information for: {}", name)))
             }
@@ -105,12 +103,12 @@ impl<'cfg> RegistryIndex<'cfg> {
         }
     }
 
-    /// Parse a line from the registry's index file into a Summary for a
-    /// package.
+    /// Parse a decoded line from the registry's index file into a Summary for
+    /// a package.
     ///
     /// The returned boolean is whether or not the summary has been yanked.
-    fn parse_registry_package(&mut self, line: &str) -> CraftResult<(Summary, bool)> {
-        let RegistryPackage { name, vers, cksum, deps, features, yanked } = json::decode::<RegistryPackage>(line)?;
+    fn parse_registry_package(&mut self, pkg: RegistryPackage) -> CraftResult<(Summary, bool)> {
+        let RegistryPackage { name, vers, cksum, deps, features, yanked } = pkg;
         let pkgid = PackageId::new(&name, &vers, &self.source_id)?;
         let deps: CraftResult<Vec<Dependency>> = deps.into_iter()
             .map(|dep| self.parse_registry_dependency(dep))
@@ -154,6 +152,40 @@ impl<'cfg> RegistryIndex<'cfg> {
     }
 }
 
+/// The path of the index file of the chest `name`, relative to the root of the
+/// index and separated by `/`.
+pub fn index_file_path(name: &str) -> String {
+    let fs_name = name.chars()
+        .flat_map(|c| c.to_lowercase())
+        .collect::<String>();
+
+    // see module comment for why this is structured the way it is
+    match fs_name.len() {
+        1 => format!("1/{}", fs_name),
+        2 => format!("2/{}", fs_name),
+        3 => format!("3/{}/{}", &fs_name[..1], fs_name),
+        _ => format!("{}/{}/{}", &fs_name[0..2], &fs_name[2..4], fs_name),
+    }
+}
+
+/// Decodes the lines of an index file, in parallel for large files. The
+/// results are in the order of `lines`, no matter which thread finishes first.
+///
+/// Only the decoding runs on other threads, summaries aren't `Send`.
+fn decode_lines(lines: &[&str]) -> Vec<json::DecodeResult<RegistryPackage>> {
+    let threads = cmp::min(::num_cpus::get(), MAX_DECODE_THREADS);
+    if threads < 2 || lines.len() < PARALLEL_DECODE_LINES {
+        return lines.iter().map(|l| json::decode(l)).collect();
+    }
+    let chunk_size = (lines.len() + threads - 1) / threads;
+    crossbeam::scope(|scope| {
+        let handles = lines.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|l| json::decode(l)).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        handles.into_iter().flat_map(|handle| handle.join()).collect()
+    })
+}
+
 impl<'cfg> Registry for RegistryIndex<'cfg> {
     fn query(&mut self, dep: &Dependency) -> CraftResult<Vec<Summary>> {
         let mut summaries = {
@@ -184,3 +216,30 @@ impl<'cfg> Registry for RegistryIndex<'cfg> {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_lines, index_file_path, PARALLEL_DECODE_LINES};
+
+    #[test]
+    fn decoded_lines_keep_their_order() {
+        let lines = (0..PARALLEL_DECODE_LINES * 4)
+            .map(|i| format!(r#"{{"name":"foo","vers":"0.1.{}","deps":[],"features":{{}},"cksum":""}}"#, i))
+            .collect::<Vec<_>>();
+        let lines = lines.iter().map(|l| &l[..]).collect::<Vec<_>>();
+        let versions = decode_lines(&lines)
+            .into_iter()
+            .map(|pkg| pkg.unwrap().vers)
+            .collect::<Vec<_>>();
+        let expected = (0..lines.len()).map(|i| format!("0.1.{}", i)).collect::<Vec<_>>();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn index_file_paths_are_sharded() {
+        assert_eq!(index_file_path("z"), "1/z");
+        assert_eq!(index_file_path("xz"), "2/xz");
+        assert_eq!(index_file_path("Png"), "3/p/png");
+        assert_eq!(index_file_path("libgit2"), "li/bg/libgit2");
+    }
+}
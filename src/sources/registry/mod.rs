@@ -49,6 +49,12 @@
 //!   the end of a file (the exact format is described later). This means that
 //!   the commits for an index are quite small and easily applied/compressable.
 //!
+//! Cloning the whole index is the slow part of using a registry for the first
+//! time though. Registries given as `sparse+https://...` therefore don't use a
+//! git repository, but serve each file of the index over HTTP. Only the files
+//! of the chests which are actually queried are fetched and cached locally, in
+//! the same format as a git index.
+//!
 //! ## The format of the Index
 //!
 //! The index is a store for the list of versions for all packages known, so its
@@ -223,12 +229,21 @@ pub trait RegistryData {
     fn index_path(&self) -> &Filesystem;
     fn config(&self) -> CraftResult<Option<RegistryConfig>>;
     fn update_index(&mut self) -> CraftResult<()>;
+
+    /// Makes sure the index file of the chest `name` is available on disk
+    /// before it is read. Registries keeping a complete copy of their index
+    /// have nothing to do here.
+    fn fetch_index_file(&mut self, _name: &str) -> CraftResult<()> {
+        Ok(())
+    }
+
     fn download(&mut self, pkg: &PackageId, checksum: &str) -> CraftResult<FileLock>;
 }
 
 mod index;
 pub mod remote;
 mod local;
+mod sparse;
 
 fn short_name(id: &SourceId) -> String {
     let hash = hex::short_hash(id);
@@ -239,8 +254,12 @@ fn short_name(id: &SourceId) -> String {
 impl<'cfg> RegistrySource<'cfg> {
     pub fn remote(source_id: &SourceId, config: &'cfg Config) -> RegistrySource<'cfg> {
         let name = short_name(source_id);
-        let ops = remote::RemoteRegistry::new(source_id, config, &name);
-        RegistrySource::new(source_id, config, &name, Box::new(ops), true)
+        let ops: Box<RegistryData + 'cfg> = if source_id.is_sparse_registry() {
+            Box::new(sparse::SparseRegistry::new(source_id, config, &name))
+        } else {
+            Box::new(remote::RemoteRegistry::new(source_id, config, &name))
+        };
+        RegistrySource::new(source_id, config, &name, ops, true)
     }
 
     pub fn local(source_id: &SourceId, path: &Path, config: &'cfg Config) -> RegistrySource<'cfg> {
@@ -302,6 +321,11 @@ impl<'cfg> RegistrySource<'cfg> {
         self.index = index::RegistryIndex::new(&self.source_id, path, self.config, self.index_locked);
         Ok(())
     }
+
+    fn query_index(&mut self, dep: &Dependency) -> CraftResult<Vec<Summary>> {
+        self.ops.fetch_index_file(dep.name())?;
+        self.index.query(dep)
+    }
 }
 
 impl<'cfg> Registry for RegistrySource<'cfg> {
@@ -311,12 +335,12 @@ impl<'cfg> Registry for RegistrySource<'cfg> {
         // come back with no summaries, then our registry may need to be
         // updated, so we fall back to performing a lazy update.
         if dep.source_id().precise().is_some() && !self.updated {
-            if self.index.query(dep)?.is_empty() {
+            if self.query_index(dep)?.is_empty() {
                 self.do_update()?;
             }
         }
 
-        self.index.query(dep)
+        self.query_index(dep)
     }
 
     fn supports_checksums(&self) -> bool {
@@ -340,6 +364,7 @@ impl<'cfg> Source for RegistrySource<'cfg> {
     }
 
     fn download(&mut self, package: &PackageId) -> CraftResult<Package> {
+        self.ops.fetch_index_file(package.name())?;
         let hash = self.index.hash(package)?;
         let path = self.ops.download(package, &hash)?;
         let path = self.unpack_package(package, &path)
@@ -1,7 +1,10 @@
 //! Source description for any kind of remote
-use std::io::SeekFrom;
+use std::cell::{Cell, RefCell};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, SeekFrom};
 use std::io::prelude::*;
 use std::path::Path;
+use std::str;
 use std::time::Duration;
 use std::env;
 
@@ -15,7 +18,8 @@ use package_id::PackageId;
 use source::SourceId;
 use sources::git;
 use sources::registry::{RegistryData, RegistryConfig, INDEX_LOCK};
-use util::{network, paths, FileLock, Filesystem, Config, CraftResult, ChainError, human, Sha256, ToUrl};
+use util::{network, paths, process, FileLock, Filesystem, Config, CraftResult, ChainError, HttpError, human, Sha256,
+           Progress, ToUrl};
 
 pub struct RemoteRegistry<'cfg> {
     index_path: Filesystem,
@@ -23,6 +27,7 @@ pub struct RemoteRegistry<'cfg> {
     source_id: SourceId,
     config: &'cfg Config,
     handle: Option<Easy>,
+    token: Option<Option<String>>,
 }
 
 impl<'cfg> RemoteRegistry<'cfg> {
@@ -33,8 +38,18 @@ impl<'cfg> RemoteRegistry<'cfg> {
             source_id: source_id.clone(),
             config: config,
             handle: None,
+            token: None,
         }
     }
+
+    /// The auth token of the registry, if any. A credential process is only
+    /// run the first time, not for every download.
+    pub fn token(&mut self) -> CraftResult<Option<String>> {
+        if self.token.is_none() {
+            self.token = Some(registry_token(self.config, &self.source_id)?);
+        }
+        Ok(self.token.clone().unwrap())
+    }
 }
 
 impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
@@ -121,6 +136,8 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
             .push(&pkg.version().to_string())
             .push("download");
 
+        let token = self.token()?;
+        let token = token.as_ref().map(|t| &t[..]);
         let handle = match self.handle {
             Some(ref mut handle) => handle,
             None => {
@@ -131,31 +148,230 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         handle.get(true)?;
         handle.url(&url.to_string())?;
         handle.follow_location(true)?;
-        let mut state = Sha256::new();
-        let mut body = Vec::new();
+
+        // The download goes to a separate file first, which is resumed if an
+        // earlier download was interrupted. In case the part downloaded before
+        // is stale or corrupt the download starts over once.
+        let part = dst.parent().join(format!("{}.part", filename));
+        let resumed = part.exists();
+        let mut actual = fetch(self.config, handle, &url, token, &part)?;
+        if actual != checksum && resumed {
+            fs::remove_file(&part)?;
+            actual = fetch(self.config, handle, &url, token, &part)?;
+        }
+
+        // Verify what we just downloaded
+        if actual != checksum {
+            let _ = fs::remove_file(&part);
+            bail!("failed to verify the checksum of `{}`", pkg)
+        }
+
+        io::copy(&mut File::open(&part)?, &mut dst)?;
+        fs::remove_file(&part)?;
+        dst.seek(SeekFrom::Start(0))?;
+        Ok(dst)
+    }
+}
+
+/// Downloads `url` into the file at `path`, streaming it to disk, and returns
+/// the hex SHA-256 of the whole file. If the file already holds the start of
+/// the download it's resumed with a ranged request, as far as the server
+/// supports them.
+fn fetch(config: &Config, handle: &mut Easy, url: &Url, token: Option<&str>, path: &Path) -> CraftResult<String> {
+    let mut file = OpenOptions::new().read(true).append(true).create(true).open(path)
+        .chain_error(|| human(format!("failed to open `{}`", path.display())))?;
+    let mut state = Sha256::new();
+    let mut len = 0;
+    let mut buf = [0; 16 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        state.update(&buf[..n]);
+        len += n as u64;
+    }
+
+    let file = RefCell::new(file);
+    let state = RefCell::new(state);
+    let len = Cell::new(len);
+    let write_error = RefCell::new(None);
+    let progress = RefCell::new(Progress::new("Downloading", config));
+    let result = network::with_retry(config, || -> Result<(), HttpError> {
+        let offset = len.get();
+        let ranged = offset > 0 && url.scheme().starts_with("http");
+        let mut headers = List::new();
+        if let Some(token) = token {
+            headers.append(&auth_header(token))?;
+        }
+        if ranged {
+            headers.append(&format!("Range: bytes={}-", offset))?;
+        }
+        handle.http_headers(headers)?;
+        // curl traces the headers it sends, the token included
+        handle.verbose(config.extra_verbose() && token.is_none())?;
+        handle.progress(true)?;
+
+        let status = Cell::new(0);
+        let first_write = Cell::new(true);
         {
             let mut handle = handle.transfer();
+            handle.header_function(|header| {
+                    if header.starts_with(b"HTTP/") {
+                        let code = str::from_utf8(header)
+                            .ok()
+                            .and_then(|h| h.split_whitespace().nth(1))
+                            .and_then(|c| c.parse().ok());
+                        status.set(code.unwrap_or(0));
+                        first_write.set(true);
+                    }
+                    true
+                })?;
+            handle.progress_function(|total, now, _, _| {
+                    // The sizes only cover the range of the file being sent
+                    let base = if status.get() == 206 { offset } else { 0 };
+                    if total > 0.0 {
+                        let _ = progress.borrow_mut().tick_bytes(base + now as u64, base + total as u64);
+                    }
+                    true
+                })?;
             handle.write_function(|buf| {
-                    state.update(buf);
-                    body.extend_from_slice(buf);
-                    Ok(buf.len())
+                    // Only the file itself goes to disk, not the body of a
+                    // redirect or an error page, which would also throw the
+                    // offset of the next attempt off. Non-HTTP transfers
+                    // don't have a status.
+                    let code = status.get();
+                    let whole = code == 200 || code == 0;
+                    if !whole && code != 206 {
+                        return Ok(buf.len());
+                    }
+                    // A server ignoring the range sends the whole file again.
+                    if first_write.get() && whole && len.get() > 0 {
+                        if let Err(e) = restart(&file, &state, &len) {
+                            *write_error.borrow_mut() = Some(e);
+                            return Ok(0);
+                        }
+                    }
+                    first_write.set(false);
+                    match file.borrow_mut().write_all(buf) {
+                        Ok(()) => {
+                            state.borrow_mut().update(buf);
+                            len.set(len.get() + buf.len() as u64);
+                            Ok(buf.len())
+                        }
+                        Err(e) => {
+                            *write_error.borrow_mut() = Some(e);
+                            Ok(0)
+                        }
+                    }
                 })?;
-            network::with_retry(self.config, || handle.perform())?
+            handle.perform()?;
         }
+        handle.progress(false)?;
         let code = handle.response_code()?;
-        if code != 200 && code != 0 {
-            bail!("failed to get 200 response from `{}`, got {}", url, code)
+        // An empty file answering a ranged request still replaces the start
+        if (code == 200 || code == 0) && first_write.get() && len.get() > 0 {
+            if let Err(e) = restart(&file, &state, &len) {
+                *write_error.borrow_mut() = Some(e);
+            }
         }
+        // Nothing is left to resume if the file is complete already, the
+        // checksum tells whether it is.
+        let complete = ranged && code == 416;
+        if code != 200 && code != 206 && code != 0 && !complete {
+            return Err(HttpError::Status {
+                url: url.to_string(),
+                code: code,
+            });
+        }
+        Ok(())
+    });
+    if let Some(e) = write_error.into_inner() {
+        return Err(e).chain_error(|| human(format!("failed to write `{}`", path.display())));
+    }
+    result.chain_error(|| human(format!("failed to download `{}`", url)))?;
+    Ok(state.into_inner().finish().to_hex())
+}
 
-        // Verify what we just downloaded
-        if state.finish().to_hex() != checksum {
-            bail!("failed to verify the checksum of `{}`", pkg)
+/// Throws away what was downloaded into `file` so far.
+fn restart(file: &RefCell<File>, state: &RefCell<Sha256>, len: &Cell<u64>) -> io::Result<()> {
+    file.borrow_mut().set_len(0)?;
+    *state.borrow_mut() = Sha256::new();
+    len.set(0);
+    Ok(())
+}
+
+/// Obtains the auth token of the registry `source_id` by running the
+/// `registries.<name>.credential-process` configured for its index, if any.
+///
+/// The process is given either as a list of the program and its arguments, or
+/// as a string which is split on whitespace, like an alias. The token is read
+/// from the first line of the process' standard output and is deliberately
+/// kept out of any log or error message.
+pub fn registry_token(config: &Config, source_id: &SourceId) -> CraftResult<Option<String>> {
+    let registries = match config.get_table("registries")? {
+        Some(registries) => registries.val,
+        None => return Ok(None),
+    };
+    let mut names = registries.keys().collect::<Vec<_>>();
+    names.sort();
+    for name in names {
+        let index = match config.get_string(&format!("registries.{}.index", name))? {
+            Some(index) => index,
+            None => continue,
+        };
+        // Sparse registries are known by their URL without the `sparse+`
+        if SourceId::for_registry(&index.val.to_url()?).url() != source_id.url() {
+            continue;
         }
+        let key = format!("registries.{}.credential-process", name);
+        let (args, definition) = match config.get_string(&key) {
+            Ok(Some(command)) => {
+                (command.val.split_whitespace().map(|s| s.to_string()).collect::<Vec<_>>(), command.definition)
+            }
+            Ok(None) => return Ok(None),
+            Err(..) => {
+                match config.get_list(&key)? {
+                    Some(command) => (command.val.into_iter().map(|a| a.0).collect(), command.definition),
+                    None => return Ok(None),
+                }
+            }
+        };
+        let program = match args.first() {
+            Some(program) => program,
+            None => bail!("`{}` in {} is empty", key, definition),
+        };
+        let mut cmd = process(program);
+        cmd.args(&args[1..]);
 
-        dst.write_all(&body)?;
-        dst.seek(SeekFrom::Start(0))?;
-        Ok(dst)
+        // Only the exit status and stderr make it into errors, stdout may
+        // already contain (part of) the token.
+        let output = cmd.build_command()
+            .output()
+            .chain_error(|| human(format!("failed to run credential process `{}` for registry `{}`", program, name)))?;
+        if !output.status.success() {
+            bail!("credential process `{}` for registry `{}` failed ({})\n{}",
+                  program,
+                  name,
+                  output.status,
+                  String::from_utf8_lossy(&output.stderr).trim_right())
+        }
+        let token = String::from_utf8(output.stdout)
+            .ok()
+            .and_then(|stdout| stdout.lines().next().map(|line| line.trim().to_string()))
+            .unwrap_or_default();
+        if token.is_empty() {
+            bail!("credential process `{}` for registry `{}` did not print a token",
+                  program,
+                  name)
+        }
+        return Ok(Some(token));
     }
+    Ok(None)
+}
+
+pub fn auth_header(token: &str) -> String {
+    format!("Authorization: {}", token)
 }
 
 /// Updating the index is done pretty regularly so we want it to be as fast as
@@ -208,16 +424,21 @@ fn github_up_to_date(handle: &mut Easy, url: &Url, oid: &git2::Oid) -> bool {
 
 /// Create a new HTTP handle with appropriate global configuration for craft.
 pub fn http_handle(config: &Config) -> CraftResult<Easy> {
-    if !config.network_allowed() {
-        bail!("attempting to make an HTTP request, but --frozen was \
-               specified")
+    if let Some(flag) = config.network_disallowed_by() {
+        bail!("attempting to make an HTTP request, but {} was specified",
+              flag)
     }
 
+    let mut handle = Easy::new();
+    configure_http_handle(&mut handle, config)?;
+    Ok(handle)
+}
+
+fn configure_http_handle(handle: &mut Easy, config: &Config) -> CraftResult<()> {
     // The timeout option for libcurl by default times out the entire transfer,
     // but we probably don't want this. Instead we only set timeouts for the
     // connect phase as well as a "low speed" timeout so if we don't receive
     // many bytes in a large-ish period of time then we time out.
-    let mut handle = Easy::new();
     handle.connect_timeout(Duration::new(30, 0))?;
     handle.low_speed_limit(10 /* bytes per second */)?;
     handle.low_speed_time(Duration::new(30, 0))?;
@@ -231,7 +452,11 @@ pub fn http_handle(config: &Config) -> CraftResult<Easy> {
         handle.connect_timeout(Duration::new(timeout as u64, 0))?;
         handle.low_speed_time(Duration::new(timeout as u64, 0))?;
     }
-    Ok(handle)
+    // Let curl trace the transfers to stderr under `-vv`
+    if config.extra_verbose() {
+        handle.verbose(true)?;
+    }
+    Ok(())
 }
 
 /// Find an explicit HTTP proxy if one is available.
@@ -262,3 +487,55 @@ fn http_timeout(config: &Config) -> CraftResult<Option<i64>> {
     }
     Ok(env::var("HTTP_TIMEOUT").ok().and_then(|s| s.parse().ok()))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempdir::TempDir;
+
+    use shell::{Verbosity, ColorConfig};
+    use source::SourceId;
+    use util::{Config, CraftResult, ToUrl, paths};
+    use super::{auth_header, registry_token};
+
+    // The token of a registry with the given `credential-process` TOML value
+    fn token_from(credential_process: &str) -> CraftResult<Option<String>> {
+        let td = TempDir::new("craft-credentials").unwrap();
+        fs::create_dir_all(td.path().join(".craft")).unwrap();
+        paths::write(&td.path().join(".craft/config"),
+                     format!(r#"
+                [registries.private]
+                index = "https://example.com/index"
+                credential-process = {}
+            "#,
+                             credential_process)
+                         .as_bytes())
+            .unwrap();
+        let shell = ::shell(Verbosity::Quiet, ColorConfig::Never);
+        let config = Config::new(shell, td.path().to_path_buf(), td.path().join("home"));
+        let source_id = SourceId::for_registry(&"https://example.com/index".to_url().unwrap());
+        registry_token(&config, &source_id)
+    }
+
+    #[test]
+    fn credential_process_provides_auth_header() {
+        let token = token_from(r#""echo s3cr3t""#).unwrap().unwrap();
+        assert_eq!(auth_header(&token), "Authorization: s3cr3t");
+    }
+
+    #[test]
+    fn credential_process_as_a_list_keeps_its_arguments() {
+        let token = token_from(r#"["sh", "-c", "echo two words"]"#).unwrap().unwrap();
+        assert_eq!(token, "two words");
+    }
+
+    #[test]
+    fn failing_credential_process_errors() {
+        let err = token_from(r#""false""#).err().unwrap();
+        let msg = err.to_string();
+        assert!(msg.contains("credential process `false` for registry `private` failed"),
+                "unexpected error: {}",
+                msg);
+    }
+}
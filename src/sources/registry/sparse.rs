@@ -0,0 +1,145 @@
+//! A registry whose index is fetched file by file over HTTP
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use curl::easy::{Easy, List};
+
+use package_id::PackageId;
+use source::SourceId;
+use sources::registry::{RegistryData, RegistryConfig, INDEX_LOCK};
+use sources::registry::index::index_file_path;
+use sources::registry::remote::{auth_header, http_handle, RemoteRegistry};
+use util::{network, paths, FileLock, Filesystem, Config, CraftResult, ChainError, HttpError, human};
+
+/// Instead of keeping a git checkout of the whole index, the index files of
+/// only the chests which are actually queried are fetched, one GET request per
+/// chest. They are cached in the same layout as a git index, so the
+/// `RegistryIndex` reads them just the same.
+///
+/// Packages are downloaded exactly like for a git based registry, so that part
+/// is left to a `RemoteRegistry` sharing the index and cache directories.
+pub struct SparseRegistry<'cfg> {
+    remote: RemoteRegistry<'cfg>,
+    source_id: SourceId,
+    config: &'cfg Config,
+    handle: Option<Easy>,
+    updated: bool,
+    fetched: HashSet<String>,
+}
+
+impl<'cfg> SparseRegistry<'cfg> {
+    pub fn new(source_id: &SourceId, config: &'cfg Config, name: &str) -> SparseRegistry<'cfg> {
+        SparseRegistry {
+            remote: RemoteRegistry::new(source_id, config, name),
+            source_id: source_id.clone(),
+            config: config,
+            handle: None,
+            updated: false,
+            fetched: HashSet::new(),
+        }
+    }
+
+    /// Fetches the file at `path`, relative to the root of the index, into the
+    /// index directory. If the registry doesn't have the file, any cached copy
+    /// is removed.
+    fn fetch(&mut self, path: &str) -> CraftResult<()> {
+        let token = self.remote.token()?;
+        let handle = match self.handle {
+            Some(ref mut handle) => handle,
+            None => {
+                self.handle = Some(http_handle(self.config)?);
+                self.handle.as_mut().unwrap()
+            }
+        };
+        let url = format!("{}/{}", self.source_id.url().as_str().trim_right_matches('/'), path);
+        handle.get(true)?;
+        handle.url(&url)?;
+        handle.follow_location(true)?;
+        let mut headers = List::new();
+        if let Some(ref token) = token {
+            headers.append(&auth_header(token))?;
+        }
+        handle.http_headers(headers)?;
+        // curl traces the headers it sends, the token included
+        handle.verbose(self.config.extra_verbose() && token.is_none())?;
+
+        let body = RefCell::new(Vec::new());
+        let code = network::with_retry(self.config, || -> Result<u32, HttpError> {
+                body.borrow_mut().clear();
+                {
+                    let mut handle = handle.transfer();
+                    handle.write_function(|buf| {
+                            body.borrow_mut().extend_from_slice(buf);
+                            Ok(buf.len())
+                        })?;
+                    handle.perform()?;
+                }
+                let code = handle.response_code()?;
+                match code {
+                    0 | 200 | 404 | 410 => Ok(code),
+                    code => {
+                        Err(HttpError::Status {
+                            url: url.clone(),
+                            code: code,
+                        })
+                    }
+                }
+            })
+            .chain_error(|| human(format!("failed to fetch `{}`", url)))?;
+
+        let lock = self.remote.index_path().open_rw(Path::new(INDEX_LOCK), self.config, "the registry index")?;
+        let dst = lock.parent().join(path);
+        if code == 404 || code == 410 {
+            if dst.exists() {
+                fs::remove_file(&dst)?;
+            }
+            return Ok(());
+        }
+        fs::create_dir_all(dst.parent().unwrap())?;
+        paths::write(&dst, &body.into_inner())
+    }
+}
+
+impl<'cfg> RegistryData for SparseRegistry<'cfg> {
+    fn index_path(&self) -> &Filesystem {
+        self.remote.index_path()
+    }
+
+    fn config(&self) -> CraftResult<Option<RegistryConfig>> {
+        self.remote.config()
+    }
+
+    fn update_index(&mut self) -> CraftResult<()> {
+        self.remote.index_path().create_dir()?;
+        self.config.shell().status("Updating", format!("registry `{}`", self.source_id.url()))?;
+        self.fetch("config.json")?;
+
+        // Everything fetched so far may be out of date now
+        self.updated = true;
+        self.fetched.clear();
+        Ok(())
+    }
+
+    fn fetch_index_file(&mut self, name: &str) -> CraftResult<()> {
+        if self.fetched.contains(name) {
+            return Ok(());
+        }
+        let path = index_file_path(name);
+
+        // Without an update of the registry a cached index file is good
+        // enough, like a git index which isn't updated either.
+        let cached = self.remote.index_path().join(&path).into_path_unlocked();
+        if !self.updated && cached.exists() {
+            return Ok(());
+        }
+        self.fetch(&path)?;
+        self.fetched.insert(name.to_string());
+        Ok(())
+    }
+
+    fn download(&mut self, pkg: &PackageId, checksum: &str) -> CraftResult<FileLock> {
+        self.remote.download(pkg, checksum)
+    }
+}
@@ -1,6 +1,22 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use util::{self, CraftResult, internal, ChainError, ProcessBuilder};
+use filetime::FileTime;
+use rustc_serialize::json;
+use tempdir::TempDir;
+
+use util::{self, CraftResult, human, internal, ChainError, ProcessBuilder};
+
+#[cfg(target_os = "macos")]
+const INSTALL_HINT: &'static str = "install the Xcode Command Line Tools with `xcode-select --install`";
+#[cfg(windows)]
+const INSTALL_HINT: &'static str = "install the Visual Studio Build Tools (MSVC) and run craft from a developer \
+                                    command prompt";
+#[cfg(all(unix, not(target_os = "macos")))]
+const INSTALL_HINT: &'static str = "install a C compiler with your package manager, e.g. the `build-essential` \
+                                    package on Debian or Ubuntu";
 
 pub struct Cc {
     pub path: PathBuf,
@@ -17,7 +33,18 @@ impl Cc {
         let mut cmd = util::process(&path);
         cmd.arg("-v");
 
-        let output = cmd.exec_with_output()?;
+        let output = match cmd.exec_with_output() {
+            Ok(output) => output,
+            // The compiler couldn't even be spawned, which most likely means there is none
+            Err(ref e) if e.exit.is_none() => {
+                return Err(human(format!("could not find a C compiler, `{}` failed to run\n\n\
+                                          To fix this, {}. Another compiler can be chosen with the \
+                                          `CC` environment variable or the `build.cc` config value.",
+                                         path.display(),
+                                         INSTALL_HINT)))
+            }
+            Err(e) => return Err(e.into()),
+        };
 
         let verbose_version =
             String::from_utf8(output.stderr).map_err(|_| internal("cc -v didn't return utf8 output"))?;
@@ -37,7 +64,96 @@ impl Cc {
         })
     }
 
+    /// Like `new`, but reuses the result of an earlier probe of the same compiler stored in the
+    /// `cache` file, and records the result there otherwise.
+    ///
+    /// Entries are keyed by the resolved path, size and modification time of the compiler binary
+    /// along with the output of `cc --version`, so replacing or upgrading the compiler probes it
+    /// again, even if `cc` is only a wrapper around the real one. Failing to read or write the
+    /// cache is not an error, the compiler is simply probed every time then.
+    pub fn cached(path: PathBuf, cache: &Path) -> CraftResult<Cc> {
+        let key = match cache_key(&path) {
+            Some(key) => key,
+            None => return Cc::new(path),
+        };
+
+        let mut entries = util::paths::read(cache)
+            .ok()
+            .and_then(|s| json::decode::<HashMap<String, CacheEntry>>(&s).ok())
+            .unwrap_or_else(HashMap::new);
+        if let Some(entry) = entries.get(&key) {
+            return Ok(Cc {
+                path: path,
+                verbose_version: entry.verbose_version.clone(),
+                host: entry.host.clone(),
+            });
+        }
+
+        let cc = Cc::new(path)?;
+        entries.insert(key,
+                       CacheEntry {
+                           verbose_version: cc.verbose_version.clone(),
+                           host: cc.host.clone(),
+                       });
+        if let Ok(contents) = json::encode(&entries) {
+            let _ = write_cache(cache, &contents);
+        }
+        Ok(cc)
+    }
+
     pub fn process(&self) -> ProcessBuilder {
         util::process(&self.path)
     }
 }
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct CacheEntry {
+    verbose_version: String,
+    host: String,
+}
+
+/// Replaces the `cache` file with `contents`. Other processes may read it at the same time, so it's
+/// written next to it first and then renamed over it.
+fn write_cache(cache: &Path, contents: &str) -> CraftResult<()> {
+    let parent = cache.parent().chain_error(|| internal("the compiler cache has no parent directory"))?;
+    fs::create_dir_all(parent)?;
+    let tmp = TempDir::new_in(parent, "cc-info")?;
+    let partial = tmp.path().join("cc-info.json");
+    util::paths::write(&partial, contents.as_bytes())?;
+    fs::rename(&partial, cache)?;
+    Ok(())
+}
+
+/// Identifies the compiler `path` refers to, or `None` if it can't be found.
+fn cache_key(path: &Path) -> Option<String> {
+    let resolved = if path.components().count() > 1 {
+        Some(path.to_path_buf())
+    } else {
+        env::var_os("PATH").and_then(|paths| {
+            env::split_paths(&paths)
+                .map(|dir| dir.join(path))
+                .find(|candidate| candidate.is_file())
+        })
+    };
+    let resolved = match resolved {
+        Some(resolved) => resolved,
+        None => return None,
+    };
+    let meta = match fs::metadata(&resolved) {
+        Ok(meta) => meta,
+        Err(..) => return None,
+    };
+    // A wrapper like `ccache` stays the same when the compiler behind it is upgraded, which its
+    // version tells.
+    let version = match util::process(path).arg("--version").exec_with_output() {
+        Ok(output) => (output.stdout, output.stderr),
+        Err(..) => return None,
+    };
+    let mtime = FileTime::from_last_modification_time(&meta);
+    Some(util::short_hash(&(path,
+                            resolved,
+                            meta.len(),
+                            mtime.seconds_relative_to_1970(),
+                            mtime.nanoseconds(),
+                            version)))
+}
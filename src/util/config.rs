@@ -8,6 +8,7 @@ use std::io::SeekFrom;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 use std::{env, fmt, mem};
 
 use rustc_serialize::{Encodable, Encoder};
@@ -23,11 +24,13 @@ pub struct Config {
     home_path: Filesystem,
     shell: RefCell<MultiShell>,
     cc: LazyCell<Cc>,
+    cxx: LazyCell<PathBuf>,
     values: LazyCell<HashMap<String, ConfigValue>>,
     cwd: PathBuf,
     doc: LazyCell<PathBuf>,
     extra_verbose: Cell<bool>,
     frozen: Cell<bool>,
+    offline: Cell<bool>,
     locked: Cell<bool>,
 }
 
@@ -37,11 +40,13 @@ impl Config {
             home_path: Filesystem::new(homedir),
             shell: RefCell::new(shell),
             cc: LazyCell::new(),
+            cxx: LazyCell::new(),
             cwd: cwd,
             values: LazyCell::new(),
             doc: LazyCell::new(),
             extra_verbose: Cell::new(false),
             frozen: Cell::new(false),
+            offline: Cell::new(false),
             locked: Cell::new(false),
         }
     }
@@ -75,6 +80,24 @@ impl Config {
         self.home_path.join("registry").join("src")
     }
 
+    /// Returns the registry token stored in `$CRAFT_HOME/credentials` by
+    /// `craft login`, if any.
+    pub fn get_registry_token(&self) -> CraftResult<Option<String>> {
+        let path = self.home_path.join("credentials").into_path_unlocked();
+        if fs::metadata(&path).is_err() {
+            return Ok(None);
+        }
+        let mut file = self.home_path.open_ro(Path::new("credentials"), self, "the credentials file")?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let toml = craft_toml::parse(&contents, file.path(), self)?;
+        match toml.get("registry").and_then(|r| r.lookup("token")) {
+            Some(&toml::Value::String(ref token)) => Ok(Some(token.clone())),
+            Some(..) => bail!("expected a string for `registry.token` in `{}`", file.path().display()),
+            None => Ok(None),
+        }
+    }
+
     pub fn shell(&self) -> RefMut<MultiShell> {
         self.shell.borrow_mut()
     }
@@ -84,7 +107,14 @@ impl Config {
     }
 
     pub fn cc(&self) -> CraftResult<&Cc> {
-        self.cc.get_or_try_init(|| Cc::new(self.get_tool("cc")?))
+        self.cc.get_or_try_init(|| {
+            let cache = self.home_path.join(".cc-info.json").into_path_unlocked();
+            Cc::cached(self.get_tool("cc")?, &cache)
+        })
+    }
+
+    pub fn cxx(&self) -> CraftResult<&Path> {
+        self.cxx.get_or_try_init(|| self.get_tool_or("cxx", "c++")).map(AsRef::as_ref)
     }
 
     pub fn values(&self) -> CraftResult<&HashMap<String, ConfigValue>> {
@@ -166,7 +196,7 @@ impl Config {
         match self.get(key)? {
             Some(CV::String(i, path)) => {
                 Ok(Some(Value {
-                    val: i,
+                    val: expand(key, &i, &path)?,
                     definition: Definition::Path(path),
                 }))
             }
@@ -212,6 +242,9 @@ impl Config {
     pub fn get_list(&self, key: &str) -> CraftResult<Option<Value<Vec<(String, PathBuf)>>>> {
         match self.get(key)? {
             Some(CV::List(i, path)) => {
+                let i = i.into_iter()
+                    .map(|(s, p)| expand(key, &s, &p).map(|s| (s, p)))
+                    .collect::<CraftResult<_>>()?;
                 Ok(Some(Value {
                     val: i,
                     definition: Definition::Path(path),
@@ -267,6 +300,66 @@ impl Config {
         }
     }
 
+    /// Returns how long to wait before retrying a spurious network error for
+    /// the first time, the delay doubles with every further attempt.
+    ///
+    /// This is read from `net.retry-delay` (in milliseconds), defaulting to
+    /// half a second. A delay of 0 retries right away.
+    pub fn net_retry_delay(&self) -> CraftResult<Duration> {
+        match self.get_i64("net.retry-delay")? {
+            Some(v) => {
+                if v.val < 0 {
+                    bail!("net.retry-delay can't be negative, but found {} in {}",
+                          v.val,
+                          v.definition)
+                } else {
+                    Ok(Duration::from_millis(v.val as u64))
+                }
+            }
+            None => Ok(Duration::from_millis(500)),
+        }
+    }
+
+    /// Returns how long to wait for a contended file lock, where `None` means
+    /// blocking until the lock is released.
+    ///
+    /// This is read from `build.lock-timeout` (in seconds), where 0 fails
+    /// right away on a contended lock. With `--frozen` and no configured
+    /// timeout, contended locks fail right away as well.
+    pub fn lock_timeout(&self) -> CraftResult<Option<Duration>> {
+        match self.get_i64("build.lock-timeout")? {
+            Some(v) => {
+                if v.val < 0 {
+                    bail!("build.lock-timeout can't be negative, but found {} in {}",
+                          v.val,
+                          v.definition)
+                } else {
+                    Ok(Some(Duration::from_secs(v.val as u64)))
+                }
+            }
+            None if self.frozen.get() => Ok(Some(Duration::from_secs(0))),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns whether local packages are fingerprinted by the contents of
+    /// their files rather than by their modification times.
+    ///
+    /// This is read from `build.fingerprint`, either `mtime` (the default) or
+    /// `content`.
+    pub fn content_fingerprints(&self) -> CraftResult<bool> {
+        match self.get_string("build.fingerprint")? {
+            Some(ref v) if v.val == "content" => Ok(true),
+            Some(ref v) if v.val == "mtime" => Ok(false),
+            Some(v) => {
+                bail!("build.fingerprint must be `mtime` or `content`, but found `{}` in {}",
+                      v.val,
+                      v.definition)
+            }
+            None => Ok(false),
+        }
+    }
+
     pub fn expected<T>(&self, ty: &str, key: &str, val: CV) -> CraftResult<T> {
         val.expected(ty, key).map_err(|e| human(format!("invalid configuration for key `{}`\n{}", key, e)))
     }
@@ -276,7 +369,8 @@ impl Config {
                      quiet: Option<bool>,
                      color: &Option<String>,
                      frozen: bool,
-                     locked: bool)
+                     locked: bool,
+                     offline: bool)
                      -> CraftResult<()> {
         let extra_verbose = verbose >= 2;
         let verbose = if verbose == 0 { None } else { Some(true) };
@@ -315,6 +409,7 @@ impl Config {
         self.extra_verbose.set(extra_verbose);
         self.frozen.set(frozen);
         self.locked.set(locked);
+        self.offline.set(offline);
 
         Ok(())
     }
@@ -324,11 +419,33 @@ impl Config {
     }
 
     pub fn network_allowed(&self) -> bool {
-        !self.frozen.get()
+        self.network_disallowed_by().is_none()
+    }
+
+    /// Returns the command line flag forbidding network access, if any.
+    pub fn network_disallowed_by(&self) -> Option<&'static str> {
+        if self.frozen.get() {
+            Some("--frozen")
+        } else if self.offline.get() {
+            Some("--offline")
+        } else {
+            None
+        }
     }
 
     pub fn lock_update_allowed(&self) -> bool {
-        !self.frozen.get() && !self.locked.get()
+        self.lock_update_disallowed_by().is_none()
+    }
+
+    /// Returns the command line flag forbidding changes to the lockfile, if any.
+    pub fn lock_update_disallowed_by(&self) -> Option<&'static str> {
+        if self.frozen.get() {
+            Some("--frozen")
+        } else if self.locked.get() {
+            Some("--locked")
+        } else {
+            None
+        }
     }
 
     fn load_values(&self) -> CraftResult<HashMap<String, ConfigValue>> {
@@ -356,6 +473,10 @@ impl Config {
     }
 
     fn get_tool(&self, tool: &str) -> CraftResult<PathBuf> {
+        self.get_tool_or(tool, tool)
+    }
+
+    fn get_tool_or(&self, tool: &str, default: &str) -> CraftResult<PathBuf> {
         let var = tool.chars().flat_map(|c| c.to_uppercase()).collect::<String>();
         if let Some(tool_path) = env::var_os(&var) {
             return Ok(PathBuf::from(tool_path));
@@ -366,7 +487,7 @@ impl Config {
             return Ok(tool_path.val);
         }
 
-        Ok(PathBuf::from(tool))
+        Ok(PathBuf::from(default))
     }
 }
 
@@ -512,9 +633,9 @@ impl ConfigValue {
         }
     }
 
-    pub fn string(&self, key: &str) -> CraftResult<(&str, &Path)> {
+    pub fn string(&self, key: &str) -> CraftResult<(String, &Path)> {
         match *self {
-            CV::String(ref s, ref p) => Ok((s, p)),
+            CV::String(ref s, ref p) => Ok((expand(key, s, p)?, p)),
             _ => self.expected("string", key),
         }
     }
@@ -526,9 +647,9 @@ impl ConfigValue {
         }
     }
 
-    pub fn list(&self, key: &str) -> CraftResult<&[(String, PathBuf)]> {
+    pub fn list(&self, key: &str) -> CraftResult<Vec<(String, PathBuf)>> {
         match *self {
-            CV::List(ref list, _) => Ok(list),
+            CV::List(ref list, _) => list.iter().map(|&(ref s, ref p)| Ok((expand(key, s, p)?, p.clone()))).collect(),
             _ => self.expected("list", key),
         }
     }
@@ -605,6 +726,49 @@ impl fmt::Display for Definition {
     }
 }
 
+/// Expands `${VAR}` to the value of the environment variable `VAR` and a
+/// leading `~` to the home directory in the value of `key`, defined in the
+/// config file `path`. A literal `$` is written as `$$`.
+fn expand(key: &str, value: &str, path: &Path) -> CraftResult<String> {
+    let mut ret = String::new();
+    let mut rest = value;
+    if rest == "~" || rest.starts_with("~/") {
+        match env::home_dir() {
+            Some(home) => ret.push_str(&home.to_string_lossy()),
+            None => bail!("can't expand `~` in `{}` (in {}), the home directory is unknown", key, path.display()),
+        }
+        rest = &rest[1..];
+    }
+    while let Some(pos) = rest.find('$') {
+        ret.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if rest.starts_with('$') {
+            ret.push('$');
+            rest = &rest[1..];
+        } else if rest.starts_with('{') {
+            let end = match rest.find('}') {
+                Some(end) => end,
+                None => bail!("unterminated `${{` in `{}` (in {}), write `$$` for a literal `$`", key, path.display()),
+            };
+            let name = &rest[1..end];
+            match env::var(name) {
+                Ok(val) => ret.push_str(&val),
+                Err(..) => {
+                    bail!("environment variable `{}` used in `{}` (in {}) is not set",
+                          name,
+                          key,
+                          path.display())
+                }
+            }
+            rest = &rest[end + 1..];
+        } else {
+            ret.push('$');
+        }
+    }
+    ret.push_str(rest);
+    Ok(ret)
+}
+
 fn homedir(cwd: &Path) -> Option<PathBuf> {
     let craft_home = env::var_os("CRAFT_HOME").map(|home| cwd.join(home));
     if craft_home.is_some() {
@@ -350,6 +350,58 @@ impl From<Box<CraftError>> for CliError {
     }
 }
 
+// =============================================================================
+// HTTP errors
+
+/// A failed HTTP request, either the transfer itself or the server responding
+/// with something other than a success.
+#[derive(Debug)]
+pub enum HttpError {
+    Transfer(curl::Error),
+    Status { url: String, code: u32 },
+}
+
+impl Error for HttpError {
+    fn description(&self) -> &str {
+        match *self {
+            HttpError::Transfer(ref e) => e.description(),
+            HttpError::Status { .. } => "unsuccessful HTTP response",
+        }
+    }
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            HttpError::Transfer(ref e) => Some(e),
+            HttpError::Status { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HttpError::Transfer(ref e) => fmt::Display::fmt(e, f),
+            HttpError::Status { ref url, code } => {
+                write!(f, "failed to get 200 response from `{}`, got {}", url, code)
+            }
+        }
+    }
+}
+
+impl CraftError for HttpError {
+    fn is_human(&self) -> bool {
+        match *self {
+            HttpError::Transfer(..) => false,
+            HttpError::Status { .. } => true,
+        }
+    }
+}
+
+impl From<curl::Error> for HttpError {
+    fn from(err: curl::Error) -> HttpError {
+        HttpError::Transfer(err)
+    }
+}
+
 // =============================================================================
 // NetworkError trait
 
@@ -357,6 +409,16 @@ pub trait NetworkError: CraftError {
     fn maybe_spurious(&self) -> bool;
 }
 
+impl NetworkError for HttpError {
+    fn maybe_spurious(&self) -> bool {
+        match *self {
+            HttpError::Transfer(ref e) => e.maybe_spurious(),
+            // Overloaded or restarting servers
+            HttpError::Status { code, .. } => code == 429 || (code >= 500 && code < 600),
+        }
+    }
+}
+
 impl NetworkError for git2::Error {
     fn maybe_spurious(&self) -> bool {
         match self.class() {
@@ -2,6 +2,8 @@ use std::fs::{self, File, OpenOptions};
 use std::io::*;
 use std::io;
 use std::path::{Path, PathBuf, Display};
+use std::time::{Duration, Instant};
+use std::{cmp, thread};
 
 use term::color::CYAN;
 use fs2::{FileExt, lock_contended_error};
@@ -165,7 +167,24 @@ impl Filesystem {
                   OpenOptions::new().read(true).write(true).create(true),
                   State::Exclusive,
                   config,
-                  msg)
+                  msg,
+                  None)
+    }
+
+    /// Like `open_rw`, but gives up waiting for the lock after `timeout`.
+    ///
+    /// If the lock is still held by someone else once the timeout elapsed, an
+    /// error stating that `msg` is locked by another process is returned. A
+    /// zero timeout fails right away on a contended lock.
+    pub fn open_rw_timeout<P>(&self, path: P, config: &Config, msg: &str, timeout: Duration) -> CraftResult<FileLock>
+        where P: AsRef<Path>
+    {
+        self.open(path.as_ref(),
+                  OpenOptions::new().read(true).write(true).create(true),
+                  State::Exclusive,
+                  config,
+                  msg,
+                  Some(timeout))
     }
 
     /// Opens shared access to a file, returning the locked version of a file.
@@ -184,10 +203,18 @@ impl Filesystem {
                   OpenOptions::new().read(true),
                   State::Shared,
                   config,
-                  msg)
+                  msg,
+                  None)
     }
 
-    fn open(&self, path: &Path, opts: &OpenOptions, state: State, config: &Config, msg: &str) -> CraftResult<FileLock> {
+    fn open(&self,
+            path: &Path,
+            opts: &OpenOptions,
+            state: State,
+            config: &Config,
+            msg: &str,
+            timeout: Option<Duration>)
+            -> CraftResult<FileLock> {
         let path = self.root.join(path);
 
         // If we want an exclusive lock then if we fail because of NotFound it's
@@ -208,6 +235,7 @@ impl Filesystem {
                 acquire(config,
                         msg,
                         &path,
+                        timeout,
                         &|| f.try_lock_exclusive(),
                         &|| f.lock_exclusive())?;
             }
@@ -215,6 +243,7 @@ impl Filesystem {
                 acquire(config,
                         msg,
                         &path,
+                        timeout,
                         &|| f.try_lock_shared(),
                         &|| f.lock_shared())?;
             }
@@ -242,11 +271,15 @@ impl Filesystem {
 /// status message based on `msg` to `config`'s shell, and then use `block` to
 /// block waiting to acquire a lock.
 ///
+/// If a `timeout` is given, the lock is polled with `try` instead of blocking
+/// and an error is returned once the timeout elapsed without acquiring it.
+///
 /// Returns an error if the lock could not be acquired or if any error other
 /// than a contention error happens.
 fn acquire(config: &Config,
            msg: &str,
            path: &Path,
+           timeout: Option<Duration>,
            try: &Fn() -> io::Result<()>,
            block: &Fn() -> io::Result<()>)
            -> CraftResult<()> {
@@ -265,26 +298,53 @@ fn acquire(config: &Config,
         return Ok(());
     }
 
-    match try() {
-        Ok(()) => return Ok(()),
-
-        // Like above, where we ignore file locking on NFS mounts on Linux, we
-        // do the same on OSX here. Note that ENOTSUP is an OSX_specific
-        // constant.
-        #[cfg(target_os = "macos")]
-        Err(ref e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+    if try_acquire(path, try)? {
+        return Ok(());
+    }
 
-        Err(e) => {
-            if e.raw_os_error() != lock_contended_error().raw_os_error() {
-                return Err(human(e)).chain_error(|| human(format!("failed to lock file: {}", path.display())));
+    if let Some(timeout) = timeout {
+        if timeout > Duration::from_secs(0) {
+            let msg = format!("waiting up to {}s for file lock on {}", timeout.as_secs(), msg);
+            config.shell().err().say_status("BLOCK", &msg, CYAN)?;
+        }
+        let start = Instant::now();
+        loop {
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                bail!("{} is locked by another process: {}", msg, path.display())
+            }
+            thread::sleep(cmp::min(timeout - elapsed, Duration::from_millis(100)));
+            if try_acquire(path, try)? {
+                return Ok(());
             }
         }
     }
+
     let msg = format!("waiting for file lock on {}", msg);
     config.shell().err().say_status("BLOCK", &msg, CYAN)?;
 
     return block().chain_error(|| human(format!("failed to lock file: {}", path.display())));
 
+    // Returns whether the lock was acquired, or `false` if it is contended.
+    fn try_acquire(path: &Path, try: &Fn() -> io::Result<()>) -> CraftResult<bool> {
+        match try() {
+            Ok(()) => Ok(true),
+
+            // Like above, where we ignore file locking on NFS mounts on Linux, we
+            // do the same on OSX here. Note that ENOTSUP is an OSX_specific
+            // constant.
+            #[cfg(target_os = "macos")]
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOTSUP) => Ok(true),
+
+            Err(e) => {
+                if e.raw_os_error() != lock_contended_error().raw_os_error() {
+                    return Err(human(e)).chain_error(|| human(format!("failed to lock file: {}", path.display())));
+                }
+                Ok(false)
+            }
+        }
+    }
+
     #[cfg(all(target_os = "linux", not(target_env = "musl")))]
     fn is_on_nfs_mount(path: &Path) -> bool {
         use std::ffi::CString;
@@ -331,3 +391,30 @@ fn create_dir(path: &Path) -> io::Result<()> {
         Err(e) => Err(e),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tempdir::TempDir;
+
+    use shell::{Verbosity, ColorConfig};
+    use util::Config;
+    use super::Filesystem;
+
+    #[test]
+    fn open_rw_timeout_fails_on_contended_lock() {
+        let td = TempDir::new("craft-flock").unwrap();
+        let shell = ::shell(Verbosity::Quiet, ColorConfig::Never);
+        let config = Config::new(shell, td.path().to_path_buf(), td.path().join("home"));
+        let root = Filesystem::new(td.path().to_path_buf());
+
+        let _held = root.open_rw(".craft-lock", &config, "build directory").unwrap();
+        let err = root.open_rw_timeout(".craft-lock", &config, "build directory", Duration::from_millis(200))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("build directory is locked by another process"),
+                "unexpected error: {}",
+                err);
+    }
+}
@@ -0,0 +1,163 @@
+//! Integration with the GNU make jobserver
+//!
+//! When craft is invoked from a recursive `make -j` build, make advertises a
+//! pool of job tokens through the `MAKEFLAGS` environment variable. Every
+//! process in the build owns one implicit token and has to read another one
+//! from the jobserver for each additional job it wants to run in parallel,
+//! writing it back once that job is done. Sharing the pool this way keeps the
+//! whole build from oversubscribing the machine.
+
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+/// A connection to the jobserver of a parent `make` process.
+pub struct Client {
+    read: File,
+    write: File,
+}
+
+/// A token acquired from the jobserver, which is handed back once dropped.
+pub struct Token {
+    client: Arc<Client>,
+    byte: u8,
+}
+
+impl Client {
+    /// Connects to the jobserver advertised in `MAKEFLAGS`, if any.
+    ///
+    /// Returns `None` if no jobserver is advertised or if its file
+    /// descriptors were not passed down to this process, which `make` does
+    /// for commands it doesn't recognize as recursive invocations.
+    pub fn from_env() -> Option<Client> {
+        let flags = match env::var("MAKEFLAGS") {
+            Ok(flags) => flags,
+            Err(..) => return None,
+        };
+        let auth = match jobserver_auth(&flags) {
+            Some(auth) => auth,
+            None => return None,
+        };
+        if auth.starts_with("fifo:") {
+            let path = &auth[5..];
+            let open = || OpenOptions::new().read(true).write(true).open(path);
+            return match (open(), open()) {
+                (Ok(read), Ok(write)) => {
+                    Some(Client {
+                        read: read,
+                        write: write,
+                    })
+                }
+                _ => None,
+            };
+        }
+        let mut fds = auth.splitn(2, ',').map(|fd| fd.parse().ok());
+        match (fds.next(), fds.next()) {
+            (Some(Some(read)), Some(Some(write))) => imp::from_fds(read, write),
+            _ => None,
+        }
+    }
+
+    /// Blocks until a token is available from the jobserver.
+    pub fn acquire(client: &Arc<Client>) -> io::Result<Token> {
+        let mut byte = [0];
+        loop {
+            match (&client.read).read(&mut byte) {
+                Ok(1) => {
+                    return Ok(Token {
+                        client: client.clone(),
+                        byte: byte[0],
+                    })
+                }
+                Ok(_) => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                              "the jobserver was closed"))
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        let _ = (&self.client.write).write_all(&[self.byte]);
+    }
+}
+
+/// Extracts the jobserver from `MAKEFLAGS`, either `--jobserver-auth=R,W`
+/// or the older `--jobserver-fds=R,W` spelling. The last one wins, as make
+/// appends the flags of nested invocations.
+fn jobserver_auth(flags: &str) -> Option<&str> {
+    flags.split_whitespace()
+        .filter_map(|flag| {
+            if flag.starts_with("--jobserver-auth=") {
+                Some(&flag[17..])
+            } else if flag.starts_with("--jobserver-fds=") {
+                Some(&flag[16..])
+            } else {
+                None
+            }
+        })
+        .last()
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::fs::File;
+    use std::os::unix::prelude::*;
+
+    use libc;
+
+    use super::Client;
+
+    // The descriptors stay open for the children which are told about them
+    // in `MAKEFLAGS`, and they may even be the same one, so the client owns
+    // duplicates of them, which aren't inherited.
+    pub fn from_fds(read: RawFd, write: RawFd) -> Option<Client> {
+        unsafe {
+            let read = libc::fcntl(read, libc::F_DUPFD_CLOEXEC, 0);
+            let write = libc::fcntl(write, libc::F_DUPFD_CLOEXEC, 0);
+            // Descriptors which aren't open can't be duplicated
+            match (read, write) {
+                (-1, -1) => None,
+                (-1, fd) | (fd, -1) => {
+                    libc::close(fd);
+                    None
+                }
+                (read, write) => {
+                    Some(Client {
+                        read: File::from_raw_fd(read),
+                        write: File::from_raw_fd(write),
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::Client;
+
+    // Make uses named semaphores on Windows which aren't supported yet.
+    pub fn from_fds(_read: i32, _write: i32) -> Option<Client> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::jobserver_auth;
+
+    #[test]
+    fn parses_jobserver_flags() {
+        assert_eq!(jobserver_auth(" -j --jobserver-fds=3,4"), Some("3,4"));
+        assert_eq!(jobserver_auth("w -- --jobserver-auth=5,6"), Some("5,6"));
+        assert_eq!(jobserver_auth("-j --jobserver-auth=fifo:/tmp/gmfifo"), Some("fifo:/tmp/gmfifo"));
+        assert_eq!(jobserver_auth("--jobserver-fds=3,4 --jobserver-auth=7,8"), Some("7,8"));
+        assert_eq!(jobserver_auth("-k"), None);
+    }
+}
@@ -1,8 +1,13 @@
 //! Compiler message parsing and emission
+use std::collections::BTreeMap;
+use std::path::Path;
+
 use rustc_serialize::json;
 
 use manifest::Target;
+use ops::BuildOutput;
 use package_id::PackageId;
+use util::ProcessBuilder;
 
 #[derive(RustcEncodable)]
 pub struct FromCompiler<'a> {
@@ -27,3 +32,126 @@ impl<'a> FromCompiler<'a> {
         println!("{}", json);
     }
 }
+
+#[derive(RustcEncodable)]
+pub struct BuildScript<'a> {
+    reason: &'static str,
+    package_id: &'a PackageId,
+    linked_libs: Vec<String>,
+    linked_paths: Vec<String>,
+    cfgs: &'a [String],
+    env: &'a [(String, String)],
+}
+
+impl<'a> BuildScript<'a> {
+    pub fn new(package_id: &'a PackageId, output: &'a BuildOutput) -> BuildScript<'a> {
+        BuildScript {
+            reason: "build-script-executed",
+            package_id: package_id,
+            linked_libs: output.library_links.iter().map(|l| l.to_string()).collect(),
+            linked_paths: output.library_paths.iter().map(|p| p.display().to_string()).collect(),
+            cfgs: &output.cfgs,
+            env: &output.env,
+        }
+    }
+
+    pub fn emit(self) {
+        let json = json::encode(&self).unwrap();
+        println!("{}", json);
+    }
+}
+
+/// A command the build runs, as planned by `craft build --build-plan`.
+#[derive(RustcEncodable)]
+pub struct Invocation {
+    package_id: PackageId,
+    target: Target,
+    program: String,
+    args: Vec<String>,
+    /// Variables set for the command, `None` for the removed ones
+    env: BTreeMap<String, Option<String>>,
+    cwd: Option<String>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    /// Whether the command is completed with the output of build scripts,
+    /// which is only known once they ran
+    unknown_until_run: bool,
+}
+
+impl Invocation {
+    pub fn new(package_id: &PackageId, target: &Target, cmd: &ProcessBuilder) -> Invocation {
+        Invocation {
+            package_id: package_id.clone(),
+            target: target.clone(),
+            program: cmd.get_program().to_string_lossy().into_owned(),
+            args: cmd.get_args().iter().map(|a| a.to_string_lossy().into_owned()).collect(),
+            env: cmd.get_envs()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.as_ref().map(|v| v.to_string_lossy().into_owned())))
+                .collect(),
+            cwd: cmd.get_cwd().map(|p| p.display().to_string()),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            unknown_until_run: false,
+        }
+    }
+
+    pub fn input<P: AsRef<Path>>(&mut self, path: P) -> &mut Invocation {
+        self.inputs.push(path.as_ref().display().to_string());
+        self
+    }
+
+    pub fn output<P: AsRef<Path>>(&mut self, path: P) -> &mut Invocation {
+        self.outputs.push(path.as_ref().display().to_string());
+        self
+    }
+
+    pub fn unknown_until_run(&mut self, unknown: bool) -> &mut Invocation {
+        self.unknown_until_run = unknown;
+        self
+    }
+}
+
+/// All commands a build would run, in the order they are planned, emitted
+/// instead of building by `craft build --build-plan`.
+#[derive(RustcEncodable)]
+pub struct BuildPlan<'a> {
+    reason: &'static str,
+    invocations: &'a [Invocation],
+}
+
+impl<'a> BuildPlan<'a> {
+    pub fn new(invocations: &'a [Invocation]) -> BuildPlan<'a> {
+        BuildPlan {
+            reason: "build-plan",
+            invocations: invocations,
+        }
+    }
+
+    pub fn emit(self) {
+        let json = json::encode(&self).unwrap();
+        println!("{}", json);
+    }
+}
+
+/// The last message of a build, emitted once all units were compiled or the
+/// build failed.
+#[derive(RustcEncodable)]
+pub struct BuildFinished {
+    reason: &'static str,
+    success: bool,
+}
+
+impl BuildFinished {
+    pub fn new(success: bool) -> BuildFinished {
+        BuildFinished {
+            reason: "build-finished",
+            success: success,
+        }
+    }
+
+    pub fn emit(self) {
+        let json = json::encode(&self).unwrap();
+        println!("{}", json);
+    }
+}
@@ -3,7 +3,7 @@ pub use self::cfg::{Cfg, CfgExpr};
 pub use self::config::Config;
 pub use self::dependency_queue::{DependencyQueue, Fresh, Dirty, Freshness};
 pub use self::errors::{CliError, ProcessError, CraftTestError, CraftResult, CraftError, ChainError, CliResult, Human,
-                       caused_human, process_error, internal_error, internal, human};
+                       HttpError, caused_human, process_error, internal_error, internal, human};
 pub use self::flock::{FileLock, Filesystem};
 pub use self::graph::Graph;
 pub use self::hex::{to_hex, short_hash, hash_u64};
@@ -12,6 +12,7 @@ pub use self::lev_distance::lev_distance;
 pub use self::paths::{join_paths, path2bytes, bytes2path, dylib_path, normalize_path, dylib_path_envvar,
                       without_prefix};
 pub use self::process_builder::{process, ProcessBuilder};
+pub use self::progress::Progress;
 pub use self::read2::read2;
 pub use self::cc::Cc;
 pub use self::sha256::Sha256;
@@ -25,6 +26,7 @@ pub mod graph;
 pub mod hex;
 pub mod important_paths;
 pub mod job;
+pub mod jobserver;
 pub mod lev_distance;
 pub mod machine_message;
 pub mod network;
@@ -38,6 +40,7 @@ mod cfg;
 mod dependency_queue;
 mod flock;
 mod lazy_cell;
+mod progress;
 mod read2;
 mod cc;
 mod sha256;
@@ -1,10 +1,16 @@
 //! Networking utilities
-use util::{CraftResult, Config, errors};
+use std::cmp;
+use std::thread;
+use std::time::Duration;
+
+use util::{CraftResult, Config, ChainError, errors, human};
 
 /// Wrapper method for network call retry logic.
 ///
 /// Retry counts provided by Config object 'net.retry'. Config shell outputs
-/// a warning on per retry.
+/// a warning on per retry. The attempts are spaced out exponentially, starting
+/// at 'net.retry-delay' and growing up to a minute at most. Only errors which
+/// may be spurious are retried.
 ///
 /// Closure must return a CraftResult.
 ///
@@ -18,6 +24,8 @@ pub fn with_retry<T, E, F>(config: &Config, mut callback: F) -> CraftResult<T>
           E: errors::NetworkError
 {
     let mut remaining = config.net_retry()?;
+    let mut delay = config.net_retry_delay()?;
+    let mut attempts = 1;
     loop {
         match callback() {
             Ok(ret) => return Ok(ret),
@@ -26,9 +34,17 @@ pub fn with_retry<T, E, F>(config: &Config, mut callback: F) -> CraftResult<T>
                                   remaining,
                                   e);
                 config.shell().warn(msg)?;
+                thread::sleep(delay);
+                delay = cmp::max(delay, cmp::min(delay * 2, Duration::from_secs(60)));
                 remaining -= 1;
+                attempts += 1;
+            }
+            Err(e) => {
+                if attempts == 1 {
+                    return Err(Box::new(e));
+                }
+                return Err(e).chain_error(|| human(format!("giving up after {} attempts", attempts)));
             }
-            Err(e) => return Err(Box::new(e)),
         }
     }
 }
@@ -85,3 +101,41 @@ fn with_retry_repeats_the_call_then_works() {
     let result = with_retry(&config, || results.pop().unwrap());
     assert_eq!(result.unwrap(), ())
 }
+
+#[test]
+fn with_retry_gives_up_on_errors_which_are_not_spurious() {
+    use std::error::Error;
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct FatalError;
+
+    impl Error for FatalError {
+        fn description(&self) -> &str {
+            "fatal"
+        }
+    }
+
+    impl fmt::Display for FatalError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            "fatal".fmt(f)
+        }
+    }
+
+    impl errors::CraftError for FatalError {}
+
+    impl errors::NetworkError for FatalError {
+        fn maybe_spurious(&self) -> bool {
+            false
+        }
+    }
+
+    let mut calls = 0;
+    let config = Config::default().unwrap();
+    let result: CraftResult<()> = with_retry(&config, || {
+        calls += 1;
+        Err(FatalError)
+    });
+    assert_eq!(result.unwrap_err().to_string(), "fatal");
+    assert_eq!(calls, 1);
+}
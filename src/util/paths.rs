@@ -81,6 +81,23 @@ pub fn without_prefix<'a>(a: &'a Path, b: &'a Path) -> Option<&'a Path> {
     }
 }
 
+/// Returns `path` relative to `base`, stepping out of `base` with `..` where
+/// necessary. Both paths are expected to be absolute.
+pub fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let mut path = path.components().peekable();
+    let mut base = base.components().peekable();
+    while path.peek().is_some() && path.peek() == base.peek() {
+        path.next();
+        base.next();
+    }
+    let mut ret = PathBuf::new();
+    for _ in base {
+        ret.push("..");
+    }
+    ret.extend(path);
+    ret
+}
+
 pub fn read(path: &Path) -> CraftResult<String> {
     (|| -> CraftResult<_> {
             let mut ret = String::new();
@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use std::env;
 use std::ffi::{OsString, OsStr};
 use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
 use std::path::Path;
 use std::process::{Command, Stdio, Output};
 
@@ -55,6 +57,10 @@ impl ProcessBuilder {
         self
     }
 
+    pub fn get_program(&self) -> &OsString {
+        &self.program
+    }
+
     pub fn get_args(&self) -> &[OsString] {
         &self.args
     }
@@ -76,6 +82,7 @@ impl ProcessBuilder {
     }
 
     pub fn exec(&self) -> Result<(), ProcessError> {
+        self.dump_command();
         let mut command = self.build_command();
         let exit = command.status()
             .map_err(|e| {
@@ -114,6 +121,7 @@ impl ProcessBuilder {
     }
 
     pub fn exec_with_output(&self) -> Result<Output, ProcessError> {
+        self.dump_command();
         let mut command = self.build_command();
 
         let output = command.output()
@@ -135,10 +143,15 @@ impl ProcessBuilder {
         }
     }
 
+    /// Runs the process, passing each line it prints to the callbacks. Unless
+    /// `capture_output` is set, the output isn't kept around for the returned
+    /// `Output` or the error, as the callbacks already took care of it.
     pub fn exec_with_streaming(&self,
                                on_stdout_line: &mut FnMut(&str) -> CraftResult<()>,
-                               on_stderr_line: &mut FnMut(&str) -> CraftResult<()>)
+                               on_stderr_line: &mut FnMut(&str) -> CraftResult<()>,
+                               capture_output: bool)
                                -> Result<Output, ProcessError> {
+        self.dump_command();
         let mut stdout = Vec::new();
         let mut stderr = Vec::new();
 
@@ -180,6 +193,9 @@ impl ProcessBuilder {
                             callback_error = Some(e);
                         }
                     }
+                    if !capture_output {
+                        dst.truncate(start);
+                    }
                 })?;
                 child.wait()
             })().map_err(|e| {
@@ -230,6 +246,47 @@ impl ProcessBuilder {
         command
     }
 
+    /// Appends the command line to the file named by `CRAFT_DUMP_COMMANDS`,
+    /// if set, so it can be rerun from a shell outside of craft.
+    ///
+    /// Environment variables set or removed for this process and its working
+    /// directory are part of the line. Failing to write the dump is ignored.
+    fn dump_command(&self) {
+        let path = match env::var_os("CRAFT_DUMP_COMMANDS") {
+            Some(ref path) if !path.is_empty() => path.clone(),
+            _ => return,
+        };
+        let mut line = String::new();
+        if let Some(cwd) = self.get_cwd() {
+            line.push_str(&format!("cd {} && ", escape(cwd.to_string_lossy())));
+        }
+        if !self.env.is_empty() {
+            let mut env = self.env.iter().collect::<Vec<_>>();
+            env.sort_by(|a, b| a.0.cmp(b.0));
+            line.push_str("env");
+            // `env` only takes options before the first assignment
+            for &(k, _) in env.iter().filter(|&&(_, v)| v.is_none()) {
+                line.push_str(&format!(" -u {}", escape(k.as_str().into())));
+            }
+            for &(k, v) in env.iter() {
+                if let Some(ref v) = *v {
+                    line.push_str(&format!(" {}", escape(format!("{}={}", k, v.to_string_lossy()).into())));
+                }
+            }
+            line.push(' ');
+        }
+        line.push_str(&escape(self.program.to_string_lossy()));
+        for arg in self.args.iter() {
+            line.push(' ');
+            line.push_str(&escape(arg.to_string_lossy()));
+        }
+        line.push('\n');
+
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+
     fn debug_string(&self) -> String {
         let mut program = format!("{}", self.program.to_string_lossy());
         for arg in self.args.iter() {
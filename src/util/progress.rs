@@ -0,0 +1,126 @@
+//! A progress bar for long running operations
+use std::time::{Duration, Instant};
+
+use shell::Verbosity;
+use util::{Config, CraftResult};
+
+/// The number of characters the bar itself is wide.
+const WIDTH: u64 = 40;
+
+/// Shows how far along an operation like compiling or downloading is, in a
+/// single line which is redrawn below the other output of the shell.
+///
+/// Nothing is shown if stderr isn't a terminal or `--quiet` was passed. The
+/// bar is removed again once the `Progress` is dropped.
+pub struct Progress<'cfg> {
+    state: Option<State<'cfg>>,
+}
+
+struct State<'cfg> {
+    config: &'cfg Config,
+    name: String,
+    last_update: Option<Instant>,
+}
+
+impl<'cfg> Progress<'cfg> {
+    pub fn new(name: &str, config: &'cfg Config) -> Progress<'cfg> {
+        let enabled = {
+            let mut shell = config.shell();
+            shell.get_verbose() != Verbosity::Quiet && shell.err().is_tty()
+        };
+        Progress {
+            state: if enabled {
+                Some(State {
+                    config: config,
+                    name: name.to_string(),
+                    last_update: None,
+                })
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Updates the bar to `cur` out of `max` steps done.
+    pub fn tick(&mut self, cur: usize, max: usize) -> CraftResult<()> {
+        self.draw(cur as u64, max as u64, format!("{}/{}", cur, max))
+    }
+
+    /// Updates the bar to `cur` out of `max` bytes transferred.
+    pub fn tick_bytes(&mut self, cur: u64, max: u64) -> CraftResult<()> {
+        self.draw(cur, max, format!("{}/{}", human_bytes(cur), human_bytes(max)))
+    }
+
+    /// Removes the bar from the shell.
+    pub fn clear(&mut self) -> CraftResult<()> {
+        match self.state {
+            Some(ref state) => state.config.shell().err().set_progress(None),
+            None => Ok(()),
+        }
+    }
+
+    fn draw(&mut self, cur: u64, max: u64, counts: String) -> CraftResult<()> {
+        let state = match self.state {
+            Some(ref mut state) => state,
+            None => return Ok(()),
+        };
+
+        // Redrawing on every tick makes fast operations flicker and slows them
+        // down, but the final state is always shown.
+        let now = Instant::now();
+        if let Some(last) = state.last_update {
+            if cur < max && now.duration_since(last) < Duration::from_millis(100) {
+                return Ok(());
+            }
+        }
+        state.last_update = Some(now);
+
+        let filled = if max == 0 { 0 } else { cur.min(max) * WIDTH / max };
+        let mut bar = String::new();
+        for i in 0..WIDTH {
+            bar.push(if i < filled {
+                '='
+            } else if i == filled {
+                '>'
+            } else {
+                ' '
+            });
+        }
+        state.config.shell().err().set_progress(Some(format!("[{}] [{}] {}", state.name, bar, counts)))
+    }
+}
+
+impl<'cfg> Drop for Progress<'cfg> {
+    fn drop(&mut self) {
+        let _ = self.clear();
+    }
+}
+
+/// Formats a number of bytes with a binary unit, like `1.5 MiB`.
+fn human_bytes(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::human_bytes;
+
+    #[test]
+    fn formats_bytes_with_binary_units() {
+        assert_eq!(human_bytes(0), "0 B");
+        assert_eq!(human_bytes(1023), "1023 B");
+        assert_eq!(human_bytes(1536), "1.5 KiB");
+        assert_eq!(human_bytes(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
@@ -7,11 +7,13 @@ use std::path::{Path, PathBuf};
 use std::str;
 
 use toml;
+use url::Url;
 use semver::{self, VersionReq};
 use rustc_serialize::{Decodable, Decoder};
 
-use dependency::{Dependency, DependencyInner, Kind, Platform};
-use manifest::{EitherManifest, VirtualManifest, LibKind, Profile, ManifestMetadata, Manifest, Profiles, Target};
+use dependency::{Dependency, DependencyInner, Kind, LinkKind, Platform, SystemDependency};
+use manifest::{EitherManifest, VirtualManifest, Language, LibKind, Profile, ManifestMetadata, Manifest, Profiles,
+               Target};
 use package_id::{PackageId, Metadata};
 use package_id_spec::PackageIdSpec;
 use source::{GitReference, SourceId};
@@ -84,11 +86,11 @@ fn try_add_files(files: &mut Vec<PathBuf>, root: PathBuf) {
     match fs::read_dir(&root) {
         Ok(new) => {
             files.extend(new.filter_map(|dir| dir.map(|d| d.path()).ok())
-                .filter(|f| f.extension().and_then(|s| s.to_str()) == Some("rs"))
+                .filter(|f| f.extension().and_then(|s| s.to_str()) == Some("c"))
                 .filter(|f| {
                     // Some unix editors may create "dotfiles" next to original
                     // source files while they're being edited, but these files are
-                    // rarely actually valid Rust source files and sometimes aren't
+                    // rarely actually valid C source files and sometimes aren't
                     // even valid UTF-8. Here we just ignore all of them and require
                     // that they are explicitly specified in Craft.toml if desired.
                     f.file_name()
@@ -113,14 +115,14 @@ pub fn to_manifest(contents: &str,
         Some(path) => path.to_path_buf(),
         None => manifest.clone(),
     };
-    let root = parse(contents, &manifest, config)?;
-    let mut d = toml::Decoder::new(toml::Value::Table(root));
+    let root = toml::Value::Table(parse(contents, &manifest, config)?);
+    let mut d = toml::Decoder::new(root.clone());
     let manifest: TomlManifest = Decodable::decode(&mut d).map_err(|e| human(e.to_string()))?;
 
     return match manifest.to_real_manifest(source_id, &layout, config) {
         Ok((mut manifest, paths)) => {
             if let Some(ref toml) = d.toml {
-                add_unused_keys(&mut manifest, toml, String::new());
+                add_unused_keys(&mut manifest, toml, Some(&root), String::new());
             }
             if !manifest.targets().iter().any(|t| !t.is_custom_build()) {
                 bail!("no targets specified in the manifest\n  \
@@ -137,15 +139,25 @@ pub fn to_manifest(contents: &str,
         }
     };
 
-    fn add_unused_keys(m: &mut Manifest, toml: &toml::Value, key: String) {
+    // `toml` is what's left over after decoding, `original` the same value as
+    // written in the manifest. A table left over untouched is reported as a
+    // whole, so a misspelled table like `[dependancies]` results in a single
+    // warning naming it instead of one per entry (or none if it's empty).
+    fn add_unused_keys(m: &mut Manifest, toml: &toml::Value, original: Option<&toml::Value>, key: String) {
         if key == "package.metadata" {
             return;
         }
         match *toml {
             toml::Value::Table(ref table) => {
+                if !key.is_empty() && original == Some(toml) {
+                    m.add_warning(format!("unused manifest key: {}", key));
+                    return;
+                }
+                let original = original.and_then(|v| v.as_table());
                 for (k, v) in table.iter() {
                     add_unused_keys(m,
                                     v,
+                                    original.and_then(|t| t.get(k)),
                                     if key.is_empty() {
                                         k.clone()
                                     } else {
@@ -155,7 +167,7 @@ pub fn to_manifest(contents: &str,
             }
             toml::Value::Array(ref arr) => {
                 for v in arr.iter() {
-                    add_unused_keys(m, v, key.clone());
+                    add_unused_keys(m, v, None, key.clone());
                 }
             }
             _ => m.add_warning(format!("unused manifest key: {}", key)),
@@ -224,9 +236,13 @@ pub struct DetailedTomlDependency {
     branch: Option<String>,
     tag: Option<String>,
     rev: Option<String>,
+    submodules: Option<bool>,
     features: Option<Vec<String>>,
     optional: Option<bool>,
+    public: Option<bool>,
     default_features: Option<bool>,
+    pkg_config: Option<String>,
+    link: Option<String>,
 }
 
 #[derive(RustcDecodable)]
@@ -245,6 +261,7 @@ pub struct TomlManifest {
     features: Option<HashMap<String, Vec<String>>>,
     target: Option<HashMap<String, TomlPlatform>>,
     replace: Option<HashMap<String, TomlDependency>>,
+    patch: Option<HashMap<String, HashMap<String, TomlDependency>>>,
     workspace: Option<TomlWorkspace>,
 }
 
@@ -262,12 +279,14 @@ pub struct TomlOptLevel(String);
 
 impl Decodable for TomlOptLevel {
     fn decode<D: Decoder>(d: &mut D) -> Result<TomlOptLevel, D::Error> {
+        let msg = "expected an opt-level of 0, 1, 2, 3, \"s\" or \"z\"";
         match d.read_u32() {
-            Ok(i) => Ok(TomlOptLevel(i.to_string())),
+            Ok(i) if i <= 3 => Ok(TomlOptLevel(i.to_string())),
+            Ok(_) => Err(d.error(msg)),
             Err(_) => {
                 match d.read_str() {
                     Ok(ref s) if s == "s" || s == "z" => Ok(TomlOptLevel(s.to_string())),
-                    Ok(_) | Err(_) => Err(d.error("expected an integer, a string \"z\" or a string \"s\"")),
+                    Ok(_) | Err(_) => Err(d.error(msg)),
                 }
             }
         }
@@ -292,6 +311,8 @@ pub struct TomlProject {
     authors: Vec<String>,
     build: Option<String>,
     links: Option<String>,
+    precompiled_header: Option<String>,
+    language: Option<String>,
     exclude: Option<Vec<String>>,
     include: Option<Vec<String>>,
     publish: Option<bool>,
@@ -306,11 +327,15 @@ pub struct TomlProject {
     license: Option<String>,
     license_file: Option<String>,
     repository: Option<String>,
+    metadata: Option<toml::Value>,
 }
 
 #[derive(RustcDecodable)]
 pub struct TomlWorkspace {
     members: Option<Vec<String>>,
+    default_members: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    metadata: Option<toml::Value>,
 }
 
 pub struct TomlVersion {
@@ -336,6 +361,7 @@ impl TomlProject {
 struct Context<'a, 'b> {
     pkgid: Option<&'a PackageId>,
     deps: &'a mut Vec<Dependency>,
+    system_deps: &'a mut Vec<SystemDependency>,
     source_id: &'a SourceId,
     nested_paths: &'a mut Vec<PathBuf>,
     config: &'b Config,
@@ -568,13 +594,16 @@ impl TomlManifest {
         }
 
         let mut deps = Vec::new();
+        let mut system_deps = Vec::new();
         let replace;
+        let patch;
 
         {
 
             let mut cx = Context {
                 pkgid: Some(&pkgid),
                 deps: &mut deps,
+                system_deps: &mut system_deps,
                 source_id: source_id,
                 nested_paths: &mut nested_paths,
                 config: config,
@@ -592,6 +621,11 @@ impl TomlManifest {
                     None => return Ok(()),
                 };
                 for (n, v) in dependencies.iter() {
+                    if let TomlDependency::Detailed(DetailedTomlDependency { pkg_config: Some(ref spec), .. }) = *v {
+                        let dep = v.to_system_dependency(n, spec, cx, kind)?;
+                        cx.system_deps.push(dep);
+                        continue;
+                    }
                     let dep = v.to_dependency(n, cx, kind)?;
                     cx.deps.push(dep);
                 }
@@ -618,6 +652,7 @@ impl TomlManifest {
             }
 
             replace = self.replace(&mut cx)?;
+            patch = self.patch(&mut cx)?;
         }
 
         {
@@ -651,10 +686,18 @@ impl TomlManifest {
             license_file: project.license_file.clone(),
             repository: project.repository.clone(),
             keywords: project.keywords.clone().unwrap_or(Vec::new()),
+            metadata: project.metadata.clone(),
         };
 
         let workspace_config = match (self.workspace.as_ref(), project.workspace.as_ref()) {
-            (Some(config), None) => WorkspaceConfig::Root { members: config.members.clone() },
+            (Some(config), None) => {
+                WorkspaceConfig::Root {
+                    members: config.members.clone(),
+                    default_members: config.default_members.clone(),
+                    exclude: config.exclude.clone(),
+                    metadata: config.metadata.clone(),
+                }
+            }
             (None, root) => WorkspaceConfig::Member { root: root.cloned() },
             (Some(..), Some(..)) => {
                 bail!("cannot configure both `package.workspace` and \
@@ -663,15 +706,23 @@ impl TomlManifest {
         };
         let profiles = build_profiles(&self.profile);
         let publish = project.publish.unwrap_or(true);
+        let language = match project.language {
+            Some(ref language) => language.parse()?,
+            None => Language::C,
+        };
         let mut manifest = Manifest::new(summary,
                                          targets,
                                          exclude,
                                          include,
                                          project.links.clone(),
+                                         project.precompiled_header.clone(),
+                                         language,
                                          metadata,
                                          profiles,
                                          publish,
                                          replace,
+                                         patch,
+                                         system_deps,
                                          workspace_config);
         if project.license_file.is_some() && project.license.is_some() {
             manifest.add_warning("only one of `license` or \
@@ -715,24 +766,36 @@ impl TomlManifest {
         let mut nested_paths = Vec::new();
         let mut warnings = Vec::new();
         let mut deps = Vec::new();
-        let replace = self.replace(&mut Context {
+        let mut system_deps = Vec::new();
+        let (replace, patch) = {
+            let mut cx = Context {
                 pkgid: None,
                 deps: &mut deps,
+                system_deps: &mut system_deps,
                 source_id: source_id,
                 nested_paths: &mut nested_paths,
                 config: config,
                 warnings: &mut warnings,
                 platform: None,
                 layout: layout,
-            })?;
+            };
+            (self.replace(&mut cx)?, self.patch(&mut cx)?)
+        };
         let profiles = build_profiles(&self.profile);
         let workspace_config = match self.workspace {
-            Some(ref config) => WorkspaceConfig::Root { members: config.members.clone() },
+            Some(ref config) => {
+                WorkspaceConfig::Root {
+                    members: config.members.clone(),
+                    default_members: config.default_members.clone(),
+                    exclude: config.exclude.clone(),
+                    metadata: config.metadata.clone(),
+                }
+            }
             None => {
                 bail!("virtual manifests must be configured with [workspace]");
             }
         };
-        Ok((VirtualManifest::new(replace, workspace_config, profiles), nested_paths))
+        Ok((VirtualManifest::new(replace, patch, workspace_config, profiles), nested_paths))
     }
 
     fn replace(&self, cx: &mut Context) -> CraftResult<Vec<(PackageIdSpec, Dependency)>> {
@@ -768,6 +831,21 @@ impl TomlManifest {
         }
         Ok(replace)
     }
+
+    fn patch(&self, cx: &mut Context) -> CraftResult<HashMap<Url, Vec<Dependency>>> {
+        let mut patch = HashMap::new();
+        for (url, deps) in self.patch.iter().flat_map(|x| x) {
+            let url = url.to_url()
+                .chain_error(|| human(format!("[patch] entry `{}` should be the URL of a source", url)))?;
+            // Registries given as `sparse+<url>` are known by the plain URL
+            let url = SourceId::for_registry(&url).url().clone();
+            let deps = deps.iter()
+                .map(|(name, dep)| dep.to_dependency(name, cx, None))
+                .collect::<CraftResult<Vec<_>>>()?;
+            patch.insert(url, deps);
+        }
+        Ok(patch)
+    }
 }
 
 /// Will check a list of toml targets, and make sure the target names are unique within a vector.
@@ -795,6 +873,32 @@ fn unique_build_targets(targets: &[Target], layout: &Layout) -> Result<(), Strin
 }
 
 impl TomlDependency {
+    fn to_system_dependency(&self,
+                            name: &str,
+                            spec: &str,
+                            cx: &mut Context,
+                            kind: Option<Kind>)
+                            -> CraftResult<SystemDependency> {
+        if kind.is_some() {
+            bail!("dependency ({}) uses `pkg-config`, which is only supported for \
+                   `[dependencies]`",
+                  name)
+        }
+        let mut link = None;
+        if let TomlDependency::Detailed(ref details) = *self {
+            if details.version.is_some() || details.path.is_some() || details.git.is_some() {
+                bail!("dependency ({}) specification is ambiguous. `pkg-config` can't be \
+                       combined with `version`, `path` or `git`",
+                      name)
+            }
+            if let Some(ref kind) = details.link {
+                link = Some(kind.parse::<LinkKind>()
+                    .chain_error(|| human(format!("invalid `link` of dependency ({})", name)))?);
+            }
+        }
+        SystemDependency::parse(name, spec, link, cx.platform.clone())
+    }
+
     fn to_dependency(&self, name: &str, cx: &mut Context, kind: Option<Kind>) -> CraftResult<Dependency> {
         let details = match *self {
             TomlDependency::Simple(ref version) => {
@@ -803,6 +907,15 @@ impl TomlDependency {
             TomlDependency::Detailed(ref details) => details.clone(),
         };
 
+        if details.pkg_config.is_some() {
+            bail!("dependency ({}) can't be found with `pkg-config` here", name)
+        }
+        if details.link.is_some() {
+            bail!("dependency ({}) specifies `link`, which is only supported for `pkg-config` \
+                   dependencies",
+                  name)
+        }
+
         if details.version.is_none() && details.path.is_none() && details.git.is_none() {
             let msg = format!("dependency ({}) specified without providing a local path, Git repository, or version \
                                to use. This will be considered an error in future versions",
@@ -822,6 +935,12 @@ impl TomlDependency {
                     cx.warnings.push(msg)
                 }
             }
+            if details.submodules.is_some() {
+                let msg = format!("key `submodules` is ignored for dependency ({}). \
+                                   This will be considered an error in future versions",
+                                  name);
+                cx.warnings.push(msg)
+            }
         }
 
         let new_source_id = match (details.git.as_ref(), details.path.as_ref()) {
@@ -852,7 +971,7 @@ impl TomlDependency {
                     .or_else(|| details.rev.clone().map(GitReference::Rev))
                     .unwrap_or_else(|| GitReference::Branch("master".to_string()));
                 let loc = git.to_url()?;
-                SourceId::for_git(&loc, reference)
+                SourceId::for_git(&loc, reference).with_submodules(details.submodules.unwrap_or(true))
             }
             (None, Some(path)) => {
                 cx.nested_paths.push(PathBuf::from(path));
@@ -886,6 +1005,7 @@ impl TomlDependency {
         dep = dep.set_features(details.features.unwrap_or(Vec::new()))
             .set_default_features(details.default_features.unwrap_or(true))
             .set_optional(details.optional.unwrap_or(false))
+            .set_public(details.public.unwrap_or(false))
             .set_platform(cx.platform.clone());
         if let Some(kind) = kind {
             dep = dep.set_kind(kind);
@@ -906,6 +1026,30 @@ struct TomlTarget {
     plugin: Option<bool>,
     proc_macro: Option<bool>,
     harness: Option<bool>,
+    filename: Option<String>,
+    cflags: Option<Vec<String>>,
+    defines: Option<HashMap<String, TomlDefine>>,
+}
+
+/// The value of a macro in the `defines` of a target. `true` defines the macro
+/// without a value, `false` doesn't define it at all.
+#[derive(RustcDecodable, Debug, Clone)]
+enum TomlDefine {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+impl TomlDefine {
+    /// Returns `None` if the macro isn't defined, otherwise its optional value.
+    fn value(&self) -> Option<Option<String>> {
+        match *self {
+            TomlDefine::String(ref s) => Some(Some(s.clone())),
+            TomlDefine::Integer(i) => Some(Some(i.to_string())),
+            TomlDefine::Boolean(true) => Some(None),
+            TomlDefine::Boolean(false) => None,
+        }
+    }
 }
 
 #[derive(RustcDecodable, Clone)]
@@ -935,6 +1079,9 @@ impl TomlTarget {
             plugin: None,
             proc_macro: None,
             harness: None,
+            filename: None,
+            cflags: None,
+            defines: None,
         }
     }
 
@@ -964,12 +1111,16 @@ impl TomlTarget {
         match self.name {
             Some(ref name) => {
                 if name.trim().is_empty() {
-                    Err(human("binary target names cannot be empty.".to_string()))
-                } else {
-                    Ok(())
+                    return Err(human("binary target names cannot be empty.".to_string()));
                 }
             }
-            None => Err(human("binary target bin.name is required".to_string())),
+            None => return Err(human("binary target bin.name is required".to_string())),
+        }
+        match self.filename {
+            Some(ref filename) if filename.trim().is_empty() || filename.contains('/') || filename.contains('\\') => {
+                Err(human(format!("binary target filename `{}` has to be a plain file name", filename)))
+            }
+            _ => Ok(()),
         }
     }
 
@@ -1058,6 +1209,12 @@ fn normalize(lib: &Option<TomlLibTarget>,
              -> Vec<Target> {
     fn configure(toml: &TomlTarget, target: &mut Target) {
         let t2 = target.clone();
+        let mut defines = toml.defines
+            .iter()
+            .flat_map(|defines| defines)
+            .filter_map(|(name, define)| define.value().map(|value| (name.clone(), value)))
+            .collect::<Vec<_>>();
+        defines.sort();
         target.set_tested(toml.test.unwrap_or(t2.tested()))
             .set_doc(toml.doc.unwrap_or(t2.documented()))
             .set_doctest(toml.doctest.unwrap_or(t2.doctested()))
@@ -1067,7 +1224,9 @@ fn normalize(lib: &Option<TomlLibTarget>,
                 (None, None) => t2.for_host(),
                 (Some(true), _) | (_, Some(true)) => true,
                 (Some(false), _) | (_, Some(false)) => false,
-            });
+            })
+            .set_cflags(toml.cflags.clone().unwrap_or_default())
+            .set_defines(defines);
     }
 
     fn lib_target(dst: &mut Vec<Target>, l: &TomlLibTarget, metadata: &Metadata) {
@@ -1100,6 +1259,7 @@ fn normalize(lib: &Option<TomlLibTarget>,
             let path = bin.path.clone().unwrap_or_else(|| PathValue::Path(default(bin)));
             let mut target = Target::bin_target(&bin.name(), &path.to_path(), None);
             configure(bin, &mut target);
+            target.set_filename(bin.filename.clone());
             dst.push(target);
         }
     }
@@ -1166,12 +1326,12 @@ fn normalize(lib: &Option<TomlLibTarget>,
                     &mut |bin| {
                         Path::new("src")
                             .join("bin")
-                            .join(&format!("{}.rs", bin.name()))
+                            .join(&format!("{}.c", bin.name()))
                     });
     } else if bins.len() > 0 {
         bin_targets(&mut ret,
                     bins,
-                    &mut |bin| Path::new("src").join(&format!("{}.rs", bin.name())));
+                    &mut |bin| Path::new("src").join(&format!("{}.c", bin.name())));
     }
 
     if let Some(custom_build) = custom_build {
@@ -1180,7 +1340,7 @@ fn normalize(lib: &Option<TomlLibTarget>,
 
     example_targets(&mut ret,
                     examples,
-                    &mut |ex| Path::new("examples").join(&format!("{}.rs", ex.name())));
+                    &mut |ex| Path::new("examples").join(&format!("{}.c", ex.name())));
 
     test_targets(&mut ret,
                  tests,
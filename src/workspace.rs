@@ -4,12 +4,16 @@ use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::slice;
 
+use glob::{glob, MatchOptions, Pattern};
+
 use dependency::Dependency;
 use manifest::{Profile, Profiles, VirtualManifest, EitherManifest};
 use ops;
 use package::Package;
 use package_id_spec::PackageIdSpec;
 use source::SourceId;
+use toml;
+use url::Url;
 use util::paths;
 use util::{Config, CraftResult, Filesystem, human};
 
@@ -41,6 +45,9 @@ pub struct Workspace<'cfg> {
     // List of members in this workspace with a listing of all their manifest paths. The packages
     // themselves can be looked up through the `packages` set above.
     members: Vec<PathBuf>,
+
+    // The members built when no package is selected explicitly, a subset of `members`.
+    default_members: Vec<PathBuf>,
 }
 
 // Separate structure for tracking loaded packages (to avoid loading anything twice), and this is
@@ -59,8 +66,15 @@ enum MaybePackage {
 #[derive(Debug, Clone)]
 pub enum WorkspaceConfig {
     /// Indicates that `[workspace]` was present and the members were
-    /// optionally specified as well.
-    Root { members: Option<Vec<String>> },
+    /// optionally specified as well, along with the members to build by
+    /// default, the paths excluded from the workspace and the opaque
+    /// `[workspace.metadata]` table for external tools.
+    Root {
+        members: Option<Vec<String>>,
+        default_members: Option<Vec<String>>,
+        exclude: Option<Vec<String>>,
+        metadata: Option<toml::Value>,
+    },
 
     /// Indicates that `[workspace]` was present and the `root` field is the
     /// optional value of `package.workspace`, if present.
@@ -92,6 +106,7 @@ impl<'cfg> Workspace<'cfg> {
             root_manifest: None,
             target_dir: target_dir,
             members: Vec::new(),
+            default_members: Vec::new(),
         };
         ws.root_manifest = ws.find_root(manifest_path)?;
         ws.find_members()?;
@@ -116,6 +131,7 @@ impl<'cfg> Workspace<'cfg> {
             root_manifest: None,
             target_dir: None,
             members: Vec::new(),
+            default_members: Vec::new(),
         };
         {
             let key = ws.current_manifest.parent().unwrap();
@@ -127,6 +143,7 @@ impl<'cfg> Workspace<'cfg> {
                 ws.config.target_dir()?
             };
             ws.members.push(ws.current_manifest.clone());
+            ws.default_members.push(ws.current_manifest.clone());
         }
         Ok(ws)
     }
@@ -196,6 +213,32 @@ impl<'cfg> Workspace<'cfg> {
         }
     }
 
+    /// Returns the root [patch] section of this workspace.
+    ///
+    /// This may be from a virtual chest or an actual chest.
+    pub fn root_patch(&self) -> &HashMap<Url, Vec<Dependency>> {
+        let path = match self.root_manifest {
+            Some(ref p) => p,
+            None => &self.current_manifest,
+        };
+        match *self.packages.get(path) {
+            MaybePackage::Package(ref p) => p.manifest().patch(),
+            MaybePackage::Virtual(ref v) => v.patch(),
+        }
+    }
+
+    /// Returns the `[workspace.metadata]` table of the root manifest, if any.
+    pub fn metadata(&self) -> Option<&toml::Value> {
+        let path = match self.root_manifest {
+            Some(ref p) => p,
+            None => &self.current_manifest,
+        };
+        match *self.packages.get(path).workspace_config() {
+            WorkspaceConfig::Root { ref metadata, .. } => metadata.as_ref(),
+            WorkspaceConfig::Member { .. } => None,
+        }
+    }
+
     /// Returns an iterator over all packages in this workspace
     pub fn members<'a>(&'a self) -> Members<'a, 'cfg> {
         Members {
@@ -204,6 +247,17 @@ impl<'cfg> Workspace<'cfg> {
         }
     }
 
+    /// Returns an iterator over the packages built when none are selected explicitly.
+    ///
+    /// That's the `workspace.default-members` when invoked at the root of the workspace, the
+    /// current package otherwise. A virtual root without `default-members` builds all members.
+    pub fn default_members<'a>(&'a self) -> Members<'a, 'cfg> {
+        Members {
+            ws: self,
+            iter: self.default_members.iter(),
+        }
+    }
+
     /// Finds the root of a workspace for the chest whose manifest is located at `manifest_path`.
     ///
     /// This will parse the `Craft.toml` at `manifest_path` and then interpret the workspace
@@ -237,9 +291,13 @@ impl<'cfg> Workspace<'cfg> {
             debug!("find_root - trying {}", manifest.display());
             if let Ok(pkg) = self.packages.load(&manifest) {
                 match *pkg.workspace_config() {
-                    WorkspaceConfig::Root { .. } => {
-                        debug!("find_root - found");
-                        return Ok(Some(manifest));
+                    WorkspaceConfig::Root { ref exclude, .. } => {
+                        let exclude = exclude.as_ref().map(|e| &e[..]).unwrap_or(&[]);
+                        if !is_excluded(path, exclude, manifest_path)? {
+                            debug!("find_root - found");
+                            return Ok(Some(manifest));
+                        }
+                        debug!("find_root - excluded from {}", manifest.display());
                     }
                     WorkspaceConfig::Member { .. } => {}
                 }
@@ -254,20 +312,24 @@ impl<'cfg> Workspace<'cfg> {
     ///
     /// If the `workspace.members` configuration is present, then this just verifies that those are
     /// all valid packages to point to. Otherwise, this will transitively follow all `path`
-    /// dependencies looking for members of the workspace.
+    /// dependencies looking for members of the workspace. Members may be given as glob patterns,
+    /// and everything matching `workspace.exclude` is left out, even if listed as a member.
     fn find_members(&mut self) -> CraftResult<()> {
         let root_manifest = match self.root_manifest {
             Some(ref path) => path.clone(),
             None => {
                 debug!("find_members - only me as a member");
                 self.members.push(self.current_manifest.clone());
+                self.default_members.push(self.current_manifest.clone());
                 return Ok(());
             }
         };
-        let members = {
+        let (members, default_members, exclude) = {
             let root = self.packages.load(&root_manifest)?;
             match *root.workspace_config() {
-                WorkspaceConfig::Root { ref members } => members.clone(),
+                WorkspaceConfig::Root { ref members, ref default_members, ref exclude, .. } => {
+                    (members.clone(), default_members.clone(), exclude.clone().unwrap_or_default())
+                }
                 _ => {
                     bail!("root of a workspace inferred but wasn't a root: {}",
                           root_manifest.display())
@@ -275,21 +337,85 @@ impl<'cfg> Workspace<'cfg> {
             }
         };
 
+        let root = root_manifest.parent().unwrap();
+        for path in exclude.iter() {
+            self.expand_members(root, "exclude", path)?;
+        }
         if let Some(list) = members {
-            let root = root_manifest.parent().unwrap();
             for path in list {
-                let manifest_path = root.join(path).join("Craft.toml");
-                self.find_path_deps(&manifest_path)?;
+                for dir in self.expand_members(root, "members", &path)? {
+                    self.find_path_deps(&dir.join("Craft.toml"), root, &exclude)?;
+                }
             }
         }
 
-        self.find_path_deps(&root_manifest)
+        self.find_path_deps(&root_manifest, root, &exclude)?;
+
+        if let Some(list) = default_members {
+            for path in list {
+                let manifest_path = paths::normalize_path(&root.join(&path).join("Craft.toml"));
+                let member = match self.members.iter().find(|m| paths::normalize_path(m) == manifest_path) {
+                    Some(member) => member.clone(),
+                    None => {
+                        bail!("package `{}` is listed in `workspace.default-members` but is not a member of the \
+                               workspace",
+                              path)
+                    }
+                };
+                if self.current_manifest == root_manifest {
+                    self.default_members.push(member);
+                }
+            }
+        }
+        if self.default_members.is_empty() {
+            match *self.packages.get(&self.current_manifest) {
+                MaybePackage::Package(_) => self.default_members.push(self.current_manifest.clone()),
+                MaybePackage::Virtual(_) => self.default_members = self.members.clone(),
+            }
+        }
+        Ok(())
     }
 
-    fn find_path_deps(&mut self, manifest_path: &Path) -> CraftResult<()> {
+    /// Expands an entry of `workspace.members` or `workspace.exclude`, named by `key`, relative to
+    /// the workspace root.
+    ///
+    /// Plain paths are returned as is, glob patterns expand to the matching directories containing
+    /// a `Craft.toml`. A pattern matching none of them only yields a warning.
+    fn expand_members(&self, root: &Path, key: &str, path: &str) -> CraftResult<Vec<PathBuf>> {
+        if !path.contains(|c| c == '*' || c == '?' || c == '[') {
+            return Ok(vec![root.join(path)]);
+        }
+
+        let pattern = root.join(path);
+        let pattern = pattern.to_str()
+            .ok_or_else(|| human(format!("workspace root `{}` is not valid UTF-8", root.display())))?;
+        let entries = glob(pattern).map_err(|e| human(format!("could not parse pattern `{}`: {}", path, e)))?;
+        let mut dirs = Vec::new();
+        for entry in entries {
+            let dir = entry.map_err(|e| human(format!("failed to expand pattern `{}`: {}", path, e)))?;
+            if dir.join("Craft.toml").is_file() {
+                dirs.push(dir);
+            }
+        }
+        if dirs.is_empty() {
+            self.config
+                .shell()
+                .warn(format!("`workspace.{}` pattern `{}` does not match any chest in {}",
+                              key,
+                              path,
+                              root.display()))?;
+        }
+        Ok(dirs)
+    }
+
+    fn find_path_deps(&mut self, manifest_path: &Path, root: &Path, exclude: &[String]) -> CraftResult<()> {
         if self.members.iter().any(|p| p == manifest_path) {
             return Ok(());
         }
+        if is_excluded(root, exclude, manifest_path)? {
+            debug!("find_members - excluded {}", manifest_path.display());
+            return Ok(());
+        }
 
         debug!("find_members - {}", manifest_path.display());
         self.members.push(manifest_path.to_path_buf());
@@ -308,7 +434,7 @@ impl<'cfg> Workspace<'cfg> {
                 .collect::<Vec<_>>()
         };
         for candidate in candidates {
-            self.find_path_deps(&candidate)?;
+            self.find_path_deps(&candidate, root, exclude)?;
         }
         Ok(())
     }
@@ -415,7 +541,7 @@ impl<'cfg> Workspace<'cfg> {
                 MaybePackage::Virtual(_) => members_msg,
                 MaybePackage::Package(ref p) => {
                     let members = match *p.manifest().workspace_config() {
-                        WorkspaceConfig::Root { ref members } => members,
+                        WorkspaceConfig::Root { ref members, .. } => members,
                         WorkspaceConfig::Member { .. } => unreachable!(),
                     };
                     if members.is_none() {
@@ -465,6 +591,29 @@ impl<'cfg> Workspace<'cfg> {
     }
 }
 
+/// Whether the chest at `manifest_path` is excluded from the workspace at `root` by one of the
+/// `exclude` patterns, which exclude the matching directories along with everything below them.
+fn is_excluded(root: &Path, exclude: &[String], manifest_path: &Path) -> CraftResult<bool> {
+    let dir = paths::normalize_path(manifest_path.parent().unwrap());
+    let rel = match dir.strip_prefix(root) {
+        Ok(rel) => rel,
+        Err(_) => return Ok(false),
+    };
+    let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+    for path in exclude {
+        let pattern = Pattern::new(path.trim_right_matches('/'))
+            .map_err(|e| human(format!("could not parse pattern `{}`: {}", path, e)))?;
+        let mut cur = Some(rel);
+        while let Some(p) = cur {
+            if p.components().next().is_some() && pattern.matches_path_with(p, &options) {
+                return Ok(true);
+            }
+            cur = p.parent();
+        }
+    }
+    Ok(false)
+}
+
 impl<'cfg> Packages<'cfg> {
     fn get(&self, manifest_path: &Path) -> &MaybePackage {
         &self.packages[manifest_path.parent().unwrap()]
@@ -0,0 +1,1952 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+extern crate filetime;
+
+use std::fs::File;
+use std::io::{Read, Write};
+
+use crafttest::support::{execs, project, ProjectBuilder};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn cxx_dependency_links_with_cxx_driver() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.c",
+              r#"
+            int bar(void);
+            int main() {
+                /* Not valid C++, so this has to be compiled as C */
+                int class = 0;
+                return bar() + class;
+            }
+        "#)
+        .file("bar/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+
+            [lib]
+            path = "src/lib.cpp"
+        "#)
+        .file("bar/src/lib.cpp",
+              r#"
+            #include <string>
+            extern "C" int bar(void) { return std::string().size(); }
+        "#);
+
+    assert_that!(p.craft_process("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Running] `c++ -x c src[/]main.c -x none [..]`"));
+}
+
+#[test]
+fn link_driver_from_config() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file(".craft/config",
+              r#"
+            [build]
+            link-driver = "cc"
+        "#);
+
+    assert_that!(p.craft_process("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Running] `cc -x c src[/]main.c -x none [..]`"));
+}
+
+#[test]
+fn future_incompat_reports_dependency_deprecations() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.c",
+              r#"
+            int bar(void);
+            int main() {
+                /* Not valid C++, so this has to be compiled as C */
+                int class = 0;
+                return bar() + class;
+            }
+        "#)
+        .file("bar/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.c",
+              r#"
+            __attribute__((deprecated)) static int old(void) { return 0; }
+            int bar(void) { return old(); }
+        "#);
+
+    assert_that!(p.craft_process("build").arg("--future-incompat"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Warning] dependency package `bar v0.5.0 ([..])` uses deprecated \
+                                            constructs:")
+                     .with_stderr_contains("    [..]'old' is deprecated[..]"));
+}
+
+#[test]
+fn unity_build_compiles_sources_at_once() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c",
+              r#"
+            int one(void);
+            int two(void);
+            int main() { return one() + two() - 3; }
+        "#)
+        .file("src/one.c", "int one(void) { return 1; }")
+        .file("src/two.c", "int two(void) { return 2; }")
+        .file(".craft/config",
+              r#"
+            [build]
+            unity = true
+        "#);
+
+    assert_that!(p.craft_process("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Running] `cc [..]-unity.c [..]`"));
+
+    assert_that!(p.process(&p.bin("foo")), execs().with_status(0));
+}
+
+#[test]
+fn unity_build_compiles_shared_sources_into_the_library_only() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/lib.c",
+              r#"
+            int util(void);
+            int foo(void) { return util(); }
+        "#)
+        .file("src/main.c",
+              r#"
+            int foo(void);
+            int util(void);
+            int main() { return foo() + util() - 2; }
+        "#)
+        .file("src/util.c", "int util(void) { return 1; }")
+        .file(".craft/config",
+              r#"
+            [build]
+            unity = true
+        "#);
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert_that!(p.process(&p.bin("foo")), execs().with_status(0));
+}
+
+#[test]
+fn dump_commands_to_file() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file(".craft/config",
+              r#"
+            [build]
+            cflags = ["-DGREETING=hello world"]
+        "#);
+    let dump = p.root().join("commands.txt");
+
+    assert_that!(p.craft_process("build").env("CRAFT_DUMP_COMMANDS", &dump),
+                 execs().with_status(0));
+
+    let mut commands = String::new();
+    File::open(&dump).unwrap().read_to_string(&mut commands).unwrap();
+    assert!(commands.lines().any(|line| {
+                line.starts_with(&format!("cd {} && ", p.root().display())) && line.contains(" src/main.c ") &&
+                line.contains(" '-DGREETING=hello world'")
+            }),
+            "unexpected commands:\n{}",
+            commands);
+}
+
+#[test]
+fn out_dir_from_config() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file(".craft/config",
+              r#"
+            [build]
+            out-dir = "out"
+        "#);
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+
+    let bin = p.root().join("out").join(p.bin("foo").file_name().unwrap());
+    assert_that!(p.process(&bin), execs().with_status(0));
+}
+
+#[test]
+fn out_dir_is_a_file() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("out", "");
+
+    assert_that!(p.craft_process("build").arg("--out-dir").arg("out"),
+                 execs().with_status(101).with_stderr_contains("[Error] out-dir `[..]out` is a file, not a directory"));
+}
+
+#[test]
+fn out_dir_warns_about_colliding_artifacts() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [workspace]
+            members = ["a", "b"]
+        "#)
+        .file("a/Craft.toml",
+              r#"
+            [package]
+            name = "a"
+            version = "0.1.0"
+            authors = []
+
+            [[bin]]
+            name = "app"
+            path = "src/main.c"
+        "#)
+        .file("a/src/main.c", "int main() { return 0; }")
+        .file("b/Craft.toml",
+              r#"
+            [package]
+            name = "b"
+            version = "0.1.0"
+            authors = []
+
+            [[bin]]
+            name = "app"
+            path = "src/main.c"
+        "#)
+        .file("b/src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build").arg("--all").arg("-j").arg("1").arg("--out-dir").arg("out"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Warning] `app[..]` of `[..] v0.1.0 ([..])` overwrites the one of \
+                                            `[..] v0.1.0 ([..])` in the out-dir"));
+}
+
+#[test]
+fn private_dependency_headers_do_not_propagate() {
+    fn checkout(name: &str, public: bool) -> ProjectBuilder {
+        project(name)
+            .file("Craft.toml",
+                  r#"
+                [package]
+                name = "foo"
+                version = "0.5.0"
+                authors = []
+
+                [dependencies.bar]
+                path = "bar"
+            "#)
+            .file("src/main.c",
+                  r#"
+                #include "baz.h"
+                int bar(void);
+                int main() { return bar() - BAZ; }
+            "#)
+            .file("bar/Craft.toml",
+                  &format!(r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+
+                [dependencies.baz]
+                path = "../baz"
+                public = {}
+            "#,
+                           public))
+            .file("bar/src/lib.c",
+                  r#"
+                #include "baz.h"
+                int bar(void) { return BAZ; }
+            "#)
+            .file("baz/Craft.toml",
+                  r#"
+                [package]
+                name = "baz"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("baz/src/lib.c", "int baz(void) { return 0; }")
+            .file("baz/include/baz.h", "#define BAZ 1")
+    }
+
+    // Only `bar` sees the headers of its private dependency
+    assert_that!(checkout("private", false).craft_process("build").arg("-v"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Running] `[..]bar[..]lib.c[..]-I [..]baz[..]include[..]`")
+                     .with_stderr_contains("[..]baz.h[..]"));
+
+    let p = checkout("public", true);
+    assert_that!(p.craft_process("build").arg("-v"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Running] `[..]main.c[..]-I [..]baz[..]include[..]`"));
+    assert_that!(p.process(&p.bin("foo")), execs().with_status(0));
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn asflags_only_reach_assembly_sources() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [[bin]]
+            name = "foo"
+            path = "src/foo.c"
+
+            [[bin]]
+            name = "bar"
+            path = "src/bar.S"
+        "#)
+        .file("src/foo.c",
+              r#"
+            #ifdef ASM_ONLY
+            #error ASFLAGS must not reach C sources
+            #endif
+            int main() { return 0; }
+        "#)
+        .file("src/bar.S",
+              r#"
+            #ifndef ASM_ONLY
+            #error ASFLAGS are missing
+            #endif
+                .globl main
+            main:
+                xor %eax, %eax
+                ret
+        "#);
+
+    assert_that!(p.craft_process("build").arg("-v").env("ASFLAGS", "-DASM_ONLY"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Running] `cc src[/]bar.S [..]-DASM_ONLY[..]`"));
+}
+
+#[cfg(unix)]
+#[test]
+fn failed_compile_leaves_no_partial_artifacts() {
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+    p.build();
+
+    // A compiler that writes its outputs and then fails anyway.
+    let wrapper = p.root().join("failing-cc");
+    File::create(&wrapper)
+        .unwrap()
+        .write_all(b"#!/bin/sh\ncc \"$@\" || exit\ncase \"$*\" in *main.c*) exit 1;; esac\n")
+        .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_that!(p.craft("build").env("CC", &wrapper),
+                 execs().with_status(101).with_stderr_contains("[Error] Could not compile `foo`."));
+
+    let deps = p.build_dir().join("debug").join("deps");
+    let leftovers = fs::read_dir(&deps)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("foo") || name.ends_with(".partial"))
+        .collect::<Vec<_>>();
+    assert!(leftovers.is_empty(), "partial artifacts left behind: {:?}", leftovers);
+
+    assert_that!(p.craft("build"), execs().with_status(0));
+    assert_that!(p.process(&p.bin("foo")), execs().with_status(0));
+}
+
+#[cfg(unix)]
+#[test]
+fn local_header_changes_rebuild_without_compiler_dep_info() {
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    use crafttest::support::paths::CraftPathExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c",
+              r#"
+            #include <stdio.h>
+            #include "foo.h"
+            int main() { return FOO; }
+        "#)
+        .file("src/foo.h",
+              r#"
+            #ifdef UNUSED
+            #include "nested/bar.h"
+            #endif
+            #define FOO 0
+        "#)
+        .file("src/nested/bar.h", "");
+    p.build();
+
+    // A compiler which doesn't leave any dep-info behind.
+    let wrapper = p.root().join("no-dep-info-cc");
+    File::create(&wrapper)
+        .unwrap()
+        .write_all(b"#!/bin/sh\ncc \"$@\" || exit\nwhile [ $# -gt 0 ]; do\n  \
+                     [ \"$1\" = --out-dir ] && rm -f \"$2\"/*.d\n  shift\ndone\nexit 0\n")
+        .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_that!(p.craft("build").env("CC", &wrapper), execs().with_status(0));
+    assert_that!(p.craft("build").arg("-v").env("CC", &wrapper),
+                 execs().with_status(0).with_stderr_contains("[Fresh] foo v0.5.0 ([..])"));
+
+    p.root().join("src/nested/bar.h").move_into_the_future();
+    assert_that!(p.craft("build").env("CC", &wrapper),
+                 execs().with_status(0).with_stderr_contains("[Compiling] foo v0.5.0 ([..])"));
+}
+
+#[test]
+fn deleted_header_rebuilds() {
+    use std::fs;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c",
+              r#"
+            #include "foo.h"
+            int main() { return FOO; }
+        "#)
+        .file("src/foo.h", "#define FOO 0\n");
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+
+    // The header is still listed in the dep-info, the unit has to be rebuilt
+    // which now fails to find it.
+    fs::remove_file(p.root().join("src/foo.h")).unwrap();
+    assert_that!(p.craft("build"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Compiling] foo v0.5.0 ([..])")
+                     .with_stderr_contains("[Error] Could not compile `foo`."));
+
+    File::create(&p.root().join("src/foo.h"))
+        .unwrap()
+        .write_all(b"#define FOO 0\n")
+        .unwrap();
+    assert_that!(p.craft("build"),
+                 execs().with_status(0).with_stderr_contains("[Compiling] foo v0.5.0 ([..])"));
+}
+
+#[test]
+fn bin_filename_override() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [[bin]]
+            name = "app"
+            path = "src/main.c"
+            filename = "coolapp"
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert_that!(p.process(&p.bin("coolapp")), execs().with_status(0));
+    assert!(!p.bin("app").exists());
+
+    assert_that!(p.craft("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Fresh] foo v0.5.0 ([..])"));
+}
+
+#[test]
+fn misspelled_table_warns_once() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependancies]
+            bar = { path = "bar", public = true }
+
+            [empty]
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Warning] unused manifest key: dependancies")
+                     .with_stderr_contains("[Warning] unused manifest key: empty"));
+}
+
+#[test]
+fn dump_fingerprints_names_changed_input() {
+    use std::fs;
+
+    use crafttest::support::paths::CraftPathExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c",
+              r#"
+            #include "foo.h"
+            int main() { return FOO; }
+        "#)
+        .file("src/foo.h", "#define FOO 0\n");
+
+    assert_that!(p.craft_process("build").arg("--dump-fingerprints"),
+                 execs().with_status(0));
+
+    let dump = || {
+        let dir = fs::read_dir(p.build_dir().join("debug").join(".fingerprint"))
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .find(|path| path.file_name().unwrap().to_str().unwrap().starts_with("foo-"))
+            .unwrap();
+        let mut contents = String::new();
+        File::open(dir.join("bin-foo.craft-fingerprint.json")).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    };
+    assert!(dump().contains(r#""dirty_reason":"not built before""#), "{}", dump());
+
+    p.root().join("src/foo.h").move_into_the_future();
+    assert_that!(p.craft("build").arg("--dump-fingerprints"),
+                 execs().with_status(0));
+    assert!(dump().contains("foo.h` has changed\""), "{}", dump());
+}
+
+#[test]
+fn hidden_symbol_visibility_only_for_shared_libraries() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [lib]
+            chest-type = ["dylib"]
+        "#)
+        .file("src/lib.c",
+              r#"
+            __attribute__((visibility("default"))) int exported(void) { return 0; }
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file(".craft/config",
+              r#"
+            [build]
+            symbol-visibility = "hidden"
+        "#);
+
+    let output = p.craft_process("build").arg("-v").exec_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let invocations = stderr.lines().filter(|l| l.contains("Running")).collect::<Vec<_>>();
+    assert_eq!(invocations.len(), 2, "{}", stderr);
+    for line in invocations {
+        let shared = line.contains("--crate-type dylib");
+        assert_eq!(line.contains("-fvisibility=hidden"), shared, "{}", line);
+    }
+}
+
+#[test]
+fn hidden_symbol_visibility_keeps_binaries_fresh() {
+    use std::fs;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+
+    fs::create_dir_all(p.root().join(".craft")).unwrap();
+    File::create(p.root().join(".craft/config"))
+        .unwrap()
+        .write_all(b"[build]\nsymbol-visibility = \"hidden\"\n")
+        .unwrap();
+    assert_that!(p.craft("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Fresh] foo v0.5.0 ([..])"));
+}
+
+#[test]
+fn invalid_symbol_visibility() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file(".craft/config",
+              r#"
+            [build]
+            symbol-visibility = "protected"
+        "#);
+
+    assert_that!(p.craft_process("build"),
+                 execs().with_status(101)
+                     .with_stderr_contains("[Error] build.symbol-visibility must be `hidden` or `default`, but \
+                                            found `protected` in [..]"));
+}
+
+#[cfg(unix)]
+#[test]
+fn jobserver_limits_concurrent_compiles() {
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    use crafttest::support::craft_dir;
+
+    let mut p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            a = { path = "a" }
+            b = { path = "b" }
+            c = { path = "c" }
+            d = { path = "d" }
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+    for name in &["a", "b", "c", "d"] {
+        p = p.file(&format!("{}/Craft.toml", name),
+                   &format!(r#"
+                [package]
+                name = "{}"
+                version = "0.5.0"
+                authors = []
+            "#,
+                            name))
+            .file(&format!("{}/src/lib.c", name), "int f(void) { return 0; }");
+    }
+    p.build();
+
+    // A compiler that records how many compiles are running at once.
+    let running = p.root().join("running");
+    let log = p.root().join("concurrency");
+    let wrapper = p.root().join("counting-cc");
+    File::create(&wrapper)
+        .unwrap()
+        .write_all(format!("#!/bin/sh\nmkdir -p {running}\ntouch {running}/$$\nls {running} | wc -l >> {log}\n\
+                            sleep 1\nrm {running}/$$\nexec cc \"$@\"\n",
+                           running = running.display(),
+                           log = log.display())
+            .as_bytes())
+        .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+
+    // Simulate `make -j2`: one implicit token plus one in the pipe.
+    assert_that!(p.process("sh")
+                     .arg("-c")
+                     .arg("mkfifo tokens && exec 3<>tokens && printf + >&3 && exec \"$@\"")
+                     .arg("sh")
+                     .arg(craft_dir().join("craft"))
+                     .arg("build")
+                     .arg("-j8")
+                     .env("MAKEFLAGS", "-j2 --jobserver-auth=3,3")
+                     .env("CC", &wrapper),
+                 execs().with_status(0));
+
+    let mut counts = String::new();
+    File::open(&log).unwrap().read_to_string(&mut counts).unwrap();
+    let counts = counts.lines().map(|l| l.trim().parse::<usize>().unwrap()).collect::<Vec<_>>();
+    assert!(!counts.is_empty());
+    assert!(counts.iter().all(|&n| n <= 2), "more than 2 concurrent compiles: {:?}", counts);
+}
+
+#[test]
+fn verify_reproducible_passes_for_deterministic_build() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build").arg("--verify-reproducible"),
+                 execs().with_status(0).with_stderr_contains("[..]Verified foo v0.5.0 ([..]) builds reproducibly"));
+}
+
+#[cfg(unix)]
+#[test]
+fn verify_reproducible_names_differing_binary() {
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c",
+              r#"
+            #include <stdio.h>
+            int main() { puts(__TIME__); return 0; }
+        "#);
+    p.build();
+
+    // Make sure both builds don't happen within the same second.
+    let wrapper = p.root().join("slow-cc");
+    File::create(&wrapper).unwrap().write_all(b"#!/bin/sh\nsleep 1\nexec cc \"$@\"\n").unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_that!(p.craft("build").arg("--verify-reproducible").env("CC", &wrapper),
+                 execs().with_status(101)
+                     .with_stderr_contains("[Error] the build of `foo v0.5.0 ([..])` is not reproducible, these \
+                                            artifacts differ between two builds:")
+                     .with_stderr_contains("  foo"));
+}
+
+#[test]
+fn missing_compiler_suggests_installing_one() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build").env("PATH", "").env_remove("CC"),
+                 execs().with_status(101)
+                     .with_stderr_contains("[Error] could not find a C compiler, `cc` failed to run")
+                     .with_stderr_contains("To fix this, install [..]. Another compiler can be chosen with the \
+                                            `CC` environment variable or the `build.cc` config value."));
+}
+
+#[test]
+fn shared_header_change_rebuilds_dependents_from_header_graph() {
+    use std::fs;
+    use std::io::Write;
+
+    use crafttest::support::paths::CraftPathExt;
+    use filetime::FileTime;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/shared.h", "#define SHARED 0")
+        .file("src/bin/a.c",
+              r#"
+            #include "../shared.h"
+            int main() { return SHARED; }
+        "#)
+        .file("src/bin/b.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+
+    let graph = p.build_dir().join(".craft-header-graph");
+    let mut contents = String::new();
+    File::open(&graph).unwrap().read_to_string(&mut contents).unwrap();
+    assert!(contents.contains("shared.h"), "{}", contents);
+    graph.move_into_the_future();
+
+    // Garble every dep-info file while keeping its mtime, the next build can
+    // only succeed if it looks up the inputs in the persisted graph.
+    let fingerprints = p.build_dir().join("debug").join(".fingerprint");
+    for dir in fs::read_dir(&fingerprints).unwrap() {
+        for file in fs::read_dir(dir.unwrap().path()).unwrap() {
+            let path = file.unwrap().path();
+            if !path.file_name().unwrap().to_str().unwrap().starts_with("dep-") {
+                continue;
+            }
+            let mtime = FileTime::from_last_modification_time(&fs::metadata(&path).unwrap());
+            File::create(&path).unwrap().write_all(b"garbage").unwrap();
+            filetime::set_file_times(&path, mtime, mtime).unwrap();
+        }
+    }
+
+    p.root().join("src/shared.h").move_into_the_future();
+
+    let output = p.craft("build").arg("-v").exec_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let compiles = stderr.lines().filter(|l| l.contains("Running")).collect::<Vec<_>>();
+    assert_eq!(compiles.len(), 1, "{}", stderr);
+    assert!(compiles[0].contains("a.c"), "{}", stderr);
+}
+
+#[test]
+fn header_graph_drops_entries_of_cleaned_units() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+    let graph = p.build_dir().join(".craft-header-graph");
+    let read_graph = || {
+        let mut contents = String::new();
+        File::open(&graph).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    };
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert_that!(p.craft("build").arg("--release"), execs().with_status(0));
+    assert!(read_graph().contains("release"), "{}", read_graph());
+
+    assert_that!(p.craft("clean").arg("--release"), execs().with_status(0));
+    assert_that!(p.craft("build"), execs().with_status(0));
+    let contents = read_graph();
+    assert!(contents.contains("debug"), "{}", contents);
+    assert!(!contents.contains("release"), "{}", contents);
+}
+
+#[cfg(unix)]
+#[test]
+fn ranlib_runs_after_ar_for_static_libraries() {
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [lib]
+            chest-type = ["staticlib"]
+        "#)
+        .file("src/lib.c", "int foo(void) { return 0; }");
+    p.build();
+
+    // Wrappers which log their invocation before doing the real work.
+    let log = p.root().join("tools.log");
+    for tool in &["ar", "ranlib"] {
+        let wrapper = p.root().join(format!("fake-{}", tool));
+        File::create(&wrapper)
+            .unwrap()
+            .write_all(format!("#!/bin/sh\necho {tool} >> {log}\nexec {tool} \"$@\"\n",
+                               tool = tool,
+                               log = log.display())
+                .as_bytes())
+            .unwrap();
+        fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let version = crafttest::process("cc").arg("-v").exec_with_output().unwrap();
+    let version = String::from_utf8(version.stderr).unwrap();
+    let host = version.lines().find(|l| l.starts_with("Target: ")).unwrap()[8..].to_string();
+    fs::create_dir_all(p.root().join(".craft")).unwrap();
+    File::create(p.root().join(".craft/config"))
+        .unwrap()
+        .write_all(format!(r#"
+            [target.{}]
+            ar = "{}"
+            ranlib = "{}"
+        "#,
+                           host,
+                           p.root().join("fake-ar").display(),
+                           p.root().join("fake-ranlib").display())
+            .as_bytes())
+        .unwrap();
+
+    assert_that!(p.craft("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Running] `[..]fake-ranlib [..]libfoo.a`"));
+
+    let mut tools = String::new();
+    File::open(&log).unwrap().read_to_string(&mut tools).unwrap();
+    let ar = tools.lines().position(|t| t == "ar").expect(&tools);
+    let ranlib = tools.lines().position(|t| t == "ranlib").expect(&tools);
+    assert!(ar < ranlib, "{}", tools);
+}
+
+#[test]
+fn all_features_conflicts_with_no_default_features() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [features]
+            default = ["fast"]
+            fast = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build").arg("--all-features").arg("--no-default-features"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr("[Error] `--all-features` and `--no-default-features` can't be used together, \
+                                   `--all-features` already enables the default features\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn parallel_compiler_output_is_not_interleaved() {
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            a = { path = "a" }
+            b = { path = "b" }
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+    for name in &["a", "b"] {
+        p = p.file(&format!("{}/Craft.toml", name),
+                   &format!(r#"
+                [package]
+                name = "{}"
+                version = "0.5.0"
+                authors = []
+            "#,
+                            name))
+            .file(&format!("{}/src/lib.c", name), "int f(void) { return 0; }");
+    }
+    p.build();
+
+    // A compiler whose diagnostic line spans the whole compile, so that two
+    // concurrent compiles would interleave within it without line buffering.
+    let wrapper = p.root().join("chatty-cc");
+    File::create(&wrapper)
+        .unwrap()
+        .write_all(b"#!/bin/sh\nprintf \"diagnostic begin $$\" >&2\nsleep 1\necho \" end $$\" >&2\n\
+                     exec cc \"$@\"\n")
+        .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = p.craft("build").arg("-j2").env("CC", &wrapper).exec_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines = stderr.lines().filter(|l| l.contains("diagnostic ")).collect::<Vec<_>>();
+    assert!(lines.len() >= 2, "{}", stderr);
+    for line in lines.iter() {
+        let pid = line.trim_left_matches("diagnostic begin ").split(' ').next().unwrap();
+        assert_eq!(*line, format!("diagnostic begin {0} end {0}", pid), "{}", stderr);
+        assert_eq!(lines.iter().filter(|l| l == &line).count(), 1, "{}", stderr);
+    }
+}
+
+#[test]
+fn compiler_errors_are_printed_once_when_verbose() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "#error broken on purpose\nint main() { return 0; }");
+
+    let output = p.craft_process("build").arg("-v").exec_with_output().unwrap_err().output.unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().filter(|l| l.contains("error: #error broken on purpose")).count(),
+               1,
+               "{}",
+               stderr);
+}
+
+#[cfg(windows)]
+#[test]
+fn resource_scripts_are_compiled_and_linked() {
+    use std::fs;
+    use std::io::Write;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("src/app.rc", "1 VERSIONINFO\nBEGIN\nEND\n");
+    p.build();
+
+    // The fake resource compiler only records that it ran, so the build fails
+    // to link, but the commands show how the resources are passed along.
+    let log = p.root().join("rc.log");
+    File::create(p.root().join("fake-rc.bat"))
+        .unwrap()
+        .write_all(format!("@echo %* >> {}\r\n", log.display()).as_bytes())
+        .unwrap();
+
+    let version = crafttest::process("cc").arg("-v").exec_with_output().unwrap();
+    let version = String::from_utf8(version.stderr).unwrap();
+    let host = version.lines().find(|l| l.starts_with("Target: ")).unwrap()[8..].to_string();
+    fs::create_dir_all(p.root().join(".craft")).unwrap();
+    File::create(p.root().join(".craft/config"))
+        .unwrap()
+        .write_all(format!("[target.{}]\nrc = '{}'\n", host, p.root().join("fake-rc.bat").display()).as_bytes())
+        .unwrap();
+
+    let output = p.craft("build").arg("-v").exec_with_output().err().unwrap().output.unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let running = stderr.lines().filter(|l| l.contains("Running")).collect::<Vec<_>>();
+    let rc = running.iter().position(|l| l.contains("fake-rc") && l.contains("app.rc")).expect(&stderr);
+    let cc = running.iter().position(|l| l.contains("main.c") && l.contains("-app.")).expect(&stderr);
+    assert!(rc < cc, "{}", stderr);
+
+    let mut invocations = String::new();
+    File::open(&log).unwrap().read_to_string(&mut invocations).unwrap();
+    assert!(invocations.contains("app.rc"), "{}", invocations);
+}
+
+#[cfg(unix)]
+#[test]
+fn resource_script_changes_rebuild() {
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+    use crafttest::support::paths::CraftPathExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("src/app.rc", "1 VERSIONINFO\nBEGIN\nEND\n");
+    p.build();
+
+    // A resource compiler taking the arguments of `windres` which produces an
+    // empty object.
+    let rc = p.root().join("fake-windres");
+    File::create(&rc)
+        .unwrap()
+        .write_all(b"#!/bin/sh\nfor out; do :; done\necho 'int resource;' | cc -x c -c - -o \"$out\"\n")
+        .unwrap();
+    fs::set_permissions(&rc, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let version = crafttest::process("cc").arg("-v").exec_with_output().unwrap();
+    let version = String::from_utf8(version.stderr).unwrap();
+    let host = version.lines().find(|l| l.starts_with("Target: ")).unwrap()[8..].to_string();
+    fs::create_dir_all(p.root().join(".craft")).unwrap();
+    File::create(p.root().join(".craft/config"))
+        .unwrap()
+        .write_all(format!("[target.{}]\nrc = '{}'\n", host, rc.display()).as_bytes())
+        .unwrap();
+
+    assert_that!(p.craft("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Running] `[..]fake-windres [..]app.rc[..]`"));
+    assert_that!(p.craft("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Fresh] foo v0.5.0 ([..])"));
+
+    p.root().join("src/app.rc").move_into_the_future();
+    assert_that!(p.craft("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Running] `[..]fake-windres [..]app.rc[..]`"));
+}
+
+#[test]
+fn warns_about_headers_exported_by_several_dependencies() {
+    let mut p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            a = { path = "a" }
+            b = { path = "b" }
+        "#)
+        .file("src/main.c",
+              r#"
+            #include "util.h"
+            int main() { return 0; }
+        "#);
+    for name in &["a", "b"] {
+        p = p.file(&format!("{}/Craft.toml", name),
+                   &format!(r#"
+                [package]
+                name = "{}"
+                version = "0.5.0"
+                authors = []
+            "#,
+                            name))
+            .file(&format!("{}/include/util.h", name), "int util(void);")
+            .file(&format!("{}/src/lib.c", name), "int util(void) { return 0; }");
+    }
+
+    assert_that!(p.craft_process("build"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Warning] header `util.h` is exported by multiple dependencies of \
+                                            `foo`, only the one of `[..]` is used: `[..] v0.5.0 ([..])`, \
+                                            `[..] v0.5.0 ([..])`"));
+}
+
+#[test]
+fn invalid_opt_level_in_profile() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [profile.release]
+            opt-level = 7
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Error] failed to parse manifest at `[..]Craft.toml`")
+                     .with_stderr_contains("[..]expected an opt-level of 0, 1, 2, 3, \"s\" or \"z\"[..]"));
+}
+
+#[test]
+fn profile_opt_level_is_passed_to_the_compiler() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [profile.dev]
+            opt-level = "s"
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Running] `[..]-C opt-level=s[..]`"));
+}
+
+#[test]
+fn json_message_format_ends_with_build_finished() {
+    fn stdout_lines(p: &ProjectBuilder) -> Vec<String> {
+        let output = p.craft("build").arg("--message-format").arg("json").build_command().output().unwrap();
+        String::from_utf8(output.stdout).unwrap().lines().map(|l| l.to_string()).collect()
+    }
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+    p.build();
+
+    let lines = stdout_lines(&p);
+    assert_eq!(lines.iter().filter(|l| l.contains(r#""reason":"build-finished""#)).count(), 1);
+    assert_eq!(lines.last().unwrap(), r#"{"reason":"build-finished","success":true}"#);
+
+    File::create(p.root().join("src/main.c")).unwrap().write_all(b"int main() { return }").unwrap();
+    let lines = stdout_lines(&p);
+    assert_eq!(lines.iter().filter(|l| l.contains(r#""reason":"build-finished""#)).count(), 1);
+    assert_eq!(lines.last().unwrap(), r#"{"reason":"build-finished","success":false}"#);
+
+    // A dependency which can't be resolved fails before anything is compiled
+    let p = project("baz")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "baz"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+            version = "2.0"
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("bar/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.c", "int bar(void) { return 0; }");
+    p.build();
+    let lines = stdout_lines(&p);
+    assert_eq!(lines, vec![r#"{"reason":"build-finished","success":false}"#]);
+}
+
+#[test]
+fn json_message_format_finishes_reproducibility_checks_once() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    let output = p.craft_process("build")
+        .arg("--verify-reproducible")
+        .arg("--message-format")
+        .arg("json")
+        .exec_with_output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let finished = stdout.lines().filter(|l| l.contains(r#""reason":"build-finished""#)).collect::<Vec<_>>();
+    assert_eq!(finished, vec![r#"{"reason":"build-finished","success":true}"#]);
+}
+
+#[test]
+fn unknown_sanitizer() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build").arg("--sanitizer").arg("memory-leaks"),
+                 execs().with_status(101).with_stderr("\
+[Error] unknown sanitizer `memory-leaks`, expected one of `address`, `undefined` or `thread`
+"));
+}
+
+#[cfg(unix)]
+#[test]
+fn address_sanitizer_flags_are_passed_to_the_compiler() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+    p.build();
+
+    // A compiler dropping the sanitizer flags, the runtime may not be installed.
+    let wrapper = p.root().join("no-sanitizer-cc");
+    File::create(&wrapper)
+        .unwrap()
+        .write_all(b"#!/bin/sh\nfor a; do\n  shift\n  case \"$a\" in\n    \
+                     -fsanitize=*|-fno-omit-frame-pointer) ;;\n    *) set -- \"$@\" \"$a\" ;;\n  \
+                     esac\ndone\nexec cc \"$@\"\n")
+        .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_that!(p.craft("build").arg("-v").arg("--sanitizer").arg("address").env("CC", &wrapper),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Running] `[..] -fsanitize=address -fno-omit-frame-pointer[..]`"));
+}
+
+#[test]
+fn precompiled_header_is_included_and_tracked() {
+    use crafttest::support::paths::CraftPathExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            precompiled-header = "src/pch.h"
+        "#)
+        .file("src/main.c", "int main() { return FOO; }")
+        .file("src/pch.h", "#include \"shared.h\"\n")
+        .file("src/shared.h", "#define FOO 0\n");
+
+    assert_that!(p.craft_process("build").arg("-v"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Running] `[..] -x c-header [..]foo[..].h -o [..]pch[..].h.gch[..]`")
+                     .with_stderr_contains("[Running] `[..] -include [..]pch[..].h[..]`"));
+    assert_that!(p.craft("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Fresh] foo v0.5.0 ([..])"));
+
+    p.root().join("src/shared.h").move_into_the_future();
+    assert_that!(p.craft("build").arg("-v"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Running] `[..] -x c-header [..]`")
+                     .with_stderr_contains("[Compiling] foo v0.5.0 ([..])"));
+}
+
+#[test]
+fn precompiled_header_uses_the_flags_of_the_unit() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            precompiled-header = "src/pch.h"
+
+            [[bin]]
+            name = "foo"
+            path = "src/main.c"
+            defines = { ANSWER = 0 }
+        "#)
+        .file("src/main.c", "int main() { return ANSWER; }")
+        .file("src/pch.h", "#define FROM_PCH 1\n");
+
+    assert_that!(p.craft_process("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Running] `[..] -x c-header [..] -D ANSWER=0[..]`"));
+
+    // Other flags make the compiler ignore the precompiled header, so it's
+    // built again with them.
+    assert_that!(p.craft("build").arg("-v").env("CFLAGS", "-DOTHER"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Running] `[..] -x c-header [..] -DOTHER[..]`")
+                     .with_stderr_contains("[Compiling] foo v0.5.0 ([..])"));
+    assert_that!(p.craft("build").arg("-v").env("CFLAGS", "-DOTHER"),
+                 execs().with_status(0).with_stderr_contains("[Fresh] foo v0.5.0 ([..])"));
+
+    // The optimization level is passed the same way as to the sources
+    assert_that!(p.craft("build").arg("-v").arg("--release"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Running] `[..] -x c-header [..] -C opt-level=3[..]`")
+                     .with_stderr_contains("[Running] `[..]main.c[..] -C opt-level=3[..]`"));
+}
+
+#[test]
+fn build_plan_lists_commands_without_building() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.c"
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("build.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build").arg("--build-plan"),
+                 execs()
+                     .with_status(0)
+                     .with_stdout_contains(r#"{"reason":"build-plan","invocations":[[..]"outputs":["[..]build-script-build[..]"],"unknown_until_run":false}[..]"#)
+                     .with_stdout_contains(r#"[..]"inputs":["[..]main.c"],"outputs":["[..]foo[..]"],"unknown_until_run":true}[..]"#));
+    assert!(!p.bin("foo").exists());
+}
+
+#[test]
+fn build_plan_writes_and_runs_nothing() {
+    use std::fs;
+    use std::path::Path;
+
+    fn files(dir: &Path, found: &mut Vec<String>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files(&path, found);
+            } else {
+                found.push(path.display().to_string());
+            }
+        }
+    }
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            precompiled-header = "src/pch.h"
+
+            [dependencies]
+            z = { pkg-config = "zlib" }
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("src/other.c", "int other(void) { return 0; }")
+        .file("src/pch.h", "#define FROM_PCH 1\n")
+        .file(".craft/config",
+              r#"
+            [build]
+            unity = true
+        "#);
+
+    // `pkg-config` would fail if it ran
+    assert_that!(p.craft_process("build").arg("--build-plan").env("PKG_CONFIG", "false"),
+                 execs()
+                     .with_status(0)
+                     .with_stdout_contains(r#"[..]"inputs":["[..]main.c","[..]other.c"],"outputs":["[..]foo[..]"],"unknown_until_run":true}[..]"#));
+
+    // Neither the unity source nor the wrapper of the precompiled header
+    let mut found = Vec::new();
+    if p.root().join("target").exists() {
+        files(&p.root().join("target"), &mut found);
+    }
+    found.retain(|f| f.ends_with("-unity.c") || f.ends_with(".h"));
+    assert!(found.is_empty(), "{:?}", found);
+}
+
+#[test]
+fn content_fingerprint_ignores_touched_files() {
+    use crafttest::support::paths::CraftPathExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file(".craft/config",
+              r#"
+            [build]
+            fingerprint = "content"
+        "#);
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+
+    p.root().join("src/main.c").move_into_the_future();
+    assert_that!(p.craft("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Fresh] foo v0.5.0 ([..])"));
+
+    File::create(p.root().join("src/main.c")).unwrap().write_all(b"int main() { return 1; }").unwrap();
+    assert_that!(p.craft("build"),
+                 execs().with_status(0).with_stderr_contains("[Compiling] foo v0.5.0 ([..])"));
+}
+
+#[test]
+fn content_fingerprint_only_hashes_the_inputs_of_a_unit() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "#include \"foo.h\"\nint main() { return FOO; }")
+        .file("src/foo.h", "#define FOO 0")
+        .file("README", "foo")
+        .file(".craft/config",
+              r#"
+            [build]
+            fingerprint = "content"
+        "#);
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+
+    File::create(p.root().join("README")).unwrap().write_all(b"bar").unwrap();
+    assert_that!(p.craft("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Fresh] foo v0.5.0 ([..])"));
+
+    File::create(p.root().join("src/foo.h")).unwrap().write_all(b"#define FOO 1").unwrap();
+    assert_that!(p.craft("build"),
+                 execs().with_status(0).with_stderr_contains("[Compiling] foo v0.5.0 ([..])"));
+}
+
+#[test]
+fn content_fingerprint_ignores_line_endings_of_headers() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "#include \"foo.h\"\nint main() { return FOO; }")
+        .file("src/foo.h", "#ifndef FOO\n#define FOO 0\n#endif\n")
+        .file(".craft/config",
+              r#"
+            [build]
+            fingerprint = "content"
+        "#);
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+
+    File::create(p.root().join("src/foo.h")).unwrap().write_all(b"#ifndef FOO\r\n#define FOO 0\r\n#endif").unwrap();
+    assert_that!(p.craft("build").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Fresh] foo v0.5.0 ([..])"));
+}
+
+#[test]
+fn config_values_expand_environment_variables() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c",
+              r#"
+            #include <string.h>
+            int main() { return VALUE + (strcmp(PRICE, "$5") != 0); }
+        "#)
+        .file(".craft/config",
+              r#"
+            [build]
+            cflags = ["-DVALUE=${CRAFT_TEST_VALUE}", "-DPRICE=\"$$5\""]
+        "#);
+
+    assert_that!(p.craft_process("build").env("CRAFT_TEST_VALUE", "0"),
+                 execs().with_status(0));
+    assert_that!(p.process(&p.bin("foo")), execs().with_status(0));
+
+    assert_that!(p.craft("build").env_remove("CRAFT_TEST_VALUE"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Error] environment variable `CRAFT_TEST_VALUE` used in `build.cflags` \
+                                            (in [..]config) is not set"));
+}
+
+#[test]
+fn trailing_args_only_reach_the_local_package() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/main.c",
+              r#"
+            int main() { int unused; return 0; }
+        "#)
+        .file("bar/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.c",
+              r#"
+            int bar() { int unused; return 0; }
+        "#);
+
+    assert_that!(p.craft_process("build").arg("--").arg("-Wall").arg("-Werror"),
+                 execs().with_status(101).with_stderr_contains("[..]Could not compile `foo`."));
+
+    File::create(p.root().join("src/main.c")).unwrap().write_all(b"int main() { return 0; }").unwrap();
+    assert_that!(p.craft("build").arg("--").arg("-Wall").arg("-Werror"),
+                 execs().with_status(0));
+}
+
+#[test]
+fn per_target_cflags_and_defines() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [[bin]]
+            name = "foo"
+            path = "src/main.c"
+            cflags = ["-DFROM_CFLAGS=2"]
+            defines = { ANSWER = 40, GREETING = '"hello world"', ENABLED = true, DISABLED = false }
+
+            [[bin]]
+            name = "plain"
+            path = "src/plain.c"
+        "#)
+        .file("src/main.c",
+              r#"
+            #include <string.h>
+            #if !defined(ENABLED) || defined(DISABLED)
+            #error unexpected defines
+            #endif
+            int main() { return ANSWER + FROM_CFLAGS - 42 + (strcmp(GREETING, "hello world") != 0); }
+        "#)
+        .file("src/plain.c",
+              r#"
+            #ifdef ANSWER
+            #error the defines of another target leaked
+            #endif
+            int main() { return 0; }
+        "#);
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert_that!(p.process(&p.bin("foo")), execs().with_status(0));
+    assert_that!(p.process(&p.bin("plain")), execs().with_status(0));
+}
+
+#[test]
+fn short_message_format_leaves_out_source_excerpts() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c",
+              r#"
+            int main() {
+                int unused;
+                return 0;
+            }
+        "#);
+
+    let output = p.craft_process("build")
+        .arg("-v")
+        .arg("--message-format")
+        .arg("short")
+        .arg("--")
+        .arg("-Wall")
+        .exec_with_output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("-fno-diagnostics-show-caret"), "{}", stderr);
+    assert!(stderr.contains("warning:"), "{}", stderr);
+    assert!(!stderr.contains("int unused;"), "{}", stderr);
+}
+
+#[test]
+fn diagnostics_summary_counts_distinct_lines() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c",
+              r#"
+            int main() {
+                int first;
+                int second;
+                return 0;
+            }
+        "#);
+
+    assert_that!(p.craft_process("build").arg("--").arg("-Wall"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[..]Diagnostics[..] 2 warnings, 0 errors across 1 chest"));
+
+    let output = p.craft("build").arg("-q").arg("--").arg("-Wall").arg("-DQUIET").exec_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("Diagnostics"), "{}", stderr);
+}
+
+#[cfg(unix)]
+#[test]
+fn compiler_probe_is_cached_until_the_compiler_changes() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+    p.build();
+
+    // A compiler that records every time it's probed with `-v`.
+    let log = p.root().join("probes.log");
+    let wrapper = p.root().join("logging-cc");
+    let write_wrapper = |extra: &str| {
+        let script = format!("#!/bin/sh\n{}\nif [ \"$*\" = -v ]; then echo probe >> {}; fi\nexec cc \"$@\"\n",
+                             extra,
+                             log.display());
+        File::create(&wrapper).unwrap().write_all(script.as_bytes()).unwrap();
+        fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    let probes = || {
+        let mut contents = String::new();
+        File::open(&log).unwrap().read_to_string(&mut contents).unwrap();
+        contents.lines().count()
+    };
+
+    write_wrapper("");
+    assert_that!(p.craft("build").env("CC", &wrapper), execs().with_status(0));
+    assert_that!(p.craft("build").env("CC", &wrapper), execs().with_status(0));
+    assert_eq!(probes(), 1);
+
+    // An upgraded compiler is probed again
+    write_wrapper("# upgraded");
+    assert_that!(p.craft("build").env("CC", &wrapper), execs().with_status(0));
+    assert_eq!(probes(), 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn compiler_is_probed_again_when_its_version_changes() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("version", "wrapped cc 1.0\n");
+    p.build();
+
+    // A wrapper like `ccache`, which stays the same when the compiler behind
+    // it changes
+    let wrapper = p.root().join("wrapper-cc");
+    let log = p.root().join("probes.log");
+    File::create(&wrapper)
+        .unwrap()
+        .write_all(format!("#!/bin/sh\n\
+                            if [ \"$*\" = --version ]; then cat {version}; exit 0; fi\n\
+                            if [ \"$*\" = -v ]; then echo probe >> {log}; fi\n\
+                            exec cc \"$@\"\n",
+                           version = p.root().join("version").display(),
+                           log = log.display())
+            .as_bytes())
+        .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+    let probes = || {
+        let mut contents = String::new();
+        File::open(&log).unwrap().read_to_string(&mut contents).unwrap();
+        contents.lines().count()
+    };
+
+    assert_that!(p.craft("build").env("CC", &wrapper), execs().with_status(0));
+    assert_that!(p.craft("build").env("CC", &wrapper), execs().with_status(0));
+    assert_eq!(probes(), 1);
+
+    File::create(p.root().join("version")).unwrap().write_all(b"wrapped cc 2.0\n").unwrap();
+    assert_that!(p.craft("build").env("CC", &wrapper), execs().with_status(0));
+    assert_eq!(probes(), 2);
+}
+
+#[test]
+fn future_incompat_reports_workspace_members_as_first_party() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("src/main.c",
+              r#"
+            int bar(void);
+            int main() { return bar(); }
+        "#)
+        .file("bar/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.c",
+              r#"
+            __attribute__((deprecated)) static int old(void) { return 0; }
+            int bar(void) { return old(); }
+        "#);
+
+    // Only `foo` is built by default, `bar` is still a member
+    assert_that!(p.craft_process("build").arg("--future-incompat"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Warning] first-party package `bar v0.5.0 ([..])` uses deprecated \
+                                            constructs:"));
+}
+
+#[test]
+fn lock_timeout_accepts_zero_but_not_negative_values() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file(".craft/config",
+              r#"
+            [build]
+            lock-timeout = 0
+        "#);
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+
+    File::create(p.root().join(".craft/config"))
+        .unwrap()
+        .write_all(b"[build]\nlock-timeout = -1\n")
+        .unwrap();
+    assert_that!(p.craft("build"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[..]build.lock-timeout can't be negative, but found -1 in [..]"));
+}
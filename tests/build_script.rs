@@ -0,0 +1,445 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use std::fs;
+
+use crafttest::support::{execs, project, ProjectBuilder};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn json_message_for_build_script() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.c"
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("build.c",
+              r#"
+            #include <stdio.h>
+            int main() {
+                printf("craft:cc-link-lib=m\n");
+                return 0;
+            }
+        "#);
+
+    assert_that!(p.craft_process("build").arg("--message-format").arg("json"),
+                 execs()
+                     .with_status(0)
+                     .with_stdout_contains(r#"{"reason":"build-script-executed","package_id":"foo 0.5.0 ([..])","linked_libs":["m"],"linked_paths":[],"cfgs":[],"env":[]}"#));
+}
+
+#[test]
+fn out_dir_is_independent_of_checkout_location() {
+    fn checkout(name: &str) -> ProjectBuilder {
+        project(name)
+            .file("Craft.toml",
+                  r#"
+                [package]
+                name = "foo"
+                version = "0.5.0"
+                authors = []
+                build = "build.c"
+            "#)
+            .file("src/main.c", "int main() { return 0; }")
+            .file("build.c", "int main() { return 0; }")
+    }
+    fn build_dirs(p: &ProjectBuilder) -> Vec<String> {
+        let mut dirs = fs::read_dir(p.build_dir().join("debug").join("build"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        dirs.sort();
+        dirs
+    }
+    let a = checkout("a");
+    let b = checkout("b");
+
+    assert_that!(a.craft_process("build"), execs().with_status(0));
+    assert_that!(b.craft_process("build"), execs().with_status(0));
+    let dirs = build_dirs(&a);
+    assert!(!dirs.is_empty());
+    assert_eq!(dirs, build_dirs(&b));
+
+    // Rebuilding from scratch ends up in the very same directory
+    assert_that!(a.craft_process("build"), execs().with_status(0));
+    assert_eq!(dirs, build_dirs(&a));
+}
+
+#[test]
+fn build_dependency_features_stay_on_host_when_cross_compiling() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.c"
+
+            [dependencies.bar]
+            path = "bar"
+
+            [build-dependencies.bar]
+            path = "bar"
+            features = ["x"]
+        "#)
+        .file("src/main.c",
+              r#"
+            int bar(void);
+            int main() { return bar(); }
+        "#)
+        .file("build.c",
+              r#"
+            int bar(void);
+            int main() { return bar(); }
+        "#)
+        .file("bar/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+
+            [features]
+            x = []
+        "#)
+        .file("bar/src/lib.c", "int bar(void) { return 0; }");
+
+    // Cross compiling to the host itself still builds the host and target
+    // graphs separately.
+    let version = crafttest::process("cc").arg("-v").exec_with_output().unwrap();
+    let version = String::from_utf8(version.stderr).unwrap();
+    let host = version.lines().find(|l| l.starts_with("Target: ")).unwrap()[8..].to_string();
+
+    let output = p.craft_process("build").arg("-v").arg("--target").arg(&host).exec_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let bar = stderr.lines()
+        .filter(|l| l.contains("Running") && l.contains("--crate-name bar "))
+        .collect::<Vec<_>>();
+    assert_eq!(bar.len(), 2, "{}", stderr);
+    for line in bar {
+        let for_target = line.contains(&format!("--target {}", host));
+        assert_eq!(line.contains("feature="), !for_target, "{}", line);
+    }
+}
+
+#[test]
+fn target_applies_to_host_controls_build_script_cflags() {
+    let version = crafttest::process("cc").arg("-v").exec_with_output().unwrap();
+    let version = String::from_utf8(version.stderr).unwrap();
+    let host = version.lines().find(|l| l.starts_with("Target: ")).unwrap()[8..].to_string();
+
+    // Whether the build script got the configured CFLAGS, for the given
+    // `target-applies-to-host` setting and with or without --target.
+    let build_script_cflags = |setting: &str, cross: bool| {
+        let p = project("foo")
+            .file("Craft.toml",
+                  r#"
+                [package]
+                name = "foo"
+                version = "0.5.0"
+                authors = []
+                build = "build.c"
+            "#)
+            .file("src/main.c", "int main() { return 0; }")
+            .file("build.c", "int main() { return 0; }")
+            .file(".craft/config",
+                  &format!(r#"
+                {}
+
+                [build]
+                cflags = ["-DMARKER"]
+            "#,
+                           setting));
+        let mut build = p.craft_process("build");
+        build.arg("-v");
+        if cross {
+            build.arg("--target").arg(&host);
+        }
+        let output = build.exec_with_output().unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        let line = stderr.lines()
+            .find(|l| l.contains("Running") && l.contains("--crate-name build_script_build "))
+            .unwrap()
+            .to_string();
+        line.contains("-DMARKER")
+    };
+
+    assert!(!build_script_cflags("", true));
+    assert!(build_script_cflags("target-applies-to-host = true", true));
+    assert!(build_script_cflags("", false));
+    assert!(!build_script_cflags("target-applies-to-host = false", false));
+}
+
+#[test]
+fn build_script_registers_generated_source() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.c"
+        "#)
+        .file("src/main.c",
+              r#"
+            #include <stdio.h>
+            const char *generated(void);
+            int main() {
+                printf("%s\n", generated());
+                return 0;
+            }
+        "#)
+        .file("build.c",
+              r#"
+            #include <stdio.h>
+            #include <stdlib.h>
+            int main() {
+                char path[4096];
+                FILE *f;
+                snprintf(path, sizeof(path), "%s/gen.c", getenv("OUT_DIR"));
+                f = fopen(path, "w");
+                if (!f) return 1;
+                fprintf(f, "const char *generated(void) { return \"from build script\"; }\n");
+                fclose(f);
+                printf("craft:rerun-if-changed=build.c\n");
+                printf("craft:compile-source=%s\n", path);
+                return 0;
+            }
+        "#);
+
+    assert_that!(p.craft_process("run"),
+                 execs().with_status(0).with_stdout_contains("from build script"));
+}
+
+#[test]
+fn relative_compile_source_is_taken_from_out_dir() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.c"
+        "#)
+        .file("src/lib.c", "int foo(void) { return 0; }")
+        .file("src/main.c",
+              r#"
+            #include <stdio.h>
+            const char *generated(void);
+            int main() {
+                printf("%s\n", generated());
+                return 0;
+            }
+        "#)
+        .file("build.c",
+              r#"
+            #include <stdio.h>
+            #include <stdlib.h>
+            int main() {
+                char path[4096];
+                FILE *f;
+                snprintf(path, sizeof(path), "%s/gen.c", getenv("OUT_DIR"));
+                f = fopen(path, "w");
+                if (!f) return 1;
+                fprintf(f, "const char *generated(void) { return \"from build script\"; }\n");
+                fclose(f);
+                printf("craft:compile-source=gen.c\n");
+                return 0;
+            }
+        "#);
+
+    assert_that!(p.craft_process("run"),
+                 execs().with_status(0).with_stdout_contains("from build script"));
+}
+
+#[test]
+fn links_conflict_shows_dependency_paths() {
+    fn links_foo(p: ProjectBuilder, name: &str) -> ProjectBuilder {
+        let manifest = format!(r#"
+            [package]
+            name = "{}"
+            version = "0.5.0"
+            authors = []
+            links = "foo"
+            build = "build.c"
+        "#,
+                               name);
+        p.file(&format!("{}/Craft.toml", name), &manifest)
+            .file(&format!("{}/src/lib.c", name), "")
+            .file(&format!("{}/build.c", name), "int main() { return 0; }")
+    }
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            a = { path = "a" }
+            b = { path = "b" }
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+    let p = links_foo(links_foo(p, "a"), "b");
+
+    assert_that!(p.craft_process("build"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Error] native library `foo` is being linked to by more than one \
+                                            package, and can only be linked to by one package")
+                     .with_stderr_contains("  a v0.5.0 ([..])")
+                     .with_stderr_contains("    required by: foo v0.5.0 -> a v0.5.0")
+                     .with_stderr_contains("  b v0.5.0 ([..])")
+                     .with_stderr_contains("    required by: foo v0.5.0 -> b v0.5.0"));
+}
+
+#[test]
+fn static_link_of_shared_only_library_fails() {
+    if !cfg!(target_os = "linux") {
+        return;
+    }
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.c"
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("build.c",
+              r#"
+            #include <stdio.h>
+            #include <stdlib.h>
+            int main() {
+                char path[4096];
+                snprintf(path, sizeof(path), "%s/libbar.so", getenv("OUT_DIR"));
+                fclose(fopen(path, "w"));
+                printf("craft:cc-link-search=%s\n", getenv("OUT_DIR"));
+                printf("craft:cc-link-lib=static=bar\n");
+                return 0;
+            }
+        "#);
+
+    assert_that!(p.craft_process("build"),
+                 execs().with_status(101).with_stderr_contains("\
+[..]native library `bar` has to be linked statically, but only the shared library `[..]libbar.so` was found"));
+}
+
+#[test]
+fn static_link_flags_depend_on_the_linker() {
+    if !cfg!(unix) {
+        return;
+    }
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.c"
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("build.c",
+              r#"
+            #include <stdio.h>
+            int main() {
+                printf("craft:cc-link-lib=static=m\n");
+                return 0;
+            }
+        "#);
+
+    let output = p.craft_process("build").arg("-v").exec_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // ld64 on macOS doesn't know the flags of GNU ld
+    if cfg!(target_os = "macos") {
+        assert!(!stderr.contains("-Bstatic"), "{}", stderr);
+        assert!(stderr.contains("-l m"), "{}", stderr);
+    } else {
+        assert!(stderr.contains("-Wl,-Bstatic -l m -Wl,-Bdynamic"), "{}", stderr);
+    }
+}
+
+#[test]
+fn unknown_link_kind() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.c"
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("build.c",
+              r#"
+            #include <stdio.h>
+            int main() {
+                printf("craft:cc-link-lib=framework=bar\n");
+                return 0;
+            }
+        "#);
+
+    assert_that!(p.craft_process("build"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[..]Invalid library `framework=bar` in build script of `foo`")
+                     .with_stderr_contains("[..]unknown link kind `framework`, expected `static` or `dylib`"));
+}
+
+#[cfg(unix)]
+#[test]
+fn build_script_defines_and_env_reach_the_compiler() {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.c"
+        "#)
+        .file("src/main.c", "int main() { return FROM_BUILD_SCRIPT - 3; }")
+        .file("build.c",
+              r#"
+            #include <stdio.h>
+            int main() {
+                printf("craft:cc-cfg=FROM_BUILD_SCRIPT=3\n");
+                printf("craft:cc-env=BUILD_SCRIPT_ENV=on\n");
+                return 0;
+            }
+        "#);
+    p.build();
+
+    // A compiler refusing to compile the package without the environment of
+    // its build script.
+    let wrapper = p.root().join("env-cc");
+    File::create(&wrapper)
+        .unwrap()
+        .write_all(b"#!/bin/sh\ncase \"$*\" in *main.c*) [ \"$BUILD_SCRIPT_ENV\" = on ] || exit 1;; esac\n\
+                     exec cc \"$@\"\n")
+        .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_that!(p.craft("build").arg("-v").env("CC", &wrapper),
+                 execs().with_status(0).with_stderr_contains("[Running] `[..]-D FROM_BUILD_SCRIPT=3[..]`"));
+    assert_that!(p.process(&p.bin("foo")), execs().with_status(0));
+}
@@ -0,0 +1,53 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{execs, project};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn extra_args_reach_the_selected_package_only() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/main.c",
+              r#"
+            #ifndef EXTRA
+            #error EXTRA is not defined
+            #endif
+            int main() { return 0; }
+        "#)
+        .file("bar/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.c",
+              r#"
+            #ifdef EXTRA
+            #error EXTRA is defined
+            #endif
+            int bar() { return 0; }
+        "#);
+
+    assert_that!(p.craft_process("cc").arg("-v").arg("--").arg("-DEXTRA"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Running] `[..]main.c[..]-DEXTRA[..]`"));
+
+    assert_that!(p.craft("cc").arg("-p").arg("bar").arg("--").arg("-DEXTRA"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[..]EXTRA is defined[..]"));
+}
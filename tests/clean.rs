@@ -0,0 +1,60 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{execs, project, ProjectBuilder};
+
+use hamcrest::prelude::*;
+
+fn with_dependency() -> ProjectBuilder {
+    project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("bar/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.c", "int bar(void) { return 0; }")
+}
+
+#[test]
+fn clean_release_keeps_debug_artifacts() {
+    let p = with_dependency();
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert_that!(p.craft("build").arg("--release"), execs().with_status(0));
+
+    assert_that!(p.craft("clean").arg("--release"), execs().with_status(0));
+    assert!(!p.build_dir().join("release").exists());
+    assert!(p.bin("foo").is_file());
+}
+
+#[test]
+fn clean_package_rebuilds_only_that_package() {
+    let p = with_dependency();
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+
+    assert_that!(p.craft("clean").arg("-p").arg("foo"), execs().with_status(0));
+    assert!(!p.bin("foo").exists());
+
+    assert_that!(p.craft("build"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr("\
+[Compiling] foo v0.5.0 ([..])
+[Finished] [..]
+"));
+}
@@ -0,0 +1,179 @@
+//! A minimal HTTP server to test registries against, answering every request
+//! with a canned response.
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Returns the value of the header `name`, which is matched case
+    /// insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .filter_map(|header| {
+                let mut parts = header.splitn(2, ':');
+                match (parts.next(), parts.next()) {
+                    (Some(key), Some(value)) if key.trim().to_lowercase() == name.to_lowercase() => {
+                        Some(value.trim())
+                    }
+                    _ => None,
+                }
+            })
+            .next()
+    }
+}
+
+pub struct Response {
+    status: u32,
+    headers: Vec<String>,
+    body: Vec<u8>,
+    length: usize,
+}
+
+impl Response {
+    pub fn new(status: u32, body: &[u8]) -> Response {
+        Response {
+            status: status,
+            headers: Vec::new(),
+            body: body.to_vec(),
+            length: body.len(),
+        }
+    }
+
+    pub fn header(mut self, header: &str) -> Response {
+        self.headers.push(header.to_string());
+        self
+    }
+
+    /// Announces a body of `length` bytes, but closes the connection after
+    /// the actual body, like an interrupted transfer.
+    pub fn truncated(mut self, length: usize) -> Response {
+        self.length = length;
+        self
+    }
+}
+
+pub struct Server {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Server {
+    /// Starts a server on a free local port, answering each request with what
+    /// `handler` returns for it. The server lives as long as the test process.
+    pub fn new<F>(mut handler: F) -> Server
+        where F: FnMut(&Request) -> Response + Send + 'static
+    {
+        let listener = t!(TcpListener::bind("127.0.0.1:0"));
+        let url = format!("http://{}", t!(listener.local_addr()));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(..) => break,
+                };
+                let request = match read_request(&mut stream) {
+                    Some(request) => request,
+                    None => continue,
+                };
+                let response = handler(&request);
+                recorded.lock().unwrap().push(request);
+                let _ = write_response(&mut stream, &response);
+            }
+        });
+        Server {
+            url: url,
+            requests: requests,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The requests received so far, in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut reader = match stream.try_clone() {
+        Ok(clone) => BufReader::new(clone),
+        Err(..) => return None,
+    };
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() {
+        return None;
+    }
+    let (method, path) = {
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+            _ => return None,
+        }
+    };
+
+    let mut request = Request {
+        method: method,
+        path: path,
+        headers: Vec::new(),
+        body: Vec::new(),
+    };
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(..) => return None,
+            Ok(..) => {}
+        }
+        let line = line.trim_right();
+        if line.is_empty() {
+            break;
+        }
+        request.headers.push(line.to_string());
+    }
+
+    if request.header("Expect") == Some("100-continue") {
+        if stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").is_err() {
+            return None;
+        }
+    }
+    let length = request.header("Content-Length").and_then(|l| l.parse().ok()).unwrap_or(0);
+    request.body = vec![0; length];
+    if reader.read_exact(&mut request.body).is_err() {
+        return None;
+    }
+    Some(request)
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> ::std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        206 => "Partial Content",
+        404 => "Not Found",
+        416 => "Range Not Satisfiable",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    };
+    write!(stream, "HTTP/1.1 {} {}\r\n", response.status, reason)?;
+    write!(stream, "Content-Length: {}\r\n", response.length)?;
+    write!(stream, "Connection: close\r\n")?;
+    for header in response.headers.iter() {
+        write!(stream, "{}\r\n", header)?;
+    }
+    write!(stream, "\r\n")?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
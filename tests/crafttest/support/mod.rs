@@ -23,6 +23,7 @@ macro_rules! t {
 
 pub mod paths;
 pub mod git;
+pub mod http;
 pub mod registry;
 
 #[derive(PartialEq,Clone)]
@@ -648,6 +649,46 @@ pub fn basic_lib_manifest(name: &str) -> String {
     "#, name, name)
 }
 
+/// The manifest of the package `name` with the path dependencies `deps`,
+/// given as pairs of the name and path of each dependency.
+pub fn path_deps_manifest(name: &str, deps: &[(&str, &str)]) -> String {
+    let mut manifest = format!(r#"
+        [package]
+        name = "{}"
+        version = "0.5.0"
+        authors = []
+
+        [dependencies]
+    "#,
+                               name);
+    for &(dep, path) in deps {
+        manifest.push_str(&format!("{} = {{ path = \"{}\" }}\n", dep, path));
+    }
+    manifest
+}
+
+/// The package `foo` with a diamond of path dependencies, where `baz` depends
+/// on `qux` with the version requirement `qux_req`, if any:
+///
+/// foo -> bar -> baz -> qux
+///    \-> baz
+pub fn diamond(qux_req: Option<&str>) -> ProjectBuilder {
+    let mut baz = path_deps_manifest("baz", &[]);
+    match qux_req {
+        Some(req) => baz.push_str(&format!("qux = {{ path = \"../qux\", version = \"{}\" }}\n", req)),
+        None => baz.push_str("qux = { path = \"../qux\" }\n"),
+    }
+    project("foo")
+        .file("Craft.toml", &path_deps_manifest("foo", &[("bar", "bar"), ("baz", "baz")]))
+        .file("src/main.c", "int main() { return 0; }")
+        .file("bar/Craft.toml", &path_deps_manifest("bar", &[("baz", "../baz")]))
+        .file("bar/src/lib.c", "int bar(void) { return 0; }")
+        .file("baz/Craft.toml", &baz)
+        .file("baz/src/lib.c", "int baz(void) { return 0; }")
+        .file("qux/Craft.toml", &path_deps_manifest("qux", &[]))
+        .file("qux/src/lib.c", "int qux(void) { return 0; }")
+}
+
 pub fn path2url(p: PathBuf) -> Url {
     Url::from_file_path(&*p).ok().unwrap()
 }
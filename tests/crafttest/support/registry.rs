@@ -12,7 +12,7 @@ use tar::{Builder, Header};
 use url::Url;
 
 use support::paths;
-use support::git::repo;
+use support::git::{self, repo};
 use craft::util::Sha256;
 
 pub fn registry_path() -> PathBuf {
@@ -70,6 +70,17 @@ pub fn init() {
     fs::create_dir_all(dl_path().join("api/v1/crates")).unwrap();
 }
 
+/// Points the downloads and the API of the registry to `url`, like the one of
+/// a local HTTP server, instead of the file system.
+pub fn serve_from(url: &str) {
+    init();
+    t!(t!(File::create(registry_path().join("config.json")))
+           .write_all(format!(r#"{{"dl":"{0}/dl","api":"{0}"}}"#, url).as_bytes()));
+    let repo = t!(git2::Repository::open(&registry_path()));
+    git::add(&repo);
+    git::commit(&repo);
+}
+
 impl Package {
     pub fn new(name: &str, vers: &str) -> Package {
         init();
@@ -0,0 +1,29 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{execs, project};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn explain_known_warning() {
+    let p = project("foo");
+
+    assert_that!(p.craft_process("explain").arg("-Wunused-variable"),
+                 execs()
+                     .with_status(0)
+                     .with_stdout_contains("-Wunused-variable: A local or static variable is declared but never \
+                                            used."));
+}
+
+#[test]
+fn explain_unknown_warning() {
+    let p = project("foo");
+
+    assert_that!(p.craft_process("explain").arg("no-such-warning"),
+                 execs()
+                     .with_status(0)
+                     .with_stdout_contains("No explanation available for `-Wno-such-warning`, check the \
+                                            documentation of your compiler:"));
+}
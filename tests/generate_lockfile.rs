@@ -0,0 +1,121 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use crafttest::support::{execs, project, ProjectBuilder};
+
+use hamcrest::prelude::*;
+
+fn path_dep_project(root: &str) -> ProjectBuilder {
+    project(root)
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file("bar/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.c", "int bar(void) { return 0; }")
+}
+
+#[test]
+fn path_deps_lockfile_is_location_independent() {
+    let a = path_dep_project("checkout-a");
+    let b = path_dep_project("checkout-b");
+
+    assert_that!(a.craft_process("generate-lockfile"), execs().with_status(0));
+    assert_that!(b.craft_process("generate-lockfile"), execs().with_status(0));
+
+    let lock_a = a.read_lockfile();
+    assert!(!lock_a.contains("source = \"path+"), "{}", lock_a);
+    assert_eq!(lock_a, b.read_lockfile());
+
+    // The lock file of one checkout has to be loadable by the other one
+    File::create(b.root().join("Craft.lock")).unwrap().write_all(lock_a.as_bytes()).unwrap();
+    assert_that!(b.craft("build").arg("--locked"), execs().with_status(0));
+}
+
+#[test]
+fn frozen_rejects_path_override_altering_the_lock_file() {
+    let p = path_dep_project("foo")
+        .file("bar2/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.baz]
+            path = "../baz"
+        "#)
+        .file("bar2/src/lib.c", "int bar(void) { return 0; }")
+        .file("baz/Craft.toml",
+              r#"
+            [package]
+            name = "baz"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("baz/src/lib.c", "int baz(void) { return 0; }");
+
+    assert_that!(p.craft_process("generate-lockfile"), execs().with_status(0));
+
+    fs::create_dir_all(p.root().join(".craft")).unwrap();
+    File::create(p.root().join(".craft/config")).unwrap().write_all(br#"paths = ["bar2"]"#).unwrap();
+
+    assert_that!(p.craft("build").arg("--frozen"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[..]path override for chest `bar` has altered the original list of")
+                     .with_stderr_contains("[..]the lock file doesn't reflect this override but --frozen was passed \
+                                            to prevent updating it"));
+}
+
+#[test]
+fn lockfile_records_its_format_version() {
+    let p = path_dep_project("foo");
+
+    assert_that!(p.craft_process("generate-lockfile"), execs().with_status(0));
+    let lock = p.read_lockfile();
+    assert!(lock.starts_with("version = 1\n\n[root]\n"), "{}", lock);
+
+    assert_that!(p.craft("build").arg("--locked"), execs().with_status(0));
+}
+
+#[test]
+fn lockfile_from_a_future_version_is_regenerated() {
+    let p = path_dep_project("foo");
+
+    assert_that!(p.craft_process("generate-lockfile"), execs().with_status(0));
+    let lock = p.read_lockfile().replace("version = 1\n", "version = 999\n");
+    File::create(p.root().join("Craft.lock")).unwrap().write_all(lock.as_bytes()).unwrap();
+
+    assert_that!(p.craft("build").arg("--locked"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[..]the lock file uses format version 999, but this version of craft \
+                                            only understands up to version 1")
+                     .with_stderr_contains("[..]it can't be regenerated as --locked was passed to prevent updating it"));
+
+    assert_that!(p.craft("build"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Warning] the lock file uses format version 999, but this version of \
+                                            craft only understands up to version 1, so it is ignored and will be \
+                                            regenerated"));
+    assert!(p.read_lockfile().starts_with("version = 1\n"));
+}
@@ -0,0 +1,364 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+extern crate git2;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+use crafttest::support::{execs, git, paths, project, ProjectBuilder};
+
+use hamcrest::prelude::*;
+
+fn dependent(name: &str, dep: &ProjectBuilder) -> ProjectBuilder {
+    project(name).file("Craft.toml",
+              &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#,
+                       dep.url()))
+        .file("src/main.c",
+              r#"
+            int bar(void);
+            int main() { return bar(); }
+        "#)
+}
+
+fn git_dep(name: &str) -> ProjectBuilder {
+    git::new(name, |p| {
+            p.file("Craft.toml",
+                      r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+                .file("src/lib.c", "int bar(void) { return 0; }")
+        })
+        .unwrap()
+}
+
+#[test]
+fn locked_offline_from_complete_cache() {
+    let p = dependent("foo", &git_dep("bar"));
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert_that!(p.craft("build").arg("--locked").arg("--offline"),
+                 execs().with_status(0));
+}
+
+#[test]
+fn locked_offline_with_dependency_missing_from_cache() {
+    let p = dependent("foo", &git_dep("bar"));
+
+    assert_that!(p.craft_process("build").arg("--locked").arg("--offline"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[..]attempting to update a git repository, but --offline was \
+                                            specified"));
+}
+
+#[test]
+fn lockfile_is_reproducible_with_same_name_from_two_sources() {
+    let bar_a = git_dep("bar-a");
+    let bar_b = git_dep("bar-b");
+    let p = project("foo")
+        .file("Craft.toml",
+              &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+
+            [dependencies.baz]
+            path = "baz"
+        "#,
+                       bar_a.url()))
+        .file("src/main.c", "int main() { return 0; }")
+        .file("baz/Craft.toml",
+              &format!(r#"
+            [package]
+            name = "baz"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#,
+                       bar_b.url()))
+        .file("baz/src/lib.c", "int baz(void) { return 0; }");
+
+    assert_that!(p.craft_process("generate-lockfile"), execs().with_status(0));
+    let first = p.read_lockfile();
+    assert!(first.contains(&bar_a.url().to_string()) && first.contains(&bar_b.url().to_string()),
+            "{}",
+            first);
+
+    for _ in 0..3 {
+        assert_that!(p.craft("generate-lockfile"), execs().with_status(0));
+        assert_eq!(first, p.read_lockfile());
+    }
+}
+
+#[test]
+fn locked_detects_changed_replacement() {
+    let bar_a = git_dep("bar-a");
+    let bar_b = git_dep("bar-b");
+    let manifest = |replacement: &ProjectBuilder| {
+        format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+
+            [replace]
+            "bar:0.5.0" = {{ git = '{}' }}
+        "#,
+                replacement.url())
+    };
+    let p = project("foo")
+        .file("Craft.toml", &manifest(&bar_a))
+        .file("src/main.c",
+              r#"
+            int bar(void);
+            int main() { return bar(); }
+        "#)
+        .file("bar/Craft.toml",
+              r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.c", "int bar(void) { return 1; }");
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+
+    File::create(p.root().join("Craft.toml")).unwrap().write_all(manifest(&bar_b).as_bytes()).unwrap();
+    assert_that!(p.craft("build").arg("--locked"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Error] the replacement of `bar v0.5.0 ([..])` changed from \
+                                            `bar v0.5.0 ([..]bar-a[..])` to `bar v0.5.0 ([..]bar-b[..])`, \
+                                            but --locked was passed to prevent updating the lock file"));
+}
+
+fn git_dep_with_submodule() -> ProjectBuilder {
+    let sub = git::new("sub", |p| p.file("sub.h", "#define SUB 1")).unwrap();
+    let dep = git_dep("bar");
+    let repo = git2::Repository::open(&dep.root()).unwrap();
+    git::add_submodule(&repo, &sub.url().to_string(), Path::new("vendor/sub"));
+    git::commit(&repo);
+    dep
+}
+
+fn checked_out_submodule_header() -> bool {
+    let checkouts = paths::home().join(".craft/git/checkouts");
+    fs::read_dir(&checkouts)
+        .unwrap()
+        .flat_map(|ident| fs::read_dir(ident.unwrap().path()).unwrap())
+        .any(|rev| rev.unwrap().path().join("vendor/sub/sub.h").exists())
+}
+
+#[test]
+fn submodules_checked_out_by_default() {
+    let dep = git_dep_with_submodule();
+    let p = dependent("foo", &dep);
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert!(checked_out_submodule_header());
+}
+
+#[test]
+fn submodules_false_skips_submodule_checkout() {
+    let dep = git_dep_with_submodule();
+    let p = project("foo")
+        .file("Craft.toml",
+              &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+            submodules = false
+        "#,
+                       dep.url()))
+        .file("src/main.c",
+              r#"
+            int bar(void);
+            int main() { return bar(); }
+        "#);
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert!(!checked_out_submodule_header());
+    assert!(p.read_lockfile().contains("?submodules=false#"));
+}
+
+#[test]
+fn fetch_with_git_cli_checks_out_requested_rev() {
+    if crafttest::process("git").arg("--version").exec_with_output().is_err() {
+        return;
+    }
+
+    let bar = git_dep("bar");
+    let repo = git2::Repository::open(&bar.root()).unwrap();
+    let first = repo.head().unwrap().target().unwrap();
+    File::create(bar.root().join("src/lib.c")).unwrap().write_all(b"int bar(void) { return 1; }").unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+
+    let p = project("foo")
+        .file("Craft.toml",
+              &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+            rev = '{}'
+        "#,
+                       bar.url(),
+                       first))
+        .file("src/main.c",
+              r#"
+            int bar(void);
+            int main() { return bar(); }
+        "#)
+        .file(".craft/config",
+              r#"
+            [net]
+            git-fetch-with-cli = true
+        "#);
+
+    assert_that!(p.craft_process("run").arg("-v"),
+                 execs().with_status(0).with_stderr_contains("[Running] `git fetch [..]`"));
+    assert!(p.read_lockfile().contains(&format!("#{}", first)), "{}", p.read_lockfile());
+}
+
+#[test]
+fn vendored_git_dependency_builds_without_the_repository() {
+    let bar = git_dep("bar");
+    let p = dependent("foo", &bar);
+
+    assert_that!(p.craft_process("vendor"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[..]Vendoring bar v0.5.0 ([..])")
+                     .with_stdout_contains("replace-with = \"vendored-sources\"")
+                     .with_stdout_contains("directory = \"vendor\""));
+    assert!(p.root().join("vendor/bar-0.5.0/src/lib.c").is_file());
+
+    let mut checksum = String::new();
+    File::open(p.root().join("vendor/bar-0.5.0/.craft-checksum.json"))
+        .unwrap()
+        .read_to_string(&mut checksum)
+        .unwrap();
+    assert!(checksum.contains("\"package\":null"), "{}", checksum);
+
+    let config = p.craft("vendor").exec_with_output().unwrap().stdout;
+    fs::create_dir_all(p.root().join(".craft")).unwrap();
+    File::create(p.root().join(".craft/config")).unwrap().write_all(&config).unwrap();
+    fs::remove_dir_all(bar.root()).unwrap();
+    fs::remove_dir_all(paths::home().join(".craft/git")).unwrap();
+
+    assert_that!(p.craft("build").arg("--frozen"), execs().with_status(0));
+}
+
+fn patched(dep: &ProjectBuilder, version: &str) -> ProjectBuilder {
+    project("foo")
+        .file("Craft.toml",
+              &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{url}'
+            version = "0.5"
+
+            [patch.'{url}']
+            bar = {{ path = "bar" }}
+        "#,
+                       url = dep.url()))
+        .file("src/main.c",
+              r#"
+            int bar_patched(void);
+            int main() { return bar_patched(); }
+        "#)
+        .file("bar/Craft.toml",
+              &format!(r#"
+            [package]
+            name = "bar"
+            version = "{}"
+            authors = []
+        "#,
+                       version))
+        .file("bar/src/lib.c", "int bar_patched(void) { return 0; }")
+}
+
+#[test]
+fn patch_overrides_git_dependency() {
+    let p = patched(&git_dep("bar"), "0.5.1");
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert_that!(p.process(&p.bin("foo")), execs().with_status(0));
+}
+
+#[test]
+fn unused_patch_warns() {
+    let p = patched(&git_dep("bar"), "0.6.0");
+
+    assert_that!(p.craft_process("build"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("\
+[Warning] patch `bar v0.6.0 ([..])` for `[..]` was not used in the chest graph
+its version doesn't match any requirement on `bar`"));
+}
+
+#[test]
+fn update_dry_run_keeps_the_lock_file() {
+    let bar = git_dep("bar");
+    let p = dependent("foo", &bar);
+    assert_that!(p.craft_process("generate-lockfile"), execs().with_status(0));
+    let lockfile = p.read_lockfile();
+
+    let repo = git2::Repository::open(&bar.root()).unwrap();
+    File::create(bar.root().join("src/lib.c")).unwrap().write_all(b"int bar(void) { return 1; }").unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+
+    assert_that!(p.craft("update").arg("-p").arg("bar").arg("--dry-run"),
+                 execs().with_status(0).with_stderr_contains("\
+[Updating] bar v0.5.0 ([..]) -> #[..]
+[Warning] not updating the lock file due to dry run"));
+    assert_eq!(p.read_lockfile(), lockfile);
+}
+
+#[test]
+fn update_precise_requires_a_package() {
+    let p = dependent("foo", &git_dep("bar"));
+    assert_that!(p.craft_process("generate-lockfile"), execs().with_status(0));
+
+    assert_that!(p.craft("update").arg("--precise").arg("0.5.0"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr("[Error] `--precise` requires a package to update, pass it with `-p`"));
+}
@@ -0,0 +1,42 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use std::env;
+
+use crafttest::support::{execs, paths, project};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn list_installed_packages_as_json() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [[bin]]
+            name = "foo"
+            path = "src/foo.c"
+
+            [[bin]]
+            name = "bar"
+            path = "src/bar.c"
+        "#)
+        .file("src/foo.c", "int main() { return 0; }")
+        .file("src/bar.c", "int main() { return 0; }");
+    let root = paths::root().join("install-root");
+
+    assert_that!(p.craft_process("install").arg("--path").arg(p.root()).arg("--root").arg(&root),
+                 execs().with_status(0));
+    assert!(root.join("bin").join(format!("foo{}", env::consts::EXE_SUFFIX)).is_file());
+
+    assert_that!(p.craft("install").arg("--list").arg("--root").arg(&root).arg("--message-format").arg("json"),
+                 execs().with_status(0).with_json(&format!(r#"
+                    [{{"package": "foo", "version": "0.5.0", "bins": ["bar{0}", "foo{0}"]}}]
+                 "#,
+                                                          env::consts::EXE_SUFFIX)));
+}
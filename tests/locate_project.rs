@@ -0,0 +1,37 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{execs, project};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn plain_message_format() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    let manifest = p.root().join("Craft.toml");
+    assert_that!(p.craft_process("locate-project").arg("--message-format").arg("plain"),
+                 execs().with_status(0).with_stdout(format!("{}\n", manifest.display())));
+    assert_that!(p.craft("locate-project").cwd(p.root().join("src")),
+                 execs().with_status(0).with_json(&format!(r#"{{"root":"{}"}}"#, manifest.display())));
+}
+
+#[test]
+fn plain_message_format_without_manifest() {
+    let p = project("foo").file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("locate-project").arg("--message-format").arg("plain"),
+                 execs()
+                     .with_status(101)
+                     .with_stdout("")
+                     .with_stderr_contains("[Error] could not find `Craft.toml` in `[..]` or any parent directory"));
+}
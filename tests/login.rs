@@ -0,0 +1,62 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+use crafttest::support::{execs, paths, project};
+
+use hamcrest::prelude::*;
+
+fn credentials() -> String {
+    let mut contents = String::new();
+    File::open(paths::home().join(".craft/credentials")).unwrap().read_to_string(&mut contents).unwrap();
+    contents
+}
+
+#[test]
+fn login_stores_token() {
+    let p = project("foo");
+
+    assert_that!(p.craft_process("login").arg("s3cr3t"), execs().with_status(0));
+    assert!(credentials().contains(r#"token = "s3cr3t""#), "{}", credentials());
+}
+
+#[cfg(unix)]
+#[test]
+fn credentials_are_only_readable_by_owner() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = project("foo");
+
+    assert_that!(p.craft_process("login").arg("s3cr3t"), execs().with_status(0));
+    let meta = fs::metadata(paths::home().join(".craft/credentials")).unwrap();
+    assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+}
+
+#[test]
+fn login_overwrites_token_with_warning() {
+    let p = project("foo");
+
+    assert_that!(p.craft_process("login").arg("first"), execs().with_status(0));
+    assert_that!(p.craft_process("login").arg("second"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr("[Warning] overwriting the registry token stored in `[..]credentials`\n"));
+    assert!(credentials().contains(r#"token = "second""#), "{}", credentials());
+    assert!(!credentials().contains("first"), "{}", credentials());
+}
+
+#[test]
+fn token_prompt_goes_to_stderr() {
+    let p = project("foo");
+
+    assert_that!(p.craft_process("login"),
+                 execs()
+                     .with_status(101)
+                     .with_stdout("")
+                     .with_stderr_contains("[Login] please paste the API token below")
+                     .with_stderr_contains("[Error] the API token must not be empty"));
+}
@@ -0,0 +1,99 @@
+extern crate crafttest;
+extern crate rustc_serialize;
+
+use crafttest::support::project;
+use rustc_serialize::json::Json;
+
+#[test]
+fn workspace_root_and_members() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    let output = p.craft_process("metadata").arg("--no-deps").exec_with_output().unwrap();
+    let metadata = Json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+
+    let root = metadata.find("workspace_root").and_then(|r| r.as_string()).unwrap();
+    assert_eq!(root, p.root().display().to_string());
+
+    let members = metadata.find("workspace_members").and_then(|m| m.as_array()).unwrap();
+    assert_eq!(members.len(), 1);
+    assert!(members[0].as_string().unwrap().starts_with("foo 0.5.0 (path+file://"));
+}
+
+#[test]
+fn package_and_workspace_metadata_passthrough() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [package.metadata.tool]
+            level = 3
+            paths = ["a", "b"]
+
+            [workspace]
+
+            [workspace.metadata.tool]
+            enabled = true
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    let output = p.craft_process("metadata").arg("--no-deps").exec_with_output().unwrap();
+    let metadata = Json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+
+    let package = &metadata.find("packages").and_then(|p| p.as_array()).unwrap()[0];
+    let tool = package.find_path(&["metadata", "tool"]).unwrap();
+    assert_eq!(*tool, Json::from_str(r#"{"level": 3, "paths": ["a", "b"]}"#).unwrap());
+
+    let tool = metadata.find_path(&["workspace_metadata", "tool"]).unwrap();
+    assert_eq!(*tool, Json::from_str(r#"{"enabled": true}"#).unwrap());
+}
+
+#[test]
+fn format_version_and_targets() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo-bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    let output = p.craft_process("metadata").arg("--no-deps").exec_with_output().unwrap();
+    let metadata = Json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+
+    assert_eq!(metadata.find("format_version").and_then(|v| v.as_u64()), Some(2));
+    let target_dir = metadata.find("target_directory").and_then(|d| d.as_string()).unwrap();
+    assert_eq!(target_dir, p.build_dir().display().to_string());
+
+    let package = &metadata.find("packages").and_then(|p| p.as_array()).unwrap()[0];
+    let target = &package.find("targets").and_then(|t| t.as_array()).unwrap()[0];
+    assert_eq!(*target,
+               Json::from_str(&format!(r#"{{"kind": ["bin"], "name": "foo-bar", "chest_name": "foo_bar",
+                                         "src_path": {}}}"#,
+                                       Json::String(p.root().join("src/main.c").display().to_string())))
+                   .unwrap());
+
+    let output = p.craft("metadata").arg("--no-deps").arg("--format-version").arg("1").exec_with_output().unwrap();
+    let metadata = Json::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    assert_eq!(metadata.find("version").and_then(|v| v.as_u64()), Some(1));
+    assert!(metadata.find("target_directory").is_some());
+
+    let output = p.craft("metadata").arg("--no-deps").arg("--format-version").arg("3").exec_with_output();
+    let stderr = String::from_utf8(output.unwrap_err().output.unwrap().stderr).unwrap();
+    assert!(stderr.contains("metadata version 3 not supported, only 1 to 2 are currently supported"),
+            "{}",
+            stderr);
+}
@@ -2,6 +2,7 @@
 extern crate hamcrest;
 extern crate craft;
 extern crate crafttest;
+extern crate git2;
 extern crate tempdir;
 
 use std::fs::{self, File};
@@ -10,7 +11,7 @@ use std::io::prelude::*;
 use craft::util::ProcessBuilder;
 
 use crafttest::process;
-use crafttest::support::{execs, paths};
+use crafttest::support::{execs, git, paths};
 
 use hamcrest::prelude::*;
 use tempdir::TempDir;
@@ -357,3 +358,134 @@ Usage:
     craft new [..]
 "));
 }
+
+#[test]
+fn with_config() {
+    assert_that!(craft_process("new").arg("--bin").arg("foo").arg("--with-config").arg("--vcs").arg("none")
+                     .env("USER", "foo"),
+                 execs().with_status(0));
+
+    let config = paths::root().join("foo/.craft/config");
+    let mut contents = String::new();
+    File::open(&config).unwrap().read_to_string(&mut contents).unwrap();
+    for key in &["jobs", "target-dir", "cflags"] {
+        assert!(contents.contains(&format!("# {} = ", key)), "missing `{}`:\n{}", key, contents);
+    }
+    assert_that!(&paths::root().join("foo/.gitignore"), is_not(existing_file()));
+
+    // All of the suggested values have to be valid once uncommented
+    let uncommented = contents.lines()
+        .map(|line| if line.contains(" = ") { line.trim_left_matches("# ") } else { line })
+        .collect::<Vec<_>>()
+        .join("\n");
+    File::create(&config).unwrap().write_all(uncommented.as_bytes()).unwrap();
+    assert_that!(craft_process("build").arg("-v").cwd(&paths::root().join("foo")),
+                 execs().with_status(0).with_stderr_contains("[Running] `cc [..]-Wall -Wextra[..]`"));
+}
+
+#[test]
+fn cpp_bin() {
+    assert_that!(craft_process("new").arg("--bin").arg("foo").arg("--lang").arg("cpp").arg("--vcs").arg("none")
+                     .env("USER", "foo"),
+                 execs().with_status(0));
+
+    assert_that!(&paths::root().join("foo/src/main.cpp"), existing_file());
+    assert_that!(&paths::root().join("foo/src/main.c"), is_not(existing_file()));
+    let mut contents = String::new();
+    File::open(&paths::root().join("foo/Craft.toml")).unwrap().read_to_string(&mut contents).unwrap();
+    assert!(contents.contains("language = \"cpp\""), "{}", contents);
+
+    assert_that!(craft_process("build").arg("-v").cwd(&paths::root().join("foo")),
+                 execs().with_status(0).with_stderr_contains("[Running] `c++ [..]`"));
+}
+
+#[test]
+fn cpp_keyword_name() {
+    assert_that!(craft_process("new").arg("class").arg("--lang").arg("cpp"),
+                 execs().with_status(101).with_stderr("\
+[Error] The name `class` cannot be used as a chest name\nuse --name to override chest name"));
+
+    assert_that!(craft_process("new").arg("class").arg("--vcs").arg("none").env("USER", "foo"),
+                 execs().with_status(0));
+}
+
+fn template_repo() -> git::RepoBuilder {
+    git::repo(&paths::root().join("template"))
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "{{name}}"
+            version = "0.1.0"
+            authors = ["{{authors}}"]
+        "#)
+        .file("src/main.c", "int main() { return 0; }\n")
+        .file("src/{{name}}.h", "/* {{name}} by {{authors}} */\n")
+        .file(".gitignore", "/target\n/build\n")
+}
+
+#[test]
+fn local_template() {
+    template_repo();
+
+    assert_that!(craft_process("new").arg("bar").arg("--template").arg("template").cwd(&paths::root())
+                     .env("USER", "foo"),
+                 execs().with_status(0));
+
+    let mut contents = String::new();
+    File::open(&paths::root().join("bar/src/bar.h")).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "/* bar by foo */\n");
+    assert_that!(&paths::root().join("bar/.git"), existing_dir());
+
+    // The ignore file of the template is kept as is
+    let mut contents = String::new();
+    File::open(&paths::root().join("bar/.gitignore")).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "/target\n/build\n");
+
+    assert_that!(craft_process("build").cwd(&paths::root().join("bar")),
+                 execs().with_status(0));
+}
+
+#[test]
+fn git_template() {
+    let repo = template_repo();
+    repo.build();
+
+    assert_that!(craft_process("new").arg("bar").arg("--template").arg(repo.url().to_string())
+                     .arg("--vcs").arg("none").cwd(&paths::root()).env("USER", "foo"),
+                 execs().with_status(0));
+
+    assert_that!(&paths::root().join("bar/src/bar.h"), existing_file());
+    assert_that!(&paths::root().join("bar/.craft-ok"), is_not(existing_file()));
+    assert_that!(&paths::root().join("bar/.git"), is_not(existing_dir()));
+    assert_that!(craft_process("build").cwd(&paths::root().join("bar")),
+                 execs().with_status(0));
+}
+
+#[test]
+fn git_template_uses_the_default_branch_of_the_remote() {
+    let repo = template_repo();
+    repo.build();
+
+    // The only branch is `main`, which HEAD points to
+    let git = git2::Repository::open(repo.root()).unwrap();
+    let head = git.head().unwrap().peel_to_commit().unwrap();
+    git.branch("main", &head, false).unwrap();
+    git.set_head("refs/heads/main").unwrap();
+    git.find_branch("master", git2::BranchType::Local).unwrap().delete().unwrap();
+
+    assert_that!(craft_process("new").arg("bar").arg("--template").arg(repo.url().to_string())
+                     .arg("--vcs").arg("none").cwd(&paths::root()).env("USER", "foo"),
+                 execs().with_status(0));
+    assert_that!(&paths::root().join("bar/src/bar.h"), existing_file());
+}
+
+#[test]
+fn template_without_manifest_warns() {
+    fs::create_dir_all(paths::root().join("template/src")).unwrap();
+    File::create(paths::root().join("template/src/main.c")).unwrap();
+
+    assert_that!(craft_process("new").arg("bar").arg("--template").arg("template").arg("--vcs").arg("none")
+                     .cwd(&paths::root()).env("USER", "foo"),
+                 execs().with_status(0).with_stderr_contains("\
+[Warning] compiling this new chest may not work due to invalid workspace configuration"));
+}
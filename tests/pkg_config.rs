@@ -0,0 +1,107 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{execs, project};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn builds_against_zlib() {
+    if crafttest::process("pkg-config").arg("--exists").arg("zlib").exec_with_output().is_err() {
+        return;
+    }
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            z = { pkg-config = "zlib >= 1.0" }
+        "#)
+        .file("src/main.c",
+              r#"
+            #include <string.h>
+            #include <zlib.h>
+            int main() {
+                return strlen(zlibVersion()) > 0 ? 0 : 1;
+            }
+        "#);
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert_that!(p.process(&p.bin("foo")), execs().with_status(0));
+}
+
+#[test]
+fn missing_system_library() {
+    if crafttest::process("pkg-config").arg("--version").exec_with_output().is_err() {
+        return;
+    }
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            nope = { pkg-config = "craft-missing-library >= 1.2" }
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build"),
+                 execs().with_status(101).with_stderr_contains("\
+[..]failed to find the system library `craft-missing-library` (>= 1.2) with `pkg-config`, \
+which `foo` depends on"));
+}
+
+#[test]
+fn pkg_config_with_version() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            z = { pkg-config = "zlib", version = "1.2" }
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Error] failed to parse manifest at `[..]Craft.toml`")
+                     .with_stderr_contains("[..]dependency (z) specification is ambiguous. `pkg-config` can't be \
+                                            combined with `version`, `path` or `git`"));
+}
+
+#[test]
+fn link_requires_pkg_config() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar", link = "static" }
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("build"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[..]dependency (bar) specifies `link`, which is only supported for \
+                                            `pkg-config` dependencies"));
+}
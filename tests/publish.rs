@@ -0,0 +1,133 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{execs, git, paths, project};
+use crafttest::support::http::{Response, Server};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn unpublishable_package() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            publish = false
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("publish").arg("--token").arg("sekrit"),
+                 execs().with_status(101).with_stderr("\
+[Error] some chests cannot be published.
+`foo` is marked as unpublishable
+"));
+}
+
+#[test]
+fn dry_run_stops_before_uploading() {
+    let index = git::repo(&paths::root().join("index"))
+        .file("config.json", r#"{"dl": "file:///nowhere/dl", "api": "file:///nowhere/api"}"#);
+    index.build();
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            homepage = "https://example.com"
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("publish")
+                     .arg("--index")
+                     .arg(index.url().to_string())
+                     .arg("--token")
+                     .arg("sekrit")
+                     .arg("--no-verify")
+                     .arg("--dry-run"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Packaging] foo v0.5.0 ([..])")
+                     .with_stderr_contains("[Uploading] foo v0.5.0 ([..])")
+                     .with_stderr_contains("[Warning] aborting upload due to dry run"));
+}
+
+#[test]
+fn publish_requires_a_registry() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("publish").arg("--token").arg("sekrit"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr("[Error] no registry to publish to, pass `--index` or set `registry.index`\n"));
+}
+
+#[test]
+fn upload_is_retried_in_full_without_logging_the_token() {
+    let mut calls = 0;
+    let server = Server::new(move |_| {
+        calls += 1;
+        if calls == 1 {
+            Response::new(503, b"")
+        } else {
+            Response::new(200, b"{}")
+        }
+    });
+    let index = git::repo(&paths::root().join("index"))
+        .file("config.json",
+              &format!(r#"{{"dl": "{0}/dl", "api": "{0}"}}"#, server.url()));
+    index.build();
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            homepage = "https://example.com"
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file(".craft/config",
+              r#"
+            [net]
+            retry-delay = 0
+        "#);
+
+    let output = p.craft_process("publish")
+        .arg("-vv")
+        .arg("--index")
+        .arg(index.url().to_string())
+        .arg("--token")
+        .arg("sekrit")
+        .arg("--no-verify")
+        .exec_with_output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("[Published] foo v0.5.0"), "{}", stderr);
+    assert!(!stderr.contains("sekrit"), "{}", stderr);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(!requests[0].body.is_empty());
+    assert_eq!(requests[0].body, requests[1].body);
+    assert_eq!(requests[1].header("Authorization"), Some("sekrit"));
+}
@@ -0,0 +1,309 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+
+use crafttest::support::{execs, paths, project, ProjectBuilder};
+use crafttest::support::http::{Request, Response, Server};
+use crafttest::support::registry::{self, Package};
+
+use hamcrest::prelude::*;
+
+// Publishes `bar` to the test registry and returns the contents of its chest.
+fn publish_bar() -> Vec<u8> {
+    let bar = Package::new("bar", "0.1.0");
+    bar.publish();
+    let mut chest = Vec::new();
+    File::open(bar.archive_dst()).unwrap().read_to_end(&mut chest).unwrap();
+    chest
+}
+
+// Answers with the chest the registry has at the path of `request`
+fn serve_chest(request: &Request) -> Response {
+    let path = registry::dl_path().join(&request.path["/dl/".len()..]);
+    let mut chest = Vec::new();
+    match File::open(&path) {
+        Ok(mut file) => {
+            file.read_to_end(&mut chest).unwrap();
+            Response::new(200, &chest)
+        }
+        Err(..) => Response::new(404, b""),
+    }
+}
+
+// Answers with the index file or chest the registry has at the path of
+// `request`, like a sparse registry
+fn serve_sparse(request: &Request) -> Response {
+    if !request.path.starts_with("/index/") {
+        return serve_chest(request);
+    }
+    let path = registry::registry_path().join(&request.path["/index/".len()..]);
+    let mut contents = Vec::new();
+    match File::open(&path) {
+        Ok(mut file) => {
+            file.read_to_end(&mut contents).unwrap();
+            Response::new(200, &contents)
+        }
+        Err(..) => Response::new(404, b""),
+    }
+}
+
+// A project depending on `bar`, which is taken from the `registry` source
+// serving its downloads from `server` in place of a git repository.
+fn registry_dependency(server: &Server, registry: &str, config: &str) -> ProjectBuilder {
+    registry::serve_from(server.url());
+    project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = "https://example.com/bar"
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file(".craft/config",
+              &format!(r#"
+            [source.bar-git]
+            git = "https://example.com/bar"
+            replace-with = "{}"
+
+            [net]
+            retry-delay = 0
+
+            {}
+        "#,
+                       registry,
+                       config))
+}
+
+// The partial downloads left behind in the registry cache
+fn partial_downloads() -> Vec<PathBuf> {
+    let cache = paths::home().join(".craft/registry/cache");
+    fs::read_dir(&cache)
+        .unwrap()
+        .flat_map(|dir| fs::read_dir(dir.unwrap().path()).unwrap())
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().map_or(false, |e| e == "part"))
+        .collect()
+}
+
+#[test]
+fn resumes_interrupted_downloads() {
+    let chest = publish_bar();
+    let server = {
+        let chest = chest.clone();
+        Server::new(move |request| {
+            match request.header("Range") {
+                None => Response::new(200, &chest[..10]).truncated(chest.len()),
+                Some(range) => {
+                    assert_eq!(range, "bytes=10-");
+                    Response::new(206, &chest[10..])
+                }
+            }
+        })
+    };
+    let p = registry_dependency(&server, "dummy-registry", "");
+
+    assert_that!(p.craft_process("fetch"), execs().with_status(101));
+    let part = partial_downloads();
+    assert_eq!(part.len(), 1);
+    assert_eq!(fs::metadata(&part[0]).unwrap().len(), 10);
+
+    assert_that!(p.craft("fetch"), execs().with_status(0));
+    assert_eq!(server.requests().len(), 2);
+    assert!(partial_downloads().is_empty());
+}
+
+#[test]
+fn error_pages_are_not_part_of_the_download() {
+    let chest = publish_bar();
+    let mut calls = 0;
+    let server = Server::new(move |_| {
+        calls += 1;
+        if calls == 1 {
+            Response::new(503, b"<html>try again later</html>")
+        } else {
+            Response::new(200, &chest)
+        }
+    });
+    let p = registry_dependency(&server, "dummy-registry", "");
+
+    assert_that!(p.craft_process("fetch"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Warning] spurious network error (2 tries remaining): [..] 503"));
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].header("Range"), None);
+}
+
+#[test]
+fn flaky_servers_are_retried_until_they_answer() {
+    let chest = publish_bar();
+    let mut calls = 0;
+    let server = Server::new(move |_| {
+        calls += 1;
+        match calls {
+            1 => Response::new(500, b""),
+            2 => Response::new(502, b""),
+            _ => Response::new(200, &chest),
+        }
+    });
+    let p = registry_dependency(&server, "dummy-registry", "");
+
+    assert_that!(p.craft_process("fetch"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Warning] spurious network error (2 tries remaining): [..] 500")
+                     .with_stderr_contains("[Warning] spurious network error (1 tries remaining): [..] 502"));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn servers_ignoring_the_range_restart_the_download() {
+    let chest = publish_bar();
+    let mut calls = 0;
+    let server = Server::new(move |_| {
+        calls += 1;
+        if calls == 1 {
+            Response::new(200, &chest[..10]).truncated(chest.len())
+        } else {
+            Response::new(200, &chest)
+        }
+    });
+    let p = registry_dependency(&server, "dummy-registry", "");
+
+    assert_that!(p.craft_process("fetch"), execs().with_status(101));
+    assert_that!(p.craft("fetch"), execs().with_status(0));
+
+    // The whole file is taken as is, without starting over once more
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].header("Range"), Some("bytes=10-"));
+    assert!(partial_downloads().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn credential_process_token_is_sent_but_never_logged() {
+    Package::new("baz", "0.1.0").publish();
+    Package::new("bar", "0.1.0").dep("baz", "*").publish();
+    let server = Server::new(serve_chest);
+    let log = paths::root().join("credential-process.log");
+    let p = registry_dependency(&server,
+                                "dummy-registry",
+                                &format!(r#"
+            [registries.private]
+            index = "{}"
+            credential-process = ["sh", "-c", "echo run >> {}; echo s3cr3t"]
+        "#,
+                                         registry::registry(),
+                                         log.display()));
+
+    let output = p.craft_process("fetch").arg("-vv").exec_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("s3cr3t"), "{}", stderr);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    for request in requests.iter() {
+        assert_eq!(request.header("Authorization"), Some("s3cr3t"));
+    }
+
+    // Once per session, not once per download
+    let mut runs = String::new();
+    File::open(&log).unwrap().read_to_string(&mut runs).unwrap();
+    assert_eq!(runs.lines().count(), 1);
+}
+
+#[test]
+fn curl_traces_transfers_only_when_extra_verbose() {
+    publish_bar();
+    let server = Server::new(serve_chest);
+    let p = registry_dependency(&server, "dummy-registry", "");
+    p.build();
+
+    let output = p.craft("fetch").arg("-v").exec_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("> GET /dl/bar/0.1.0/download"), "{}", stderr);
+
+    fs::remove_dir_all(paths::home().join(".craft/registry/cache")).unwrap();
+    assert_that!(p.craft("fetch").arg("-vv"),
+                 execs().with_status(0).with_stderr_contains("> GET /dl/bar/0.1.0/download HTTP/1.1"));
+}
+
+#[test]
+fn sparse_registry_fetches_only_the_needed_index_files() {
+    Package::new("baz", "0.1.0").publish();
+    Package::new("bar", "0.1.0").dep("baz", "*").publish();
+    Package::new("unused", "0.1.0").publish();
+    let server = Server::new(serve_sparse);
+    let p = registry_dependency(&server,
+                                "sparse",
+                                &format!(r#"
+            [source.sparse]
+            registry = "sparse+{}/index"
+        "#,
+                                         server.url()));
+
+    assert_that!(p.craft_process("fetch"),
+                 execs().with_status(0).with_stderr_contains("[Updating] registry `[..]/index`"));
+    let mut paths = server.requests().into_iter().map(|r| r.path).collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(paths,
+               vec!["/dl/bar/0.1.0/download",
+                    "/dl/baz/0.1.0/download",
+                    "/index/3/b/bar",
+                    "/index/3/b/baz",
+                    "/index/config.json"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn sparse_registry_sends_the_token_for_the_index() {
+    Package::new("bar", "0.1.0").publish();
+    let server = Server::new(serve_sparse);
+    let p = registry_dependency(&server,
+                                "sparse",
+                                &format!(r#"
+            [source.sparse]
+            registry = "sparse+{0}/index"
+
+            [registries.private]
+            index = "sparse+{0}/index"
+            credential-process = ["echo", "s3cr3t"]
+        "#,
+                                         server.url()));
+
+    assert_that!(p.craft_process("fetch"), execs().with_status(0));
+    let requests = server.requests();
+    assert!(requests.iter().any(|r| r.path.starts_with("/index/")));
+    for request in requests.iter() {
+        assert_eq!(request.header("Authorization"), Some("s3cr3t"), "{}", request.path);
+    }
+}
+
+#[test]
+fn update_precise_to_a_version_the_registry_lacks() {
+    publish_bar();
+    let server = Server::new(serve_chest);
+    let p = registry_dependency(&server, "dummy-registry", "");
+    assert_that!(p.craft_process("generate-lockfile"), execs().with_status(0));
+    let lockfile = p.read_lockfile();
+
+    assert_that!(p.craft("update").arg("-p").arg("bar").arg("--precise").arg("0.9.0"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Error] version 0.9.0 of `bar` was not found in registry [..]"));
+    assert_that!(p.craft("update").arg("-p").arg("bar").arg("--precise").arg("latest"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Error] invalid version `latest` passed to `--precise`"));
+    assert_eq!(p.read_lockfile(), lockfile);
+}
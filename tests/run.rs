@@ -0,0 +1,164 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{execs, project};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn run_with_env_and_cwd() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c",
+              r#"
+            #include <stdio.h>
+            #include <stdlib.h>
+            #include <unistd.h>
+            int main() {
+                char cwd[4096];
+                printf("greeting: %s\n", getenv("GREETING"));
+                printf("cwd: %s\n", getcwd(cwd, sizeof(cwd)));
+                return 0;
+            }
+        "#)
+        .file("sub/data.txt", "");
+
+    assert_that!(p.craft_process("run").arg("--env").arg("GREETING=hello").arg("--cwd").arg("sub"),
+                 execs()
+                     .with_status(0)
+                     .with_stdout_contains("greeting: hello")
+                     .with_stdout_contains("cwd: [..][/]foo[/]sub"));
+}
+
+#[test]
+fn run_with_missing_cwd() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("run").arg("--cwd").arg("missing"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[..]the `--cwd` directory `[..]missing` does not exist"));
+}
+
+#[test]
+fn run_lib_test() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [lib]
+            harness = false
+        "#)
+        .file("src/lib.c",
+              r#"
+            #include <stdio.h>
+            int foo(void) { return 0; }
+            int main() { puts("lib tests ran"); return foo(); }
+        "#);
+
+    assert_that!(p.craft_process("run").arg("--lib-test"),
+                 execs().with_status(0)
+                     .with_stderr_contains("[Running] `[..]foo-[..]`")
+                     .with_stdout_contains("lib tests ran"));
+}
+
+#[test]
+fn run_lib_test_rejects_bin() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/lib.c", "int foo(void) { return 0; }");
+
+    assert_that!(p.craft_process("run").arg("--lib-test").arg("--bin").arg("foo"),
+                 execs().with_status(101)
+                     .with_stderr_contains("[Error] `--lib-test` can't be combined with `--bin` or `--example`"));
+}
+
+#[test]
+fn run_example() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [[example]]
+            name = "hello"
+
+            [[example]]
+            name = "bye"
+            path = "examples/other/bye.c"
+        "#)
+        .file("src/main.c", "int main() { return 1; }")
+        .file("examples/hello.c",
+              r#"
+            #include <stdio.h>
+            int main() { printf("hello\n"); return 0; }
+        "#)
+        .file("examples/other/bye.c",
+              r#"
+            #include <stdio.h>
+            int main() { printf("bye\n"); return 0; }
+        "#);
+
+    assert_that!(p.craft_process("run").arg("--example").arg("hello"),
+                 execs().with_status(0).with_stdout("hello\n"));
+    assert_that!(p.craft("run").arg("--example").arg("bye"),
+                 execs().with_status(0).with_stdout("bye\n"));
+
+    assert_that!(p.craft("run").arg("--example").arg("nope"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Error] no example target named `nope`, available example targets:")
+                     .with_stderr_contains("    bye")
+                     .with_stderr_contains("    hello"));
+}
+
+#[test]
+fn run_requires_bin_with_multiple_bins() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/bin/a.c", "int main() { return 0; }")
+        .file("src/bin/b.c", "int main() { return 3; }");
+
+    assert_that!(p.craft_process("run"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[..]use the `--bin` option to specify which one to run, available bin \
+                                            targets:")
+                     .with_stderr_contains("    a")
+                     .with_stderr_contains("    b"));
+    assert_that!(p.craft("run").arg("--bin").arg("b"), execs().with_status(3));
+}
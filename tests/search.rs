@@ -0,0 +1,89 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{execs, git, paths, project};
+use crafttest::support::http::{Response, Server};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn search_requires_a_registry() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("search").arg("zlib"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr("[Error] no registry to search, pass `--index` or set `registry.index`\n"));
+}
+
+#[test]
+fn search_offline() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }");
+
+    assert_that!(p.craft_process("search")
+                     .arg("zlib")
+                     .arg("--index")
+                     .arg("https://example.com/index")
+                     .arg("--offline"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[..]attempting to make an HTTP request, but --offline was specified"));
+}
+
+#[test]
+fn search_retries_with_a_fresh_response() {
+    let mut calls = 0;
+    let server = Server::new(move |_| {
+        calls += 1;
+        if calls == 1 {
+            Response::new(503, b"<html>try again later</html>")
+        } else {
+            Response::new(200,
+                          br#"{"chests": [{"name": "zlib", "max_version": "1.2.11", "description": "zlib"}],
+                               "meta": {"total": 1}}"#)
+        }
+    });
+    let index = git::repo(&paths::root().join("index"))
+        .file("config.json",
+              &format!(r#"{{"dl": "{0}/dl", "api": "{0}"}}"#, server.url()));
+    index.build();
+
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/main.c", "int main() { return 0; }")
+        .file(".craft/config",
+              r#"
+            [net]
+            retry-delay = 0
+        "#);
+
+    assert_that!(p.craft_process("search").arg("zlib").arg("--index").arg(index.url().to_string()),
+                 execs()
+                     .with_status(0)
+                     .with_stdout("zlib  1.2.11  zlib\n")
+                     .with_stderr_contains("[Warning] spurious network error (2 tries remaining): [..] 503"));
+    assert_eq!(server.requests().len(), 2);
+}
@@ -0,0 +1,43 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{diamond, execs};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn prints_dependency_tree() {
+    let p = diamond(None);
+
+    assert_that!(p.craft_process("tree"),
+                 execs().with_status(0).with_stdout("\
+foo v0.5.0 ([..])
+├── bar v0.5.0 ([..])
+│   └── baz v0.5.0 ([..])
+│       └── qux v0.5.0 ([..])
+└── baz v0.5.0 ([..]) (*)
+"));
+}
+
+#[test]
+fn prints_inverted_dependency_tree() {
+    let p = diamond(None);
+
+    assert_that!(p.craft_process("tree").arg("--invert").arg("qux"),
+                 execs().with_status(0).with_stdout("\
+qux v0.5.0 ([..])
+└── baz v0.5.0 ([..])
+    ├── bar v0.5.0 ([..])
+    │   └── foo v0.5.0 ([..])
+    └── foo v0.5.0 ([..])
+"));
+}
+
+#[test]
+fn quiet_prints_nothing() {
+    let p = diamond(None);
+
+    assert_that!(p.craft_process("tree").arg("-q"),
+                 execs().with_status(0).with_stdout(""));
+}
@@ -0,0 +1,57 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{diamond, execs, path_deps_manifest, project};
+
+use hamcrest::prelude::*;
+
+#[test]
+fn prints_every_path_to_a_package() {
+    let p = diamond(Some("0.5"));
+
+    assert_that!(p.craft_process("why").arg("qux"),
+                 execs().with_status(0).with_stdout("\
+qux v0.5.0 ([..])
+  foo v0.5.0 -> bar v0.5.0 (*) -> baz v0.5.0 (*) -> qux v0.5.0 ([..]0.5, binding)
+  foo v0.5.0 -> baz v0.5.0 (*) -> qux v0.5.0 ([..]0.5, binding)
+"));
+}
+
+#[test]
+fn version_has_to_be_part_of_the_graph() {
+    let p = diamond(Some("0.5"));
+
+    assert_that!(p.craft_process("why").arg("qux:0.6.0"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr("[Error] package `qux:0.6.0` is not part of the dependency graph"));
+}
+
+#[test]
+fn caps_the_paths_of_stacked_diamonds() {
+    // foo -> a1/b1 -> a2/b2 -> ... -> a6/b6 -> qux, that's 2^6 paths
+    let mut p = project("foo")
+        .file("Craft.toml", &path_deps_manifest("foo", &[("a1", "a1"), ("b1", "b1")]))
+        .file("src/main.c", "int main() { return 0; }")
+        .file("qux/Craft.toml", &path_deps_manifest("qux", &[]))
+        .file("qux/src/lib.c", "int qux(void) { return 0; }");
+    for level in 1..7 {
+        let next = if level == 6 {
+            vec![("qux".to_string(), "../qux".to_string())]
+        } else {
+            ["a", "b"].iter().map(|n| (format!("{}{}", n, level + 1), format!("../{}{}", n, level + 1))).collect()
+        };
+        let deps = next.iter().map(|&(ref dep, ref path)| (&dep[..], &path[..])).collect::<Vec<_>>();
+        for name in &[format!("a{}", level), format!("b{}", level)] {
+            p = p.file(&format!("{}/Craft.toml", name), &path_deps_manifest(name, &deps))
+                .file(&format!("{}/src/lib.c", name), "int lib(void) { return 0; }");
+        }
+    }
+
+    let output = p.craft_process("why").arg("qux").exec_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.iter().filter(|l| l.starts_with("  foo v0.5.0 -> ")).count(), 32, "{}", stdout);
+    assert_eq!(lines.last(), Some(&"  ... only the first 32 paths are shown"), "{}", stdout);
+}
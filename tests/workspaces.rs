@@ -0,0 +1,120 @@
+#[macro_use]
+extern crate hamcrest;
+extern crate crafttest;
+
+use crafttest::support::{execs, project, ProjectBuilder};
+
+use hamcrest::prelude::*;
+
+fn virtual_workspace(default_members: &str) -> ProjectBuilder {
+    project("foo")
+        .file("Craft.toml",
+              &format!(r#"
+            [workspace]
+            members = ["a", "b"]
+            {}
+        "#,
+                       default_members))
+        .file("a/Craft.toml",
+              r#"
+            [package]
+            name = "a"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("a/src/main.c", "int main() { return 0; }")
+        .file("b/Craft.toml",
+              r#"
+            [package]
+            name = "b"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("b/src/main.c", "int main() { return 0; }")
+}
+
+#[test]
+fn virtual_root_builds_all_members() {
+    let p = virtual_workspace("");
+
+    assert_that!(p.craft_process("build"), execs().with_status(0));
+    assert!(p.bin("a").is_file());
+    assert!(p.bin("b").is_file());
+}
+
+#[test]
+fn default_members_are_built_at_the_root() {
+    let p = virtual_workspace(r#"default-members = ["a"]"#);
+
+    assert_that!(p.craft_process("build"),
+                 execs().with_status(0).with_stderr_contains("[Compiling] a v0.1.0 ([..])"));
+    assert!(p.bin("a").is_file());
+    assert!(!p.bin("b").is_file());
+
+    assert_that!(p.craft("build").arg("--all"),
+                 execs().with_status(0).with_stderr_contains("[Compiling] b v0.1.0 ([..])"));
+    assert!(p.bin("b").is_file());
+}
+
+#[test]
+fn default_members_are_ignored_in_a_member() {
+    let p = virtual_workspace(r#"default-members = ["a"]"#);
+    p.build();
+
+    assert_that!(p.craft("build").cwd(p.root().join("b")),
+                 execs().with_status(0).with_stderr_contains("[Compiling] b v0.1.0 ([..])"));
+    assert!(!p.bin("a").is_file());
+}
+
+#[test]
+fn default_members_must_be_members() {
+    let p = virtual_workspace(r#"default-members = ["c"]"#);
+
+    assert_that!(p.craft_process("build"),
+                 execs()
+                     .with_status(101)
+                     .with_stderr_contains("[Error] package `c` is listed in `workspace.default-members` but is \
+                                            not a member of the workspace"));
+}
+
+#[test]
+fn glob_members_exclude_wins() {
+    let p = project("foo")
+        .file("Craft.toml",
+              r#"
+            [workspace]
+            members = ["chests/*", "tools/*"]
+            exclude = ["chests/old"]
+        "#)
+        .file("chests/a/Craft.toml",
+              r#"
+            [package]
+            name = "a"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("chests/a/src/main.c", "int main() { return 0; }")
+        .file("chests/old/Craft.toml",
+              r#"
+            [package]
+            name = "old"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("chests/old/src/main.c", "int main() { return 0; }")
+        .file("chests/notes/README", "");
+
+    assert_that!(p.craft_process("build"),
+                 execs()
+                     .with_status(0)
+                     .with_stderr_contains("[Warning] `workspace.members` pattern `tools/*` does not match any \
+                                            chest in [..]")
+                     .with_stderr_contains("[Compiling] a v0.1.0 ([..])"));
+    assert!(p.bin("a").is_file());
+    assert!(!p.bin("old").is_file());
+
+    // The excluded chest is a workspace of its own
+    assert_that!(p.craft("build").cwd(p.root().join("chests/old")),
+                 execs().with_status(0).with_stderr_contains("[Compiling] old v0.1.0 ([..])"));
+    assert!(p.root().join("chests/old/target").is_dir());
+}